@@ -5,6 +5,7 @@ use core_bancho_state::*;
 use core_chat::*;
 use core_gateway::bancho_endpoints::{routes::*, *};
 use core_geoip::*;
+use core_pp::*;
 use core_signature::*;
 use infra_services::IntoService;
 use peace_api::{ApiFrameConfig, WebApplication};
@@ -12,7 +13,22 @@ use peace_db::{
     peace::{Peace, PeaceDbConfig},
     DbConfig, DbConnection,
 };
-use peace_repositories::users::{DynUsersRepository, UsersRepositoryImpl};
+use peace_repositories::{
+    beatmap_ratings::{
+        BeatmapRatingsRepositoryImpl, DynBeatmapRatingsRepository,
+    },
+    beatmaps::{BeatmapsRepositoryImpl, DynBeatmapsRepository},
+    channels::{ChannelsRepositoryImpl, DynChannelsRepository},
+    chat_messages::{ChatMessagesRepositoryImpl, DynChatMessagesRepository},
+    favourite_beatmaps::{
+        DynFavouriteBeatmapsRepository, FavouriteBeatmapsRepositoryImpl,
+    },
+    followers::{DynFollowersRepository, FollowersRepositoryImpl},
+    leaderboard::{DynLeaderboardRepository, LeaderboardRepositoryImpl},
+    scores::{DynScoresRepository, ScoresRepositoryImpl},
+    screenshots::{DynScreenshotsRepository, ScreenshotsRepositoryImpl},
+    users::{DynUsersRepository, UsersRepositoryImpl},
+};
 use peace_runtime::cfg::RuntimeConfig;
 use std::{net::SocketAddr, sync::Arc};
 use utoipa::OpenApi;
@@ -49,12 +65,48 @@ pub struct BanchoStandaloneConfig {
     #[command(flatten)]
     pub chat_background_service_configs: CliChatBackgroundServiceConfigs,
 
+    #[command(flatten)]
+    pub chat_spam_configs: CliChatSpamConfigs,
+
+    #[command(flatten)]
+    pub chat_command_configs: CliChatCommandConfigs,
+
     #[command(flatten)]
     pub geoip: GeoipRpcConfig,
 
     #[arg(long, short = 'P')]
     pub geo_db_path: Option<String>,
 
+    #[arg(long, default_value = "./.data/replays")]
+    pub replay_dir: String,
+
+    #[arg(long, default_value = "./.data/screenshots")]
+    pub screenshot_dir: String,
+
+    #[command(flatten)]
+    pub pp: PpRpcConfig,
+
+    #[arg(long)]
+    pub osu_files_dir: Option<String>,
+
+    #[command(flatten)]
+    pub beatmap_fetcher_configs: CliBeatmapFetcherConfigs,
+
+    #[command(flatten)]
+    pub registration_configs: CliBanchoRegistrationConfigs,
+
+    #[command(flatten)]
+    pub screenshot_configs: CliBanchoScreenshotConfigs,
+
+    #[command(flatten)]
+    pub direct_configs: CliBeatmapDirectConfigs,
+
+    #[command(flatten)]
+    pub client_configs: CliBanchoClientConfigs,
+
+    #[command(flatten)]
+    pub gateway_client_configs: CliGatewayClientConfigs,
+
     #[command(flatten)]
     pub signature_rpc_cfg: SignatureRpcConfig,
 
@@ -76,8 +128,21 @@ pub struct App {
     pub signature_service: DynSignatureService,
     pub bancho_state_service: DynBanchoStateService,
     pub users_repository: DynUsersRepository,
+    pub chat_messages_repository: DynChatMessagesRepository,
+    pub channels_repository: DynChannelsRepository,
+    pub followers_repository: DynFollowersRepository,
+    pub beatmaps_repository: DynBeatmapsRepository,
+    pub scores_repository: DynScoresRepository,
+    pub leaderboard_repository: DynLeaderboardRepository,
+    pub screenshots_repository: DynScreenshotsRepository,
+    pub favourite_beatmaps_repository: DynFavouriteBeatmapsRepository,
+    pub beatmap_ratings_repository: DynBeatmapRatingsRepository,
+    pub replay_store: DynReplayStore,
+    pub screenshot_store: DynScreenshotStore,
+    pub beatmap_fetcher: DynBeatmapFetcher,
     pub password_service: DynPasswordService,
     pub geoip_service: DynGeoipService,
+    pub pp_service: DynPpService,
     pub chat_service: DynChatService,
     pub chat_background_service: DynChatBackgroundService,
     pub chat_background_service_config: ChatBackgroundServiceConfigs,
@@ -122,6 +187,46 @@ impl App {
         let users_repository =
             UsersRepositoryImpl::new(peace_db_conn.clone()).into_service();
 
+        let followers_repository =
+            FollowersRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let beatmaps_repository =
+            BeatmapsRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let scores_repository =
+            ScoresRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let leaderboard_repository =
+            LeaderboardRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let screenshots_repository =
+            ScreenshotsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let favourite_beatmaps_repository =
+            FavouriteBeatmapsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let beatmap_ratings_repository =
+            BeatmapRatingsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let replay_store =
+            LocalReplayStore::new(cfg.replay_dir.as_str()).into_service();
+
+        let screenshot_store =
+            LocalScreenshotStore::new(cfg.screenshot_dir.as_str())
+                .into_service();
+
+        let beatmap_fetcher = BeatmapFetcherImpl::new(
+            beatmaps_repository.clone(),
+            BeatmapFetcherImpl::build_mirrors(&cfg.beatmap_fetcher_configs),
+            cfg.osu_files_dir.as_deref(),
+            &cfg.beatmap_fetcher_configs,
+        )
+        .into_service();
+
         let password_service = PasswordServiceImpl::default();
         let password_cache_store = password_service.cache_store().clone();
         let password_service = password_service.into_service();
@@ -133,9 +238,33 @@ impl App {
             )
             .await;
 
+        let pp_service = PpServiceBuilder::build::<
+            PpServiceImpl,
+            PpServiceRemote,
+        >(cfg.osu_files_dir.as_deref(), Some(&cfg.pp))
+        .await;
+
+        let chat_messages_repository =
+            ChatMessagesRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let channels_repository =
+            ChannelsRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let chat_commands = ChatCommandRegistryImpl::with_builtin_commands(
+            &cfg.chat_command_configs,
+            leaderboard_repository.clone(),
+        )
+        .into_service();
+
         let chat_service = ChatServiceSnapshotLoader::load(
             &cfg.chat_snapshot,
+            &cfg.chat_spam_configs,
             users_repository.clone(),
+            chat_messages_repository.clone(),
+            channels_repository.clone(),
+            followers_repository.clone(),
+            chat_commands,
         )
         .await
         .into_service();
@@ -182,11 +311,27 @@ impl App {
 
         let bancho_service = BanchoServiceImpl::new(
             users_repository.clone(),
+            followers_repository.clone(),
+            beatmaps_repository.clone(),
+            scores_repository.clone(),
+            leaderboard_repository.clone(),
+            screenshots_repository.clone(),
+            favourite_beatmaps_repository.clone(),
+            beatmap_ratings_repository.clone(),
+            replay_store.clone(),
+            screenshot_store.clone(),
+            beatmap_fetcher.clone(),
+            HttpDirectMirror::from_cfg(&cfg.direct_configs),
             bancho_state_service.clone(),
             password_service.clone(),
             bancho_background_service.clone(),
             geoip_service.clone(),
+            pp_service.clone(),
             chat_service.clone(),
+            &cfg.registration_configs,
+            &cfg.screenshot_configs,
+            &cfg.direct_configs,
+            &cfg.client_configs,
         )
         .into_service();
 
@@ -197,9 +342,11 @@ impl App {
         )
         .into_service();
 
-        let bancho_routing_service =
-            BanchoRoutingServiceImpl::new(bancho_handler_service.clone())
-                .into_service();
+        let bancho_routing_service = BanchoRoutingServiceImpl::new(
+            bancho_handler_service.clone(),
+            &cfg.gateway_client_configs,
+        )
+        .into_service();
 
         Self {
             cfg,
@@ -208,8 +355,21 @@ impl App {
             signature_service,
             bancho_state_service,
             users_repository,
+            chat_messages_repository,
+            channels_repository,
+            followers_repository,
+            beatmaps_repository,
+            scores_repository,
+            leaderboard_repository,
+            screenshots_repository,
+            favourite_beatmaps_repository,
+            beatmap_ratings_repository,
+            replay_store,
+            screenshot_store,
+            beatmap_fetcher,
             password_service,
             geoip_service,
+            pp_service,
             chat_service,
             chat_background_service,
             chat_background_service_config,
@@ -19,6 +19,10 @@ pub async fn run(
     // Start serving the HTTP(s) server with the `App` instance.
     peace_api::http::serve(app.clone()).await;
 
+    if let Err(err) = app.chat_service.chat_messages().flush().await {
+        warn!("Failed to save pending chat messages, err: {err}");
+    }
+
     if cfg.chat_snapshot.should_save_snapshot() {
         let _ = app
             .chat_service
@@ -227,4 +227,170 @@ impl bancho_state_rpc_server::BanchoStateRpc for BanchoStateRpcImpl {
 
         Ok(Response::new(res))
     }
+
+    async fn start_spectating(
+        &self,
+        request: Request<StartSpectatingRequest>,
+    ) -> Result<Response<StartSpectatingResponse>, Status> {
+        let res = self
+            .bancho_state_service
+            .start_spectating(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn stop_spectating(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<StopSpectatingResponse>, Status> {
+        let res = self
+            .bancho_state_service
+            .stop_spectating(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn cant_spectate(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .cant_spectate(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn broadcast_spectate_frames(
+        &self,
+        request: Request<BroadcastSpectateFramesRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .broadcast_spectate_frames(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_spectate_state(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<SpectateState>, Status> {
+        let res = self
+            .bancho_state_service
+            .get_spectate_state(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn join_lobby(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .join_lobby(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn leave_lobby(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .leave_lobby(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn create_match(
+        &self,
+        request: Request<CreateMatchRequest>,
+    ) -> Result<Response<JoinMatchResponse>, Status> {
+        let res = self
+            .bancho_state_service
+            .create_match(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn join_match(
+        &self,
+        request: Request<JoinMatchRequest>,
+    ) -> Result<Response<JoinMatchResponse>, Status> {
+        let res =
+            self.bancho_state_service.join_match(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn leave_match(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<LeaveMatchResponse>, Status> {
+        let res = self
+            .bancho_state_service
+            .leave_match(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn update_match(
+        &self,
+        request: Request<UpdateMatchRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .update_match(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_user_match(
+        &self,
+        request: Request<RawUserQuery>,
+    ) -> Result<Response<UserMatch>, Status> {
+        let res = self
+            .bancho_state_service
+            .get_user_match(request.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn add_user_friend(
+        &self,
+        request: Request<UserFriendRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .add_user_friend(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn remove_user_friend(
+        &self,
+        request: Request<UserFriendRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .bancho_state_service
+            .remove_user_friend(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
 }
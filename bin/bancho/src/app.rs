@@ -9,6 +9,9 @@ use core_geoip::{
     DynGeoipService, GeoipRpcConfig, GeoipServiceBuilder, GeoipServiceImpl,
     GeoipServiceRemote,
 };
+use core_pp::{
+    DynPpService, PpRpcConfig, PpServiceBuilder, PpServiceImpl, PpServiceRemote,
+};
 use infra_services::{FromRpcClient, IntoService};
 use pb_bancho::{bancho_rpc_server::BanchoRpcServer, BANCHO_DESCRIPTOR_SET};
 use pb_bancho_state::bancho_state_rpc_client::BanchoStateRpcClient;
@@ -17,7 +20,20 @@ use peace_db::{
     peace::{Peace, PeaceDbConfig},
     DbConfig, DbConnection,
 };
-use peace_repositories::users::{DynUsersRepository, UsersRepositoryImpl};
+use peace_repositories::{
+    beatmap_ratings::{
+        BeatmapRatingsRepositoryImpl, DynBeatmapRatingsRepository,
+    },
+    beatmaps::{BeatmapsRepositoryImpl, DynBeatmapsRepository},
+    favourite_beatmaps::{
+        DynFavouriteBeatmapsRepository, FavouriteBeatmapsRepositoryImpl,
+    },
+    followers::{DynFollowersRepository, FollowersRepositoryImpl},
+    leaderboard::{DynLeaderboardRepository, LeaderboardRepositoryImpl},
+    scores::{DynScoresRepository, ScoresRepositoryImpl},
+    screenshots::{DynScreenshotsRepository, ScreenshotsRepositoryImpl},
+    users::{DynUsersRepository, UsersRepositoryImpl},
+};
 use peace_rpc::{
     interceptor::client_ip, RpcApplication, RpcClientConfig, RpcFrameConfig,
 };
@@ -55,6 +71,33 @@ pub struct BanchoConfig {
 
     #[arg(long, short = 'P')]
     pub geo_db_path: Option<String>,
+
+    #[arg(long, default_value = "./.data/replays")]
+    pub replay_dir: String,
+
+    #[arg(long, default_value = "./.data/screenshots")]
+    pub screenshot_dir: String,
+
+    #[command(flatten)]
+    pub pp: PpRpcConfig,
+
+    #[arg(long)]
+    pub osu_files_dir: Option<String>,
+
+    #[command(flatten)]
+    pub beatmap_fetcher_configs: CliBeatmapFetcherConfigs,
+
+    #[command(flatten)]
+    pub registration_configs: CliBanchoRegistrationConfigs,
+
+    #[command(flatten)]
+    pub screenshot_configs: CliBanchoScreenshotConfigs,
+
+    #[command(flatten)]
+    pub direct_configs: CliBeatmapDirectConfigs,
+
+    #[command(flatten)]
+    pub client_configs: CliBanchoClientConfigs,
 }
 
 #[derive(Clone)]
@@ -64,7 +107,18 @@ pub struct App {
     pub bancho_state_rpc_client: BanchoStateRpcClient<Channel>,
     pub chat_rpc_client: ChatRpcClient<Channel>,
     pub geoip_service: DynGeoipService,
+    pub pp_service: DynPpService,
     pub users_repository: DynUsersRepository,
+    pub followers_repository: DynFollowersRepository,
+    pub beatmaps_repository: DynBeatmapsRepository,
+    pub scores_repository: DynScoresRepository,
+    pub leaderboard_repository: DynLeaderboardRepository,
+    pub screenshots_repository: DynScreenshotsRepository,
+    pub favourite_beatmaps_repository: DynFavouriteBeatmapsRepository,
+    pub beatmap_ratings_repository: DynBeatmapRatingsRepository,
+    pub replay_store: DynReplayStore,
+    pub screenshot_store: DynScreenshotStore,
+    pub beatmap_fetcher: DynBeatmapFetcher,
     pub bancho_state_service: DynBanchoStateService,
     pub chat_service: DynChatService,
     pub password_service: DynPasswordService,
@@ -93,9 +147,55 @@ impl App {
             )
             .await;
 
+        let pp_service = PpServiceBuilder::build::<
+            PpServiceImpl,
+            PpServiceRemote,
+        >(cfg.osu_files_dir.as_deref(), Some(&cfg.pp))
+        .await;
+
         let users_repository =
             UsersRepositoryImpl::new(peace_db_conn.clone()).into_service();
 
+        let followers_repository =
+            FollowersRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let beatmaps_repository =
+            BeatmapsRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let scores_repository =
+            ScoresRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let leaderboard_repository =
+            LeaderboardRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let screenshots_repository =
+            ScreenshotsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let favourite_beatmaps_repository =
+            FavouriteBeatmapsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let beatmap_ratings_repository =
+            BeatmapRatingsRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let replay_store =
+            LocalReplayStore::new(cfg.replay_dir.as_str()).into_service();
+
+        let screenshot_store =
+            LocalScreenshotStore::new(cfg.screenshot_dir.as_str())
+                .into_service();
+
+        let beatmap_fetcher = BeatmapFetcherImpl::new(
+            beatmaps_repository.clone(),
+            BeatmapFetcherImpl::build_mirrors(&cfg.beatmap_fetcher_configs),
+            cfg.osu_files_dir.as_deref(),
+            &cfg.beatmap_fetcher_configs,
+        )
+        .into_service();
+
         let bancho_state_service = BanchoStateServiceRemote::from_client(
             bancho_state_rpc_client.clone(),
         )
@@ -124,11 +224,27 @@ impl App {
 
         let bancho_service = BanchoServiceImpl::new(
             users_repository.clone(),
+            followers_repository.clone(),
+            beatmaps_repository.clone(),
+            scores_repository.clone(),
+            leaderboard_repository.clone(),
+            screenshots_repository.clone(),
+            favourite_beatmaps_repository.clone(),
+            beatmap_ratings_repository.clone(),
+            replay_store.clone(),
+            screenshot_store.clone(),
+            beatmap_fetcher.clone(),
+            HttpDirectMirror::from_cfg(&cfg.direct_configs),
             bancho_state_service.clone(),
             password_service.clone(),
             bancho_background_service.clone(),
             geoip_service.clone(),
+            pp_service.clone(),
             chat_service.clone(),
+            &cfg.registration_configs,
+            &cfg.screenshot_configs,
+            &cfg.direct_configs,
+            &cfg.client_configs,
         )
         .into_service();
 
@@ -140,7 +256,18 @@ impl App {
             bancho_state_rpc_client,
             chat_rpc_client,
             geoip_service,
+            pp_service,
             users_repository,
+            followers_repository,
+            beatmaps_repository,
+            scores_repository,
+            leaderboard_repository,
+            screenshots_repository,
+            favourite_beatmaps_repository,
+            beatmap_ratings_repository,
+            replay_store,
+            screenshot_store,
+            beatmap_fetcher,
             bancho_state_service,
             chat_service,
             password_service,
@@ -1,9 +1,13 @@
 use bancho_packets::Packet;
 use core_bancho::DynBanchoService;
+use futures::TryStreamExt;
 use pb_bancho::*;
-use pb_bancho_state::RawUserQuery;
+use pb_bancho_state::{
+    CreateMatchRequest, JoinMatchRequest, RawUserQuery, UpdateMatchRequest,
+};
 use peace_rpc::extensions::ClientIp;
-use tonic::{Request, Response, Status};
+use std::pin::Pin;
+use tonic::{codegen::futures_core::Stream, Request, Response, Status};
 
 #[derive(Clone)]
 pub struct BanchoRpcImpl {
@@ -163,6 +167,16 @@ impl bancho_rpc_server::BanchoRpc for BanchoRpcImpl {
         Ok(Response::new(res))
     }
 
+    async fn spectate_start(
+        &self,
+        request: Request<SpectateRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res =
+            self.bancho_service.spectate_start(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
     async fn spectate_stop(
         &self,
         raw_user_query: Request<RawUserQuery>,
@@ -187,6 +201,16 @@ impl bancho_rpc_server::BanchoRpc for BanchoRpcImpl {
         Ok(Response::new(res))
     }
 
+    async fn spectate_frames(
+        &self,
+        request: Request<SpectateFramesRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res =
+            self.bancho_service.spectate_frames(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
     async fn lobby_part(
         &self,
         raw_user_query: Request<RawUserQuery>,
@@ -210,4 +234,269 @@ impl bancho_rpc_server::BanchoRpc for BanchoRpcImpl {
 
         Ok(Response::new(res))
     }
+
+    async fn match_create(
+        &self,
+        request: Request<CreateMatchRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res =
+            self.bancho_service.match_create(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn match_join(
+        &self,
+        request: Request<JoinMatchRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res = self.bancho_service.match_join(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn match_part(
+        &self,
+        raw_user_query: Request<RawUserQuery>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res = self
+            .bancho_service
+            .match_part(raw_user_query.into_inner().into_user_query()?)
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn match_update(
+        &self,
+        request: Request<UpdateMatchRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res =
+            self.bancho_service.match_update(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn friend_add(
+        &self,
+        request: Request<FriendRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res = self.bancho_service.friend_add(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn friend_remove(
+        &self,
+        request: Request<FriendRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res =
+            self.bancho_service.friend_remove(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_friends(
+        &self,
+        request: Request<GetFriendsRequest>,
+    ) -> Result<Response<GetFriendsResponse>, Status> {
+        let res = self.bancho_service.get_friends(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn submit_score(
+        &self,
+        request: Request<SubmitScoreRequest>,
+    ) -> Result<Response<SubmitScoreResponse>, Status> {
+        let res =
+            self.bancho_service.submit_score(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_beatmap_scores(
+        &self,
+        request: Request<GetBeatmapScoresRequest>,
+    ) -> Result<Response<GetBeatmapScoresResponse>, Status> {
+        let res = self
+            .bancho_service
+            .get_beatmap_scores(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_replay(
+        &self,
+        request: Request<GetReplayRequest>,
+    ) -> Result<Response<GetReplayResponse>, Status> {
+        let res = self.bancho_service.get_replay(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn export_replay(
+        &self,
+        request: Request<GetReplayRequest>,
+    ) -> Result<Response<GetReplayResponse>, Status> {
+        let res =
+            self.bancho_service.export_replay(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn request_beatmap_info(
+        &self,
+        request: Request<BeatmapInfoRequest>,
+    ) -> Result<Response<HandleCompleted>, Status> {
+        let res = self
+            .bancho_service
+            .request_beatmap_info(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_beatmap_info(
+        &self,
+        request: Request<GetBeatmapInfoRequest>,
+    ) -> Result<Response<GetBeatmapInfoResponse>, Status> {
+        let res =
+            self.bancho_service.get_beatmap_info(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn verify_credentials(
+        &self,
+        request: Request<VerifyCredentialsRequest>,
+    ) -> Result<Response<VerifyCredentialsResponse>, Status> {
+        let res = self
+            .bancho_service
+            .verify_credentials(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        let client_ip = ClientIp::from_request(&request)?;
+
+        let res = self
+            .bancho_service
+            .register(client_ip.into(), request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn bancho_connect(
+        &self,
+        request: Request<BanchoConnectRequest>,
+    ) -> Result<Response<BanchoConnectResponse>, Status> {
+        let client_ip = ClientIp::from_request(&request)?;
+
+        let res = self
+            .bancho_service
+            .bancho_connect(client_ip.into(), request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn upload_screenshot(
+        &self,
+        request: Request<UploadScreenshotRequest>,
+    ) -> Result<Response<UploadScreenshotResponse>, Status> {
+        let res =
+            self.bancho_service.upload_screenshot(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_screenshot(
+        &self,
+        request: Request<GetScreenshotRequest>,
+    ) -> Result<Response<GetScreenshotResponse>, Status> {
+        let res =
+            self.bancho_service.get_screenshot(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_favourite_beatmaps(
+        &self,
+        request: Request<GetFavouriteBeatmapsRequest>,
+    ) -> Result<Response<GetFavouriteBeatmapsResponse>, Status> {
+        let res = self
+            .bancho_service
+            .get_favourite_beatmaps(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn add_favourite_beatmap(
+        &self,
+        request: Request<AddFavouriteBeatmapRequest>,
+    ) -> Result<Response<AddFavouriteBeatmapResponse>, Status> {
+        let res = self
+            .bancho_service
+            .add_favourite_beatmap(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn rate_beatmap(
+        &self,
+        request: Request<RateBeatmapRequest>,
+    ) -> Result<Response<RateBeatmapResponse>, Status> {
+        let res =
+            self.bancho_service.rate_beatmap(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn search_beatmapsets(
+        &self,
+        request: Request<SearchBeatmapsetsRequest>,
+    ) -> Result<Response<SearchBeatmapsetsResponse>, Status> {
+        let res = self
+            .bancho_service
+            .search_beatmapsets(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_beatmapset_info(
+        &self,
+        request: Request<GetBeatmapsetInfoRequest>,
+    ) -> Result<Response<GetBeatmapsetInfoResponse>, Status> {
+        let res = self
+            .bancho_service
+            .get_beatmapset_info(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    type DownloadBeatmapsetStream =
+        Pin<Box<dyn Stream<Item = Result<BeatmapsetChunk, Status>> + Send>>;
+
+    async fn download_beatmapset(
+        &self,
+        request: Request<DownloadBeatmapsetRequest>,
+    ) -> Result<Response<Self::DownloadBeatmapsetStream>, Status> {
+        let stream = self
+            .bancho_service
+            .download_beatmapset(request.into_inner())
+            .await?;
+
+        Ok(Response::new(Box::pin(stream.map_err(Status::from))))
+    }
 }
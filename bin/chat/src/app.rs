@@ -6,7 +6,13 @@ use peace_db::{
     peace::{Peace, PeaceDbConfig},
     DbConfig, DbConnection,
 };
-use peace_repositories::users::{DynUsersRepository, UsersRepositoryImpl};
+use peace_repositories::{
+    channels::{ChannelsRepositoryImpl, DynChannelsRepository},
+    chat_messages::{ChatMessagesRepositoryImpl, DynChatMessagesRepository},
+    followers::{DynFollowersRepository, FollowersRepositoryImpl},
+    leaderboard::{DynLeaderboardRepository, LeaderboardRepositoryImpl},
+    users::{DynUsersRepository, UsersRepositoryImpl},
+};
 use peace_rpc::{RpcApplication, RpcFrameConfig};
 use peace_runtime::cfg::RuntimeConfig;
 use std::{net::SocketAddr, sync::Arc};
@@ -31,6 +37,12 @@ pub struct ChatServiceConfig {
     #[command(flatten)]
     pub chat_background_service_configs: CliChatBackgroundServiceConfigs,
 
+    #[command(flatten)]
+    pub chat_spam_configs: CliChatSpamConfigs,
+
+    #[command(flatten)]
+    pub chat_command_configs: CliChatCommandConfigs,
+
     #[command(flatten)]
     pub chat_snapshot: CliChatServiceSnapshotConfigs,
 }
@@ -40,6 +52,10 @@ pub struct App {
     pub cfg: Arc<ChatServiceConfig>,
     pub peace_db_conn: DbConnection<Peace>,
     pub users_repository: DynUsersRepository,
+    pub chat_messages_repository: DynChatMessagesRepository,
+    pub channels_repository: DynChannelsRepository,
+    pub followers_repository: DynFollowersRepository,
+    pub leaderboard_repository: DynLeaderboardRepository,
     pub chat_service: DynChatService,
     pub chat_background_service: DynChatBackgroundService,
     pub chat_background_service_config: ChatBackgroundServiceConfigs,
@@ -57,9 +73,34 @@ impl App {
         let users_repository =
             UsersRepositoryImpl::new(peace_db_conn.clone()).into_service();
 
+        let chat_messages_repository =
+            ChatMessagesRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let channels_repository =
+            ChannelsRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let followers_repository =
+            FollowersRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let leaderboard_repository =
+            LeaderboardRepositoryImpl::new(peace_db_conn.clone())
+                .into_service();
+
+        let chat_commands = ChatCommandRegistryImpl::with_builtin_commands(
+            &cfg.chat_command_configs,
+            leaderboard_repository.clone(),
+        )
+        .into_service();
+
         let chat_service = ChatServiceSnapshotLoader::load(
             &cfg.chat_snapshot,
+            &cfg.chat_spam_configs,
             users_repository.clone(),
+            chat_messages_repository.clone(),
+            channels_repository.clone(),
+            followers_repository.clone(),
+            chat_commands,
         )
         .await
         .into_service();
@@ -86,6 +127,10 @@ impl App {
             cfg,
             peace_db_conn,
             users_repository,
+            chat_messages_repository,
+            channels_repository,
+            followers_repository,
+            leaderboard_repository,
             chat_service,
             chat_background_service,
             chat_background_service_config,
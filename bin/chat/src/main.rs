@@ -22,6 +22,10 @@ pub async fn run(
     // Start serving the RPC server with the `App` instance.
     peace_rpc::server::serve(app.clone()).await;
 
+    if let Err(err) = app.chat_service.chat_messages().flush().await {
+        warn!("Failed to save pending chat messages, err: {err}");
+    }
+
     if cfg.chat_snapshot.should_save_snapshot() {
         let _ = app
             .chat_service
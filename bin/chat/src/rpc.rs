@@ -1,5 +1,5 @@
 use core_chat::{ChatError, DynChatService};
-use pb_bancho_state::{BanchoPackets, RawUserQuery};
+use pb_bancho_state::BanchoPackets;
 use pb_base::ExecSuccess;
 use pb_chat::*;
 use tonic::{Request, Response, Status};
@@ -49,6 +49,55 @@ impl chat_rpc_server::ChatRpc for ChatRpcImpl {
         Ok(Response::new(res))
     }
 
+    async fn get_channel_members(
+        &self,
+        request: Request<GetChannelMembersRequest>,
+    ) -> Result<Response<ChannelMembers>, Status> {
+        let res =
+            self.chat_service.get_channel_members(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn create_channel(
+        &self,
+        request: Request<CreateChannelRequest>,
+    ) -> Result<Response<ChannelInfo>, Status> {
+        let res =
+            self.chat_service.create_channel(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn update_channel(
+        &self,
+        request: Request<UpdateChannelRequest>,
+    ) -> Result<Response<ChannelInfo>, Status> {
+        let res =
+            self.chat_service.update_channel(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn delete_channel(
+        &self,
+        request: Request<DeleteChannelRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res =
+            self.chat_service.delete_channel(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn reload_channels(
+        &self,
+        _: Request<ReloadChannelsRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self.chat_service.reload_channels().await?;
+
+        Ok(Response::new(res))
+    }
+
     async fn load_public_channels(
         &self,
         _: Request<LoadPublicChannelsRequest>,
@@ -58,6 +107,54 @@ impl chat_rpc_server::ChatRpc for ChatRpcImpl {
         Ok(Response::new(res))
     }
 
+    async fn create_spectator_channel(
+        &self,
+        request: Request<SpectatorChannelRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .chat_service
+            .create_spectator_channel(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn remove_spectator_channel(
+        &self,
+        request: Request<SpectatorChannelRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .chat_service
+            .remove_spectator_channel(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn create_multiplayer_channel(
+        &self,
+        request: Request<MultiplayerChannelRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .chat_service
+            .create_multiplayer_channel(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn remove_multiplayer_channel(
+        &self,
+        request: Request<MultiplayerChannelRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self
+            .chat_service
+            .remove_multiplayer_channel(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
     async fn join_channel(
         &self,
         request: Request<JoinChannelRequest>,
@@ -87,11 +184,67 @@ impl chat_rpc_server::ChatRpc for ChatRpcImpl {
 
     async fn pull_chat_packets(
         &self,
-        request: Request<RawUserQuery>,
+        request: Request<PullChatPacketsRequest>,
     ) -> Result<Response<BanchoPackets>, Status> {
+        let PullChatPacketsRequest { user_query, platform } =
+            request.into_inner();
+        let user_query =
+            user_query.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
+
+        let res = self
+            .chat_service
+            .dequeue_chat_packets(user_query, platform.into())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+    async fn get_channel_history(
+        &self,
+        request: Request<GetChannelHistoryRequest>,
+    ) -> Result<Response<ChatHistory>, Status> {
+        let res =
+            self.chat_service.get_channel_history(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn get_private_conversation(
+        &self,
+        request: Request<GetPrivateConversationRequest>,
+    ) -> Result<Response<ChatHistory>, Status> {
+        let res = self
+            .chat_service
+            .get_private_conversation(request.into_inner())
+            .await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn kick_user(
+        &self,
+        request: Request<KickUserRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self.chat_service.kick_user(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn mute_user(
+        &self,
+        request: Request<MuteUserRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
+        let res = self.chat_service.mute_user(request.into_inner()).await?;
+
+        Ok(Response::new(res))
+    }
+
+    async fn set_only_friend_pm_allowed(
+        &self,
+        request: Request<SetOnlyFriendPmAllowedRequest>,
+    ) -> Result<Response<ExecSuccess>, Status> {
         let res = self
             .chat_service
-            .dequeue_chat_packets(request.into_inner().into_user_query()?)
+            .set_only_friend_pm_allowed(request.into_inner())
             .await?;
 
         Ok(Response::new(res))
@@ -1,11 +1,31 @@
 use clap::{Parser, Subcommand};
 use clap4 as clap;
+use core_pp::{CalculatePerformance, PpServiceImpl};
+use domain_pp::PP_WEIGHTED_SCORES;
 use domain_users::{
     CreateUser, Email, Password, UsernameAscii, UsernameUnicode,
 };
 use dotenvy::dotenv;
-use peace_db::{Database, DbConnection};
-use peace_repositories::users::{UsersRepository, UsersRepositoryImpl};
+use pb_pp::CalculatePerformanceRequest;
+use peace_db::{
+    peace::{
+        entity::{
+            score_pp,
+            sea_orm_active_enums::{GameMode, PpVersion, RankingType},
+            user_pp,
+        },
+        Peace,
+    },
+    prelude::Decimal,
+    ActiveEnum, Database, DbConnection,
+};
+use peace_repositories::{
+    beatmaps::{BeatmapsRepository, BeatmapsRepositoryImpl},
+    leaderboard::{LeaderboardRepository, LeaderboardRepositoryImpl},
+    scores::{ScoresRepository, ScoresRepositoryImpl},
+    users::{UsersRepository, UsersRepositoryImpl},
+};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Parser)]
 #[clap(version, author, about = "Peace db CLI")]
@@ -53,6 +73,16 @@ pub enum Commands {
         #[arg(long)]
         md5_password: Option<String>,
     },
+    #[clap(
+        about = "[peace] Recalculate the pp of all scores, then rebuild the pp leaderboards and users' pp"
+    )]
+    RecalculatePp {
+        #[arg(long, default_value = "./.data/beatmaps")]
+        osu_files_dir: String,
+
+        #[arg(long, default_value_t = 1000)]
+        batch_size: u64,
+    },
 }
 
 #[tokio::main]
@@ -154,5 +184,183 @@ async fn main() {
             .unwrap();
             println!("Success")
         },
+        Commands::RecalculatePp { osu_files_dir, batch_size } => {
+            let db = DbConnection::from(
+                Database::connect(
+                    cli.database_url.expect("database-url is required."),
+                )
+                .await
+                .unwrap(),
+            );
+
+            recalculate_pp(db, osu_files_dir, batch_size).await;
+            println!("Success")
+        },
+    }
+}
+
+async fn recalculate_pp(
+    db: DbConnection<Peace>,
+    osu_files_dir: String,
+    batch_size: u64,
+) {
+    let pp_service = PpServiceImpl::new(osu_files_dir);
+    let beatmaps_repo = BeatmapsRepositoryImpl::new(db.clone());
+    let scores_repo = ScoresRepositoryImpl::new(db.clone());
+    let leaderboard_repo = LeaderboardRepositoryImpl::new(db.clone());
+    let users_repo = UsersRepositoryImpl::new(db);
+
+    let mut beatmap_ids = HashMap::<String, Option<i32>>::new();
+    // The best pp score of each user on each beatmap and mode, the mode is
+    // keyed by its db value since `GameMode` is not hashable.
+    let mut best_scores = HashMap::<(i32, String, i32), (Decimal, i64)>::new();
+
+    println!("Calculating pp of scores...");
+    let mut after_id = 0;
+    loop {
+        let scores = scores_repo
+            .get_completed_classic_scores(after_id, batch_size)
+            .await
+            .unwrap();
+
+        let Some((last, _)) = scores.last() else { break };
+        after_id = last.id;
+
+        for (score, classic) in scores {
+            if !beatmap_ids.contains_key(&score.map_hash) {
+                let beatmap_id = beatmaps_repo
+                    .get_beatmap_by_md5(&score.map_hash)
+                    .await
+                    .ok()
+                    .map(|beatmap| beatmap.bid);
+                beatmap_ids.insert(score.map_hash.clone(), beatmap_id);
+            }
+
+            let Some(beatmap_id) = beatmap_ids[&score.map_hash] else {
+                continue;
+            };
+
+            let performance = match pp_service
+                .calculate_performance(CalculatePerformanceRequest {
+                    beatmap_id,
+                    mode: match classic.mode {
+                        GameMode::Standard
+                        | GameMode::StandardRelax
+                        | GameMode::StandardAutopilot
+                        | GameMode::StandardScoreV2 => 0,
+                        GameMode::Taiko | GameMode::TaikoRelax => 1,
+                        GameMode::Fruits | GameMode::FruitsRelax => 2,
+                        GameMode::Mania => 3,
+                    },
+                    mods: classic.mods as u32,
+                    n300: classic.n300,
+                    n100: classic.n100,
+                    n50: classic.n50,
+                    geki: classic.geki,
+                    katu: classic.katu,
+                    miss: classic.miss,
+                    max_combo: classic.combo,
+                    passed_objects: None,
+                })
+                .await
+            {
+                Ok(performance) => performance,
+                Err(err) => {
+                    println!("Skipped score {}: {err}", score.id);
+                    continue;
+                },
+            };
+
+            let pp = Decimal::from_f64_retain(performance.pp)
+                .unwrap_or_default()
+                .round_dp(2);
+
+            scores_repo
+                .save_score_pp(score_pp::Model {
+                    score_id: score.id,
+                    mode: classic.mode.clone(),
+                    pp_version: PpVersion::V2,
+                    pp,
+                    raw_pp: serde_json::to_value(&performance).ok(),
+                })
+                .await
+                .unwrap();
+
+            best_scores
+                .entry((beatmap_id, classic.mode.to_value(), score.user_id))
+                .and_modify(|best| {
+                    if pp > best.0 {
+                        *best = (pp, score.id)
+                    }
+                })
+                .or_insert((pp, score.id));
+        }
+
+        println!("Calculated scores up to id {after_id}");
+    }
+
+    println!("Rebuilding pp leaderboards...");
+    let mut users = HashSet::new();
+    for ((beatmap_id, mode, user_id), (_, score_id)) in best_scores {
+        let mode = GameMode::try_from_value(&mode).unwrap();
+        leaderboard_repo
+            .update_leaderboard(
+                beatmap_id,
+                mode.clone(),
+                RankingType::PpV2,
+                user_id,
+                score_id,
+            )
+            .await
+            .unwrap();
+
+        users.insert((user_id, mode.to_value()));
+    }
+
+    println!("Updating pp of {} users...", users.len());
+    for (user_id, mode) in users {
+        let mode = GameMode::try_from_value(&mode).unwrap();
+        let top_scores = leaderboard_repo
+            .get_user_top_scores(
+                user_id,
+                mode.clone(),
+                RankingType::PpV2,
+                PP_WEIGHTED_SCORES,
+            )
+            .await
+            .unwrap();
+
+        let pp_values = top_scores
+            .iter()
+            .map(|s| s.pp.and_then(|pp| pp.try_into().ok()).unwrap_or_default())
+            .collect::<Vec<f64>>();
+
+        users_repo
+            .save_user_pp(user_pp::Model {
+                user_id,
+                mode: mode.clone(),
+                pp_version: PpVersion::V2,
+                pp: Decimal::from_f64_retain(domain_pp::total_pp(&pp_values))
+                    .unwrap_or_default()
+                    .round_dp(2),
+                raw_pp: None,
+            })
+            .await
+            .unwrap();
+
+        if let Some(mut stats) =
+            users_repo.get_user_stats(user_id, mode).await.unwrap()
+        {
+            stats.accuracy =
+                Decimal::from_f64_retain(domain_pp::weighted_average(
+                    top_scores
+                        .iter()
+                        .map(|s| s.accuracy.try_into().unwrap_or_default()),
+                ))
+                .unwrap_or_default()
+                .round_dp(2);
+
+            users_repo.save_user_stats(stats).await.unwrap();
+        }
     }
 }
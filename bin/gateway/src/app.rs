@@ -9,7 +9,8 @@ use core_gateway::{
     bancho_endpoints::{
         routes::{BanchoDebugRouter, BanchoRouter},
         BanchoHandlerServiceImpl, BanchoRoutingServiceImpl,
-        DynBanchoHandlerService, DynBanchoRoutingService,
+        CliGatewayClientConfigs, DynBanchoHandlerService,
+        DynBanchoRoutingService,
     },
     docs::GatewayApiDocs,
 };
@@ -41,6 +42,9 @@ pub struct GatewayConfig {
     #[command(flatten)]
     pub chat: ChatRpcConfig,
 
+    #[command(flatten)]
+    pub client_configs: CliGatewayClientConfigs,
+
     #[arg(long)]
     pub debug_endpoints: bool,
 }
@@ -85,9 +89,11 @@ impl App {
         )
         .into_service();
 
-        let bancho_routing_service =
-            BanchoRoutingServiceImpl::new(bancho_handler_service.clone())
-                .into_service();
+        let bancho_routing_service = BanchoRoutingServiceImpl::new(
+            bancho_handler_service.clone(),
+            &cfg.client_configs,
+        )
+        .into_service();
 
         Self {
             cfg,
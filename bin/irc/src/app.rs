@@ -0,0 +1,91 @@
+use clap_serde_derive::ClapSerde;
+use core_chat::{ChatRpcConfig, ChatServiceRemote, DynChatService};
+use core_irc::{CliIrcServerConfigs, IrcServer, DEFAULT_IRC_ADDR};
+use infra_services::{FromRpcClient, IntoService};
+use pb_chat::chat_rpc_client::ChatRpcClient;
+use peace_cfg::{impl_config, peace_config, RpcClientConfig, SingletonConfig};
+use peace_db::{
+    peace::{Peace, PeaceDbConfig},
+    DbConfig, DbConnection,
+};
+use peace_logs::{impl_logging_config, LoggingConfigArgs};
+use peace_repositories::users::{DynUsersRepository, UsersRepositoryImpl};
+use peace_runtime::cfg::RuntimeConfig;
+use std::{net::SocketAddr, sync::Arc};
+use tonic::transport::Channel;
+
+/// PEACE IRC server
+#[peace_config]
+#[command(name = "irc", author, version, about, propagate_version = true)]
+pub struct IrcConfig {
+    #[command(flatten)]
+    pub runtime_cfg: RuntimeConfig,
+
+    /// Logging configurations.
+    #[command(flatten)]
+    pub logging: LoggingConfigArgs,
+
+    #[command(flatten)]
+    pub peace_db: PeaceDbConfig,
+
+    #[command(flatten)]
+    pub chat: ChatRpcConfig,
+
+    #[command(flatten)]
+    pub irc: CliIrcServerConfigs,
+}
+
+impl_logging_config!(IrcConfig);
+
+#[derive(Clone)]
+pub struct App {
+    pub cfg: Arc<IrcConfig>,
+    pub peace_db_conn: DbConnection<Peace>,
+    pub chat_rpc_client: ChatRpcClient<Channel>,
+    pub users_repository: DynUsersRepository,
+    pub chat_service: DynChatService,
+    pub irc_server: Arc<IrcServer>,
+}
+
+impl App {
+    pub async fn initialize(cfg: Arc<IrcConfig>) -> Self {
+        let peace_db_conn = cfg
+            .peace_db
+            .connect()
+            .await
+            .expect("failed to connect peace db, please check.");
+
+        let chat_rpc_client = cfg.chat.connect().await;
+
+        let users_repository =
+            UsersRepositoryImpl::new(peace_db_conn.clone()).into_service();
+
+        let chat_service =
+            ChatServiceRemote::from_client(chat_rpc_client.clone())
+                .into_service();
+
+        let irc_server = IrcServer::new(
+            &cfg.irc,
+            users_repository.clone(),
+            chat_service.clone(),
+        )
+        .into_service();
+
+        Self {
+            cfg,
+            peace_db_conn,
+            chat_rpc_client,
+            users_repository,
+            chat_service,
+            irc_server,
+        }
+    }
+
+    #[inline]
+    pub fn listen_addr(&self) -> SocketAddr {
+        self.cfg
+            .irc
+            .irc_addr
+            .unwrap_or_else(|| DEFAULT_IRC_ADDR.parse().unwrap())
+    }
+}
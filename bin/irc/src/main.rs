@@ -0,0 +1,36 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate peace_logs;
+
+pub mod app;
+
+pub use app::*;
+
+pub async fn run(cfg: std::sync::Arc<IrcConfig>) {
+    // Create a new instance of the `App.
+    let app = App::initialize(cfg).await;
+
+    // Start serving the IRC server with the `App` instance.
+    tokio::select! {
+        res = app.irc_server.clone().serve(app.listen_addr()) => {
+            if let Err(err) = res {
+                error!("IRC server stopped with error: {err}");
+            }
+        },
+        _ = tokio::signal::ctrl_c() => {
+            info!("IRC server shutting down");
+        },
+    }
+}
+
+/// The main entry point of the application.
+pub fn main() {
+    tools::main_startup_info!();
+
+    let cfg = IrcConfig::get();
+    // Initialize the logger.
+    peace_logs::init(cfg.as_ref());
+
+    // Initialize runtime and run app.
+    peace_runtime::runtime(&cfg.runtime_cfg).unwrap().block_on(run(cfg))
+}
@@ -0,0 +1,63 @@
+use crate::PpRpcImpl;
+use clap_serde_derive::ClapSerde;
+use core_pp::{DynPpService, PpServiceImpl};
+use infra_services::IntoService;
+use pb_pp::{pp_rpc_server::PpRpcServer, PP_DESCRIPTOR_SET};
+use peace_rpc::{RpcApplication, RpcFrameConfig};
+use peace_runtime::cfg::RuntimeConfig;
+use std::{net::SocketAddr, sync::Arc};
+use tonic::{
+    async_trait,
+    transport::{server::Router, Server},
+};
+
+/// PEACE Performance-point gRPC service
+#[peace_config]
+#[command(name = "pp", author, version, about, propagate_version = true)]
+pub struct PpConfig {
+    #[command(flatten)]
+    pub runtime_cfg: RuntimeConfig,
+
+    #[command(flatten)]
+    pub frame_cfg: RpcFrameConfig,
+
+    #[arg(long, default_value = "./.data/beatmaps")]
+    pub osu_files_dir: String,
+}
+
+#[derive(Clone)]
+pub struct App {
+    pub cfg: Arc<PpConfig>,
+    pub pp_service: DynPpService,
+    pub pp_rpc: PpRpcImpl,
+}
+
+impl App {
+    pub async fn initialize(cfg: Arc<PpConfig>) -> Self {
+        let pp_service =
+            PpServiceImpl::new(cfg.osu_files_dir.as_str()).into_service();
+
+        let pp_rpc = PpRpcImpl::new(pp_service.clone());
+
+        Self { cfg, pp_service, pp_rpc }
+    }
+}
+
+#[async_trait]
+impl RpcApplication for App {
+    fn frame_cfg(&self) -> &RpcFrameConfig {
+        &self.cfg.frame_cfg
+    }
+
+    fn default_listen_addr(&self) -> Option<SocketAddr> {
+        Some("127.0.0.1:5016".parse().unwrap())
+    }
+
+    fn service_descriptors(&self) -> Option<&[&[u8]]> {
+        Some(&[PP_DESCRIPTOR_SET])
+    }
+
+    async fn service(&self, mut configured_server: Server) -> Router {
+        configured_server.add_service(PpRpcServer::new(self.pp_rpc.clone()))
+    }
+}
@@ -0,0 +1,27 @@
+use core_pp::DynPpService;
+use pb_pp::{PerformanceAttributes as RpcPerformanceAttributes, *};
+use tonic::{Request, Response, Status};
+
+#[derive(Clone)]
+pub struct PpRpcImpl {
+    pub pp_service: DynPpService,
+}
+
+impl PpRpcImpl {
+    pub fn new(pp_service: DynPpService) -> Self {
+        Self { pp_service }
+    }
+}
+
+#[tonic::async_trait]
+impl pp_rpc_server::PpRpc for PpRpcImpl {
+    async fn calculate_performance(
+        &self,
+        request: Request<CalculatePerformanceRequest>,
+    ) -> Result<Response<RpcPerformanceAttributes>, Status> {
+        let res =
+            self.pp_service.calculate_performance(request.into_inner()).await?;
+
+        Ok(Response::new(res.into()))
+    }
+}
@@ -30,7 +30,7 @@ macro_rules! define_db {
 /// examples
 ///
 /// ```rust
-/// use peace_cfg::define_db_config;
+/// use peace_db::define_db_config;
 ///
 /// define_db_config!(db_name: Bancho, command_prefix: bancho);
 /// define_db_config!(db_name: Lazer, command_prefix: lazer);
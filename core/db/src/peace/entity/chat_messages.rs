@@ -8,13 +8,15 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub sender_id: i32,
-    pub channel_id: i64,
+    pub channel_id: Option<i64>,
+    pub target_user_id: Option<i32>,
     pub timestamp: DateTimeWithTimeZone,
     #[sea_orm(column_type = "Text")]
     pub content_string: String,
     #[sea_orm(column_type = "Text", nullable)]
     pub content_html: Option<String>,
     pub is_action: bool,
+    pub delivered: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -13,6 +13,7 @@ pub struct Model {
     pub mode: GameMode,
     #[sea_orm(primary_key, auto_increment = false)]
     pub ranking_type: RankingType,
+    #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: i32,
     pub score_id: i64,
 }
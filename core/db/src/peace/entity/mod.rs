@@ -5,6 +5,7 @@ pub mod prelude;
 pub mod bancho_client_hardware_records;
 pub mod beatmap_ratings;
 pub mod beatmaps;
+pub mod channel_moderation_logs;
 pub mod channel_privileges;
 pub mod channel_users;
 pub mod channels;
@@ -17,7 +18,9 @@ pub mod score_pp;
 pub mod scores;
 pub mod scores_classic;
 pub mod scores_generic;
+pub mod screenshots;
 pub mod sea_orm_active_enums;
+pub mod user_blocks;
 pub mod user_pp;
 pub mod user_privileges;
 pub mod user_settings;
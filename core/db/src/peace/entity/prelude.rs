@@ -3,6 +3,7 @@
 pub use super::bancho_client_hardware_records::Entity as BanchoClientHardwareRecords;
 pub use super::beatmap_ratings::Entity as BeatmapRatings;
 pub use super::beatmaps::Entity as Beatmaps;
+pub use super::channel_moderation_logs::Entity as ChannelModerationLogs;
 pub use super::channel_privileges::Entity as ChannelPrivileges;
 pub use super::channel_users::Entity as ChannelUsers;
 pub use super::channels::Entity as Channels;
@@ -15,6 +16,8 @@ pub use super::score_pp::Entity as ScorePp;
 pub use super::scores::Entity as Scores;
 pub use super::scores_classic::Entity as ScoresClassic;
 pub use super::scores_generic::Entity as ScoresGeneric;
+pub use super::screenshots::Entity as Screenshots;
+pub use super::user_blocks::Entity as UserBlocks;
 pub use super::user_pp::Entity as UserPp;
 pub use super::user_privileges::Entity as UserPrivileges;
 pub use super::user_settings::Entity as UserSettings;
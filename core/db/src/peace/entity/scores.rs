@@ -18,6 +18,7 @@ pub struct Model {
     // discriminator for which extended table owns this score row (e.g. "classic")
     pub kind: ScoreKind,
 
+    #[sea_orm(column_name = "play_time")]
     pub playtime: i32,
     pub completed: bool,
 
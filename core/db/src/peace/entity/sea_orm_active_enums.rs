@@ -19,6 +19,20 @@ pub enum ChannelHandleType {
     SendMessage,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "channel_moderation_action"
+)]
+pub enum ChannelModerationAction {
+    #[sea_orm(string_value = "kick")]
+    Kick,
+    #[sea_orm(string_value = "mute")]
+    Mute,
+    #[sea_orm(string_value = "unmute")]
+    Unmute,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "channel_type")]
 pub enum ChannelType {
     #[sea_orm(string_value = "group")]
@@ -37,12 +51,22 @@ pub enum ChannelType {
 pub enum GameMode {
     #[sea_orm(string_value = "Fruits")]
     Fruits,
+    #[sea_orm(string_value = "FruitsRelax")]
+    FruitsRelax,
     #[sea_orm(string_value = "Mania")]
     Mania,
     #[sea_orm(string_value = "Standard")]
     Standard,
+    #[sea_orm(string_value = "StandardAutopilot")]
+    StandardAutopilot,
+    #[sea_orm(string_value = "StandardRelax")]
+    StandardRelax,
+    #[sea_orm(string_value = "StandardScoreV2")]
+    StandardScoreV2,
     #[sea_orm(string_value = "Taiko")]
     Taiko,
+    #[sea_orm(string_value = "TaikoRelax")]
+    TaikoRelax,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "pp_version")]
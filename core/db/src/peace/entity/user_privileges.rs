@@ -7,6 +7,7 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
     pub privilege_id: i64,
     pub grantor_id: i32,
     pub created_at: DateTimeWithTimeZone,
@@ -11,6 +11,7 @@ pub struct Model {
     pub display_unicode_name: bool,
     pub scoreboard_ranking_type: RankingType,
     pub invisible_online: bool,
+    pub only_friend_pm_allowed: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -21,6 +21,7 @@ pub struct Model {
     pub country: Option<String>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub silence_end: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -35,6 +36,8 @@ pub enum Relation {
     Leaderboard,
     #[sea_orm(has_many = "super::scores::Entity")]
     Scores,
+    #[sea_orm(has_many = "super::screenshots::Entity")]
+    Screenshots,
     #[sea_orm(has_many = "super::user_pp::Entity")]
     UserPp,
     #[sea_orm(has_many = "super::user_settings::Entity")]
@@ -73,6 +76,12 @@ impl Related<super::scores::Entity> for Entity {
     }
 }
 
+impl Related<super::screenshots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Screenshots.def()
+    }
+}
+
 impl Related<super::user_pp::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserPp.def()
@@ -10,6 +10,20 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(versions::init_tables::Migration),
             Box::new(versions::create_seed_data::Migration),
+            Box::new(versions::add_users_silence_end::Migration),
+            Box::new(versions::add_scores_invisible::Migration),
+            Box::new(versions::add_game_mode_variants::Migration),
+            Box::new(versions::alter_leaderboard_primary_key::Migration),
+            Box::new(versions::alter_chat_messages_targets::Migration),
+            Box::new(versions::add_chat_messages_delivered::Migration),
+            Box::new(versions::create_channel_moderation_logs::Migration),
+            Box::new(versions::create_user_blocks::Migration),
+            Box::new(versions::alter_user_privileges_primary_key::Migration),
+            Box::new(versions::create_public_channels::Migration),
+            Box::new(versions::create_screenshots::Migration),
+            Box::new(
+                versions::add_user_settings_only_friend_pm_allowed::Migration,
+            ),
         ]
     }
 }
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+const INDEX_UNDELIVERED: &str = "IDX_chat_msg_undelivered";
+
+#[derive(Iden)]
+enum ChatMessages {
+    Table,
+    Id,
+    TargetUserId,
+    Delivered,
+}
+
+/// The private messages sent to offline users are kept undelivered until the
+/// users log in.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMessages::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(ChatMessages::Delivered)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(INDEX_UNDELIVERED)
+                    .table(ChatMessages::Table)
+                    .col(ChatMessages::TargetUserId)
+                    .col(ChatMessages::Delivered)
+                    .col(ChatMessages::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ChatMessages::Table)
+                    .name(INDEX_UNDELIVERED)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMessages::Table)
+                    .drop_column(ChatMessages::Delivered)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,102 @@
+use sea_orm_migration::prelude::*;
+
+/// The game modes of the `Relax`, `Autopilot` and `ScoreV2` scores, which are
+/// ranked separately from the vanilla modes.
+#[derive(Iden)]
+enum GameMode {
+    #[iden = "game_mode"]
+    Enum,
+    #[iden = "StandardRelax"]
+    StandardRelax,
+    #[iden = "TaikoRelax"]
+    TaikoRelax,
+    #[iden = "FruitsRelax"]
+    FruitsRelax,
+    #[iden = "StandardAutopilot"]
+    StandardAutopilot,
+    #[iden = "StandardScoreV2"]
+    StandardScoreV2,
+}
+
+#[derive(Iden)]
+enum PgType {
+    Table,
+    Oid,
+    Typname,
+}
+
+#[derive(Iden)]
+enum PgEnum {
+    Table,
+    Enumtypid,
+    Enumlabel,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// The values of the enum type already in the database.
+async fn existing_values(
+    manager: &SchemaManager<'_>,
+) -> Result<Vec<String>, DbErr> {
+    let stmt = Query::select()
+        .column((PgEnum::Table, PgEnum::Enumlabel))
+        .from(PgEnum::Table)
+        .inner_join(
+            PgType::Table,
+            Expr::col((PgType::Table, PgType::Oid))
+                .equals((PgEnum::Table, PgEnum::Enumtypid)),
+        )
+        .and_where(
+            Expr::col((PgType::Table, PgType::Typname))
+                .eq(GameMode::Enum.to_string()),
+        )
+        .to_owned();
+
+    let db = manager.get_connection();
+    db.query_all(db.get_database_backend().build(&stmt))
+        .await?
+        .iter()
+        .map(|row| row.try_get("", &PgEnum::Enumlabel.to_string()))
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// `ADD VALUE IF NOT EXISTS` can't be built, the values already added by
+    /// a partial run are skipped instead.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let existing = existing_values(manager).await?;
+
+        for variant in [
+            GameMode::StandardRelax,
+            GameMode::TaikoRelax,
+            GameMode::FruitsRelax,
+            GameMode::StandardAutopilot,
+            GameMode::StandardScoreV2,
+        ] {
+            if existing.contains(&variant.to_string()) {
+                continue;
+            }
+
+            manager
+                .alter_type(
+                    extension::postgres::Type::alter()
+                        .name(GameMode::Enum)
+                        .add_value(variant),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Postgres can't drop the values of an enum type, and the scores and
+    /// stats using them would be lost, so this migration can't be reverted.
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Err(DbErr::Migration(
+            "the game mode variants can't be removed from the enum type"
+                .to_owned(),
+        ))
+    }
+}
@@ -0,0 +1,152 @@
+use sea_orm_migration::prelude::*;
+
+const FOREIGN_KEY_CHANNEL_ID: &str = "FK_chat_msg_channel_id";
+const FOREIGN_KEY_TARGET_USER_ID: &str = "FK_chat_msg_target_user_id";
+const INDEX_CHANNEL_ID: &str = "IDX_chat_msg_channel_id";
+const INDEX_TARGET_USER_ID: &str = "IDX_chat_msg_target_user_id";
+
+#[derive(Iden)]
+enum ChatMessages {
+    Table,
+    Id,
+    SenderId,
+    ChannelId,
+    TargetUserId,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
+
+/// Private messages have no channel, they are stored with the target user
+/// instead. The instance channels (spectator, multiplayer) are not stored in
+/// `channels`, so the channel foreign key is dropped, and a channel keeps more
+/// than one message, so its index must not be unique.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name(FOREIGN_KEY_CHANNEL_ID)
+                    .table(ChatMessages::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMessages::Table)
+                    .modify_column(
+                        ColumnDef::new(ChatMessages::ChannelId).null(),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(ChatMessages::TargetUserId)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(FOREIGN_KEY_TARGET_USER_ID)
+                    .from(ChatMessages::Table, ChatMessages::TargetUserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ChatMessages::Table)
+                    .name(INDEX_CHANNEL_ID)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(INDEX_CHANNEL_ID)
+                    .table(ChatMessages::Table)
+                    .col(ChatMessages::ChannelId)
+                    .col(ChatMessages::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(INDEX_TARGET_USER_ID)
+                    .table(ChatMessages::Table)
+                    .col(ChatMessages::TargetUserId)
+                    .col(ChatMessages::SenderId)
+                    .col(ChatMessages::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    /// The messages without a channel don't fit the old columns, so
+    /// `channel_id` stays nullable and unchecked instead of deleting them.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ChatMessages::Table)
+                    .name(INDEX_TARGET_USER_ID)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ChatMessages::Table)
+                    .name(INDEX_CHANNEL_ID)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(INDEX_CHANNEL_ID)
+                    .table(ChatMessages::Table)
+                    .col(ChatMessages::ChannelId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name(FOREIGN_KEY_TARGET_USER_ID)
+                    .table(ChatMessages::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMessages::Table)
+                    .drop_column(ChatMessages::TargetUserId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,91 @@
+use super::init_tables::leaderboard::{self, Leaderboard};
+use sea_orm_migration::prelude::*;
+
+/// The leaderboard keeps the best score of every user on a beatmap, so
+/// `user_id` has to be part of the primary key.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum LeaderboardCopy {
+    Table,
+}
+
+fn create<T: IntoTableRef>(table: T) -> TableCreateStatement {
+    Table::create()
+        .table(table)
+        .if_not_exists()
+        .col(ColumnDef::new(Leaderboard::BeatmapId).integer().not_null())
+        .col(ColumnDef::new(Leaderboard::Mode).string().not_null())
+        .col(ColumnDef::new(Leaderboard::RankingType).string().not_null())
+        .col(ColumnDef::new(Leaderboard::UserId).integer().not_null())
+        .col(ColumnDef::new(Leaderboard::ScoreId).big_integer().not_null())
+        .primary_key(
+            Index::create()
+                .col(Leaderboard::BeatmapId)
+                .col(Leaderboard::Mode)
+                .col(Leaderboard::RankingType)
+                .col(Leaderboard::UserId),
+        )
+        .to_owned()
+}
+
+fn copy<F, T>(from: F, to: T) -> Result<InsertStatement, DbErr>
+where
+    F: IntoTableRef,
+    T: IntoTableRef,
+{
+    let columns = || {
+        [
+            Leaderboard::BeatmapId,
+            Leaderboard::Mode,
+            Leaderboard::RankingType,
+            Leaderboard::UserId,
+            Leaderboard::ScoreId,
+        ]
+    };
+
+    Ok(Query::insert()
+        .into_table(to)
+        .columns(columns())
+        .select_from(Query::select().columns(columns()).from(from).to_owned())
+        .map_err(|err| DbErr::Migration(err.to_string()))?
+        .to_owned())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// The primary key can't be altered in place, the entries are moved
+    /// through a copy while the table is created again with the new key.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(create(LeaderboardCopy::Table)).await?;
+        manager
+            .exec_stmt(copy(Leaderboard::Table, LeaderboardCopy::Table)?)
+            .await?;
+
+        manager.drop_table(leaderboard::drop()).await?;
+        manager.create_table(create(Leaderboard::Table)).await?;
+
+        for stmt in leaderboard::create_foreign_keys() {
+            manager.create_foreign_key(stmt).await?;
+        }
+
+        for stmt in leaderboard::create_indexes() {
+            manager.create_index(stmt).await?;
+        }
+
+        manager
+            .exec_stmt(copy(LeaderboardCopy::Table, Leaderboard::Table)?)
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LeaderboardCopy::Table).to_owned())
+            .await
+    }
+
+    /// The entries of more than one user on a beatmap don't fit the old key,
+    /// the new key is kept instead of deleting them.
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use super::init_tables::user_privileges::{self, UserPrivileges};
+use sea_orm_migration::prelude::*;
+
+/// A user can be granted more than one privilege, so `privilege_id` has to be
+/// part of the primary key.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum UserPrivilegesCopy {
+    Table,
+}
+
+fn create<T: IntoTableRef>(table: T) -> TableCreateStatement {
+    Table::create()
+        .table(table)
+        .if_not_exists()
+        .col(ColumnDef::new(UserPrivileges::UserId).integer().not_null())
+        .col(
+            ColumnDef::new(UserPrivileges::PrivilegeId)
+                .big_integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(UserPrivileges::GrantorId).integer().not_null())
+        .col(
+            ColumnDef::new(UserPrivileges::CreatedAt)
+                .timestamp_with_time_zone()
+                .default(Expr::current_timestamp())
+                .not_null(),
+        )
+        .primary_key(
+            Index::create()
+                .col(UserPrivileges::UserId)
+                .col(UserPrivileges::PrivilegeId),
+        )
+        .to_owned()
+}
+
+fn copy<F, T>(from: F, to: T) -> Result<InsertStatement, DbErr>
+where
+    F: IntoTableRef,
+    T: IntoTableRef,
+{
+    let columns = || {
+        [
+            UserPrivileges::UserId,
+            UserPrivileges::PrivilegeId,
+            UserPrivileges::GrantorId,
+            UserPrivileges::CreatedAt,
+        ]
+    };
+
+    Ok(Query::insert()
+        .into_table(to)
+        .columns(columns())
+        .select_from(Query::select().columns(columns()).from(from).to_owned())
+        .map_err(|err| DbErr::Migration(err.to_string()))?
+        .to_owned())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// The primary key can't be altered in place, the privileges are moved
+    /// through a copy while the table is created again with the new key.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(create(UserPrivilegesCopy::Table)).await?;
+        manager
+            .exec_stmt(copy(UserPrivileges::Table, UserPrivilegesCopy::Table)?)
+            .await?;
+
+        manager.drop_table(user_privileges::drop()).await?;
+        manager.create_table(create(UserPrivileges::Table)).await?;
+
+        for stmt in user_privileges::create_foreign_keys() {
+            manager.create_foreign_key(stmt).await?;
+        }
+
+        for stmt in user_privileges::create_indexes() {
+            manager.create_index(stmt).await?;
+        }
+
+        manager
+            .exec_stmt(copy(UserPrivilegesCopy::Table, UserPrivileges::Table)?)
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop().table(UserPrivilegesCopy::Table).to_owned(),
+            )
+            .await
+    }
+
+    /// The users granted more than one privilege don't fit the old key, the
+    /// new key is kept instead of deleting their privileges.
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
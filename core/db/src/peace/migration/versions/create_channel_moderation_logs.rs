@@ -0,0 +1,184 @@
+use sea_orm_migration::prelude::*;
+
+const FOREIGN_KEY_MODERATOR_ID: &str = "FK_channel_mod_log_moderator_id";
+const FOREIGN_KEY_TARGET_USER_ID: &str = "FK_channel_mod_log_target_user_id";
+const INDEX_CHANNEL_ID: &str = "IDX_channel_mod_log_channel_id";
+const INDEX_TARGET_USER_ID: &str = "IDX_channel_mod_log_target_user_id";
+
+#[derive(Iden)]
+enum ChannelModerationAction {
+    #[iden = "channel_moderation_action"]
+    Enum,
+    #[iden = "kick"]
+    Kick,
+    #[iden = "mute"]
+    Mute,
+    #[iden = "unmute"]
+    Unmute,
+}
+
+#[derive(Iden)]
+enum ChannelModerationLogs {
+    Table,
+    Id,
+    ChannelId,
+    ModeratorId,
+    TargetUserId,
+    Action,
+    Reason,
+    MuteEnd,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
+
+/// Audit trail of the moderation actions taken in chat channels.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                extension::postgres::Type::create()
+                    .as_enum(ChannelModerationAction::Enum)
+                    .values([
+                        ChannelModerationAction::Kick,
+                        ChannelModerationAction::Mute,
+                        ChannelModerationAction::Unmute,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelModerationLogs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::Id)
+                            .big_integer()
+                            .primary_key()
+                            .auto_increment()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::ModeratorId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::TargetUserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::Action)
+                            .enumeration(
+                                ChannelModerationAction::Enum,
+                                [
+                                    ChannelModerationAction::Kick,
+                                    ChannelModerationAction::Mute,
+                                    ChannelModerationAction::Unmute,
+                                ],
+                            )
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::Reason)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::MuteEnd)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelModerationLogs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for stmt in [
+            ForeignKey::create()
+                .name(FOREIGN_KEY_MODERATOR_ID)
+                .from(
+                    ChannelModerationLogs::Table,
+                    ChannelModerationLogs::ModeratorId,
+                )
+                .to(Users::Table, Users::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned(),
+            ForeignKey::create()
+                .name(FOREIGN_KEY_TARGET_USER_ID)
+                .from(
+                    ChannelModerationLogs::Table,
+                    ChannelModerationLogs::TargetUserId,
+                )
+                .to(Users::Table, Users::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned(),
+        ] {
+            manager.create_foreign_key(stmt).await?;
+        }
+
+        for stmt in [
+            Index::create()
+                .if_not_exists()
+                .name(INDEX_CHANNEL_ID)
+                .table(ChannelModerationLogs::Table)
+                .col(ChannelModerationLogs::ChannelId)
+                .col(ChannelModerationLogs::Id)
+                .to_owned(),
+            Index::create()
+                .if_not_exists()
+                .name(INDEX_TARGET_USER_ID)
+                .table(ChannelModerationLogs::Table)
+                .col(ChannelModerationLogs::TargetUserId)
+                .col(ChannelModerationLogs::Id)
+                .to_owned(),
+        ] {
+            manager.create_index(stmt).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ChannelModerationLogs::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(
+                extension::postgres::Type::drop()
+                    .if_exists()
+                    .name(ChannelModerationAction::Enum)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,102 @@
+use super::init_tables::{channels::Channels, ChannelType};
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Sequence {
+    #[iden = "setval"]
+    SetVal,
+    #[iden = "pg_get_serial_sequence"]
+    PgGetSerialSequence,
+}
+
+/// The public channels are loaded from `channels` instead of being hardcoded,
+/// the ids of the new channels are generated by the identity column.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Channels::Table)
+                    .columns([
+                        Channels::Id,
+                        Channels::ChannelType,
+                        Channels::Name,
+                        Channels::Description,
+                        Channels::AutoJoin,
+                    ])
+                    .values_panic([
+                        0.into(),
+                        Expr::val(ChannelType::Public.to_string())
+                            .as_enum(ChannelType::Enum),
+                        "#osu".into(),
+                        "default channel".into(),
+                        true.into(),
+                    ])
+                    .values_panic([
+                        1.into(),
+                        Expr::val(ChannelType::Public.to_string())
+                            .as_enum(ChannelType::Enum),
+                        "#peace".into(),
+                        "peace channel".into(),
+                        false.into(),
+                    ])
+                    .on_conflict(OnConflict::new().do_nothing().to_owned())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The identity clause has no builder, it is appended to the column.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channels::Table)
+                    .modify_column(ColumnDef::new(Channels::Id).extra(format!(
+                        "ALTER COLUMN {} ADD GENERATED BY DEFAULT AS IDENTITY",
+                        Channels::Id.to_string()
+                    )))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Start the generated ids after the existing channels.
+        let sequence = Func::cust(Sequence::PgGetSerialSequence).args([
+            Channels::Table.to_string().into(),
+            Channels::Id.to_string().into(),
+        ]);
+        let last_id = Func::coalesce([
+            Func::max(Expr::col(Channels::Id)).into(),
+            Expr::val(1).into(),
+        ]);
+
+        manager
+            .exec_stmt(
+                Query::select()
+                    .expr(
+                        Func::cust(Sequence::SetVal)
+                            .args([sequence.into(), last_id.into()]),
+                    )
+                    .from(Channels::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    /// The channels are kept, only the generated ids are dropped.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channels::Table)
+                    .modify_column(ColumnDef::new(Channels::Id).extra(format!(
+                        "ALTER COLUMN {} DROP IDENTITY IF EXISTS",
+                        Channels::Id.to_string()
+                    )))
+                    .to_owned(),
+            )
+            .await
+    }
+}
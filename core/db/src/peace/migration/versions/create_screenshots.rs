@@ -0,0 +1,117 @@
+use sea_orm_migration::prelude::*;
+
+const FOREIGN_KEY_USER_ID: &str = "FK_screenshots_user_id";
+const INDEX_USER_ID: &str = "IDX_screenshots_user_id";
+const INDEX_HASH: &str = "IDX_screenshots_hash";
+
+#[derive(Iden)]
+enum Screenshots {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Hash,
+    ContentType,
+    Size,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
+
+/// Screenshots uploaded by the osu! clients, the files are stored by the hash
+/// of their content and served by the short name.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Screenshots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Screenshots::Id)
+                            .big_integer()
+                            .primary_key()
+                            .auto_increment()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Screenshots::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Screenshots::Name)
+                            .string_len(64)
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Screenshots::Hash)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Screenshots::ContentType)
+                            .string_len(32)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Screenshots::Size).integer().not_null())
+                    .col(
+                        ColumnDef::new(Screenshots::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(FOREIGN_KEY_USER_ID)
+                    .from(Screenshots::Table, Screenshots::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+
+        for stmt in [
+            Index::create()
+                .if_not_exists()
+                .name(INDEX_USER_ID)
+                .table(Screenshots::Table)
+                .col(Screenshots::UserId)
+                .col(Screenshots::CreatedAt)
+                .to_owned(),
+            Index::create()
+                .if_not_exists()
+                .name(INDEX_HASH)
+                .table(Screenshots::Table)
+                .col(Screenshots::Hash)
+                .to_owned(),
+        ] {
+            manager.create_index(stmt).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop().table(Screenshots::Table).if_exists().to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+const FOREIGN_KEY_USER_ID: &str = "FK_user_blocks_user_id";
+const FOREIGN_KEY_BLOCK_ID: &str = "FK_user_blocks_block_id";
+
+#[derive(Iden)]
+enum UserBlocks {
+    Table,
+    UserId,
+    BlockId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
+
+/// Users blocked by a user, messages from them are dropped.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserBlocks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserBlocks::UserId).integer().not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserBlocks::BlockId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserBlocks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(UserBlocks::UserId)
+                            .col(UserBlocks::BlockId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for stmt in [
+            ForeignKey::create()
+                .name(FOREIGN_KEY_USER_ID)
+                .from(UserBlocks::Table, UserBlocks::UserId)
+                .to(Users::Table, Users::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned(),
+            ForeignKey::create()
+                .name(FOREIGN_KEY_BLOCK_ID)
+                .from(UserBlocks::Table, UserBlocks::BlockId)
+                .to(Users::Table, Users::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned(),
+        ] {
+            manager.create_foreign_key(stmt).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop().table(UserBlocks::Table).if_exists().to_owned(),
+            )
+            .await
+    }
+}
@@ -1,2 +1,14 @@
+pub mod add_chat_messages_delivered;
+pub mod add_game_mode_variants;
+pub mod add_scores_invisible;
+pub mod add_user_settings_only_friend_pm_allowed;
+pub mod add_users_silence_end;
+pub mod alter_chat_messages_targets;
+pub mod alter_leaderboard_primary_key;
+pub mod alter_user_privileges_primary_key;
+pub mod create_channel_moderation_logs;
+pub mod create_public_channels;
+pub mod create_screenshots;
 pub mod create_seed_data;
+pub mod create_user_blocks;
 pub mod init_tables;
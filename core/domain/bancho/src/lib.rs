@@ -1,5 +1,6 @@
 use bitmask_enum::bitmask;
 use enum_primitive_derive::Primitive;
+use num_traits::FromPrimitive;
 use pb_bancho_state::CheckUserTokenRequest;
 use peace_unique_id::Ulid;
 use serde::{Deserialize, Serialize};
@@ -29,6 +30,111 @@ impl GameMode {
     pub fn val(&self) -> u8 {
         *self as u8
     }
+
+    /// Resolve the game mode from the vanilla mode sent by the client and the
+    /// enabled mods, e.g. `Standard` with `Relax` becomes `StandardRelax`.
+    #[inline]
+    pub fn from_params(mode: u8, mods: Mods) -> Option<Self> {
+        let mode = match Self::from_u8(mode)? {
+            mode @ (Self::Standard
+            | Self::Taiko
+            | Self::Fruits
+            | Self::Mania) => mode,
+            _ => return None,
+        };
+
+        Some(match mode {
+            Self::Standard if mods.contains(Mods::Relax) => Self::StandardRelax,
+            Self::Standard if mods.contains(Mods::AutoPilot) => {
+                Self::StandardAutopilot
+            },
+            Self::Standard if mods.contains(Mods::ScoreV2) => {
+                Self::StandardScoreV2
+            },
+            Self::Taiko if mods.contains(Mods::Relax) => Self::TaikoRelax,
+            Self::Fruits if mods.contains(Mods::Relax) => Self::FruitsRelax,
+            mode => mode,
+        })
+    }
+
+    /// Returns the vanilla mode (`Standard`, `Taiko`, `Fruits` or `Mania`).
+    #[inline]
+    pub fn as_vanilla(&self) -> Self {
+        match self {
+            Self::Standard
+            | Self::StandardRelax
+            | Self::StandardAutopilot
+            | Self::StandardScoreV2 => Self::Standard,
+            Self::Taiko | Self::TaikoRelax => Self::Taiko,
+            Self::Fruits | Self::FruitsRelax => Self::Fruits,
+            Self::Mania => Self::Mania,
+        }
+    }
+}
+
+/// Hit counts of a score, the meaning of `geki` and `katu` depends on the
+/// game mode.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct HitCounts {
+    pub n300: i32,
+    pub n100: i32,
+    pub n50: i32,
+    pub geki: i32,
+    pub katu: i32,
+    pub miss: i32,
+}
+
+impl HitCounts {
+    /// Count of the objects that were hit.
+    #[inline]
+    pub fn total_hits(&self, mode: GameMode) -> i32 {
+        match mode.as_vanilla() {
+            GameMode::Mania => {
+                self.n300 + self.n100 + self.n50 + self.geki + self.katu
+            },
+            _ => self.n300 + self.n100 + self.n50,
+        }
+    }
+
+    /// Accuracy in percent (`0.0..=100.0`).
+    #[inline]
+    pub fn accuracy(&self, mode: GameMode) -> f64 {
+        let Self { n300, n100, n50, geki, katu, miss } = *self;
+        let (n300, n100, n50, geki, katu, miss) = (
+            n300 as f64,
+            n100 as f64,
+            n50 as f64,
+            geki as f64,
+            katu as f64,
+            miss as f64,
+        );
+
+        let (hit, total) = match mode.as_vanilla() {
+            GameMode::Taiko => (n300 + n100 * 0.5, n300 + n100 + miss),
+            GameMode::Fruits => {
+                (n300 + n100 + n50, n300 + n100 + n50 + katu + miss)
+            },
+            GameMode::Mania => (
+                (n300 + geki) * 300.0
+                    + katu * 200.0
+                    + n100 * 100.0
+                    + n50 * 50.0,
+                (n300 + geki + katu + n100 + n50 + miss) * 300.0,
+            ),
+            _ => (
+                n300 * 300.0 + n100 * 100.0 + n50 * 50.0,
+                (n300 + n100 + n50 + miss) * 300.0,
+            ),
+        };
+
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        hit / total * 100.0
+    }
 }
 
 #[rustfmt::skip]
@@ -62,6 +168,80 @@ impl<'de> serde::Deserialize<'de> for BanchoPrivileges {
     }
 }
 
+/// Privileges granted to users through the `privileges` table, each row is
+/// matched by its name (case-insensitive).
+#[rustfmt::skip]
+#[derive(Default)]
+#[bitmask(i32)]
+pub enum UserPrivileges {
+    #[default]
+    Normal          = 1 << 0,
+    Restricted      = 1 << 1,
+    Banned          = 1 << 2,
+    Supporter       = 1 << 3,
+    Tournament      = 1 << 4,
+    Moderator       = 1 << 5,
+    Administrator   = 1 << 6,
+    Developer       = 1 << 7,
+}
+
+impl UserPrivileges {
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "normal" => Self::Normal,
+            "restricted" => Self::Restricted,
+            "banned" => Self::Banned,
+            "supporter" => Self::Supporter,
+            "tournament" => Self::Tournament,
+            "moderator" => Self::Moderator,
+            "administrator" => Self::Administrator,
+            "developer" => Self::Developer,
+            _ => return None,
+        })
+    }
+
+    /// Collect privileges from their names, unknown names are ignored.
+    #[inline]
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        names
+            .into_iter()
+            .filter_map(Self::from_name)
+            .fold(Self::Normal, |privileges, p| privileges | p)
+    }
+
+    #[inline]
+    pub fn is_banned(&self) -> bool {
+        self.contains(Self::Banned)
+    }
+
+    #[inline]
+    pub fn is_restricted(&self) -> bool {
+        self.contains(Self::Restricted)
+    }
+
+    /// Moderators, administrators and developers.
+    #[inline]
+    pub fn is_staff(&self) -> bool {
+        self.intersects(Self::Moderator | Self::Administrator | Self::Developer)
+    }
+
+    /// Map onto the privileges understood by the osu! client.
+    #[inline]
+    pub fn bancho_privileges(&self) -> BanchoPrivileges {
+        [
+            (Self::Supporter, BanchoPrivileges::Supporter),
+            (Self::Tournament, BanchoPrivileges::Tournament),
+            (Self::Moderator, BanchoPrivileges::Moderator),
+            (Self::Administrator, BanchoPrivileges::Administrator),
+            (Self::Developer, BanchoPrivileges::Developer),
+        ]
+        .into_iter()
+        .filter(|(p, _)| self.contains(*p))
+        .fold(BanchoPrivileges::Normal, |privileges, (_, p)| privileges | p)
+    }
+}
+
 #[rustfmt::skip]
 #[derive(Default)]
 #[bitmask(u32)]
@@ -187,6 +367,130 @@ impl PresenceFilter {
     }
 }
 
+#[rustfmt::skip]
+#[derive(Default)]
+#[bitmask(u8)]
+pub enum SlotStatus {
+    #[default]
+    Open        = 1 << 0,
+    Locked      = 1 << 1,
+    NotReady    = 1 << 2,
+    Ready       = 1 << 3,
+    NoMap       = 1 << 4,
+    Playing     = 1 << 5,
+    Complete    = 1 << 6,
+    Quit        = 1 << 7,
+
+    HasPlayer = Self::NotReady.bits
+        | Self::Ready.bits
+        | Self::NoMap.bits
+        | Self::Playing.bits
+        | Self::Complete.bits,
+}
+
+impl serde::Serialize for SlotStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(self.bits())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SlotStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        u8::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl SlotStatus {
+    #[inline]
+    pub fn has_player(&self) -> bool {
+        self.intersects(Self::HasPlayer)
+    }
+}
+
+#[rustfmt::skip]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Primitive, Serialize, Deserialize)]
+pub enum MatchTeam {
+    #[default]
+    Neutral   = 0,
+    Blue      = 1,
+    Red       = 2,
+}
+
+impl MatchTeam {
+    #[inline]
+    pub fn val(&self) -> u8 {
+        *self as u8
+    }
+}
+
+#[rustfmt::skip]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Primitive, Serialize, Deserialize)]
+pub enum MatchTeamType {
+    #[default]
+    HeadToHead  = 0,
+    TagCoop     = 1,
+    TeamVs      = 2,
+    TagTeamVs   = 3,
+}
+
+impl MatchTeamType {
+    #[inline]
+    pub fn val(&self) -> u8 {
+        *self as u8
+    }
+
+    #[inline]
+    pub fn is_team_mode(&self) -> bool {
+        matches!(self, Self::TeamVs | Self::TagTeamVs)
+    }
+}
+
+/// The leaderboard type selected in the song select of the osu! client.
+#[rustfmt::skip]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Primitive, Serialize, Deserialize)]
+pub enum LeaderboardType {
+    Local           = 0,
+    #[default]
+    Global          = 1,
+    SelectedMods    = 2,
+    Friends         = 3,
+    Country         = 4,
+}
+
+impl LeaderboardType {
+    #[inline]
+    pub fn val(&self) -> i32 {
+        *self as i32
+    }
+}
+
+/// The rank status of a beatmap as understood by the osu! client.
+#[rustfmt::skip]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BeatmapRankStatus {
+    #[default]
+    NotSubmitted    = -1,
+    Pending         = 0,
+    UpdateAvailable = 1,
+    Ranked          = 2,
+    Approved        = 3,
+    Qualified       = 4,
+    Loved           = 5,
+}
+
+impl BeatmapRankStatus {
+    #[inline]
+    pub fn val(&self) -> i32 {
+        *self as i32
+    }
+}
+
 #[rustfmt::skip]
 #[derive(Debug, Clone, Copy, PartialEq, EnumString, Serialize, Deserialize)]
 pub enum BanchoCountryCode {
@@ -312,3 +616,65 @@ impl std::fmt::Display for BanchoClientToken {
         write!(f, "{}.{}.{}", self.user_id, self.session_id, self.signature)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hits(n300: i32, n100: i32, n50: i32, miss: i32) -> HitCounts {
+        HitCounts { n300, n100, n50, miss, ..Default::default() }
+    }
+
+    #[test]
+    fn test_accuracy_standard() {
+        assert_eq!(hits(100, 0, 0, 0).accuracy(GameMode::Standard), 100.0);
+        assert_eq!(hits(0, 0, 0, 10).accuracy(GameMode::Standard), 0.0);
+
+        let accuracy = hits(90, 6, 3, 1).accuracy(GameMode::Standard);
+        assert!((accuracy - 92.5).abs() < 1e-9);
+
+        // the variants are calculated as the vanilla mode
+        assert_eq!(
+            hits(90, 6, 3, 1).accuracy(GameMode::StandardRelax),
+            accuracy
+        );
+    }
+
+    #[test]
+    fn test_accuracy_taiko() {
+        let accuracy = hits(90, 8, 0, 2).accuracy(GameMode::Taiko);
+        assert!((accuracy - 94.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_accuracy_fruits() {
+        let counts = HitCounts { katu: 5, ..hits(80, 10, 5, 0) };
+        assert!((counts.accuracy(GameMode::Fruits) - 95.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_accuracy_mania() {
+        let counts = HitCounts { geki: 50, katu: 10, ..hits(40, 0, 0, 0) };
+        let expected = (90.0 * 300.0 + 10.0 * 200.0) / (100.0 * 300.0) * 100.0;
+        assert!((counts.accuracy(GameMode::Mania) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_accuracy_without_hits() {
+        for mode in [
+            GameMode::Standard,
+            GameMode::Taiko,
+            GameMode::Fruits,
+            GameMode::Mania,
+        ] {
+            assert_eq!(HitCounts::default().accuracy(mode), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_total_hits() {
+        let counts = HitCounts { geki: 4, katu: 2, ..hits(10, 5, 1, 3) };
+        assert_eq!(counts.total_hits(GameMode::Standard), 16);
+        assert_eq!(counts.total_hits(GameMode::Mania), 22);
+    }
+}
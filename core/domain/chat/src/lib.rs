@@ -2,6 +2,9 @@ use bitmask_enum::bitmask;
 use enum_primitive_derive::Primitive;
 use serde::{Deserialize, Serialize};
 
+pub mod message;
+pub use message::*;
+
 #[rustfmt::skip]
 #[derive(
     Debug,
@@ -26,6 +29,51 @@ pub enum ChannelType {
     Spectaor      = 4,
 }
 
+impl ChannelType {
+    /// Bancho clients only know a single `#spectator` and `#multiplayer`
+    /// channel, so instance channels must be displayed with these names.
+    #[inline]
+    pub fn bancho_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Multiplayer => Some(MultiplayerChannel::BANCHO_NAME),
+            Self::Spectaor => Some(SpectatorChannel::BANCHO_NAME),
+            _ => None,
+        }
+    }
+}
+
+pub struct SpectatorChannel;
+
+impl SpectatorChannel {
+    pub const BANCHO_NAME: &'static str = "#spectator";
+
+    #[inline]
+    pub fn channel_id(host_user_id: i32) -> u64 {
+        ((ChannelType::Spectaor as u64) << 32) | host_user_id as u32 as u64
+    }
+
+    #[inline]
+    pub fn channel_name(host_user_id: i32) -> String {
+        format!("#spec_{host_user_id}")
+    }
+}
+
+pub struct MultiplayerChannel;
+
+impl MultiplayerChannel {
+    pub const BANCHO_NAME: &'static str = "#multiplayer";
+
+    #[inline]
+    pub fn channel_id(match_id: i32) -> u64 {
+        ((ChannelType::Multiplayer as u64) << 32) | match_id as u32 as u64
+    }
+
+    #[inline]
+    pub fn channel_name(match_id: i32) -> String {
+        format!("#mp_{match_id}")
+    }
+}
+
 #[rustfmt::skip]
 #[derive(Default)]
 #[bitmask(i32)]
@@ -35,6 +83,7 @@ pub enum Platform {
     Bancho  = 1,
     Lazer   = 2,
     Web     = 3,
+    Irc     = 4,
 }
 
 impl serde::Serialize for Platform {
@@ -57,16 +106,17 @@ impl<'de> serde::Deserialize<'de> for Platform {
 
 impl Platform {
     #[inline]
-    pub const fn all_platforms() -> [Self; 3] {
-        [Self::Bancho, Self::Lazer, Self::Web]
+    pub const fn all_platforms() -> [Self; 4] {
+        [Self::Bancho, Self::Lazer, Self::Web, Self::Irc]
     }
 
     #[inline]
-    pub fn platforms_array(&self) -> [Option<Self>; 3] {
+    pub fn platforms_array(&self) -> [Option<Self>; 4] {
         [
             self.contains(Self::Bancho).then_some(Self::Bancho),
             self.contains(Self::Lazer).then_some(Self::Lazer),
             self.contains(Self::Web).then_some(Self::Web),
+            self.contains(Self::Irc).then_some(Self::Irc),
         ]
     }
 
@@ -0,0 +1,547 @@
+use serde::{Deserialize, Serialize};
+
+const ACTION_PREFIX: &str = "\x01ACTION ";
+const ACTION_SUFFIX: char = '\x01';
+const ME_COMMAND: &str = "/me ";
+
+const LINK_SCHEMES: &[&str] = &["http://", "https://", "osu://"];
+const OSU_WEB_HOSTS: &[&str] = &["osu.ppy.sh", "old.ppy.sh"];
+
+/// A piece of a chat message, in the order it appears in the message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageToken {
+    Text { text: String },
+    Link { url: String, text: String },
+    Beatmap { id: i32, url: String, text: String },
+    Beatmapset { id: i32, url: String, text: String },
+    Mention { username: String },
+}
+
+impl MessageToken {
+    #[inline]
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Text { text }
+            | Self::Link { text, .. }
+            | Self::Beatmap { text, .. }
+            | Self::Beatmapset { text, .. } => text,
+            Self::Mention { username } => username,
+        }
+    }
+
+    #[inline]
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Self::Link { url, .. }
+            | Self::Beatmap { url, .. }
+            | Self::Beatmapset { url, .. } => Some(url),
+            _ => None,
+        }
+    }
+
+    /// Classify the link, osu! beatmap links become beatmap tokens.
+    pub fn link(url: &str, text: &str) -> Self {
+        let (url, text) = (url.to_owned(), text.to_owned());
+
+        match beatmap_link_id(&url) {
+            Some(BeatmapLinkId::Beatmap(id)) => Self::Beatmap { id, url, text },
+            Some(BeatmapLinkId::Beatmapset(id)) => {
+                Self::Beatmapset { id, url, text }
+            },
+            None => Self::Link { url, text },
+        }
+    }
+}
+
+/// A chat message parsed the way osu! stable renders it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedMessage {
+    /// `/me` message, sent as `\x01ACTION ...\x01` by the bancho clients.
+    pub is_action: bool,
+    /// The message without the action wrapper.
+    pub content: String,
+    pub tokens: Vec<MessageToken>,
+}
+
+impl ParsedMessage {
+    pub fn parse(raw: &str) -> Self {
+        let (is_action, content) = match raw.strip_prefix(ACTION_PREFIX) {
+            Some(action) => {
+                (true, action.strip_suffix(ACTION_SUFFIX).unwrap_or(action))
+            },
+            None => match raw.strip_prefix(ME_COMMAND) {
+                Some(action) => (true, action),
+                None => (false, raw),
+            },
+        };
+
+        Self {
+            is_action,
+            content: content.to_owned(),
+            tokens: tokenize(content),
+        }
+    }
+
+    /// The message as sent to the bancho clients.
+    #[inline]
+    pub fn bancho_content(&self) -> String {
+        if self.is_action {
+            format!("{ACTION_PREFIX}{}{ACTION_SUFFIX}", self.content)
+        } else {
+            self.content.clone()
+        }
+    }
+
+    /// Sanitised html of the message, every user provided text is escaped.
+    pub fn to_html(&self) -> String {
+        let mut html = String::with_capacity(self.content.len() * 2);
+
+        if self.is_action {
+            html.push_str(r#"<span class="action">"#);
+        }
+
+        for token in self.tokens.iter() {
+            match token {
+                MessageToken::Text { text } => escape_html(&mut html, text),
+                MessageToken::Link { url, text } => {
+                    push_anchor(&mut html, url, text, None)
+                },
+                MessageToken::Beatmap { id, url, text } => push_anchor(
+                    &mut html,
+                    url,
+                    text,
+                    Some(("beatmap-link", "data-beatmap-id", *id)),
+                ),
+                MessageToken::Beatmapset { id, url, text } => push_anchor(
+                    &mut html,
+                    url,
+                    text,
+                    Some(("beatmapset-link", "data-beatmapset-id", *id)),
+                ),
+                MessageToken::Mention { username } => {
+                    html.push_str(r#"<span class="mention">@"#);
+                    escape_html(&mut html, username);
+                    html.push_str("</span>");
+                },
+            }
+        }
+
+        if self.is_action {
+            html.push_str("</span>");
+        }
+
+        html
+    }
+}
+
+enum BeatmapLinkId {
+    Beatmap(i32),
+    Beatmapset(i32),
+}
+
+/// Supports `osu://b/{id}`, `osu://s/{id}`, `osu://dl/{id}` and the
+/// `/b`, `/s`, `/beatmaps` and `/beatmapsets` pages of osu! web.
+fn beatmap_link_id(url: &str) -> Option<BeatmapLinkId> {
+    let path = match url.strip_prefix("osu://") {
+        Some(path) => path,
+        None => {
+            let rest = url
+                .strip_prefix("https://")
+                .or_else(|| url.strip_prefix("http://"))?;
+            let (host, path) = rest.split_once('/')?;
+            if !OSU_WEB_HOSTS.contains(&host) {
+                return None;
+            }
+            path
+        },
+    };
+
+    let (kind, rest) = path.split_once('/')?;
+    let leading_id = |s: &str| {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s[..end].parse::<i32>().ok()
+    };
+
+    match kind {
+        "b" | "beatmaps" => leading_id(rest).map(BeatmapLinkId::Beatmap),
+        "s" | "dl" => leading_id(rest).map(BeatmapLinkId::Beatmapset),
+        // `/beatmapsets/{set_id}#{mode}/{beatmap_id}` points to the beatmap.
+        "beatmapsets" => match rest.split_once('#') {
+            Some((_, fragment)) => fragment
+                .split_once('/')
+                .and_then(|(_, id)| leading_id(id))
+                .map(BeatmapLinkId::Beatmap),
+            None => None,
+        }
+        .or_else(|| leading_id(rest).map(BeatmapLinkId::Beatmapset)),
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_link(s: &str) -> bool {
+    LINK_SCHEMES.iter().any(|scheme| {
+        s.len() > scheme.len()
+            && s.get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    })
+}
+
+#[inline]
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// `[url text]`, the text is optional.
+fn parse_bracket_link(s: &str) -> Option<(MessageToken, usize)> {
+    let end = s.find(']')?;
+    let inner = &s[1..end];
+    let (url, text) = inner.split_once(' ').unwrap_or((inner, inner));
+
+    if !is_link(url) || text.trim().is_empty() {
+        return None;
+    }
+
+    Some((MessageToken::link(url, text.trim()), end + 1))
+}
+
+/// `(text)[url]`, the innermost parentheses are the text.
+fn parse_markdown_link(s: &str) -> Option<(MessageToken, usize)> {
+    let text_end = s.find(')')?;
+    let text = &s[1..text_end];
+    if text.contains('(') {
+        return None;
+    }
+    let rest = s[text_end + 1..].strip_prefix('[')?;
+    let url_end = rest.find(']')?;
+    let url = &rest[..url_end];
+
+    if !is_link(url) || url.contains(char::is_whitespace) || text.is_empty() {
+        return None;
+    }
+
+    Some((MessageToken::link(url, text), text_end + 1 + 1 + url_end + 1))
+}
+
+/// A bare url ends at the whitespace, trailing punctuations are not a part
+/// of the url.
+fn parse_bare_link(s: &str) -> Option<(MessageToken, usize)> {
+    if !is_link(s) {
+        return None;
+    }
+
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    let url = s[..end].trim_end_matches(['.', ',', '!', '?', ':', ';', ')']);
+
+    is_link(url).then(|| (MessageToken::link(url, url), url.len()))
+}
+
+/// `@username`
+fn parse_mention(s: &str) -> Option<(MessageToken, usize)> {
+    let name = &s[1..];
+    let end = name.find(|c| !is_username_char(c)).unwrap_or(name.len());
+
+    if end == 0 {
+        return None;
+    }
+
+    Some((MessageToken::Mention { username: name[..end].to_owned() }, end + 1))
+}
+
+fn tokenize(content: &str) -> Vec<MessageToken> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut pos = 0;
+    let mut word_start = true;
+
+    while let Some(c) = content[pos..].chars().next() {
+        let rest = &content[pos..];
+        let parsed = match c {
+            '[' => parse_bracket_link(rest),
+            '(' => parse_markdown_link(rest),
+            '@' if word_start => parse_mention(rest),
+            _ if word_start => parse_bare_link(rest),
+            _ => None,
+        };
+
+        match parsed {
+            Some((token, len)) => {
+                if !text.is_empty() {
+                    tokens.push(MessageToken::Text {
+                        text: std::mem::take(&mut text),
+                    });
+                }
+                tokens.push(token);
+                pos += len;
+                word_start = false;
+            },
+            None => {
+                text.push(c);
+                pos += c.len_utf8();
+                word_start = c.is_whitespace() || c == '(';
+            },
+        }
+    }
+
+    if !text.is_empty() {
+        tokens.push(MessageToken::Text { text });
+    }
+
+    tokens
+}
+
+fn escape_html(html: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+}
+
+fn push_anchor(
+    html: &mut String,
+    url: &str,
+    text: &str,
+    beatmap: Option<(&str, &str, i32)>,
+) {
+    html.push_str("<a href=\"");
+    escape_html(html, url);
+    html.push('"');
+
+    if let Some((class, data_attr, id)) = beatmap {
+        html.push_str(&format!(r#" class="{class}" {data_attr}="{id}""#));
+    }
+
+    html.push_str(r#" rel="nofollow noopener noreferrer" target="_blank">"#);
+    escape_html(html, text);
+    html.push_str("</a>");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn html(raw: &str) -> String {
+        ParsedMessage::parse(raw).to_html()
+    }
+
+    fn text(text: &str) -> MessageToken {
+        MessageToken::Text { text: text.to_owned() }
+    }
+
+    fn link(url: &str, text: &str) -> MessageToken {
+        MessageToken::Link { url: url.to_owned(), text: text.to_owned() }
+    }
+
+    #[test]
+    fn test_action() {
+        let parsed = ParsedMessage::parse("\x01ACTION waves\x01");
+        assert!(parsed.is_action);
+        assert_eq!(parsed.content, "waves");
+        assert_eq!(parsed.bancho_content(), "\x01ACTION waves\x01");
+
+        let parsed = ParsedMessage::parse("/me waves");
+        assert!(parsed.is_action);
+        assert_eq!(parsed.content, "waves");
+        assert_eq!(html("/me <b>"), r#"<span class="action">&lt;b&gt;</span>"#);
+
+        assert!(!ParsedMessage::parse("/mewaves").is_action);
+    }
+
+    #[test]
+    fn test_script_injection_escaped() {
+        assert_eq!(
+            html("<script>alert('x')</script>"),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"
+        );
+        assert_eq!(
+            html(r#"<img src=x onerror="alert(1)">"#),
+            "&lt;img src=x onerror=&quot;alert(1)&quot;&gt;"
+        );
+        assert_eq!(html("@<b>"), "@&lt;b&gt;");
+        assert_eq!(html("a & b"), "a &amp; b");
+    }
+
+    #[test]
+    fn test_attribute_injection_escaped() {
+        let rendered =
+            html(r#"[https://a.com/"onmouseover="alert(1) <b>hi</b>]"#);
+        assert!(rendered.starts_with(
+            r#"<a href="https://a.com/&quot;onmouseover=&quot;alert(1)""#
+        ));
+        assert!(rendered.contains(">&lt;b&gt;hi&lt;/b&gt;</a>"));
+        assert!(!rendered.contains(r#"" onmouseover"#));
+
+        let rendered = html(r#"(x)[https://a.com/"'<>]"#);
+        assert!(
+            rendered.contains(r#"href="https://a.com/&quot;&#39;&lt;&gt;""#)
+        );
+
+        let rendered = html("https://a.com/?q=\"><script>");
+        assert!(!rendered.contains("<script>"));
+        assert!(rendered.contains("&quot;&gt;&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_unsafe_schemes_not_linked() {
+        for raw in [
+            "javascript:alert(1)",
+            "[javascript:alert(1) click]",
+            "(click)[javascript:alert(1)]",
+            "[data:text/html;base64,PHNjcmlwdD4= click]",
+            "(click)[data:text/html,<script>alert(1)</script>]",
+            "[JaVaScRiPt:alert(1) click]",
+            "[ javascript:alert(1) click]",
+        ] {
+            let parsed = ParsedMessage::parse(raw);
+            assert!(
+                parsed.tokens.iter().all(|t| t.url().is_none()),
+                "{raw} is linked"
+            );
+            assert!(!parsed.to_html().contains("<a "), "{raw} is linked");
+        }
+
+        // the scheme has to be at the start
+        let parsed = ParsedMessage::parse("[javascript:https://a.com x]");
+        assert!(parsed.tokens.iter().all(|t| t.url().is_none()));
+
+        // case insensitive schemes
+        assert_eq!(
+            ParsedMessage::parse("HTTPS://a.com").tokens,
+            [link("HTTPS://a.com", "HTTPS://a.com")]
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            ParsedMessage::parse("see [https://a.com a site]!").tokens,
+            [text("see "), link("https://a.com", "a site"), text("!")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("[https://a.com]").tokens,
+            [link("https://a.com", "https://a.com")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("(a site)[https://a.com]").tokens,
+            [link("https://a.com", "a site")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("go https://a.com/x, now").tokens,
+            [
+                text("go "),
+                link("https://a.com/x", "https://a.com/x"),
+                text(", now"),
+            ]
+        );
+
+        // not at a word start
+        assert_eq!(
+            ParsedMessage::parse("xhttps://a.com").tokens,
+            [text("xhttps://a.com")]
+        );
+        // no host
+        assert_eq!(ParsedMessage::parse("https://").tokens, [text("https://")]);
+        // empty text
+        assert_eq!(
+            ParsedMessage::parse("()[https://a.com]").tokens,
+            [text("()"), link("https://a.com", "https://a.com")]
+        );
+    }
+
+    #[test]
+    fn test_nested_and_unbalanced_brackets() {
+        assert_eq!(
+            ParsedMessage::parse("[[https://a.com x]]").tokens,
+            [text("["), link("https://a.com", "x"), text("]")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("[https://a.com x").tokens,
+            [text("[https://a.com x")]
+        );
+        assert_eq!(ParsedMessage::parse("]]][[[").tokens, [text("]]][[[")]);
+        assert_eq!(
+            ParsedMessage::parse("((x)[https://a.com]").tokens,
+            [text("("), link("https://a.com", "x")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("(x)[https://a.com").tokens,
+            [text("(x)[https://a.com")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("(x[https://a.com y])").tokens,
+            [text("(x"), link("https://a.com", "y"), text(")")]
+        );
+        assert_eq!(ParsedMessage::parse("[").tokens, [text("[")]);
+        assert_eq!(ParsedMessage::parse("(").tokens, [text("(")]);
+        assert_eq!(ParsedMessage::parse("@").tokens, [text("@")]);
+    }
+
+    #[test]
+    fn test_unicode_at_link_boundaries() {
+        assert_eq!(
+            ParsedMessage::parse("見てhttps://a.com").tokens,
+            [text("見てhttps://a.com")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("見て https://a.com/譜面 です").tokens,
+            [
+                text("見て "),
+                link("https://a.com/譜面", "https://a.com/譜面"),
+                text(" です"),
+            ]
+        );
+        assert_eq!(
+            ParsedMessage::parse("[https://a.com/é 譜面🎵]").tokens,
+            [link("https://a.com/é", "譜面🎵")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("(🎵)[https://a.com/🎵]").tokens,
+            [link("https://a.com/🎵", "🎵")]
+        );
+        // the unicode space ends the bare link
+        assert_eq!(
+            ParsedMessage::parse("https://a.com\u{3000}x").tokens,
+            [link("https://a.com", "https://a.com"), text("\u{3000}x")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("@ユーザー").tokens,
+            [text("@ユーザー")]
+        );
+        assert_eq!(
+            ParsedMessage::parse("@peppy、").tokens,
+            [
+                MessageToken::Mention { username: "peppy".to_owned() },
+                text("、"),
+            ]
+        );
+        assert_eq!(ParsedMessage::parse("é[").tokens, [text("é[")]);
+    }
+
+    #[test]
+    fn test_beatmap_links() {
+        let tokens = ParsedMessage::parse(
+            "https://osu.ppy.sh/beatmapsets/1#osu/2 osu://s/3 \
+            [https://osu.ppy.sh/b/4?m=0 map] https://evil.com/b/5",
+        )
+        .tokens;
+
+        assert!(matches!(tokens[0], MessageToken::Beatmap { id: 2, .. }));
+        assert!(matches!(tokens[2], MessageToken::Beatmapset { id: 3, .. }));
+        assert!(matches!(tokens[4], MessageToken::Beatmap { id: 4, .. }));
+        assert!(matches!(tokens[6], MessageToken::Link { .. }));
+
+        assert_eq!(
+            html("osu://b/1"),
+            "<a href=\"osu://b/1\" class=\"beatmap-link\" \
+            data-beatmap-id=\"1\" rel=\"nofollow noopener noreferrer\" \
+            target=\"_blank\">osu://b/1</a>"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use pb_pp::PerformanceAttributes as RpcPerformanceAttributes;
+use serde::{Deserialize, Serialize};
+
+/// The weight of each next best performance of the user.
+pub const PP_WEIGHT: f64 = 0.95;
+
+/// Only the best performances of the user are counted.
+pub const PP_WEIGHTED_SCORES: u64 = 100;
+
+/// The calculated star rating and performance of a score.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct PerformanceAttributes {
+    pub pp: f64,
+    pub stars: f64,
+    pub max_combo: i32,
+    pub pp_acc: Option<f64>,
+    pub pp_aim: Option<f64>,
+    pub pp_speed: Option<f64>,
+    pub pp_flashlight: Option<f64>,
+    pub pp_difficulty: Option<f64>,
+}
+
+impl From<RpcPerformanceAttributes> for PerformanceAttributes {
+    fn from(resp: RpcPerformanceAttributes) -> Self {
+        Self {
+            pp: resp.pp,
+            stars: resp.stars,
+            max_combo: resp.max_combo,
+            pp_acc: resp.pp_acc,
+            pp_aim: resp.pp_aim,
+            pp_speed: resp.pp_speed,
+            pp_flashlight: resp.pp_flashlight,
+            pp_difficulty: resp.pp_difficulty,
+        }
+    }
+}
+
+impl From<PerformanceAttributes> for RpcPerformanceAttributes {
+    fn from(val: PerformanceAttributes) -> Self {
+        RpcPerformanceAttributes {
+            pp: val.pp,
+            stars: val.stars,
+            max_combo: val.max_combo,
+            pp_acc: val.pp_acc,
+            pp_aim: val.pp_aim,
+            pp_speed: val.pp_speed,
+            pp_flashlight: val.pp_flashlight,
+            pp_difficulty: val.pp_difficulty,
+        }
+    }
+}
+
+/// Sum up the values of the best performances, which must be sorted in
+/// descending order, each one weighted [`PP_WEIGHT`] times the previous one.
+#[inline]
+pub fn weighted_sum(values: impl IntoIterator<Item = f64>) -> f64 {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| value * PP_WEIGHT.powi(i as i32))
+        .sum()
+}
+
+/// The weighted average of the values, which must be sorted in the order of
+/// their performance.
+#[inline]
+pub fn weighted_average(values: impl IntoIterator<Item = f64>) -> f64 {
+    let (sum, total_weight) = values.into_iter().enumerate().fold(
+        (0.0, 0.0),
+        |(sum, total_weight), (i, value)| {
+            let weight = PP_WEIGHT.powi(i as i32);
+            (sum + value * weight, total_weight + weight)
+        },
+    );
+
+    if total_weight > 0.0 {
+        sum / total_weight
+    } else {
+        0.0
+    }
+}
+
+/// The total pp of the user from the pp of the best performances, which must
+/// be sorted in descending order.
+#[inline]
+pub fn total_pp(values: &[f64]) -> f64 {
+    weighted_sum(values.iter().copied()) + bonus_pp(values.len())
+}
+
+/// The bonus pp awarded by the number of the user's ranked scores.
+#[inline]
+pub fn bonus_pp(scores_count: usize) -> f64 {
+    416.6667 * (1.0 - 0.9994_f64.powi(scores_count as i32))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn approx_eq(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_weighted_sum() {
+        assert_eq!(weighted_sum([]), 0.0);
+        assert!(approx_eq(weighted_sum([100.0]), 100.0));
+        assert!(approx_eq(weighted_sum([100.0, 100.0]), 195.0));
+        assert!(approx_eq(weighted_sum([200.0, 100.0, 50.0]), 340.125));
+    }
+
+    #[test]
+    fn test_weighted_average() {
+        assert_eq!(weighted_average([]), 0.0);
+        assert!(approx_eq(weighted_average([98.5]), 98.5));
+        assert!(approx_eq(weighted_average([90.0, 90.0, 90.0]), 90.0));
+
+        // the earlier values weigh more
+        let average = weighted_average([100.0, 90.0]);
+        assert!(approx_eq(average, (100.0 + 90.0 * PP_WEIGHT) / 1.95));
+        assert!(average > 95.0);
+    }
+
+    #[test]
+    fn test_bonus_pp() {
+        assert_eq!(bonus_pp(0), 0.0);
+        assert!(approx_eq(bonus_pp(1), 416.6667 * 0.0006));
+        assert!(bonus_pp(100) < bonus_pp(1000));
+
+        // approaches 416.6667 with the count of the scores
+        assert!(bonus_pp(100_000) <= 416.6667);
+        assert!(approx_eq(bonus_pp(100_000), 416.6667));
+    }
+
+    #[test]
+    fn test_total_pp() {
+        assert_eq!(total_pp(&[]), 0.0);
+        assert!(approx_eq(total_pp(&[100.0]), 100.0 + bonus_pp(1)));
+        assert!(approx_eq(
+            total_pp(&[200.0, 100.0, 50.0]),
+            340.125 + bonus_pp(3)
+        ));
+
+        let values = vec![100.0; PP_WEIGHTED_SCORES as usize];
+        let max = 100.0 / (1.0 - PP_WEIGHT);
+        assert!(total_pp(&values) < max + bonus_pp(values.len()));
+    }
+}
@@ -16,7 +16,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn build_all(builder: ProtoBuilder) -> Result<(), Box<dyn std::error::Error>> {
     builder.build("base")?;
     builder.build("frame.logs")?;
-    builder.build("services.chat")?;
+    builder.build_with_attrs(
+        "services.chat",
+        &[StructAttr::new(
+            SERDE,
+            &["ChatMessageToken", "ChatHistoryMessage", "ChatHistory"],
+        )],
+    )?;
     builder.build("services.bancho")?;
     builder.build_with_attrs(
         "services.bancho_state",
@@ -41,6 +47,11 @@ fn build_all(builder: ProtoBuilder) -> Result<(), Box<dyn std::error::Error>> {
         )],
     )?;
 
+    builder.build_with_attrs(
+        "services.pp",
+        &[StructAttr::new(SERDE, &["PerformanceAttributes"])],
+    )?;
+
     builder.build("services.signature")?;
     builder.build("services.events")?;
 
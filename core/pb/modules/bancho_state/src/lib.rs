@@ -56,13 +56,13 @@ mod peace {
                 }
             }
 
-            impl ToString for UserQuery {
-                fn to_string(&self) -> String {
+            impl std::fmt::Display for UserQuery {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                     match self {
-                        Self::SessionId(val) => val.to_string(),
-                        Self::UserId(val) => val.to_string(),
-                        Self::Username(val) => val.to_owned(),
-                        Self::UsernameUnicode(val) => val.to_owned(),
+                        Self::SessionId(val) => val.fmt(f),
+                        Self::UserId(val) => val.fmt(f),
+                        Self::Username(val) => val.fmt(f),
+                        Self::UsernameUnicode(val) => val.fmt(f),
                     }
                 }
             }
@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+#![allow(non_snake_case)]
+
+mod peace {
+    pub mod services {
+        pub mod pp {
+            include!("../../../generated/peace.services.pp.rs");
+
+            pub const PP_DESCRIPTOR_SET: &[u8] = include_bytes!(
+                "../../../generated/peace.services.pp.descriptor.bin"
+            );
+        }
+    }
+}
+
+pub use peace::services::pp::*;
@@ -0,0 +1,110 @@
+use crate::BeatmapRatingsError;
+use peace_db::{
+    peace::{entity::beatmap_ratings, Peace},
+    sea_query::{Expr, OnConflict},
+    *,
+};
+use std::sync::Arc;
+
+pub type DynBeatmapRatingsRepository =
+    Arc<dyn BeatmapRatingsRepository + Send + Sync>;
+
+/// Each user has one vote per beatmap, from 1 to 10.
+#[async_trait]
+pub trait BeatmapRatingsRepository {
+    async fn get_user_rating(
+        &self,
+        user_id: i32,
+        map_md5: &str,
+    ) -> Result<Option<i16>, BeatmapRatingsError>;
+
+    /// Returns `false` if the user has already rated the beatmap, the
+    /// previous vote is kept.
+    async fn rate_beatmap(
+        &self,
+        user_id: i32,
+        map_md5: &str,
+        rating: i16,
+    ) -> Result<bool, BeatmapRatingsError>;
+
+    /// The average rating of the beatmap, `None` if it has no votes.
+    async fn get_average_rating(
+        &self,
+        map_md5: &str,
+    ) -> Result<Option<f64>, BeatmapRatingsError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BeatmapRatingsRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl BeatmapRatingsRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> Self {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynBeatmapRatingsRepository {
+        Arc::new(self) as DynBeatmapRatingsRepository
+    }
+}
+
+#[async_trait]
+impl BeatmapRatingsRepository for BeatmapRatingsRepositoryImpl {
+    async fn get_user_rating(
+        &self,
+        user_id: i32,
+        map_md5: &str,
+    ) -> Result<Option<i16>, BeatmapRatingsError> {
+        Ok(beatmap_ratings::Entity::find_by_id((user_id, map_md5.to_owned()))
+            .one(self.conn.as_ref())
+            .await?
+            .map(|r| r.rating))
+    }
+
+    async fn rate_beatmap(
+        &self,
+        user_id: i32,
+        map_md5: &str,
+        rating: i16,
+    ) -> Result<bool, BeatmapRatingsError> {
+        let res =
+            beatmap_ratings::Entity::insert(beatmap_ratings::ActiveModel {
+                user_id: Set(user_id),
+                map_md5: Set(map_md5.to_owned()),
+                rating: Set(rating),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    beatmap_ratings::Column::UserId,
+                    beatmap_ratings::Column::MapMd5,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec(self.conn.as_ref())
+            .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            // already rated
+            Err(DbErr::RecordNotInserted) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get_average_rating(
+        &self,
+        map_md5: &str,
+    ) -> Result<Option<f64>, BeatmapRatingsError> {
+        Ok(beatmap_ratings::Entity::find()
+            .select_only()
+            .column_as(Expr::cust("AVG(rating)::FLOAT8"), "rating")
+            .filter(beatmap_ratings::Column::MapMd5.eq(map_md5))
+            .into_tuple::<Option<f64>>()
+            .one(self.conn.as_ref())
+            .await?
+            .flatten())
+    }
+}
@@ -0,0 +1,394 @@
+use crate::GetBeatmapError;
+use peace_db::{
+    peace::{entity::beatmaps, Peace},
+    *,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+use tools::{
+    atomic::{AtomicValue, U64},
+    cache::CachedAtomic,
+};
+
+pub type DynBeatmapsRepository = Arc<dyn BeatmapsRepository + Send + Sync>;
+
+/// Cached beatmaps are refetched from the database after this many seconds.
+pub const BEATMAP_CACHE_EXPIRES: u64 = 300;
+
+/// Max count of the cached beatmaps.
+pub const BEATMAP_CACHE_CAPACITY: usize = 10_000;
+
+#[async_trait]
+pub trait BeatmapsRepository {
+    async fn get_beatmap_by_md5(
+        &self,
+        md5: &str,
+    ) -> Result<beatmaps::Model, GetBeatmapError>;
+
+    async fn get_beatmap_by_bid(
+        &self,
+        bid: i32,
+    ) -> Result<beatmaps::Model, GetBeatmapError>;
+
+    /// Get all difficulties of the beatmapset.
+    async fn get_beatmaps_by_sid(
+        &self,
+        sid: i32,
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError>;
+
+    /// Get the beatmaps by their `.osu` file names, the missing ones are
+    /// skipped.
+    async fn get_beatmaps_by_file_names(
+        &self,
+        file_names: &[String],
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError>;
+
+    /// Get the beatmaps by their ids, the missing ones are skipped.
+    async fn get_beatmaps_by_bids(
+        &self,
+        bids: &[i32],
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError>;
+
+    /// Insert the beatmap, or update the stored one with the same id.
+    async fn save_beatmap(
+        &self,
+        beatmap: beatmaps::Model,
+    ) -> Result<(), GetBeatmapError>;
+}
+
+/// The beatmaps recently read from the database, keyed by md5 and indexed by
+/// the beatmap id. When it's full, the expired beatmaps are evicted first,
+/// then the least recently cached one.
+pub struct BeatmapsCache {
+    pub beatmaps: RwLock<HashMap<String, Arc<CachedAtomic<beatmaps::Model>>>>,
+    pub md5_by_bid: RwLock<HashMap<i32, String>>,
+    pub expires: u64,
+    pub capacity: usize,
+}
+
+impl BeatmapsCache {
+    #[inline]
+    pub fn new(expires: u64, capacity: usize) -> Self {
+        Self {
+            beatmaps: RwLock::new(HashMap::new()),
+            md5_by_bid: RwLock::new(HashMap::new()),
+            expires,
+            capacity,
+        }
+    }
+
+    /// Get the cached beatmap if it has not expired.
+    pub async fn get(&self, md5: &str) -> Option<Arc<beatmaps::Model>> {
+        self.beatmaps
+            .read()
+            .await
+            .get(md5)?
+            .get()
+            .filter(|cached| !cached.expired)
+            .map(|cached| cached.cache)
+    }
+
+    pub async fn get_by_bid(&self, bid: i32) -> Option<Arc<beatmaps::Model>> {
+        let md5 = self.md5_by_bid.read().await.get(&bid)?.clone();
+        self.get(&md5).await
+    }
+
+    /// Cache the beatmap, replacing the stale entry if the beatmap has been
+    /// updated with a new md5.
+    pub async fn cache(&self, beatmap: beatmaps::Model) {
+        let bid = beatmap.bid;
+        let md5 = beatmap.md5.clone();
+        let beatmap = Arc::new(beatmap);
+
+        let stale_md5 = self.md5_by_bid.write().await.insert(bid, md5.clone());
+
+        let mut beatmaps = self.beatmaps.write().await;
+        if let Some(stale_md5) = stale_md5.filter(|stale| stale != &md5) {
+            beatmaps.remove(&stale_md5);
+        }
+
+        match beatmaps.get(&md5) {
+            Some(cached) => {
+                cached.set(Some(beatmap));
+            },
+            None => {
+                if beatmaps.len() >= self.capacity {
+                    self.evict(&mut beatmaps).await;
+                }
+
+                let cached = CachedAtomic::new(U64::new(self.expires));
+                cached.set(Some(beatmap));
+                beatmaps.insert(md5, Arc::new(cached));
+            },
+        }
+    }
+
+    /// Make room for a new beatmap.
+    async fn evict(
+        &self,
+        beatmaps: &mut HashMap<String, Arc<CachedAtomic<beatmaps::Model>>>,
+    ) {
+        let mut evicted = Vec::new();
+
+        beatmaps.retain(|_, cached| {
+            let expired = cached.snapshot_expired();
+            if expired {
+                evicted.extend(cached.inner.val());
+            }
+            !expired
+        });
+
+        if beatmaps.len() >= self.capacity {
+            let oldest = beatmaps
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_update.val())
+                .map(|(md5, _)| md5.clone());
+
+            if let Some(cached) = oldest.and_then(|md5| beatmaps.remove(&md5)) {
+                evicted.extend(cached.inner.val());
+            }
+        }
+
+        let mut md5_by_bid = self.md5_by_bid.write().await;
+        for beatmap in evicted {
+            if md5_by_bid.get(&beatmap.bid) == Some(&beatmap.md5) {
+                md5_by_bid.remove(&beatmap.bid);
+            }
+        }
+    }
+
+    pub async fn remove(&self, md5: &str) {
+        if let Some(cached) = self.beatmaps.write().await.remove(md5) {
+            if let Some(beatmap) = cached.inner.val() {
+                self.md5_by_bid.write().await.remove(&beatmap.bid);
+            }
+        }
+    }
+
+    pub async fn clear(&self) {
+        self.beatmaps.write().await.clear();
+        self.md5_by_bid.write().await.clear();
+    }
+}
+
+impl Default for BeatmapsCache {
+    #[inline]
+    fn default() -> Self {
+        Self::new(BEATMAP_CACHE_EXPIRES, BEATMAP_CACHE_CAPACITY)
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct BeatmapsRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+    pub cache: Arc<BeatmapsCache>,
+}
+
+impl BeatmapsRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> BeatmapsRepositoryImpl {
+        Self { conn, cache: Arc::default() }
+    }
+
+    pub fn into_service(self) -> DynBeatmapsRepository {
+        Arc::new(self) as DynBeatmapsRepository
+    }
+
+    async fn cache_all(&self, beatmaps: &[beatmaps::Model]) {
+        for beatmap in beatmaps {
+            self.cache.cache(beatmap.clone()).await;
+        }
+    }
+}
+
+#[async_trait]
+impl BeatmapsRepository for BeatmapsRepositoryImpl {
+    async fn get_beatmap_by_md5(
+        &self,
+        md5: &str,
+    ) -> Result<beatmaps::Model, GetBeatmapError> {
+        if let Some(beatmap) = self.cache.get(md5).await {
+            return Ok(beatmap.as_ref().clone());
+        }
+
+        let beatmap = beatmaps::Entity::find()
+            .filter(beatmaps::Column::Md5.eq(md5))
+            .one(self.conn.as_ref())
+            .await?
+            .ok_or(GetBeatmapError::BeatmapNotExists)?;
+
+        self.cache.cache(beatmap.clone()).await;
+        Ok(beatmap)
+    }
+
+    async fn get_beatmap_by_bid(
+        &self,
+        bid: i32,
+    ) -> Result<beatmaps::Model, GetBeatmapError> {
+        if let Some(beatmap) = self.cache.get_by_bid(bid).await {
+            return Ok(beatmap.as_ref().clone());
+        }
+
+        let beatmap = beatmaps::Entity::find_by_id(bid)
+            .one(self.conn.as_ref())
+            .await?
+            .ok_or(GetBeatmapError::BeatmapNotExists)?;
+
+        self.cache.cache(beatmap.clone()).await;
+        Ok(beatmap)
+    }
+
+    async fn get_beatmaps_by_sid(
+        &self,
+        sid: i32,
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+        let beatmaps = beatmaps::Entity::find()
+            .filter(beatmaps::Column::Sid.eq(sid))
+            .order_by_asc(beatmaps::Column::Bid)
+            .all(self.conn.as_ref())
+            .await?;
+
+        self.cache_all(&beatmaps).await;
+        Ok(beatmaps)
+    }
+
+    async fn get_beatmaps_by_file_names(
+        &self,
+        file_names: &[String],
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+        if file_names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let beatmaps = beatmaps::Entity::find()
+            .filter(beatmaps::Column::FileName.is_in(file_names.to_vec()))
+            .all(self.conn.as_ref())
+            .await?;
+
+        self.cache_all(&beatmaps).await;
+        Ok(beatmaps)
+    }
+
+    async fn get_beatmaps_by_bids(
+        &self,
+        bids: &[i32],
+    ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+        let mut beatmaps = Vec::with_capacity(bids.len());
+        let mut missing = Vec::new();
+
+        for bid in bids {
+            match self.cache.get_by_bid(*bid).await {
+                Some(beatmap) => beatmaps.push(beatmap.as_ref().clone()),
+                None => missing.push(*bid),
+            }
+        }
+
+        if !missing.is_empty() {
+            let fetched = beatmaps::Entity::find()
+                .filter(beatmaps::Column::Bid.is_in(missing))
+                .all(self.conn.as_ref())
+                .await?;
+
+            self.cache_all(&fetched).await;
+            beatmaps.extend(fetched);
+        }
+
+        Ok(beatmaps)
+    }
+
+    async fn save_beatmap(
+        &self,
+        beatmap: beatmaps::Model,
+    ) -> Result<(), GetBeatmapError> {
+        beatmaps::Entity::insert(beatmaps::ActiveModel::from(beatmap.clone()))
+            .on_conflict(
+                sea_query::OnConflict::column(beatmaps::Column::Bid)
+                    .update_columns(
+                        beatmaps::Column::iter().filter(|col| {
+                            !matches!(col, beatmaps::Column::Bid)
+                        }),
+                    )
+                    .to_owned(),
+            )
+            .exec(self.conn.as_ref())
+            .await?;
+
+        self.cache.cache(beatmap).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peace_db::{
+        peace::entity::sea_orm_active_enums::{GameMode, RankStatus},
+        prelude::{DateTimeWithTimeZone, Decimal},
+    };
+
+    fn beatmap(bid: i32) -> beatmaps::Model {
+        beatmaps::Model {
+            bid,
+            sid: 1,
+            md5: format!("md5-{bid}"),
+            title: "Test".to_owned(),
+            file_name: format!("{bid}.osu"),
+            artist: "Peace".to_owned(),
+            diff_name: "Easy".to_owned(),
+            origin_server: "test".to_owned(),
+            mapper_name: "Tester".to_owned(),
+            mapper_id: "1".to_owned(),
+            rank_status: RankStatus::Ranked,
+            game_mode: GameMode::Standard,
+            stars: Decimal::default(),
+            bpm: Decimal::default(),
+            cs: Decimal::default(),
+            od: Decimal::default(),
+            ar: Decimal::default(),
+            hp: Decimal::default(),
+            length: 0,
+            length_drain: 0,
+            source: None,
+            tags: None,
+            genre_id: None,
+            language_id: None,
+            storyboard: None,
+            video: None,
+            object_count: None,
+            slider_count: None,
+            spinner_count: None,
+            max_combo: None,
+            immutable: false,
+            last_update: DateTimeWithTimeZone::default(),
+            upload_time: DateTimeWithTimeZone::default(),
+            approved_time: None,
+            updated_at: DateTimeWithTimeZone::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_bounded() {
+        let cache = BeatmapsCache::new(BEATMAP_CACHE_EXPIRES, 2);
+
+        for bid in 1..=3 {
+            cache.cache(beatmap(bid)).await;
+        }
+
+        assert_eq!(cache.beatmaps.read().await.len(), 2);
+        assert_eq!(cache.md5_by_bid.read().await.len(), 2);
+        assert!(cache.get_by_bid(3).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cache_replaces_stale_md5() {
+        let cache = BeatmapsCache::new(BEATMAP_CACHE_EXPIRES, 2);
+        cache.cache(beatmap(1)).await;
+
+        let updated =
+            beatmaps::Model { md5: "updated".to_owned(), ..beatmap(1) };
+        cache.cache(updated).await;
+
+        assert!(cache.get("md5-1").await.is_none());
+        assert_eq!(cache.get_by_bid(1).await.unwrap().md5, "updated");
+        assert_eq!(cache.beatmaps.read().await.len(), 1);
+    }
+}
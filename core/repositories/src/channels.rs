@@ -0,0 +1,136 @@
+use crate::ChannelsError;
+use peace_db::{
+    peace::{
+        entity::{
+            channel_moderation_logs, channel_privileges, channels, privileges,
+            sea_orm_active_enums::ChannelType,
+        },
+        Peace,
+    },
+    *,
+};
+use std::sync::Arc;
+
+pub type DynChannelsRepository = Arc<dyn ChannelsRepository + Send + Sync>;
+
+#[async_trait]
+pub trait ChannelsRepository {
+    /// Get the channels of the type ordered by id.
+    async fn get_channels(
+        &self,
+        channel_type: ChannelType,
+    ) -> Result<Vec<channels::Model>, ChannelsError>;
+
+    /// Create the channel, the id is generated if not set.
+    async fn create_channel(
+        &self,
+        channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError>;
+
+    /// Update the set columns of the channel.
+    async fn update_channel(
+        &self,
+        channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError>;
+
+    /// Delete the channel with its users and privileges.
+    async fn delete_channel(&self, channel_id: i64)
+        -> Result<(), ChannelsError>;
+
+    /// Get the privileges required by the handles of all channels, with the
+    /// required privileges.
+    async fn get_channel_privileges(
+        &self,
+    ) -> Result<
+        Vec<(channel_privileges::Model, Option<privileges::Model>)>,
+        ChannelsError,
+    >;
+
+    async fn save_moderation_log(
+        &self,
+        log: channel_moderation_logs::ActiveModel,
+    ) -> Result<(), ChannelsError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ChannelsRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl ChannelsRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> ChannelsRepositoryImpl {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynChannelsRepository {
+        Arc::new(self) as DynChannelsRepository
+    }
+}
+
+#[async_trait]
+impl ChannelsRepository for ChannelsRepositoryImpl {
+    async fn get_channels(
+        &self,
+        channel_type: ChannelType,
+    ) -> Result<Vec<channels::Model>, ChannelsError> {
+        Ok(channels::Entity::find()
+            .filter(channels::Column::ChannelType.eq(channel_type))
+            .order_by_asc(channels::Column::Id)
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn create_channel(
+        &self,
+        channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError> {
+        Ok(channels::Entity::insert(channel)
+            .exec_with_returning(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn update_channel(
+        &self,
+        channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError> {
+        Ok(channel.update(self.conn.as_ref()).await?)
+    }
+
+    async fn delete_channel(
+        &self,
+        channel_id: i64,
+    ) -> Result<(), ChannelsError> {
+        let res = channels::Entity::delete_by_id(channel_id)
+            .exec(self.conn.as_ref())
+            .await?;
+
+        if res.rows_affected == 0 {
+            return Err(ChannelsError::ChannelNotExists);
+        }
+
+        Ok(())
+    }
+
+    async fn get_channel_privileges(
+        &self,
+    ) -> Result<
+        Vec<(channel_privileges::Model, Option<privileges::Model>)>,
+        ChannelsError,
+    > {
+        Ok(channel_privileges::Entity::find()
+            .find_also_related(privileges::Entity)
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn save_moderation_log(
+        &self,
+        log: channel_moderation_logs::ActiveModel,
+    ) -> Result<(), ChannelsError> {
+        channel_moderation_logs::Entity::insert(log)
+            .exec_without_returning(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use crate::ChatMessagesError;
+use peace_db::{
+    peace::{
+        entity::{chat_messages, users},
+        Peace,
+    },
+    sea_query::Expr,
+    *,
+};
+use std::sync::Arc;
+
+pub type DynChatMessagesRepository =
+    Arc<dyn ChatMessagesRepository + Send + Sync>;
+
+/// Rows per insert statement, keeps the bind parameters of a batch under the
+/// postgres limit.
+const INSERT_CHUNK_SIZE: usize = 1000;
+
+#[async_trait]
+pub trait ChatMessagesRepository {
+    /// Insert the messages in batches, returns the number of saved messages.
+    async fn save_messages(
+        &self,
+        messages: Vec<chat_messages::ActiveModel>,
+    ) -> Result<usize, ChatMessagesError>;
+
+    /// Save a private message sent to an offline user right away, it is kept
+    /// undelivered until the user logs in. Returns `false` without saving it
+    /// if the target already has `max_undelivered` undelivered messages. The
+    /// target's `users` row is locked while counting and inserting, so the
+    /// concurrent messages to the same user can't exceed the cap.
+    async fn save_undelivered_message(
+        &self,
+        message: chat_messages::ActiveModel,
+        max_undelivered: u64,
+    ) -> Result<bool, ChatMessagesError>;
+
+    /// Get the undelivered private messages of the user in the order they
+    /// were sent, with their senders.
+    async fn get_undelivered_messages(
+        &self,
+        target_user_id: i32,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    >;
+
+    /// Mark the messages delivered once they are queued to the user.
+    async fn mark_messages_delivered(
+        &self,
+        message_ids: Vec<i64>,
+    ) -> Result<(), ChatMessagesError>;
+
+    /// Get up to `limit` messages of the channel sent before the message
+    /// `before_id` (the latest ones if not set), with their senders, in the
+    /// order they were sent.
+    async fn get_channel_messages(
+        &self,
+        channel_id: i64,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    >;
+
+    /// Get up to `limit` private messages between the two users sent before
+    /// the message `before_id` (the latest ones if not set), with their
+    /// senders, in the order they were sent.
+    async fn get_private_messages(
+        &self,
+        user_id: i32,
+        other_user_id: i32,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    >;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ChatMessagesRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl ChatMessagesRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> ChatMessagesRepositoryImpl {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynChatMessagesRepository {
+        Arc::new(self) as DynChatMessagesRepository
+    }
+}
+
+#[async_trait]
+impl ChatMessagesRepository for ChatMessagesRepositoryImpl {
+    async fn save_messages(
+        &self,
+        messages: Vec<chat_messages::ActiveModel>,
+    ) -> Result<usize, ChatMessagesError> {
+        let count = messages.len();
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let txn = self.conn.as_ref().begin().await?;
+
+        for chunk in messages.chunks(INSERT_CHUNK_SIZE) {
+            chat_messages::Entity::insert_many(chunk.to_vec())
+                .exec_without_returning(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(count)
+    }
+
+    async fn save_undelivered_message(
+        &self,
+        mut message: chat_messages::ActiveModel,
+        max_undelivered: u64,
+    ) -> Result<bool, ChatMessagesError> {
+        message.delivered = Set(false);
+
+        let target_user_id = message.target_user_id.clone().take().flatten();
+
+        let txn = self.conn.as_ref().begin().await?;
+
+        // concurrent messages to the same user wait here, so each of them
+        // counts the ones saved before it
+        users::Entity::find()
+            .filter(users::Column::Id.eq(target_user_id))
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+
+        let undelivered = chat_messages::Entity::find()
+            .filter(chat_messages::Column::TargetUserId.eq(target_user_id))
+            .filter(chat_messages::Column::Delivered.eq(false))
+            .count(&txn)
+            .await?;
+
+        if undelivered >= max_undelivered {
+            return Ok(false);
+        }
+
+        chat_messages::Entity::insert(message)
+            .exec_without_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn get_undelivered_messages(
+        &self,
+        target_user_id: i32,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        Ok(chat_messages::Entity::find()
+            .find_also_related(users::Entity)
+            .filter(chat_messages::Column::TargetUserId.eq(target_user_id))
+            .filter(chat_messages::Column::Delivered.eq(false))
+            .order_by_asc(chat_messages::Column::Id)
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn mark_messages_delivered(
+        &self,
+        message_ids: Vec<i64>,
+    ) -> Result<(), ChatMessagesError> {
+        if message_ids.is_empty() {
+            return Ok(());
+        }
+
+        chat_messages::Entity::update_many()
+            .col_expr(chat_messages::Column::Delivered, Expr::value(true))
+            .filter(chat_messages::Column::Id.is_in(message_ids))
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_channel_messages(
+        &self,
+        channel_id: i64,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        let mut query = chat_messages::Entity::find()
+            .find_also_related(users::Entity)
+            .filter(chat_messages::Column::ChannelId.eq(channel_id));
+
+        if let Some(before_id) = before_id {
+            query = query.filter(chat_messages::Column::Id.lt(before_id));
+        }
+
+        let mut messages = query
+            .order_by_desc(chat_messages::Column::Id)
+            .limit(limit)
+            .all(self.conn.as_ref())
+            .await?;
+
+        messages.reverse();
+
+        Ok(messages)
+    }
+
+    async fn get_private_messages(
+        &self,
+        user_id: i32,
+        other_user_id: i32,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        let mut query = chat_messages::Entity::find()
+            .find_also_related(users::Entity)
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(chat_messages::Column::SenderId.eq(user_id))
+                            .add(
+                                chat_messages::Column::TargetUserId
+                                    .eq(other_user_id),
+                            ),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(
+                                chat_messages::Column::SenderId
+                                    .eq(other_user_id),
+                            )
+                            .add(
+                                chat_messages::Column::TargetUserId.eq(user_id),
+                            ),
+                    ),
+            );
+
+        if let Some(before_id) = before_id {
+            query = query.filter(chat_messages::Column::Id.lt(before_id));
+        }
+
+        let mut messages = query
+            .order_by_desc(chat_messages::Column::Id)
+            .limit(limit)
+            .all(self.conn.as_ref())
+            .await?;
+
+        messages.reverse();
+
+        Ok(messages)
+    }
+}
@@ -1,4 +1,24 @@
-use peace_db::DbErr;
+use serde::{Deserialize, Serialize};
+use peace_db::{DbErr, RuntimeErr};
+
+/// Sqlstate of the unique violations.
+pub const UNIQUE_VIOLATION: &str = "23505";
+
+/// The constraint name if the error is a unique violation, e.g. the
+/// `users_email_key` of the duplicated email.
+pub fn unique_violation(err: &DbErr) -> Option<&str> {
+    let (DbErr::Exec(RuntimeErr::SqlxError(err))
+    | DbErr::Query(RuntimeErr::SqlxError(err))) = err else {
+        return None;
+    };
+
+    let err = err.as_database_error()?;
+    if err.code().as_deref() != Some(UNIQUE_VIOLATION) {
+        return None;
+    }
+
+    Some(err.constraint().unwrap_or_default())
+}
 
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 pub enum GetUserError {
@@ -13,3 +33,123 @@ impl From<DbErr> for GetUserError {
         Self::DbErr(err.to_string())
     }
 }
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum FollowersError {
+    #[error("can not add yourself as a friend")]
+    FollowSelf,
+    #[error("can not block yourself")]
+    BlockSelf,
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for FollowersError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum GetBeatmapError {
+    #[error("beatmap not exists")]
+    BeatmapNotExists,
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for GetBeatmapError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ScoresError {
+    #[error("score already submitted")]
+    DuplicateScore,
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for ScoresError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum LeaderboardError {
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for LeaderboardError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ChatMessagesError {
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for ChatMessagesError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ChannelsError {
+    #[error("channel not exists")]
+    ChannelNotExists,
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for ChannelsError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ScreenshotsError {
+    #[error("screenshot quota exceeded")]
+    QuotaExceeded,
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for ScreenshotsError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum FavouriteBeatmapsError {
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for FavouriteBeatmapsError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum BeatmapRatingsError {
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for BeatmapRatingsError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
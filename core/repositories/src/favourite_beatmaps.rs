@@ -0,0 +1,90 @@
+use crate::FavouriteBeatmapsError;
+use peace_db::{
+    peace::{entity::favourite_beatmaps, Peace},
+    sea_query::OnConflict,
+    *,
+};
+use std::sync::Arc;
+
+pub type DynFavouriteBeatmapsRepository =
+    Arc<dyn FavouriteBeatmapsRepository + Send + Sync>;
+
+/// Beatmaps are favourited by their beatmapset, as the osu! clients do.
+#[async_trait]
+pub trait FavouriteBeatmapsRepository {
+    /// Get the favourite beatmapset ids of the user, newest first.
+    async fn get_favourite_beatmapsets(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FavouriteBeatmapsError>;
+
+    /// Returns `false` if the beatmapset is already favourited.
+    async fn add_favourite_beatmapset(
+        &self,
+        user_id: i32,
+        beatmapset_id: i32,
+    ) -> Result<bool, FavouriteBeatmapsError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FavouriteBeatmapsRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl FavouriteBeatmapsRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> Self {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynFavouriteBeatmapsRepository {
+        Arc::new(self) as DynFavouriteBeatmapsRepository
+    }
+}
+
+#[async_trait]
+impl FavouriteBeatmapsRepository for FavouriteBeatmapsRepositoryImpl {
+    async fn get_favourite_beatmapsets(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FavouriteBeatmapsError> {
+        Ok(favourite_beatmaps::Entity::find()
+            .select_only()
+            .column(favourite_beatmaps::Column::BeatmapsetId)
+            .filter(favourite_beatmaps::Column::UserId.eq(user_id))
+            .order_by_desc(favourite_beatmaps::Column::CreatedAt)
+            .into_tuple::<i32>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn add_favourite_beatmapset(
+        &self,
+        user_id: i32,
+        beatmapset_id: i32,
+    ) -> Result<bool, FavouriteBeatmapsError> {
+        let res = favourite_beatmaps::Entity::insert(
+            favourite_beatmaps::ActiveModel {
+                user_id: Set(user_id),
+                beatmapset_id: Set(beatmapset_id),
+                ..Default::default()
+            },
+        )
+        .on_conflict(
+            OnConflict::columns([
+                favourite_beatmaps::Column::UserId,
+                favourite_beatmaps::Column::BeatmapsetId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec(self.conn.as_ref())
+        .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            // already favourited
+            Err(DbErr::RecordNotInserted) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::FollowersError;
+use peace_db::{
+    peace::{
+        entity::{followers, user_blocks},
+        Peace,
+    },
+    sea_query::OnConflict,
+    *,
+};
+use std::sync::Arc;
+
+pub type DynFollowersRepository = Arc<dyn FollowersRepository + Send + Sync>;
+
+/// Friends in osu! are one-way follows, the `followers` table stores
+/// `user_id` following `follow_id`. Blocks are stored the same way in the
+/// `user_blocks` table, `user_id` blocking `block_id`.
+#[async_trait]
+pub trait FollowersRepository {
+    async fn get_friends(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError>;
+
+    async fn is_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<bool, FollowersError>;
+
+    async fn add_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError>;
+
+    async fn remove_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError>;
+
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError>;
+
+    async fn is_blocked(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<bool, FollowersError>;
+
+    async fn block_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError>;
+
+    async fn unblock_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FollowersRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl FollowersRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> FollowersRepositoryImpl {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynFollowersRepository {
+        Arc::new(self) as DynFollowersRepository
+    }
+}
+
+#[async_trait]
+impl FollowersRepository for FollowersRepositoryImpl {
+    async fn get_friends(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError> {
+        Ok(followers::Entity::find()
+            .select_only()
+            .column(followers::Column::FollowId)
+            .filter(followers::Column::UserId.eq(user_id))
+            .into_tuple::<i32>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn is_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<bool, FollowersError> {
+        Ok(followers::Entity::find_by_id((user_id, friend_id))
+            .one(self.conn.as_ref())
+            .await?
+            .is_some())
+    }
+
+    async fn add_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError> {
+        if user_id == friend_id {
+            return Err(FollowersError::FollowSelf);
+        }
+
+        let res = followers::Entity::insert(followers::ActiveModel {
+            user_id: Set(user_id),
+            follow_id: Set(friend_id),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                followers::Column::UserId,
+                followers::Column::FollowId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec(self.conn.as_ref())
+        .await;
+
+        match res {
+            // already friends
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn remove_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError> {
+        followers::Entity::delete_by_id((user_id, friend_id))
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError> {
+        Ok(user_blocks::Entity::find()
+            .select_only()
+            .column(user_blocks::Column::BlockId)
+            .filter(user_blocks::Column::UserId.eq(user_id))
+            .into_tuple::<i32>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn is_blocked(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<bool, FollowersError> {
+        Ok(user_blocks::Entity::find_by_id((user_id, block_id))
+            .one(self.conn.as_ref())
+            .await?
+            .is_some())
+    }
+
+    async fn block_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError> {
+        if user_id == block_id {
+            return Err(FollowersError::BlockSelf);
+        }
+
+        let res = user_blocks::Entity::insert(user_blocks::ActiveModel {
+            user_id: Set(user_id),
+            block_id: Set(block_id),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                user_blocks::Column::UserId,
+                user_blocks::Column::BlockId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec(self.conn.as_ref())
+        .await;
+
+        match res {
+            // already blocked
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn unblock_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError> {
+        user_blocks::Entity::delete_by_id((user_id, block_id))
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+}
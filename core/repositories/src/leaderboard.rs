@@ -0,0 +1,504 @@
+use crate::LeaderboardError;
+use peace_db::{
+    peace::{
+        entity::{
+            beatmaps, leaderboard, score_pp, scores, scores_classic,
+            sea_orm_active_enums::{
+                GameMode, PpVersion, RankStatus, RankingType, ScoreGrade,
+            },
+            users,
+        },
+        Peace,
+    },
+    prelude::{DateTimeWithTimeZone, Decimal},
+    sea_query::{Expr, IntoCondition, OnConflict, Query, SelectStatement},
+    *,
+};
+use std::sync::Arc;
+
+pub type DynLeaderboardRepository =
+    Arc<dyn LeaderboardRepository + Send + Sync>;
+
+/// A row of the beatmap leaderboard, joined with the score and the user.
+#[derive(Debug, Clone)]
+pub struct LeaderboardScore {
+    pub score_id: i64,
+    pub user_id: i32,
+    pub username: String,
+    pub score: i32,
+    pub pp: Option<Decimal>,
+    pub accuracy: Decimal,
+    pub combo: i32,
+    pub mods: i32,
+    pub n300: i32,
+    pub n100: i32,
+    pub n50: i32,
+    pub miss: i32,
+    pub geki: i32,
+    pub katu: i32,
+    pub perfect: bool,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl FromQueryResult for LeaderboardScore {
+    fn from_query_result(res: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        Ok(Self {
+            score_id: res.try_get(pre, "score_id")?,
+            user_id: res.try_get(pre, "user_id")?,
+            username: res.try_get(pre, "username")?,
+            score: res.try_get(pre, "score")?,
+            pp: res.try_get(pre, "pp")?,
+            accuracy: res.try_get(pre, "accuracy")?,
+            combo: res.try_get(pre, "combo")?,
+            mods: res.try_get(pre, "mods")?,
+            n300: res.try_get(pre, "n300")?,
+            n100: res.try_get(pre, "n100")?,
+            n50: res.try_get(pre, "n50")?,
+            miss: res.try_get(pre, "miss")?,
+            geki: res.try_get(pre, "geki")?,
+            katu: res.try_get(pre, "katu")?,
+            perfect: res.try_get(pre, "perfect")?,
+            created_at: res.try_get(pre, "created_at")?,
+        })
+    }
+}
+
+/// The grade of the user's leaderboard entry on a beatmap.
+#[derive(Debug, Clone)]
+pub struct UserBeatmapGrade {
+    pub beatmap_id: i32,
+    pub mode: GameMode,
+    pub grade: ScoreGrade,
+}
+
+impl FromQueryResult for UserBeatmapGrade {
+    fn from_query_result(res: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        Ok(Self {
+            beatmap_id: res.try_get(pre, "beatmap_id")?,
+            mode: res.try_get(pre, "mode")?,
+            grade: res.try_get(pre, "grade")?,
+        })
+    }
+}
+
+/// Restricts the leaderboard to a subset of the scores.
+#[derive(Debug, Clone, Default)]
+pub struct LeaderboardFilter {
+    pub country: Option<String>,
+    pub user_ids: Option<Vec<i32>>,
+    pub mods: Option<i32>,
+}
+
+#[async_trait]
+pub trait LeaderboardRepository {
+    /// Get the top scores of the beatmap, ordered by the ranking type.
+    async fn get_leaderboard(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        limit: u64,
+    ) -> Result<Vec<LeaderboardScore>, LeaderboardError>;
+
+    /// Get the user's entry on the leaderboard, `None` if the user has no
+    /// entry or it's excluded by the filter.
+    async fn get_user_leaderboard_score(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        user_id: i32,
+    ) -> Result<Option<LeaderboardScore>, LeaderboardError>;
+
+    /// Get the 1-based rank of the score on the leaderboard, the ties are
+    /// ranked by the submission time as in [`Self::get_leaderboard`].
+    async fn get_leaderboard_rank(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        score: &LeaderboardScore,
+    ) -> Result<u64, LeaderboardError>;
+
+    /// Set the score as the user's entry on the leaderboard.
+    async fn update_leaderboard(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        user_id: i32,
+        score_id: i64,
+    ) -> Result<(), LeaderboardError>;
+
+    /// Get the user's best entries of all ranked or approved beatmaps,
+    /// ordered by the ranking type.
+    async fn get_user_top_scores(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        limit: u64,
+    ) -> Result<Vec<LeaderboardScore>, LeaderboardError>;
+
+    /// Get the grades of the user's entries on the beatmaps in all modes.
+    async fn get_user_beatmap_grades(
+        &self,
+        user_id: i32,
+        beatmap_ids: &[i32],
+        ranking_type: RankingType,
+    ) -> Result<Vec<UserBeatmapGrade>, LeaderboardError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LeaderboardRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl LeaderboardRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> LeaderboardRepositoryImpl {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynLeaderboardRepository {
+        Arc::new(self) as DynLeaderboardRepository
+    }
+
+    #[inline]
+    fn pp_version(ranking_type: &RankingType) -> Option<PpVersion> {
+        match ranking_type {
+            RankingType::PpV1 => Some(PpVersion::V1),
+            RankingType::PpV2 => Some(PpVersion::V2),
+            RankingType::ScoreV1 | RankingType::ScoreV2 => None,
+        }
+    }
+
+    /// Select the leaderboard rows of the beatmap, pp ranking types only
+    /// contain the scores which have pp of the same version. With the mods
+    /// filter, the rows are each user's best score played with those mods
+    /// instead of the users' overall best entries.
+    fn select_leaderboard(
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+    ) -> Select<scores::Entity> {
+        match filter.mods {
+            Some(mods) => {
+                let best_scores = Self::select_best_scores_with_mods(
+                    beatmap_id,
+                    mode.clone(),
+                    ranking_type.clone(),
+                    mods,
+                );
+
+                Self::select_scores(mode, ranking_type, filter)
+                    .filter(scores::Column::Id.in_subquery(best_scores))
+            },
+            None => Self::select_entries(mode, ranking_type, filter)
+                .filter(leaderboard::Column::BeatmapId.eq(beatmap_id)),
+        }
+    }
+
+    /// Select the leaderboard rows of all beatmaps.
+    fn select_entries(
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+    ) -> Select<scores::Entity> {
+        Self::select_scores(mode.clone(), ranking_type.clone(), filter)
+            .join(JoinType::InnerJoin, scores::Relation::Leaderboard.def())
+            .filter(leaderboard::Column::Mode.eq(mode))
+            .filter(leaderboard::Column::RankingType.eq(ranking_type))
+    }
+
+    /// Select the visible scores joined with the user and the pp of the
+    /// ranking type, restricted by the country and users of the filter.
+    fn select_scores(
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+    ) -> Select<scores::Entity> {
+        let pp_version = Self::pp_version(&ranking_type);
+
+        let mut query = scores::Entity::find()
+            .select_only()
+            .column_as(scores::Column::Id, "score_id")
+            .column(scores::Column::UserId)
+            .column_as(users::Column::Name, "username")
+            .column(scores_classic::Column::Score)
+            .column(score_pp::Column::Pp)
+            .column(scores_classic::Column::Accuracy)
+            .column(scores_classic::Column::Combo)
+            .column(scores_classic::Column::Mods)
+            .column(scores_classic::Column::N300)
+            .column(scores_classic::Column::N100)
+            .column(scores_classic::Column::N50)
+            .column(scores_classic::Column::Miss)
+            .column(scores_classic::Column::Geki)
+            .column(scores_classic::Column::Katu)
+            .column(scores_classic::Column::Perfect)
+            .column(scores::Column::CreatedAt)
+            .join(JoinType::InnerJoin, scores::Relation::Users.def())
+            .join(JoinType::InnerJoin, scores::Relation::ScoresClassic.def())
+            .join(
+                if pp_version.is_some() {
+                    JoinType::InnerJoin
+                } else {
+                    JoinType::LeftJoin
+                },
+                scores::Relation::ScorePp.def().on_condition(
+                    move |_left, right| {
+                        Expr::col((right.clone(), score_pp::Column::Mode))
+                            .eq(mode.clone())
+                            .and(
+                                Expr::col((right, score_pp::Column::PpVersion))
+                                    .eq(pp_version
+                                        .clone()
+                                        .unwrap_or(PpVersion::V2)),
+                            )
+                            .into_condition()
+                    },
+                ),
+            )
+            .filter(scores::Column::Invisible.eq(false));
+
+        if let Some(country) = &filter.country {
+            query = query.filter(users::Column::Country.eq(country.as_str()));
+        }
+
+        if let Some(user_ids) = &filter.user_ids {
+            query =
+                query.filter(scores::Column::UserId.is_in(user_ids.clone()));
+        }
+
+        query
+    }
+
+    /// Select the id of each user's best completed score on the beatmap
+    /// played with exactly the mods, ordered as the ranking type.
+    fn select_best_scores_with_mods(
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        mods: i32,
+    ) -> SelectStatement {
+        let mut query = Query::select();
+        query
+            .distinct_on([(scores::Entity, scores::Column::UserId)])
+            .column((scores::Entity, scores::Column::Id))
+            .from(scores::Entity)
+            .inner_join(
+                scores_classic::Entity,
+                Expr::col((scores_classic::Entity, scores_classic::Column::Id))
+                    .equals((scores::Entity, scores::Column::Id)),
+            )
+            .inner_join(
+                beatmaps::Entity,
+                Expr::col((beatmaps::Entity, beatmaps::Column::Md5))
+                    .equals((scores::Entity, scores::Column::MapHash)),
+            )
+            .and_where(beatmaps::Column::Bid.eq(beatmap_id))
+            .and_where(scores_classic::Column::Mode.eq(mode.clone()))
+            .and_where(scores_classic::Column::Mods.eq(mods))
+            .and_where(scores::Column::Completed.eq(true))
+            .and_where(scores::Column::Invisible.eq(false))
+            .order_by((scores::Entity, scores::Column::UserId), Order::Asc);
+
+        match Self::pp_version(&ranking_type) {
+            Some(pp_version) => query
+                .inner_join(
+                    score_pp::Entity,
+                    Expr::col((score_pp::Entity, score_pp::Column::ScoreId))
+                        .equals((scores::Entity, scores::Column::Id))
+                        .and(score_pp::Column::Mode.eq(mode))
+                        .and(score_pp::Column::PpVersion.eq(pp_version)),
+                )
+                .order_by(
+                    (score_pp::Entity, score_pp::Column::Pp),
+                    Order::Desc,
+                ),
+            None => query.order_by(
+                (scores_classic::Entity, scores_classic::Column::Score),
+                Order::Desc,
+            ),
+        };
+
+        query
+            .order_by((scores::Entity, scores::Column::CreatedAt), Order::Asc)
+            .to_owned()
+    }
+}
+
+#[async_trait]
+impl LeaderboardRepository for LeaderboardRepositoryImpl {
+    async fn get_leaderboard(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        limit: u64,
+    ) -> Result<Vec<LeaderboardScore>, LeaderboardError> {
+        let order_by_pp = Self::pp_version(&ranking_type).is_some();
+
+        let mut query =
+            Self::select_leaderboard(beatmap_id, mode, ranking_type, filter);
+
+        query = if order_by_pp {
+            query.order_by_desc(score_pp::Column::Pp)
+        } else {
+            query.order_by_desc(scores_classic::Column::Score)
+        };
+
+        Ok(query
+            .order_by_asc(scores::Column::CreatedAt)
+            .limit(limit)
+            .into_model::<LeaderboardScore>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn get_user_leaderboard_score(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        user_id: i32,
+    ) -> Result<Option<LeaderboardScore>, LeaderboardError> {
+        Ok(Self::select_leaderboard(beatmap_id, mode, ranking_type, filter)
+            .filter(scores::Column::UserId.eq(user_id))
+            .into_model::<LeaderboardScore>()
+            .one(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn get_leaderboard_rank(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        filter: &LeaderboardFilter,
+        score: &LeaderboardScore,
+    ) -> Result<u64, LeaderboardError> {
+        let order_by_pp = Self::pp_version(&ranking_type).is_some();
+
+        let query =
+            Self::select_leaderboard(beatmap_id, mode, ranking_type, filter);
+
+        let submitted_earlier = scores::Column::CreatedAt.lt(score.created_at);
+        let query = match (order_by_pp, score.pp) {
+            (true, Some(pp)) => query.filter(
+                Condition::any().add(score_pp::Column::Pp.gt(pp)).add(
+                    Condition::all()
+                        .add(score_pp::Column::Pp.eq(pp))
+                        .add(submitted_earlier),
+                ),
+            ),
+            _ => query.filter(
+                Condition::any()
+                    .add(scores_classic::Column::Score.gt(score.score))
+                    .add(
+                        Condition::all()
+                            .add(scores_classic::Column::Score.eq(score.score))
+                            .add(submitted_earlier),
+                    ),
+            ),
+        };
+
+        Ok(query.count(self.conn.as_ref()).await? + 1)
+    }
+
+    async fn update_leaderboard(
+        &self,
+        beatmap_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        user_id: i32,
+        score_id: i64,
+    ) -> Result<(), LeaderboardError> {
+        leaderboard::Entity::insert(leaderboard::ActiveModel {
+            beatmap_id: Set(beatmap_id),
+            mode: Set(mode),
+            ranking_type: Set(ranking_type),
+            user_id: Set(user_id),
+            score_id: Set(score_id),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                leaderboard::Column::BeatmapId,
+                leaderboard::Column::Mode,
+                leaderboard::Column::RankingType,
+                leaderboard::Column::UserId,
+            ])
+            .update_column(leaderboard::Column::ScoreId)
+            .to_owned(),
+        )
+        .exec(self.conn.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_top_scores(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        ranking_type: RankingType,
+        limit: u64,
+    ) -> Result<Vec<LeaderboardScore>, LeaderboardError> {
+        let order_by_pp = Self::pp_version(&ranking_type).is_some();
+
+        let mut query = Self::select_entries(
+            mode,
+            ranking_type,
+            &LeaderboardFilter::default(),
+        )
+        .join(JoinType::InnerJoin, leaderboard::Relation::Beatmaps.def())
+        .filter(scores::Column::UserId.eq(user_id))
+        .filter(
+            beatmaps::Column::RankStatus
+                .is_in([RankStatus::Ranked, RankStatus::Approved]),
+        );
+
+        query = if order_by_pp {
+            query.order_by_desc(score_pp::Column::Pp)
+        } else {
+            query.order_by_desc(scores_classic::Column::Score)
+        };
+
+        Ok(query
+            .limit(limit)
+            .into_model::<LeaderboardScore>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn get_user_beatmap_grades(
+        &self,
+        user_id: i32,
+        beatmap_ids: &[i32],
+        ranking_type: RankingType,
+    ) -> Result<Vec<UserBeatmapGrade>, LeaderboardError> {
+        if beatmap_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(leaderboard::Entity::find()
+            .select_only()
+            .column(leaderboard::Column::BeatmapId)
+            .column(leaderboard::Column::Mode)
+            .column(scores_classic::Column::Grade)
+            .join(JoinType::InnerJoin, leaderboard::Relation::Scores.def())
+            .join(JoinType::InnerJoin, scores::Relation::ScoresClassic.def())
+            .filter(leaderboard::Column::UserId.eq(user_id))
+            .filter(leaderboard::Column::BeatmapId.is_in(beatmap_ids.to_vec()))
+            .filter(leaderboard::Column::RankingType.eq(ranking_type))
+            .into_model::<UserBeatmapGrade>()
+            .all(self.conn.as_ref())
+            .await?)
+    }
+}
@@ -2,7 +2,16 @@
 #[macro_use]
 extern crate peace_logs;
 
+pub mod beatmap_ratings;
+pub mod beatmaps;
+pub mod channels;
+pub mod chat_messages;
 pub mod error;
+pub mod favourite_beatmaps;
+pub mod followers;
+pub mod leaderboard;
+pub mod scores;
+pub mod screenshots;
 pub mod users;
 
 pub use error::*;
@@ -0,0 +1,176 @@
+use crate::{unique_violation, ScoresError};
+use peace_db::{
+    peace::{
+        entity::{
+            score_pp, scores, scores_classic,
+            sea_orm_active_enums::{GameMode, ScoreKind},
+        },
+        Peace,
+    },
+    *,
+};
+use std::sync::Arc;
+
+pub type DynScoresRepository = Arc<dyn ScoresRepository + Send + Sync>;
+
+#[async_trait]
+pub trait ScoresRepository {
+    async fn score_exists(&self, cksm: &str) -> Result<bool, ScoresError>;
+
+    async fn get_classic_score(
+        &self,
+        score_id: i64,
+    ) -> Result<Option<(scores::Model, scores_classic::Model)>, ScoresError>;
+
+    /// Get the user's best completed score on the beatmap, ordered by score.
+    async fn get_user_best_classic_score(
+        &self,
+        user_id: i32,
+        map_hash: &str,
+        mode: GameMode,
+    ) -> Result<Option<(scores::Model, scores_classic::Model)>, ScoresError>;
+
+    /// Insert the base `scores` row and its `scores_classic` row in one
+    /// transaction, returns the id of the new score. A score with a checksum
+    /// already submitted fails with [`ScoresError::DuplicateScore`].
+    async fn create_classic_score(
+        &self,
+        score: scores::ActiveModel,
+        classic: scores_classic::ActiveModel,
+    ) -> Result<i64, ScoresError>;
+
+    /// Get a page of the completed classic scores with an id greater than
+    /// `after_id`, ordered by id.
+    async fn get_completed_classic_scores(
+        &self,
+        after_id: i64,
+        limit: u64,
+    ) -> Result<Vec<(scores::Model, scores_classic::Model)>, ScoresError>;
+
+    /// Insert the pp row, or overwrite it if one exists for the same score,
+    /// mode and pp version.
+    async fn save_score_pp(
+        &self,
+        pp: score_pp::Model,
+    ) -> Result<(), ScoresError>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ScoresRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl ScoresRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> ScoresRepositoryImpl {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynScoresRepository {
+        Arc::new(self) as DynScoresRepository
+    }
+}
+
+#[async_trait]
+impl ScoresRepository for ScoresRepositoryImpl {
+    async fn score_exists(&self, cksm: &str) -> Result<bool, ScoresError> {
+        Ok(scores::Entity::find()
+            .filter(scores::Column::Cksm.eq(cksm))
+            .one(self.conn.as_ref())
+            .await?
+            .is_some())
+    }
+
+    async fn get_classic_score(
+        &self,
+        score_id: i64,
+    ) -> Result<Option<(scores::Model, scores_classic::Model)>, ScoresError>
+    {
+        Ok(scores::Entity::find_by_id(score_id)
+            .find_also_related(scores_classic::Entity)
+            .one(self.conn.as_ref())
+            .await?
+            .and_then(|(score, classic)| classic.map(|c| (score, c))))
+    }
+
+    async fn get_user_best_classic_score(
+        &self,
+        user_id: i32,
+        map_hash: &str,
+        mode: GameMode,
+    ) -> Result<Option<(scores::Model, scores_classic::Model)>, ScoresError>
+    {
+        Ok(scores::Entity::find()
+            .find_also_related(scores_classic::Entity)
+            .filter(scores::Column::UserId.eq(user_id))
+            .filter(scores::Column::MapHash.eq(map_hash))
+            .filter(scores::Column::Completed.eq(true))
+            .filter(scores_classic::Column::Mode.eq(mode))
+            .order_by_desc(scores_classic::Column::Score)
+            .one(self.conn.as_ref())
+            .await?
+            .and_then(|(score, classic)| classic.map(|c| (score, c))))
+    }
+
+    async fn create_classic_score(
+        &self,
+        mut score: scores::ActiveModel,
+        mut classic: scores_classic::ActiveModel,
+    ) -> Result<i64, ScoresError> {
+        let txn = self.conn.as_ref().begin().await?;
+
+        score.kind = Set(ScoreKind::Classic);
+        let score_id = scores::Entity::insert(score)
+            .exec(&txn)
+            .await
+            .map_err(|err| match unique_violation(&err) {
+                Some(_) => ScoresError::DuplicateScore,
+                None => err.into(),
+            })?
+            .last_insert_id;
+
+        classic.id = Set(score_id);
+        scores_classic::Entity::insert(classic).exec(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(score_id)
+    }
+
+    async fn get_completed_classic_scores(
+        &self,
+        after_id: i64,
+        limit: u64,
+    ) -> Result<Vec<(scores::Model, scores_classic::Model)>, ScoresError> {
+        Ok(scores::Entity::find()
+            .find_also_related(scores_classic::Entity)
+            .filter(scores::Column::Id.gt(after_id))
+            .filter(scores::Column::Completed.eq(true))
+            .order_by_asc(scores::Column::Id)
+            .limit(limit)
+            .all(self.conn.as_ref())
+            .await?
+            .into_iter()
+            .filter_map(|(score, classic)| classic.map(|c| (score, c)))
+            .collect())
+    }
+
+    async fn save_score_pp(
+        &self,
+        pp: score_pp::Model,
+    ) -> Result<(), ScoresError> {
+        score_pp::Entity::insert(score_pp::ActiveModel::from(pp))
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    score_pp::Column::ScoreId,
+                    score_pp::Column::Mode,
+                    score_pp::Column::PpVersion,
+                ])
+                .update_columns([score_pp::Column::Pp, score_pp::Column::RawPp])
+                .to_owned(),
+            )
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+}
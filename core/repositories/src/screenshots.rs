@@ -0,0 +1,155 @@
+use crate::ScreenshotsError;
+use peace_db::{
+    peace::{
+        entity::{screenshots, users},
+        Peace,
+    },
+    prelude::DateTimeWithTimeZone,
+    sea_query::Expr,
+    *,
+};
+use std::sync::Arc;
+
+pub type DynScreenshotsRepository =
+    Arc<dyn ScreenshotsRepository + Send + Sync>;
+
+#[async_trait]
+pub trait ScreenshotsRepository {
+    async fn get_screenshot(
+        &self,
+        name: &str,
+    ) -> Result<Option<screenshots::Model>, ScreenshotsError>;
+
+    /// Get the screenshot by the full hash of its content.
+    async fn get_screenshot_by_hash(
+        &self,
+        hash: &str,
+    ) -> Result<Option<screenshots::Model>, ScreenshotsError>;
+
+    /// Count and total bytes of the screenshots uploaded by the user since
+    /// the time.
+    async fn get_user_usage(
+        &self,
+        user_id: i32,
+        since: DateTimeWithTimeZone,
+    ) -> Result<(i64, i64), ScreenshotsError>;
+
+    async fn create_screenshot(
+        &self,
+        screenshot: screenshots::ActiveModel,
+    ) -> Result<screenshots::Model, ScreenshotsError>;
+
+    /// Create the screenshot if the uploads of the user since the time are
+    /// within the quota, `0` means unlimited. The check and the insert of
+    /// the user are serialized by locking the user row.
+    async fn create_screenshot_within_quota(
+        &self,
+        screenshot: screenshots::ActiveModel,
+        since: DateTimeWithTimeZone,
+        max_count: u64,
+        max_bytes: u64,
+    ) -> Result<screenshots::Model, ScreenshotsError>;
+}
+
+async fn user_usage<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    since: DateTimeWithTimeZone,
+) -> Result<(i64, i64), DbErr> {
+    Ok(screenshots::Entity::find()
+        .select_only()
+        .column_as(screenshots::Column::Id.count(), "count")
+        .column_as(Expr::cust("COALESCE(SUM(size), 0)::BIGINT"), "size")
+        .filter(screenshots::Column::UserId.eq(user_id))
+        .filter(screenshots::Column::CreatedAt.gte(since))
+        .into_tuple::<(i64, i64)>()
+        .one(conn)
+        .await?
+        .unwrap_or_default())
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ScreenshotsRepositoryImpl {
+    pub conn: DbConnection<Peace>,
+}
+
+impl ScreenshotsRepositoryImpl {
+    pub fn new(conn: DbConnection<Peace>) -> Self {
+        Self { conn }
+    }
+
+    pub fn into_service(self) -> DynScreenshotsRepository {
+        Arc::new(self) as DynScreenshotsRepository
+    }
+}
+
+#[async_trait]
+impl ScreenshotsRepository for ScreenshotsRepositoryImpl {
+    async fn get_screenshot(
+        &self,
+        name: &str,
+    ) -> Result<Option<screenshots::Model>, ScreenshotsError> {
+        Ok(screenshots::Entity::find()
+            .filter(screenshots::Column::Name.eq(name))
+            .one(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn get_screenshot_by_hash(
+        &self,
+        hash: &str,
+    ) -> Result<Option<screenshots::Model>, ScreenshotsError> {
+        Ok(screenshots::Entity::find()
+            .filter(screenshots::Column::Hash.eq(hash))
+            .one(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn get_user_usage(
+        &self,
+        user_id: i32,
+        since: DateTimeWithTimeZone,
+    ) -> Result<(i64, i64), ScreenshotsError> {
+        Ok(user_usage(self.conn.as_ref(), user_id, since).await?)
+    }
+
+    async fn create_screenshot(
+        &self,
+        screenshot: screenshots::ActiveModel,
+    ) -> Result<screenshots::Model, ScreenshotsError> {
+        Ok(screenshots::Entity::insert(screenshot)
+            .exec_with_returning(self.conn.as_ref())
+            .await?)
+    }
+
+    async fn create_screenshot_within_quota(
+        &self,
+        screenshot: screenshots::ActiveModel,
+        since: DateTimeWithTimeZone,
+        max_count: u64,
+        max_bytes: u64,
+    ) -> Result<screenshots::Model, ScreenshotsError> {
+        let user_id = screenshot.user_id.clone().take().unwrap_or_default();
+        let size = screenshot.size.clone().take().unwrap_or_default() as u64;
+
+        let txn = self.conn.as_ref().begin().await?;
+
+        users::Entity::find_by_id(user_id).lock_exclusive().one(&txn).await?;
+
+        let (count, bytes) = user_usage(&txn, user_id, since).await?;
+
+        if (max_count != 0 && count as u64 >= max_count)
+            || (max_bytes != 0 && bytes as u64 + size > max_bytes)
+        {
+            return Err(ScreenshotsError::QuotaExceeded);
+        }
+
+        let screenshot = screenshots::Entity::insert(screenshot)
+            .exec_with_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(screenshot)
+    }
+}
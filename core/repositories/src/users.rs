@@ -1,7 +1,16 @@
 use crate::GetUserError;
 use domain_users::{CreateUser, UsernameAscii, UsernameSafe, UsernameUnicode};
 use peace_db::{
-    peace::{entity::users, Peace},
+    peace::{
+        entity::{
+            privileges,
+            sea_orm_active_enums::{GameMode, PpVersion},
+            user_pp, user_privileges, user_settings, user_stats, users,
+        },
+        Peace,
+    },
+    prelude::Decimal,
+    sea_query::{Alias, Expr, Func},
     *,
 };
 use std::sync::Arc;
@@ -32,6 +41,56 @@ pub trait UsersRepository {
         username_unicode: &str,
     ) -> Result<users::Model, GetUserError>;
 
+    async fn get_user_by_email(
+        &self,
+        email: &str,
+    ) -> Result<users::Model, GetUserError>;
+
+    /// Get the rows of `privileges` granted to the user.
+    async fn get_user_privileges(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<privileges::Model>, GetUserError>;
+
+    async fn get_user_settings(
+        &self,
+        user_id: i32,
+    ) -> Result<Option<user_settings::Model>, DbErr>;
+
+    async fn get_user_stats(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+    ) -> Result<Option<user_stats::Model>, DbErr>;
+
+    /// Add the stats of a play to the stats row of the same user and mode in
+    /// one statement, the counters are incremented and the higher max combo
+    /// is kept. The row is inserted if there is none, the updated row is
+    /// returned.
+    async fn add_user_stats(
+        &self,
+        stats: user_stats::Model,
+    ) -> Result<user_stats::Model, DbErr>;
+
+    /// Set the accuracy of the stats row, the counters are left untouched.
+    async fn save_user_accuracy(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        accuracy: Decimal,
+    ) -> Result<(), DbErr>;
+
+    async fn get_user_pp(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        pp_version: PpVersion,
+    ) -> Result<Option<user_pp::Model>, DbErr>;
+
+    /// Insert the pp row, or overwrite it if one exists for the same user,
+    /// mode and pp version.
+    async fn save_user_pp(&self, pp: user_pp::Model) -> Result<(), DbErr>;
+
     async fn create_user(
         &self,
         creat_user: CreateUser,
@@ -44,6 +103,30 @@ pub trait UsersRepository {
         username_unicode: Option<UsernameSafe>,
         password: String,
     ) -> Result<InsertResult<users::ActiveModel>, DbErr>;
+
+    /// Set the end of the user's silence, `None` to unsilence the user.
+    async fn update_silence_end(
+        &self,
+        user_id: i32,
+        silence_end: Option<prelude::DateTimeWithTimeZone>,
+    ) -> Result<(), DbErr>;
+
+    /// Save whether the user only accepts private messages from friends, the
+    /// settings row is created if not exists.
+    async fn update_only_friend_pm_allowed(
+        &self,
+        user_id: i32,
+        only_friend_pm_allowed: bool,
+    ) -> Result<(), DbErr>;
+
+    /// Grant the privilege to the user, the privilege is created if there is
+    /// no privilege with the name.
+    async fn grant_user_privilege(
+        &self,
+        user_id: i32,
+        privilege_name: &str,
+        grantor_id: i32,
+    ) -> Result<(), DbErr>;
 }
 
 #[derive(Debug, Default, Clone)]
@@ -135,6 +218,147 @@ impl UsersRepository for UsersRepositoryImpl {
             .ok_or(GetUserError::UserNotExists)
     }
 
+    async fn get_user_by_email(
+        &self,
+        email: &str,
+    ) -> Result<users::Model, GetUserError> {
+        users::Entity::find()
+            .filter(users::Column::Email.eq(email.trim().to_ascii_lowercase()))
+            .one(self.conn.as_ref())
+            .await
+            .map_err(GetUserError::from)?
+            .ok_or(GetUserError::UserNotExists)
+    }
+
+    async fn get_user_privileges(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<privileges::Model>, GetUserError> {
+        privileges::Entity::find()
+            .inner_join(user_privileges::Entity)
+            .filter(user_privileges::Column::UserId.eq(user_id))
+            .all(self.conn.as_ref())
+            .await
+            .map_err(GetUserError::from)
+    }
+
+    async fn get_user_settings(
+        &self,
+        user_id: i32,
+    ) -> Result<Option<user_settings::Model>, DbErr> {
+        user_settings::Entity::find_by_id(user_id).one(self.conn.as_ref()).await
+    }
+
+    async fn get_user_stats(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+    ) -> Result<Option<user_stats::Model>, DbErr> {
+        user_stats::Entity::find_by_id((user_id, mode))
+            .one(self.conn.as_ref())
+            .await
+    }
+
+    async fn add_user_stats(
+        &self,
+        stats: user_stats::Model,
+    ) -> Result<user_stats::Model, DbErr> {
+        let excluded = |column: user_stats::Column| {
+            Expr::col((Alias::new("excluded"), column))
+        };
+        let add = |column: user_stats::Column| {
+            (
+                column,
+                Expr::col((user_stats::Entity, column)).add(excluded(column)),
+            )
+        };
+
+        user_stats::Entity::insert(user_stats::ActiveModel::from(stats))
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    user_stats::Column::UserId,
+                    user_stats::Column::Mode,
+                ])
+                .values([
+                    add(user_stats::Column::TotalScore),
+                    add(user_stats::Column::RankedScore),
+                    add(user_stats::Column::Playcount),
+                    add(user_stats::Column::TotalHits),
+                    add(user_stats::Column::TotalSecondsPlayed),
+                    add(user_stats::Column::Count300),
+                    add(user_stats::Column::Count100),
+                    add(user_stats::Column::Count50),
+                    add(user_stats::Column::CountMiss),
+                    add(user_stats::Column::CountFailed),
+                    add(user_stats::Column::CountQuit),
+                    (
+                        user_stats::Column::MaxCombo,
+                        Func::cust(Alias::new("GREATEST"))
+                            .args([
+                                Expr::col((
+                                    user_stats::Entity,
+                                    user_stats::Column::MaxCombo,
+                                ))
+                                .into(),
+                                excluded(user_stats::Column::MaxCombo).into(),
+                            ])
+                            .into(),
+                    ),
+                    (
+                        user_stats::Column::UpdatedAt,
+                        excluded(user_stats::Column::UpdatedAt).into(),
+                    ),
+                ])
+                .to_owned(),
+            )
+            .exec_with_returning(self.conn.as_ref())
+            .await
+    }
+
+    async fn save_user_accuracy(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        accuracy: Decimal,
+    ) -> Result<(), DbErr> {
+        user_stats::Entity::update_many()
+            .col_expr(user_stats::Column::Accuracy, Expr::value(accuracy))
+            .filter(user_stats::Column::UserId.eq(user_id))
+            .filter(user_stats::Column::Mode.eq(mode))
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_pp(
+        &self,
+        user_id: i32,
+        mode: GameMode,
+        pp_version: PpVersion,
+    ) -> Result<Option<user_pp::Model>, DbErr> {
+        user_pp::Entity::find_by_id((user_id, mode, pp_version))
+            .one(self.conn.as_ref())
+            .await
+    }
+
+    async fn save_user_pp(&self, pp: user_pp::Model) -> Result<(), DbErr> {
+        user_pp::Entity::insert(user_pp::ActiveModel::from(pp))
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    user_pp::Column::UserId,
+                    user_pp::Column::Mode,
+                    user_pp::Column::PpVersion,
+                ])
+                .update_columns([user_pp::Column::Pp, user_pp::Column::RawPp])
+                .to_owned(),
+            )
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
     async fn create_user(
         &self,
         creat_user: CreateUser,
@@ -190,6 +414,93 @@ impl UsersRepository for UsersRepositoryImpl {
 
         todo!()
     }
+
+    async fn update_silence_end(
+        &self,
+        user_id: i32,
+        silence_end: Option<prelude::DateTimeWithTimeZone>,
+    ) -> Result<(), DbErr> {
+        users::Entity::update_many()
+            .col_expr(
+                users::Column::SilenceEnd,
+                sea_query::Expr::value(silence_end),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .exec(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_only_friend_pm_allowed(
+        &self,
+        user_id: i32,
+        only_friend_pm_allowed: bool,
+    ) -> Result<(), DbErr> {
+        user_settings::Entity::insert(user_settings::ActiveModel {
+            user_id: Set(user_id),
+            only_friend_pm_allowed: Set(only_friend_pm_allowed),
+            ..Default::default()
+        })
+        .on_conflict(
+            sea_query::OnConflict::column(user_settings::Column::UserId)
+                .update_column(user_settings::Column::OnlyFriendPmAllowed)
+                .to_owned(),
+        )
+        .exec(self.conn.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn grant_user_privilege(
+        &self,
+        user_id: i32,
+        privilege_name: &str,
+        grantor_id: i32,
+    ) -> Result<(), DbErr> {
+        let privilege_id = match privileges::Entity::find()
+            .filter(privileges::Column::Name.eq(privilege_name))
+            .one(self.conn.as_ref())
+            .await?
+        {
+            Some(privilege) => privilege.id,
+            None => {
+                privileges::Entity::insert(privileges::ActiveModel {
+                    name: Set(privilege_name.to_owned()),
+                    creator_id: Set(Some(grantor_id)),
+                    ..Default::default()
+                })
+                .exec(self.conn.as_ref())
+                .await?
+                .last_insert_id
+            },
+        };
+
+        let res =
+            user_privileges::Entity::insert(user_privileges::ActiveModel {
+                user_id: Set(user_id),
+                privilege_id: Set(privilege_id),
+                grantor_id: Set(grantor_id),
+                ..Default::default()
+            })
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    user_privileges::Column::UserId,
+                    user_privileges::Column::PrivilegeId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec(self.conn.as_ref())
+            .await;
+
+        match res {
+            // already granted
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,9 +1,15 @@
+use crate::RegistrationErrors;
 use bancho_packets::PacketId;
 use core_bancho_state::BanchoStateError;
 use core_chat::ChatError;
 use domain_users::PasswordError;
+use peace_db::DbErr;
 use peace_pb::ConvertError;
-use peace_repositories::GetUserError;
+use peace_repositories::{
+    BeatmapRatingsError, FavouriteBeatmapsError, FollowersError,
+    GetBeatmapError, GetUserError, LeaderboardError, ScoresError,
+    ScreenshotsError,
+};
 use peace_rpc_error::{RpcError, TonicError};
 use tonic::Status;
 
@@ -22,6 +28,8 @@ pub enum ProcessBanchoPacketError {
     #[error(transparent)]
     BanchoServiceError(#[from] BanchoServiceError),
     #[error(transparent)]
+    BanchoStateError(#[from] BanchoStateError),
+    #[error(transparent)]
     ChatError(#[from] ChatError),
     #[error("TonicError: {0}")]
     TonicError(String),
@@ -35,11 +43,73 @@ impl TonicError for ProcessBanchoPacketError {
 
 #[derive(thiserror::Error, Debug, Serialize, Deserialize, RpcError)]
 pub enum BanchoServiceError {
+    #[error("user is banned")]
+    UserBanned,
+    #[error("outdated client: {0}")]
+    OutdatedClient(String),
+    #[error("country not allowed: {0}")]
+    CountryNotAllowed(String),
     #[error(transparent)]
     PasswordError(#[from] PasswordError),
     #[error(transparent)]
     UserNotExists(#[from] GetUserError),
     #[error(transparent)]
+    FollowersError(#[from] FollowersError),
+    #[error(transparent)]
+    BeatmapError(#[from] GetBeatmapError),
+    #[error(transparent)]
+    ScoresError(#[from] ScoresError),
+    #[error(transparent)]
+    LeaderboardError(#[from] LeaderboardError),
+    #[error(transparent)]
+    ReplayStoreError(#[from] ReplayStoreError),
+    #[error(transparent)]
+    BeatmapFetchError(#[from] BeatmapFetchError),
+    #[error(transparent)]
+    BeatmapMirrorError(#[from] BeatmapMirrorError),
+    #[error(transparent)]
+    ScreenshotsError(#[from] ScreenshotsError),
+    #[error(transparent)]
+    ScreenshotStoreError(#[from] ScreenshotStoreError),
+    #[error(transparent)]
+    FavouriteBeatmapsError(#[from] FavouriteBeatmapsError),
+    #[error(transparent)]
+    BeatmapRatingsError(#[from] BeatmapRatingsError),
+    #[error("invalid score: {0}")]
+    InvalidScore(String),
+    #[error("duplicate score")]
+    DuplicateScore,
+    #[error("score not exists")]
+    ScoreNotExists,
+    #[error("replay not exists")]
+    ReplayNotExists,
+    #[error("replay access denied")]
+    ReplayAccessDenied,
+    #[error("invalid screenshot, only png and jpeg are allowed")]
+    InvalidScreenshot,
+    #[error("screenshot is too large")]
+    ScreenshotTooLarge,
+    #[error("screenshot quota exceeded")]
+    ScreenshotQuotaExceeded,
+    #[error("screenshot not exists")]
+    ScreenshotNotExists,
+    #[error("only ranked and loved beatmaps can be rated")]
+    BeatmapNotRateable,
+    #[error("invalid rating: {0}")]
+    InvalidRating(i32),
+    #[error("osu!direct is disabled")]
+    DirectDisabled,
+    #[error("beatmapset not exists")]
+    BeatmapsetNotExists,
+    #[error("registration is disabled")]
+    RegistrationDisabled,
+    #[error("too many registrations from the ip")]
+    RegistrationRateLimited,
+    #[error("invalid registration form")]
+    InvalidRegistration(RegistrationErrors),
+    #[error("database err: {0}")]
+    DbErr(String),
+    #[error(transparent)]
     BanchoStateError(#[from] BanchoStateError),
     #[error(transparent)]
     ChatError(#[from] ChatError),
@@ -49,8 +119,78 @@ pub enum BanchoServiceError {
     TonicError(String),
 }
 
+impl From<DbErr> for BanchoServiceError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
+
 impl TonicError for BanchoServiceError {
     fn tonic_error(s: Status) -> Self {
         Self::TonicError(s.message().to_owned())
     }
 }
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ReplayStoreError {
+    #[error("io err: {0}")]
+    IoError(String),
+}
+
+impl From<std::io::Error> for ReplayStoreError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum ScreenshotStoreError {
+    #[error("io err: {0}")]
+    IoError(String),
+}
+
+impl From<std::io::Error> for ScreenshotStoreError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum BeatmapMirrorError {
+    #[error("request err: {0}")]
+    RequestError(String),
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("file is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("io err: {0}")]
+    IoError(String),
+}
+
+impl From<reqwest::Error> for BeatmapMirrorError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::RequestError(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for BeatmapMirrorError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+pub enum BeatmapFetchError {
+    #[error(transparent)]
+    MirrorError(#[from] BeatmapMirrorError),
+    #[error(transparent)]
+    BeatmapError(#[from] GetBeatmapError),
+    #[error("io err: {0}")]
+    IoError(String),
+}
+
+impl From<std::io::Error> for BeatmapFetchError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err.to_string())
+    }
+}
@@ -1,14 +1,19 @@
 use crate::{traits::*, ProcessBanchoPacketError};
 use async_trait::async_trait;
 use bancho_packets::{
-    BanchoMessage, ClientChangeAction, Packet, PayloadReader,
+    BanchoMessage, BeatmapInfoQuery, ClientChangeAction, MatchData, Packet,
+    PayloadReader,
 };
 use core_bancho_state::BanchoStateService;
-use core_chat::ChatService;
+use core_chat::{ChatError, ChatService};
 use domain_bancho::PresenceFilter;
+use domain_chat::{MultiplayerChannel, SpectatorChannel};
 use num_traits::FromPrimitive;
 use pb_bancho::*;
-use pb_bancho_state::UserQuery;
+use pb_bancho_state::{
+    update_match_request::MatchAction, CreateMatchRequest, JoinMatchRequest,
+    SpectateState, UpdateMatchRequest, UserMatch, UserQuery,
+};
 use pb_chat::{
     ChannelQuery, ChatMessageTarget, JoinChannelRequest, LeaveChannelRequest,
     SendMessageRequest,
@@ -33,6 +38,46 @@ impl<'a> Debug for PacketProcessor<'a> {
     }
 }
 
+impl<'a> PacketProcessor<'a> {
+    /// Bancho clients use `#spectator` and `#multiplayer` for the instance
+    /// channels they are currently in, so resolve them to the real channels.
+    #[inline]
+    pub async fn channel_query(
+        &self,
+        channel_name: String,
+    ) -> Result<ChannelQuery, ProcessBanchoPacketError> {
+        if channel_name == MultiplayerChannel::BANCHO_NAME {
+            let UserMatch { match_id } = self
+                .bancho_state_service
+                .get_user_match(UserQuery::UserId(self.user_id))
+                .await?;
+
+            let match_id = match_id.ok_or(ChatError::ChannelNotExists)?;
+
+            return Ok(ChannelQuery::ChannelId(
+                MultiplayerChannel::channel_id(match_id),
+            ));
+        }
+
+        if channel_name != SpectatorChannel::BANCHO_NAME {
+            return Ok(ChannelQuery::ChannelName(channel_name));
+        }
+
+        let SpectateState { user_id, spectating, spectators } = self
+            .bancho_state_service
+            .get_spectate_state(UserQuery::UserId(self.user_id))
+            .await?;
+
+        let host_user_id = match spectating {
+            Some(host_user_id) => host_user_id,
+            None if !spectators.is_empty() => user_id,
+            None => return Err(ChatError::ChannelNotExists.into()),
+        };
+
+        Ok(ChannelQuery::ChannelId(SpectatorChannel::channel_id(host_user_id)))
+    }
+}
+
 #[inline]
 pub fn read_channel_name(
     payload: Option<&[u8]>,
@@ -65,30 +110,14 @@ impl<'a> ProcessSendPublicMessage for PacketProcessor<'a> {
     async fn send_public_message(
         &self,
     ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
-        #[allow(unused_mut)]
-        let mut chat_message = read_chat_message(self.packet.payload)?;
+        let chat_message = read_chat_message(self.packet.payload)?;
 
-        match chat_message.target.as_str() {
-            "#spectator" => {
-                // TODO: spectator chat
-                todo!("get user's current #spectator channel id")
-            },
-            "#multiplayer" => {
-                // TODO: multiplayer chat
-                todo!("get user's current #multiplayer channel id")
-            },
-            _ => {},
-        };
+        let channel_query = self.channel_query(chat_message.target).await?;
 
         let request = SendMessageRequest {
             sender: Some(UserQuery::UserId(self.user_id).into()),
             message: chat_message.content,
-            target: Some(
-                ChatMessageTarget::Channel(ChannelQuery::ChannelName(
-                    chat_message.target,
-                ))
-                .into(),
-            ),
+            target: Some(ChatMessageTarget::Channel(channel_query).into()),
         };
 
         self.chat_service.send_message(request).await?;
@@ -129,12 +158,11 @@ impl<'a> ProcessUserChannelJoin for PacketProcessor<'a> {
         &self,
     ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
         let channel_name = read_channel_name(self.packet.payload)?;
+        let channel_query = self.channel_query(channel_name).await?;
 
         self.chat_service
             .join_channel(JoinChannelRequest {
-                channel_query: Some(
-                    ChannelQuery::ChannelName(channel_name).into(),
-                ),
+                channel_query: Some(channel_query.into()),
                 user_query: Some(UserQuery::UserId(self.user_id).into()),
             })
             .await?;
@@ -150,12 +178,11 @@ impl<'a> ProcessUserChannelPart for PacketProcessor<'a> {
         &self,
     ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
         let channel_name = read_channel_name(self.packet.payload)?;
+        let channel_query = self.channel_query(channel_name).await?;
 
         self.chat_service
             .leave_channel(LeaveChannelRequest {
-                channel_query: Some(
-                    ChannelQuery::ChannelName(channel_name).into(),
-                ),
+                channel_query: Some(channel_query.into()),
                 user_query: Some(UserQuery::UserId(self.user_id).into()),
             })
             .await?;
@@ -346,3 +373,291 @@ impl<'a> ProcessUserPresenceRequest for PacketProcessor<'a> {
         Ok(HandleCompleted::default())
     }
 }
+
+#[async_trait]
+impl<'a> ProcessSpectateStart for PacketProcessor<'a> {
+    #[inline]
+    async fn spectate_start(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let target_id = PayloadReader::new(
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?,
+        )
+        .read::<i32>()
+        .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+        self.bancho_service
+            .spectate_start(SpectateRequest {
+                user_id: self.user_id,
+                target_id,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessSpectateStop for PacketProcessor<'a> {
+    #[inline]
+    async fn spectate_stop(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        self.bancho_service
+            .spectate_stop(UserQuery::UserId(self.user_id))
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessSpectateCant for PacketProcessor<'a> {
+    #[inline]
+    async fn spectate_cant(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        self.bancho_service
+            .spectate_cant(UserQuery::UserId(self.user_id))
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessSpectateFrames for PacketProcessor<'a> {
+    #[inline]
+    async fn spectate_frames(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        // frames are relayed to spectators as is
+        let frames = self
+            .packet
+            .payload
+            .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?
+            .to_vec();
+
+        self.bancho_service
+            .spectate_frames(SpectateFramesRequest {
+                user_id: self.user_id,
+                frames,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessLobbyPart for PacketProcessor<'a> {
+    #[inline]
+    async fn lobby_part(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        self.bancho_service.lobby_part(UserQuery::UserId(self.user_id)).await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessLobbyJoin for PacketProcessor<'a> {
+    #[inline]
+    async fn lobby_join(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        self.bancho_service.lobby_join(UserQuery::UserId(self.user_id)).await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessMatchCreate for PacketProcessor<'a> {
+    #[inline]
+    async fn match_create(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let match_data = self
+            .packet
+            .payload
+            .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?
+            .to_vec();
+
+        self.bancho_service
+            .match_create(CreateMatchRequest {
+                user_query: Some(UserQuery::UserId(self.user_id).into()),
+                match_data,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessMatchJoin for PacketProcessor<'a> {
+    #[inline]
+    async fn match_join(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let mut reader = PayloadReader::new(
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?,
+        );
+
+        let match_id = reader
+            .read::<i32>()
+            .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+        // empty passwords are sent without the string marker
+        let password = reader.read::<String>().unwrap_or_default();
+
+        self.bancho_service
+            .match_join(JoinMatchRequest {
+                user_query: Some(UserQuery::UserId(self.user_id).into()),
+                match_id,
+                password,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessMatchPart for PacketProcessor<'a> {
+    #[inline]
+    async fn match_part(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        self.bancho_service.match_part(UserQuery::UserId(self.user_id)).await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessMatchUpdate for PacketProcessor<'a> {
+    #[inline]
+    async fn match_update(
+        &self,
+        action: MatchAction,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let payload = || {
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)
+        };
+
+        let (int_val, string_val, bytes_val) = match action {
+            MatchAction::ChangeSlot
+            | MatchAction::Lock
+            | MatchAction::ChangeMods
+            | MatchAction::TransferHost
+            | MatchAction::Invite => {
+                let val = PayloadReader::new(payload()?)
+                    .read::<i32>()
+                    .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+                (Some(val), None, None)
+            },
+            MatchAction::ChangeSettings | MatchAction::ScoreUpdate => {
+                (None, None, Some(payload()?.to_vec()))
+            },
+            MatchAction::ChangePassword => {
+                // the new password is sent within the whole match data
+                let MatchData { password, .. } = PayloadReader::new(payload()?)
+                    .read::<MatchData>()
+                    .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+                (None, Some(password.unwrap_or_default()), None)
+            },
+            _ => (None, None, None),
+        };
+
+        self.bancho_service
+            .match_update(UpdateMatchRequest {
+                user_query: Some(UserQuery::UserId(self.user_id).into()),
+                action: action as i32,
+                int_val,
+                string_val,
+                bytes_val,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessUserFriendAdd for PacketProcessor<'a> {
+    #[inline]
+    async fn user_friend_add(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let friend_id = PayloadReader::new(
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?,
+        )
+        .read::<i32>()
+        .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+        self.bancho_service
+            .friend_add(FriendRequest { user_id: self.user_id, friend_id })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessUserFriendRemove for PacketProcessor<'a> {
+    #[inline]
+    async fn user_friend_remove(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let friend_id = PayloadReader::new(
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?,
+        )
+        .read::<i32>()
+        .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+        self.bancho_service
+            .friend_remove(FriendRequest { user_id: self.user_id, friend_id })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl<'a> ProcessBeatmapInfoRequest for PacketProcessor<'a> {
+    #[inline]
+    async fn beatmap_info_request(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
+        let BeatmapInfoQuery { file_names, beatmap_ids } = PayloadReader::new(
+            self.packet
+                .payload
+                .ok_or(ProcessBanchoPacketError::PacketPayloadNotExists)?,
+        )
+        .read::<BeatmapInfoQuery>()
+        .ok_or(ProcessBanchoPacketError::InvalidPacketPayload)?;
+
+        Ok(self
+            .bancho_service
+            .request_beatmap_info(BeatmapInfoRequest {
+                user_id: self.user_id,
+                file_names,
+                beatmap_ids,
+            })
+            .await?)
+    }
+}
@@ -1,47 +1,503 @@
 use crate::*;
-use bancho_packets::{server, Packet, PacketBuilder, PacketId, PacketReader};
-use core_bancho_state::DynBanchoStateService;
-use core_chat::DynChatService;
+use bancho_packets::{
+    server, BeatmapInfo, Grade, Packet, PacketBuilder, PacketId, PacketReader,
+};
+use chrono::Utc;
+use core_bancho_state::{BanchoStateError, DynBanchoStateService};
+use core_chat::{update_user_performance, ChatBot, DynChatService};
 use core_geoip::DynGeoipService;
-use domain_bancho::BanchoCountryCode;
-use domain_chat::Platform;
+use core_pp::{DynPpService, PpError};
+use domain_bancho::{
+    BanchoCountryCode, BeatmapRankStatus, GameMode, HitCounts, LeaderboardType,
+    Mods, UserPrivileges,
+};
+use domain_chat::{MultiplayerChannel, Platform, SpectatorChannel};
+use domain_users::{CreateUser, Password};
+use futures::{stream, TryStreamExt};
 use infra_services::{FromRpcClient, IntoService, RpcClient};
+use num_traits::FromPrimitive;
 use pb_bancho::{bancho_rpc_client::BanchoRpcClient, *};
-use pb_bancho_state::*;
-use peace_repositories::users::DynUsersRepository;
-use std::{net::IpAddr, sync::Arc, time::Instant};
+use pb_bancho_state::{update_match_request::MatchAction, *};
+use pb_chat::{
+    ChannelQuery, JoinChannelRequest, LeaveChannelRequest,
+    MultiplayerChannelRequest, SetOnlyFriendPmAllowedRequest,
+    SpectatorChannelRequest,
+};
+use pb_pp::CalculatePerformanceRequest;
+use peace_db::{
+    peace::entity::{
+        beatmaps, score_pp, scores, scores_classic, screenshots,
+        sea_orm_active_enums::{
+            GameMode as DbGameMode, PpVersion, RankStatus, RankingType,
+            ScoreGrade, ScoreVersion,
+        },
+        user_stats, users,
+    },
+    prelude::Decimal,
+    ActiveEnum, Set,
+};
+use peace_repositories::{
+    beatmap_ratings::DynBeatmapRatingsRepository,
+    beatmaps::DynBeatmapsRepository,
+    favourite_beatmaps::DynFavouriteBeatmapsRepository,
+    followers::DynFollowersRepository,
+    leaderboard::{
+        DynLeaderboardRepository, LeaderboardFilter, LeaderboardScore,
+        UserBeatmapGrade,
+    },
+    scores::DynScoresRepository,
+    screenshots::DynScreenshotsRepository,
+    unique_violation,
+    users::DynUsersRepository,
+    GetBeatmapError, GetUserError, ScoresError, ScreenshotsError,
+};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Instant};
+use tokio::io::AsyncReadExt;
 use tonic::{async_trait, transport::Channel};
 use tools::{lazy_init, tonic_utils::RawRequest};
 
 #[derive(Clone)]
 pub struct BanchoServiceImpl {
     pub users_repository: DynUsersRepository,
+    pub followers_repository: DynFollowersRepository,
+    pub beatmaps_repository: DynBeatmapsRepository,
+    pub scores_repository: DynScoresRepository,
+    pub leaderboard_repository: DynLeaderboardRepository,
+    pub screenshots_repository: DynScreenshotsRepository,
+    pub favourite_beatmaps_repository: DynFavouriteBeatmapsRepository,
+    pub beatmap_ratings_repository: DynBeatmapRatingsRepository,
+    pub replay_store: DynReplayStore,
+    pub screenshot_store: DynScreenshotStore,
+    pub beatmap_fetcher: DynBeatmapFetcher,
+    pub direct_mirror: Option<DynDirectMirror>,
+    pub beatmapset_cache: Arc<BeatmapsetCache>,
     pub bancho_state_service: DynBanchoStateService,
     pub password_service: DynPasswordService,
     pub bancho_background_service: DynBanchoBackgroundService,
     pub geoip_service: DynGeoipService,
+    pub pp_service: DynPpService,
     pub chat_service: DynChatService,
+    pub registration_guard: Arc<RegistrationGuard>,
+    pub screenshot_cfg: Arc<CliBanchoScreenshotConfigs>,
+    pub client_policy: Arc<ClientPolicy>,
 }
 
 impl BanchoServiceImpl {
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         users_repository: DynUsersRepository,
+        followers_repository: DynFollowersRepository,
+        beatmaps_repository: DynBeatmapsRepository,
+        scores_repository: DynScoresRepository,
+        leaderboard_repository: DynLeaderboardRepository,
+        screenshots_repository: DynScreenshotsRepository,
+        favourite_beatmaps_repository: DynFavouriteBeatmapsRepository,
+        beatmap_ratings_repository: DynBeatmapRatingsRepository,
+        replay_store: DynReplayStore,
+        screenshot_store: DynScreenshotStore,
+        beatmap_fetcher: DynBeatmapFetcher,
+        direct_mirror: Option<DynDirectMirror>,
         bancho_state_service: DynBanchoStateService,
         password_service: DynPasswordService,
         bancho_background_service: DynBanchoBackgroundService,
         geoip_service: DynGeoipService,
+        pp_service: DynPpService,
         chat_service: DynChatService,
+        registration_cfg: &CliBanchoRegistrationConfigs,
+        screenshot_cfg: &CliBanchoScreenshotConfigs,
+        direct_cfg: &CliBeatmapDirectConfigs,
+        client_cfg: &CliBanchoClientConfigs,
     ) -> Self {
         Self {
             users_repository,
+            followers_repository,
+            beatmaps_repository,
+            scores_repository,
+            leaderboard_repository,
+            screenshots_repository,
+            favourite_beatmaps_repository,
+            beatmap_ratings_repository,
+            replay_store,
+            screenshot_store,
+            beatmap_fetcher,
+            direct_mirror,
+            beatmapset_cache: BeatmapsetCache::new(direct_cfg).into(),
             bancho_state_service,
             password_service,
             bancho_background_service,
             geoip_service,
+            pp_service,
             chat_service,
+            registration_guard: RegistrationGuard::new(registration_cfg).into(),
+            screenshot_cfg: screenshot_cfg.clone().into(),
+            client_policy: ClientPolicy::new(client_cfg).into(),
         }
     }
+
+    /// Leave the spectator channel of the host, the channel will be
+    /// removed if there are no spectators left.
+    pub async fn leave_spectator_channel(
+        &self,
+        user_query: UserQuery,
+        host_user_id: i32,
+        remaining_spectators: u32,
+    ) -> Result<(), BanchoServiceError> {
+        let _ = self
+            .chat_service
+            .leave_channel(LeaveChannelRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelId(SpectatorChannel::channel_id(
+                        host_user_id,
+                    ))
+                    .into(),
+                ),
+                user_query: Some(user_query.into()),
+            })
+            .await;
+
+        if remaining_spectators == 0 {
+            self.chat_service
+                .remove_spectator_channel(SpectatorChannelRequest {
+                    host_user_id,
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Leave the multiplayer channel of the match, the channel will be
+    /// removed if the match has been disbanded.
+    pub async fn leave_multiplayer_channel(
+        &self,
+        user_query: UserQuery,
+        left: LeaveMatchResponse,
+    ) -> Result<(), BanchoServiceError> {
+        let LeaveMatchResponse { match_id, disbanded } = left;
+
+        let match_id = match match_id {
+            Some(match_id) => match_id,
+            None => return Ok(()),
+        };
+
+        let _ = self
+            .chat_service
+            .leave_channel(LeaveChannelRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelId(MultiplayerChannel::channel_id(
+                        match_id,
+                    ))
+                    .into(),
+                ),
+                user_query: Some(user_query.into()),
+            })
+            .await;
+
+        if disbanded {
+            self.chat_service
+                .remove_multiplayer_channel(MultiplayerChannelRequest {
+                    match_id,
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Join the multiplayer channel of the match just joined, and leave
+    /// the channel of the previous match.
+    pub async fn join_multiplayer_channel(
+        &self,
+        user_query: UserQuery,
+        joined: JoinMatchResponse,
+    ) -> Result<(), BanchoServiceError> {
+        let JoinMatchResponse { match_id, previous } = joined;
+
+        if let Some(previous) = previous {
+            self.leave_multiplayer_channel(user_query.clone(), previous)
+                .await?;
+        }
+
+        self.chat_service
+            .create_multiplayer_channel(MultiplayerChannelRequest { match_id })
+            .await?;
+
+        self.chat_service
+            .join_channel(JoinChannelRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelId(MultiplayerChannel::channel_id(
+                        match_id,
+                    ))
+                    .into(),
+                ),
+                user_query: Some(user_query.into()),
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl BanchoServiceImpl {
+    /// Get the user by name and verify the password, the requests made with
+    /// the osu! client credentials are all authenticated here.
+    pub async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<users::Model, BanchoServiceError> {
+        let user = self
+            .users_repository
+            .get_user(None, Some(username), Some(username))
+            .await?;
+
+        let () = self
+            .password_service
+            .verify_password(user.password.as_str(), password)
+            .await?;
+
+        Ok(user)
+    }
+
+    /// Get the rank of the user's entry on the global leaderboard.
+    pub async fn user_leaderboard_rank(
+        &self,
+        beatmap_id: i32,
+        mode: DbGameMode,
+        ranking_type: RankingType,
+        user_id: i32,
+    ) -> Result<Option<u64>, BanchoServiceError> {
+        let entry = match self
+            .leaderboard_repository
+            .get_user_leaderboard_score(
+                beatmap_id,
+                mode.clone(),
+                ranking_type.clone(),
+                &LeaderboardFilter::default(),
+                user_id,
+            )
+            .await?
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        Ok(Some(
+            self.leaderboard_repository
+                .get_leaderboard_rank(
+                    beatmap_id,
+                    mode,
+                    ranking_type,
+                    &LeaderboardFilter::default(),
+                    &entry,
+                )
+                .await?,
+        ))
+    }
+
+    /// Get the beatmap by md5, the unknown ones are fetched from the beatmap
+    /// mirrors. Returns `None` if no mirror has the beatmap.
+    #[inline]
+    pub async fn get_or_fetch_beatmap(
+        &self,
+        md5: &str,
+    ) -> Result<Option<beatmaps::Model>, BanchoServiceError> {
+        self.get_or_fetch(BeatmapQuery::Md5(md5.to_owned())).await
+    }
+
+    /// Get the beatmap, the unknown ones are fetched from the beatmap
+    /// mirrors. Returns `None` if no mirror has the beatmap.
+    pub async fn get_or_fetch(
+        &self,
+        query: BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BanchoServiceError> {
+        match self.beatmap_fetcher.fetch_beatmap(query.clone()).await {
+            Ok(beatmap) => Ok(beatmap),
+            Err(BeatmapFetchError::BeatmapError(err)) => Err(err.into()),
+            Err(err) => {
+                warn!("failed to fetch beatmap ({query}): {err}");
+                Ok(None)
+            },
+        }
+    }
+
+    /// Calculate the pp of the score and save it, returns `None` if the pp
+    /// can not be calculated (e.g. the `.osu` file is missing).
+    pub async fn calculate_score_pp(
+        &self,
+        score_id: i64,
+        mode: DbGameMode,
+        request: CalculatePerformanceRequest,
+    ) -> Result<Option<Decimal>, BanchoServiceError> {
+        let mut result =
+            self.pp_service.calculate_performance(request.clone()).await;
+
+        if let Err(PpError::BeatmapFileNotExists(beatmap_id)) = result {
+            match self.beatmap_fetcher.fetch_osu_file(beatmap_id).await {
+                Ok(true) => {
+                    result =
+                        self.pp_service.calculate_performance(request).await
+                },
+                Ok(false) => {},
+                Err(err) => warn!(
+                    "failed to fetch .osu file of beatmap {beatmap_id}: {err}"
+                ),
+            }
+        }
+
+        let performance = match result {
+            Ok(performance) => performance,
+            Err(err) => {
+                warn!("failed to calculate pp of score {score_id}: {err}");
+                return Ok(None);
+            },
+        };
+
+        let pp = Decimal::from_f64_retain(performance.pp)
+            .unwrap_or_default()
+            .round_dp(2);
+
+        self.scores_repository
+            .save_score_pp(score_pp::Model {
+                score_id,
+                mode,
+                pp_version: PpVersion::V2,
+                pp,
+                raw_pp: serde_json::to_value(&performance).ok(),
+            })
+            .await?;
+
+        Ok(Some(pp))
+    }
+
+    /// Get a score with its replay frames, scores that are invisible are only
+    /// available to its owner and the staff.
+    pub async fn replay_score(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<
+        (scores::Model, scores_classic::Model, Vec<u8>),
+        BanchoServiceError,
+    > {
+        let GetReplayRequest { username, password, score_id } = request;
+
+        let (score, classic) = self
+            .scores_repository
+            .get_classic_score(score_id)
+            .await?
+            .ok_or(BanchoServiceError::ScoreNotExists)?;
+
+        if !can_access_replay(&score, None, false) {
+            let (Some(username), Some(password)) = (username, password) else {
+                return Err(BanchoServiceError::ReplayAccessDenied);
+            };
+
+            let user = self.authenticate(&username, &password).await?;
+
+            let is_staff = UserPrivileges::from_names(
+                self.users_repository
+                    .get_user_privileges(user.id)
+                    .await?
+                    .iter()
+                    .map(|p| p.name.as_str()),
+            )
+            .is_staff();
+
+            if !can_access_replay(&score, Some(user.id), is_staff) {
+                return Err(BanchoServiceError::ReplayAccessDenied);
+            }
+        }
+
+        let replay = self
+            .replay_store
+            .load_replay(score.id)
+            .await?
+            .ok_or(BanchoServiceError::ReplayNotExists)?;
+
+        Ok((score, classic, replay))
+    }
+
+    /// Resolve the beatmaps queried by the song select with the user's grades
+    /// on them, the ones queried by file names are indexed by their position,
+    /// the others by `-1`.
+    pub async fn beatmap_infos(
+        &self,
+        user_id: i32,
+        file_names: &[String],
+        beatmap_ids: &[i32],
+    ) -> Result<Vec<BeatmapInfo>, BanchoServiceError> {
+        let by_file_name = self
+            .beatmaps_repository
+            .get_beatmaps_by_file_names(file_names)
+            .await?
+            .into_iter()
+            .map(|beatmap| (beatmap.file_name.clone(), beatmap))
+            .collect::<HashMap<_, _>>();
+
+        let mut beatmaps = file_names
+            .iter()
+            .enumerate()
+            .filter_map(|(index, file_name)| {
+                by_file_name
+                    .get(file_name)
+                    .map(|beatmap| (index as i16, beatmap.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        beatmaps.extend(
+            self.beatmaps_repository
+                .get_beatmaps_by_bids(beatmap_ids)
+                .await?
+                .into_iter()
+                .map(|beatmap| (-1, beatmap)),
+        );
+
+        let ranking_type = self
+            .users_repository
+            .get_user_settings(user_id)
+            .await?
+            .map(|settings| {
+                scoreboard_ranking_type(settings.scoreboard_ranking_type)
+            })
+            .unwrap_or(RankingType::ScoreV1);
+
+        let mut grades = HashMap::new();
+        for UserBeatmapGrade { beatmap_id, mode, grade } in self
+            .leaderboard_repository
+            .get_user_beatmap_grades(
+                user_id,
+                &beatmaps.iter().map(|(_, b)| b.bid).collect::<Vec<_>>(),
+                ranking_type,
+            )
+            .await?
+        {
+            grades.insert((beatmap_id, mode.to_value()), client_grade(&grade));
+        }
+
+        let grade = |bid: i32, mode: DbGameMode| {
+            grades.get(&(bid, mode.to_value())).copied().unwrap_or_default()
+                as u8
+        };
+
+        Ok(beatmaps
+            .into_iter()
+            .map(|(index, beatmap)| BeatmapInfo {
+                index,
+                beatmap_id: beatmap.bid,
+                beatmapset_id: beatmap.sid,
+                thread_id: 0,
+                rank_status: client_rank_status(&beatmap.rank_status).val()
+                    as u8,
+                osu_grade: grade(beatmap.bid, DbGameMode::Standard),
+                fruits_grade: grade(beatmap.bid, DbGameMode::Fruits),
+                taiko_grade: grade(beatmap.bid, DbGameMode::Taiko),
+                mania_grade: grade(beatmap.bid, DbGameMode::Mania),
+                beatmap_md5: beatmap.md5,
+            })
+            .collect())
+    }
 }
 
 impl BanchoService for BanchoServiceImpl {}
@@ -78,6 +534,8 @@ impl Login for BanchoServiceImpl {
         );
         let start = Instant::now();
 
+        self.client_policy.check_client_version(&client_version)?;
+
         // MOCK -------------------
         #[cfg(feature = "bancho-mock-test")]
         let user = {
@@ -88,21 +546,7 @@ impl Login for BanchoServiceImpl {
             static MOCK_COUNT: U64 = U64::new(10000);
             const EXCLUDE_USERS: [&str; 2] = ["test1", "test"];
             if EXCLUDE_USERS.contains(&username.as_str()) {
-                let user = self
-                    .users_repository
-                    .get_user(
-                        None,
-                        Some(username.as_str()),
-                        Some(username.as_str()),
-                    )
-                    .await?;
-
-                let () = self
-                    .password_service
-                    .verify_password(user.password.as_str(), password.as_str())
-                    .await?;
-
-                user
+                self.authenticate(&username, &password).await?
             } else {
                 peace_db::peace::entity::users::Model {
                     id: MOCK_COUNT.add(1) as i32,
@@ -123,47 +567,65 @@ impl Login for BanchoServiceImpl {
                     country: Some("".into()),
                     created_at: Utc::now().into(),
                     updated_at: Utc::now().into(),
+                    silence_end: None,
                 }
             }
         };
 
         #[cfg(not(feature = "bancho-mock-test"))]
-        let user = self
-            .users_repository
-            .get_user(None, Some(username.as_str()), Some(username.as_str()))
-            .await?;
+        let user = self.authenticate(&username, &password).await?;
+
+        let privileges = UserPrivileges::from_names(
+            self.users_repository
+                .get_user_privileges(user.id)
+                .await?
+                .iter()
+                .map(|p| p.name.as_str()),
+        );
 
-        #[cfg(not(feature = "bancho-mock-test"))]
-        let () = self
-            .password_service
-            .verify_password(user.password.as_str(), password.as_str())
-            .await?;
+        if privileges.is_banned() {
+            return Err(BanchoServiceError::UserBanned);
+        }
+
+        let bancho_privileges = privileges.bancho_privileges();
+
+        let silence_end = user
+            .silence_end
+            .map(|end| (end.timestamp() - Utc::now().timestamp()).max(0))
+            .unwrap_or_default() as i32;
 
         let geoip_data =
             self.geoip_service.lookup_with_ip_address(client_ip).await.ok();
 
+        self.client_policy.check_country(
+            geoip_data.as_ref().map(|d| d.country.code.as_str()),
+        )?;
+
         let country_code = geoip_data
             .as_ref()
             .map(|d| BanchoCountryCode::get_code(&d.country.code))
             .unwrap_or_default();
 
+        let friends = self.followers_repository.get_friends(user.id).await?;
+
         let CreateUserSessionResponse { session_id, signature } = self
             .bancho_state_service
             .create_user_session(CreateUserSessionRequest {
                 user_id: user.id,
                 username: user.name.to_owned(),
                 username_unicode: user.name_unicode.to_owned(),
-                privileges: 1, // todo
+                privileges: privileges.bits(),
                 client_version,
                 utc_offset,
                 display_city,
                 only_friend_pm_allowed,
-                bancho_privileges: 1, // todo
+                bancho_privileges: bancho_privileges.bits(),
                 connection_info: Some(ConnectionInfo {
                     ip: client_ip.to_string(),
                     geoip_data: geoip_data.map(|g| g.into()),
                 }),
                 country_code: country_code as i32,
+                friends: friends.clone(),
             })
             .await?;
 
@@ -173,8 +635,10 @@ impl Login for BanchoServiceImpl {
                 user_id: user.id,
                 username: user.name.to_owned(),
                 username_unicode: user.name_unicode,
-                privileges: 1, // todo
+                privileges: privileges.bits(),
                 platforms: Platform::Bancho.bits(),
+                silence_end: user.silence_end.map(|end| end.timestamp()),
+                only_friend_pm_allowed,
             })
             .await
         {
@@ -184,12 +648,17 @@ impl Login for BanchoServiceImpl {
             )
         }
 
-        let packet_builder = PacketBuilder::new()
+        let mut packet_builder = PacketBuilder::new()
             .add(server::ProtocolVersion::new(19))
             .add(server::LoginReply::success(user.id))
-            .add(server::BanchoPrivileges::new(1))
-            .add(server::SilenceEnd::new(0)) // todo
-            .add(server::FriendsList::new(&[]));
+            .add(server::BanchoPrivileges::new(bancho_privileges.bits()))
+            .add(server::SilenceEnd::new(silence_end))
+            .add(server::FriendsList::new(&friends))
+            .add(ChatBot::presence_packets());
+
+        if privileges.is_restricted() {
+            packet_builder.add_ref(server::AccountRestricted::new());
+        }
 
         info!(
             target: LOG_TARGET,
@@ -299,8 +768,12 @@ impl ProcessPackets for BanchoServiceImpl {
             PacketId::OSU_USER_RECEIVE_UPDATES => {
                 processor.user_receive_updates().await?
             },
-            PacketId::OSU_USER_FRIEND_ADD => todo!(),
-            PacketId::OSU_USER_FRIEND_REMOVE => todo!(),
+            PacketId::OSU_USER_FRIEND_ADD => {
+                processor.user_friend_add().await?
+            },
+            PacketId::OSU_USER_FRIEND_REMOVE => {
+                processor.user_friend_remove().await?
+            },
             PacketId::OSU_USER_TOGGLE_BLOCK_NON_FRIEND_DMS => {
                 processor.user_toggle_block_non_friend_dms().await?
             },
@@ -310,34 +783,76 @@ impl ProcessPackets for BanchoServiceImpl {
                 processor.user_presence_request().await?
             },
             // Spectate
-            PacketId::OSU_SPECTATE_START => todo!(),
-            PacketId::OSU_SPECTATE_STOP => todo!(),
-            PacketId::OSU_SPECTATE_CANT => todo!(),
-            PacketId::OSU_SPECTATE_FRAMES => todo!(),
+            PacketId::OSU_SPECTATE_START => processor.spectate_start().await?,
+            PacketId::OSU_SPECTATE_STOP => processor.spectate_stop().await?,
+            PacketId::OSU_SPECTATE_CANT => processor.spectate_cant().await?,
+            PacketId::OSU_SPECTATE_FRAMES => {
+                processor.spectate_frames().await?
+            },
             // Multiplayer
-            PacketId::OSU_USER_PART_LOBBY => todo!(),
-            PacketId::OSU_USER_JOIN_LOBBY => todo!(),
-            PacketId::OSU_USER_PART_MATCH => todo!(),
-            PacketId::OSU_USER_MATCH_READY => todo!(),
-            PacketId::OSU_USER_CREATE_MATCH => todo!(),
-            PacketId::OSU_USER_JOIN_MATCH => todo!(),
-            PacketId::OSU_MATCH_START => todo!(),
-            PacketId::OSU_MATCH_COMPLETE => todo!(),
-            PacketId::OSU_MATCH_LOAD_COMPLETE => todo!(),
-            PacketId::OSU_MATCH_NO_BEATMAP => todo!(),
-            PacketId::OSU_MATCH_NOT_READY => todo!(),
-            PacketId::OSU_MATCH_FAILED => todo!(),
-            PacketId::OSU_MATCH_HAS_BEATMAP => todo!(),
-            PacketId::OSU_MATCH_SKIP_REQUEST => todo!(),
-            PacketId::OSU_MATCH_CHANGE_TEAM => todo!(),
-            PacketId::OSU_MATCH_CHANGE_SLOT => todo!(),
-            PacketId::OSU_MATCH_LOCK => todo!(),
-            PacketId::OSU_MATCH_CHANGE_SETTINGS => todo!(),
-            PacketId::OSU_MATCH_SCORE_UPDATE => todo!(),
-            PacketId::OSU_MATCH_CHANGE_MODS => todo!(),
-            PacketId::OSU_MATCH_TRANSFER_HOST => todo!(),
-            PacketId::OSU_MATCH_INVITE => todo!(),
-            PacketId::OSU_MATCH_CHANGE_PASSWORD => todo!(),
+            PacketId::OSU_USER_PART_LOBBY => processor.lobby_part().await?,
+            PacketId::OSU_USER_JOIN_LOBBY => processor.lobby_join().await?,
+            PacketId::OSU_USER_PART_MATCH => processor.match_part().await?,
+            PacketId::OSU_USER_CREATE_MATCH => processor.match_create().await?,
+            PacketId::OSU_USER_JOIN_MATCH => processor.match_join().await?,
+            PacketId::OSU_USER_MATCH_READY => {
+                processor.match_update(MatchAction::Ready).await?
+            },
+            PacketId::OSU_MATCH_START => {
+                processor.match_update(MatchAction::Start).await?
+            },
+            PacketId::OSU_MATCH_COMPLETE => {
+                processor.match_update(MatchAction::Complete).await?
+            },
+            PacketId::OSU_MATCH_LOAD_COMPLETE => {
+                processor.match_update(MatchAction::LoadComplete).await?
+            },
+            PacketId::OSU_MATCH_NO_BEATMAP => {
+                processor.match_update(MatchAction::NoBeatmap).await?
+            },
+            PacketId::OSU_MATCH_NOT_READY => {
+                processor.match_update(MatchAction::NotReady).await?
+            },
+            PacketId::OSU_MATCH_FAILED => {
+                processor.match_update(MatchAction::Failed).await?
+            },
+            PacketId::OSU_MATCH_HAS_BEATMAP => {
+                processor.match_update(MatchAction::HasBeatmap).await?
+            },
+            PacketId::OSU_MATCH_SKIP_REQUEST => {
+                processor.match_update(MatchAction::SkipRequest).await?
+            },
+            PacketId::OSU_MATCH_CHANGE_TEAM => {
+                processor.match_update(MatchAction::ChangeTeam).await?
+            },
+            PacketId::OSU_MATCH_CHANGE_SLOT => {
+                processor.match_update(MatchAction::ChangeSlot).await?
+            },
+            PacketId::OSU_MATCH_LOCK => {
+                processor.match_update(MatchAction::Lock).await?
+            },
+            PacketId::OSU_MATCH_CHANGE_SETTINGS => {
+                processor.match_update(MatchAction::ChangeSettings).await?
+            },
+            PacketId::OSU_MATCH_SCORE_UPDATE => {
+                processor.match_update(MatchAction::ScoreUpdate).await?
+            },
+            PacketId::OSU_MATCH_CHANGE_MODS => {
+                processor.match_update(MatchAction::ChangeMods).await?
+            },
+            PacketId::OSU_MATCH_TRANSFER_HOST => {
+                processor.match_update(MatchAction::TransferHost).await?
+            },
+            PacketId::OSU_MATCH_INVITE => {
+                processor.match_update(MatchAction::Invite).await?
+            },
+            PacketId::OSU_MATCH_CHANGE_PASSWORD => {
+                processor.match_update(MatchAction::ChangePassword).await?
+            },
+            // Beatmap
+            PacketId::OSU_BEATMAP_INFO_REQUEST => {
+                processor.beatmap_info_request().await?
+            },
             // Tournament
             PacketId::OSU_TOURNAMENT_MATCH_INFO_REQUEST => todo!(),
             PacketId::OSU_TOURNAMENT_JOIN_MATCH_CHANNEL => todo!(),
@@ -468,9 +983,14 @@ impl ToggleBlockNonFriendDms for BanchoServiceImpl {
         &self,
         request: ToggleBlockNonFriendDmsRequest,
     ) -> Result<HandleCompleted, BanchoServiceError> {
-        println!("Got a request: {:?}", request);
+        let ToggleBlockNonFriendDmsRequest { user_id, toggle } = request;
 
-        // todo chat service
+        self.chat_service
+            .set_only_friend_pm_allowed(SetOnlyFriendPmAllowedRequest {
+                user_query: Some(UserQuery::UserId(user_id).into()),
+                only_friend_pm_allowed: toggle,
+            })
+            .await?;
 
         Ok(HandleCompleted::default())
     }
@@ -482,6 +1002,26 @@ impl UserLogout for BanchoServiceImpl {
         &self,
         query: UserQuery,
     ) -> Result<HandleCompleted, BanchoServiceError> {
+        // clean up spectating before the session is gone
+        if let Ok(SpectateState { user_id, spectating, spectators }) =
+            self.bancho_state_service.get_spectate_state(query.clone()).await
+        {
+            if spectating.is_some() {
+                let _ = self.spectate_stop(query.clone()).await;
+            }
+
+            if !spectators.is_empty() {
+                let _ = self
+                    .chat_service
+                    .remove_spectator_channel(SpectatorChannelRequest {
+                        host_user_id: user_id,
+                    })
+                    .await;
+            }
+        }
+
+        let _ = self.match_part(query.clone()).await;
+
         self.bancho_state_service.delete_user_session(query.clone()).await?;
         let _ = self.chat_service.logout(query, Platform::Bancho).await;
 
@@ -513,13 +1053,77 @@ impl RequestPresence for BanchoServiceImpl {
     }
 }
 
+#[async_trait]
+impl SpectateStart for BanchoServiceImpl {
+    async fn spectate_start(
+        &self,
+        request: SpectateRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let SpectateRequest { user_id, target_id } = request;
+
+        let StartSpectatingResponse { host_user_id, previous } = self
+            .bancho_state_service
+            .start_spectating(StartSpectatingRequest {
+                user_query: Some(UserQuery::UserId(user_id).into()),
+                target_query: Some(UserQuery::UserId(target_id).into()),
+            })
+            .await?;
+
+        // leave previous host's spectator channel
+        if let Some(StopSpectatingResponse {
+            host_user_id: Some(prev_host_user_id),
+            remaining_spectators,
+        }) = previous
+        {
+            if prev_host_user_id != host_user_id {
+                self.leave_spectator_channel(
+                    UserQuery::UserId(user_id),
+                    prev_host_user_id,
+                    remaining_spectators,
+                )
+                .await?;
+            }
+        }
+
+        self.chat_service
+            .create_spectator_channel(SpectatorChannelRequest { host_user_id })
+            .await?;
+
+        self.chat_service
+            .join_channel(JoinChannelRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelId(SpectatorChannel::channel_id(
+                        host_user_id,
+                    ))
+                    .into(),
+                ),
+                user_query: Some(UserQuery::UserId(user_id).into()),
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
 #[async_trait]
 impl SpectateStop for BanchoServiceImpl {
     async fn spectate_stop(
         &self,
         user_query: UserQuery,
     ) -> Result<HandleCompleted, BanchoServiceError> {
-        println!("Got a request: {:?}", user_query);
+        let StopSpectatingResponse { host_user_id, remaining_spectators } =
+            self.bancho_state_service
+                .stop_spectating(user_query.clone())
+                .await?;
+
+        if let Some(host_user_id) = host_user_id {
+            self.leave_spectator_channel(
+                user_query,
+                host_user_id,
+                remaining_spectators,
+            )
+            .await?;
+        }
 
         Ok(HandleCompleted::default())
     }
@@ -531,7 +1135,26 @@ impl SpectateCant for BanchoServiceImpl {
         &self,
         user_query: UserQuery,
     ) -> Result<HandleCompleted, BanchoServiceError> {
-        println!("Got a request: {:?}", user_query);
+        self.bancho_state_service.cant_spectate(user_query).await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl SpectateFrames for BanchoServiceImpl {
+    async fn spectate_frames(
+        &self,
+        request: SpectateFramesRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let SpectateFramesRequest { user_id, frames } = request;
+
+        self.bancho_state_service
+            .broadcast_spectate_frames(BroadcastSpectateFramesRequest {
+                user_query: Some(UserQuery::UserId(user_id).into()),
+                frames,
+            })
+            .await?;
 
         Ok(HandleCompleted::default())
     }
@@ -543,7 +1166,7 @@ impl LobbyPart for BanchoServiceImpl {
         &self,
         user_query: UserQuery,
     ) -> Result<HandleCompleted, BanchoServiceError> {
-        println!("Got a request: {:?}", user_query);
+        self.bancho_state_service.leave_lobby(user_query).await?;
 
         Ok(HandleCompleted::default())
     }
@@ -555,58 +1178,1493 @@ impl LobbyJoin for BanchoServiceImpl {
         &self,
         user_query: UserQuery,
     ) -> Result<HandleCompleted, BanchoServiceError> {
-        println!("Got a request: {:?}", user_query);
+        self.bancho_state_service.join_lobby(user_query).await?;
 
         Ok(HandleCompleted::default())
     }
 }
 
-#[derive(Clone)]
-pub struct BanchoServiceRemote(BanchoRpcClient<Channel>);
+#[async_trait]
+impl MatchCreate for BanchoServiceImpl {
+    async fn match_create(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let user_query = request
+            .user_query
+            .clone()
+            .ok_or(BanchoStateError::InvalidArgument)?
+            .into_user_query()?;
 
-impl BanchoService for BanchoServiceRemote {}
+        let joined = self.bancho_state_service.create_match(request).await?;
 
-impl IntoService<DynBanchoService> for BanchoServiceRemote {
-    #[inline]
-    fn into_service(self) -> DynBanchoService {
-        Arc::new(self) as DynBanchoService
-    }
-}
+        self.join_multiplayer_channel(user_query, joined).await?;
 
-impl FromRpcClient for BanchoServiceRemote {
-    #[inline]
-    fn from_client(client: Self::Client) -> Self {
-        Self(client)
+        Ok(HandleCompleted::default())
     }
 }
 
-impl RpcClient for BanchoServiceRemote {
-    type Client = BanchoRpcClient<Channel>;
+#[async_trait]
+impl MatchJoin for BanchoServiceImpl {
+    async fn match_join(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let user_query = request
+            .user_query
+            .clone()
+            .ok_or(BanchoStateError::InvalidArgument)?
+            .into_user_query()?;
+
+        let joined = match self.bancho_state_service.join_match(request).await {
+            Ok(joined) => joined,
+            // the client has already been notified
+            Err(BanchoStateError::JoinMatchFailed) => {
+                return Ok(HandleCompleted::default())
+            },
+            Err(err) => return Err(err.into()),
+        };
 
-    fn client(&self) -> Self::Client {
-        self.0.clone()
+        self.join_multiplayer_channel(user_query, joined).await?;
+
+        Ok(HandleCompleted::default())
     }
 }
 
 #[async_trait]
-impl Login for BanchoServiceRemote {
-    async fn login(
+impl MatchPart for BanchoServiceImpl {
+    async fn match_part(
         &self,
-        client_ip: IpAddr,
-        request: LoginRequest,
-    ) -> Result<LoginSuccess, BanchoServiceError> {
-        Ok(self
-            .client()
-            .login(RawRequest::add_client_ip(request, client_ip))
-            .await?
-            .into_inner())
+        user_query: UserQuery,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let left =
+            self.bancho_state_service.leave_match(user_query.clone()).await?;
+
+        self.leave_multiplayer_channel(user_query, left).await?;
+
+        Ok(HandleCompleted::default())
     }
 }
+
 #[async_trait]
-impl BatchProcessPackets for BanchoServiceRemote {
-    async fn batch_process_bancho_packets(
+impl MatchUpdate for BanchoServiceImpl {
+    async fn match_update(
         &self,
-        request: BatchProcessBanchoPacketsRequest,
+        request: UpdateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        self.bancho_state_service.update_match(request).await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl FriendAdd for BanchoServiceImpl {
+    async fn friend_add(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let FriendRequest { user_id, friend_id } = request;
+
+        self.followers_repository.add_friend(user_id, friend_id).await?;
+
+        self.bancho_state_service
+            .add_user_friend(UserFriendRequest {
+                user_query: Some(UserQuery::UserId(user_id).into()),
+                friend_id,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl FriendRemove for BanchoServiceImpl {
+    async fn friend_remove(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let FriendRequest { user_id, friend_id } = request;
+
+        self.followers_repository.remove_friend(user_id, friend_id).await?;
+
+        self.bancho_state_service
+            .remove_user_friend(UserFriendRequest {
+                user_query: Some(UserQuery::UserId(user_id).into()),
+                friend_id,
+            })
+            .await?;
+
+        Ok(HandleCompleted::default())
+    }
+}
+
+#[async_trait]
+impl GetFriends for BanchoServiceImpl {
+    async fn get_friends(
+        &self,
+        request: GetFriendsRequest,
+    ) -> Result<GetFriendsResponse, BanchoServiceError> {
+        let GetFriendsRequest { username, password } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let friends = self.followers_repository.get_friends(user.id).await?;
+
+        Ok(GetFriendsResponse { friends })
+    }
+}
+
+#[async_trait]
+impl VerifyCredentials for BanchoServiceImpl {
+    async fn verify_credentials(
+        &self,
+        request: VerifyCredentialsRequest,
+    ) -> Result<VerifyCredentialsResponse, BanchoServiceError> {
+        let VerifyCredentialsRequest { username, password } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        Ok(VerifyCredentialsResponse { user_id: user.id })
+    }
+}
+
+#[async_trait]
+impl Register for BanchoServiceImpl {
+    async fn register(
+        &self,
+        client_ip: IpAddr,
+        request: RegisterRequest,
+    ) -> Result<RegisterResponse, BanchoServiceError> {
+        const LOG_TARGET: &str = "bancho::register";
+
+        let RegisterRequest { username, email, password, check } = request;
+
+        if self.registration_guard.disabled {
+            return Err(BanchoServiceError::RegistrationDisabled);
+        }
+
+        // the checks and the failed registrations are limited too
+        if !self.registration_guard.try_acquire(client_ip) {
+            return Err(BanchoServiceError::RegistrationRateLimited);
+        }
+
+        let (name, email, mut errors) =
+            RegistrationErrors::validate(&username, &email, &password);
+
+        if let Some(name) = &name {
+            match self
+                .users_repository
+                .get_user(None, Some(name.as_str()), Some(name.as_str()))
+                .await
+            {
+                Ok(_) => errors
+                    .username
+                    .push("Username already taken by another player.".into()),
+                Err(GetUserError::UserNotExists) => {},
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if let Some(email) = &email {
+            match self.users_repository.get_user_by_email(email).await {
+                Ok(_) => errors
+                    .user_email
+                    .push("Email already taken by another player.".into()),
+                Err(GetUserError::UserNotExists) => {},
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let (Some(name), Some(email)) = (name, email) else {
+            return Err(BanchoServiceError::InvalidRegistration(errors));
+        };
+
+        if !errors.is_empty() {
+            return Err(BanchoServiceError::InvalidRegistration(errors));
+        }
+
+        if check {
+            return Ok(RegisterResponse { user_id: None });
+        }
+
+        // the osu! clients log in with the md5 of the password
+        let password = Password::hash_password(format!(
+            "{:x}",
+            md5::compute(password.as_bytes())
+        ))?;
+
+        let country = self
+            .geoip_service
+            .lookup_with_ip_address(client_ip)
+            .await
+            .ok()
+            .map(|geo| geo.country.code);
+
+        let user_id = match self
+            .users_repository
+            .create_user(CreateUser {
+                name,
+                name_unicode: None,
+                password,
+                email,
+                country,
+            })
+            .await
+        {
+            Ok(res) => res.last_insert_id,
+            Err(err) => {
+                // the username or email is taken after the checks
+                let Some(constraint) = unique_violation(&err) else {
+                    return Err(err.into());
+                };
+
+                let mut errors = RegistrationErrors::default();
+                if constraint.contains("email") {
+                    errors
+                        .user_email
+                        .push("Email already taken by another player.".into());
+                } else {
+                    errors.username.push(
+                        "Username already taken by another player.".into(),
+                    );
+                }
+
+                return Err(BanchoServiceError::InvalidRegistration(errors));
+            },
+        };
+
+        info!(
+            target: LOG_TARGET,
+            "User {username}({user_id}) registered from {client_ip}"
+        );
+
+        Ok(RegisterResponse { user_id: Some(user_id) })
+    }
+}
+
+#[async_trait]
+impl BanchoConnect for BanchoServiceImpl {
+    async fn bancho_connect(
+        &self,
+        client_ip: IpAddr,
+        request: BanchoConnectRequest,
+    ) -> Result<BanchoConnectResponse, BanchoServiceError> {
+        let BanchoConnectRequest { username, password, client_version } =
+            request;
+
+        self.client_policy.check_client_version(&client_version)?;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let privileges = UserPrivileges::from_names(
+            self.users_repository
+                .get_user_privileges(user.id)
+                .await?
+                .iter()
+                .map(|p| p.name.as_str()),
+        );
+
+        if privileges.is_banned() {
+            return Err(BanchoServiceError::UserBanned);
+        }
+
+        let geoip_country = self
+            .geoip_service
+            .lookup_with_ip_address(client_ip)
+            .await
+            .ok()
+            .map(|geo| geo.country.code);
+
+        self.client_policy.check_country(geoip_country.as_deref())?;
+
+        let country_code =
+            geoip_country.or(user.country).unwrap_or_default().to_lowercase();
+
+        Ok(BanchoConnectResponse { country_code })
+    }
+}
+
+#[async_trait]
+impl UploadScreenshot for BanchoServiceImpl {
+    async fn upload_screenshot(
+        &self,
+        request: UploadScreenshotRequest,
+    ) -> Result<UploadScreenshotResponse, BanchoServiceError> {
+        const LOG_TARGET: &str = "bancho::upload_screenshot";
+
+        let UploadScreenshotRequest { username, password, data } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let cfg = &self.screenshot_cfg;
+
+        if data.len() as u64 > cfg.screenshot_max_bytes {
+            return Err(BanchoServiceError::ScreenshotTooLarge);
+        }
+
+        let format = ScreenshotFormat::detect(&data)
+            .ok_or(BanchoServiceError::InvalidScreenshot)?;
+
+        let hash = format!("{:x}", Sha256::digest(&data));
+
+        // same content, same screenshot, the missing file is saved again
+        if let Some(screenshot) =
+            self.screenshots_repository.get_screenshot_by_hash(&hash).await?
+        {
+            self.screenshot_store.save_screenshot(&hash, &data).await?;
+            return Ok(UploadScreenshotResponse { name: screenshot.name });
+        }
+
+        // the full hash is used if the short name is taken by other content
+        let short_name = format!(
+            "{}.{}",
+            &hash[..SCREENSHOT_NAME_HASH_LENGTH],
+            format.extension()
+        );
+        let name = match self
+            .screenshots_repository
+            .get_screenshot(&short_name)
+            .await?
+        {
+            Some(_) => format!("{hash}.{}", format.extension()),
+            None => short_name,
+        };
+
+        let created = self
+            .screenshots_repository
+            .create_screenshot_within_quota(
+                screenshots::ActiveModel {
+                    user_id: Set(user.id),
+                    name: Set(name.clone()),
+                    hash: Set(hash.clone()),
+                    content_type: Set(format.content_type().to_owned()),
+                    size: Set(data.len() as i32),
+                    ..Default::default()
+                },
+                (Utc::now()
+                    - chrono::Duration::seconds(
+                        cfg.screenshot_quota_window_secs as i64,
+                    ))
+                .into(),
+                cfg.screenshot_quota_count,
+                cfg.screenshot_quota_bytes,
+            )
+            .await;
+
+        let name = match created {
+            Ok(screenshot) => screenshot.name,
+            Err(ScreenshotsError::QuotaExceeded) => {
+                return Err(BanchoServiceError::ScreenshotQuotaExceeded)
+            },
+            // the same content is uploaded concurrently
+            Err(err) => match self
+                .screenshots_repository
+                .get_screenshot_by_hash(&hash)
+                .await?
+            {
+                Some(screenshot) => screenshot.name,
+                None => return Err(err.into()),
+            },
+        };
+
+        self.screenshot_store.save_screenshot(&hash, &data).await?;
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) uploaded screenshot {name}", user.name, user.id
+        );
+
+        Ok(UploadScreenshotResponse { name })
+    }
+}
+
+#[async_trait]
+impl GetScreenshot for BanchoServiceImpl {
+    async fn get_screenshot(
+        &self,
+        request: GetScreenshotRequest,
+    ) -> Result<GetScreenshotResponse, BanchoServiceError> {
+        let screenshot = self
+            .screenshots_repository
+            .get_screenshot(&request.name)
+            .await?
+            .ok_or(BanchoServiceError::ScreenshotNotExists)?;
+
+        let data = self
+            .screenshot_store
+            .load_screenshot(&screenshot.hash)
+            .await?
+            .ok_or(BanchoServiceError::ScreenshotNotExists)?;
+
+        Ok(GetScreenshotResponse {
+            data,
+            content_type: screenshot.content_type,
+            hash: screenshot.hash,
+        })
+    }
+}
+
+#[async_trait]
+impl GetFavouriteBeatmaps for BanchoServiceImpl {
+    async fn get_favourite_beatmaps(
+        &self,
+        request: GetFavouriteBeatmapsRequest,
+    ) -> Result<GetFavouriteBeatmapsResponse, BanchoServiceError> {
+        let GetFavouriteBeatmapsRequest { username, password } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let beatmapset_ids = self
+            .favourite_beatmaps_repository
+            .get_favourite_beatmapsets(user.id)
+            .await?;
+
+        Ok(GetFavouriteBeatmapsResponse { beatmapset_ids })
+    }
+}
+
+#[async_trait]
+impl AddFavouriteBeatmap for BanchoServiceImpl {
+    async fn add_favourite_beatmap(
+        &self,
+        request: AddFavouriteBeatmapRequest,
+    ) -> Result<AddFavouriteBeatmapResponse, BanchoServiceError> {
+        let AddFavouriteBeatmapRequest { username, password, beatmapset_id } =
+            request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        // the beatmapsets unknown to the server are fetched from the mirrors
+        self.get_or_fetch(BeatmapQuery::BeatmapsetId(beatmapset_id))
+            .await?
+            .ok_or(GetBeatmapError::BeatmapNotExists)?;
+
+        let added = self
+            .favourite_beatmaps_repository
+            .add_favourite_beatmapset(user.id, beatmapset_id)
+            .await?;
+
+        Ok(AddFavouriteBeatmapResponse { added })
+    }
+}
+
+#[async_trait]
+impl RateBeatmap for BanchoServiceImpl {
+    async fn rate_beatmap(
+        &self,
+        request: RateBeatmapRequest,
+    ) -> Result<RateBeatmapResponse, BanchoServiceError> {
+        const RATING_RANGE: std::ops::RangeInclusive<i32> = 1..=10;
+
+        let RateBeatmapRequest { username, password, beatmap_md5, rating } =
+            request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let beatmap = self
+            .get_or_fetch_beatmap(&beatmap_md5)
+            .await?
+            .ok_or(GetBeatmapError::BeatmapNotExists)?;
+
+        if !matches!(
+            beatmap.rank_status,
+            RankStatus::Ranked | RankStatus::Loved
+        ) {
+            return Err(BanchoServiceError::BeatmapNotRateable);
+        }
+
+        match rating {
+            Some(rating) => {
+                if !RATING_RANGE.contains(&rating) {
+                    return Err(BanchoServiceError::InvalidRating(rating));
+                }
+
+                // one vote per user, the previous vote is kept
+                self.beatmap_ratings_repository
+                    .rate_beatmap(user.id, &beatmap.md5, rating as i16)
+                    .await?;
+            },
+            None => {
+                if self
+                    .beatmap_ratings_repository
+                    .get_user_rating(user.id, &beatmap.md5)
+                    .await?
+                    .is_none()
+                {
+                    return Ok(RateBeatmapResponse { average_rating: None });
+                }
+            },
+        }
+
+        let average_rating = self
+            .beatmap_ratings_repository
+            .get_average_rating(&beatmap.md5)
+            .await?;
+
+        Ok(RateBeatmapResponse { average_rating })
+    }
+}
+
+#[async_trait]
+impl SearchBeatmapsets for BanchoServiceImpl {
+    async fn search_beatmapsets(
+        &self,
+        request: SearchBeatmapsetsRequest,
+    ) -> Result<SearchBeatmapsetsResponse, BanchoServiceError> {
+        let SearchBeatmapsetsRequest {
+            username,
+            password,
+            query,
+            mode,
+            ranked_status,
+            page,
+        } = request;
+
+        self.authenticate(&username, &password).await?;
+
+        let mirror = self
+            .direct_mirror
+            .as_ref()
+            .ok_or(BanchoServiceError::DirectDisabled)?;
+
+        let beatmapsets = mirror
+            .search(&DirectSearchQuery::from_client(
+                query,
+                mode,
+                ranked_status,
+                page,
+            ))
+            .await?;
+
+        Ok(SearchBeatmapsetsResponse {
+            beatmapsets: direct_search_response(&beatmapsets),
+        })
+    }
+}
+
+#[async_trait]
+impl GetBeatmapsetInfo for BanchoServiceImpl {
+    async fn get_beatmapset_info(
+        &self,
+        request: GetBeatmapsetInfoRequest,
+    ) -> Result<GetBeatmapsetInfoResponse, BanchoServiceError> {
+        let GetBeatmapsetInfoRequest {
+            username,
+            password,
+            beatmapset_id,
+            beatmap_id,
+            beatmap_md5,
+        } = request;
+
+        self.authenticate(&username, &password).await?;
+
+        let mirror = self
+            .direct_mirror
+            .as_ref()
+            .ok_or(BanchoServiceError::DirectDisabled)?;
+
+        let beatmap_query = match (beatmap_id, beatmap_md5) {
+            (Some(bid), _) => Some(BeatmapQuery::BeatmapId(bid)),
+            (None, Some(md5)) => Some(BeatmapQuery::Md5(md5)),
+            (None, None) => None,
+        };
+
+        // the beatmaps are looked up by the beatmapset they belong to
+        let beatmapset_id = match (beatmapset_id, beatmap_query) {
+            (Some(sid), _) => sid,
+            (None, Some(query)) => {
+                self.beatmap_fetcher
+                    .fetch_beatmap(query)
+                    .await?
+                    .ok_or(BanchoServiceError::BeatmapsetNotExists)?
+                    .sid
+            },
+            (None, None) => {
+                return Err(BanchoServiceError::BeatmapsetNotExists)
+            },
+        };
+
+        let beatmapset = mirror
+            .get_beatmapset(beatmapset_id)
+            .await?
+            .ok_or(BanchoServiceError::BeatmapsetNotExists)?;
+
+        Ok(GetBeatmapsetInfoResponse {
+            beatmapset: beatmapset.search_set_line(),
+        })
+    }
+}
+
+#[async_trait]
+impl DownloadBeatmapset for BanchoServiceImpl {
+    async fn download_beatmapset(
+        &self,
+        request: DownloadBeatmapsetRequest,
+    ) -> Result<BeatmapsetChunkStream, BanchoServiceError> {
+        const CHUNK_SIZE: usize = 64 * 1024;
+
+        let DownloadBeatmapsetRequest {
+            beatmapset_id,
+            no_video,
+            username,
+            password,
+        } = request;
+
+        self.authenticate(&username, &password).await?;
+
+        let mirror = self
+            .direct_mirror
+            .as_ref()
+            .ok_or(BanchoServiceError::DirectDisabled)?;
+
+        let path = self
+            .beatmapset_cache
+            .get_or_download(mirror, beatmapset_id, no_video)
+            .await?
+            .ok_or(BanchoServiceError::BeatmapsetNotExists)?;
+
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(BeatmapMirrorError::from)?;
+
+        Ok(Box::pin(stream::try_unfold(file, |mut file| async move {
+            let mut data = vec![0; CHUNK_SIZE];
+            let read =
+                file.read(&mut data).await.map_err(BeatmapMirrorError::from)?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            data.truncate(read);
+
+            Ok(Some((BeatmapsetChunk { data }, file)))
+        })))
+    }
+}
+
+#[async_trait]
+impl SubmitScore for BanchoServiceImpl {
+    async fn submit_score(
+        &self,
+        request: SubmitScoreRequest,
+    ) -> Result<SubmitScoreResponse, BanchoServiceError> {
+        let SubmitScoreRequest {
+            username,
+            password,
+            beatmap_md5,
+            checksum,
+            n300,
+            n100,
+            n50,
+            geki,
+            katu,
+            miss,
+            score,
+            max_combo,
+            perfect,
+            grade,
+            mods,
+            passed,
+            mode,
+            client_version,
+            client_flags,
+            fail_time,
+            exited,
+            replay,
+        } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let _ = self
+            .bancho_state_service
+            .is_user_online(UserQuery::UserId(user.id))
+            .await?;
+
+        let mods = Mods::from(mods);
+        let mode =
+            GameMode::from_params(mode as u8, mods).ok_or_else(|| {
+                BanchoServiceError::InvalidScore("game mode".to_owned())
+            })?;
+        let db_mode = db_game_mode(mode);
+
+        let grade = if passed {
+            ScoreGrade::try_from_value(&grade).map_err(|_| {
+                BanchoServiceError::InvalidScore("grade".to_owned())
+            })?
+        } else {
+            ScoreGrade::F
+        };
+
+        let beatmap = self
+            .get_or_fetch_beatmap(&beatmap_md5)
+            .await?
+            .ok_or(GetBeatmapError::BeatmapNotExists)?;
+
+        // fast path, the resubmits racing past it fail on the unique checksum
+        if self.scores_repository.score_exists(&checksum).await? {
+            return Err(BanchoServiceError::DuplicateScore);
+        }
+
+        let hits = HitCounts { n300, n100, n50, geki, katu, miss };
+        let accuracy = Decimal::from_f64_retain(hits.accuracy(mode))
+            .unwrap_or_default()
+            .round_dp(2);
+
+        let previous_best = self
+            .scores_repository
+            .get_user_best_classic_score(user.id, &beatmap_md5, db_mode.clone())
+            .await?
+            .map(|(_, classic)| classic);
+
+        let stats_before = self
+            .users_repository
+            .get_user_stats(user.id, db_mode.clone())
+            .await?
+            .unwrap_or_else(|| empty_user_stats(user.id, db_mode.clone()));
+
+        let score_version = if mods.contains(Mods::ScoreV2) {
+            ScoreVersion::V2
+        } else {
+            ScoreVersion::V1
+        };
+
+        let score_id = self
+            .scores_repository
+            .create_classic_score(
+                scores::ActiveModel {
+                    map_hash: Set(beatmap_md5),
+                    user_id: Set(user.id),
+                    cksm: Set(checksum),
+                    playtime: Set(if passed {
+                        beatmap.length
+                    } else {
+                        fail_time / 1000
+                    }),
+                    completed: Set(passed),
+                    ..Default::default()
+                },
+                scores_classic::ActiveModel {
+                    mode: Set(db_mode.clone()),
+                    score_version: Set(score_version.clone()),
+                    score: Set(score),
+                    accuracy: Set(accuracy),
+                    combo: Set(max_combo),
+                    mods: Set(mods.bits() as i32),
+                    n300: Set(n300),
+                    n100: Set(n100),
+                    n50: Set(n50),
+                    miss: Set(miss),
+                    geki: Set(geki),
+                    katu: Set(katu),
+                    perfect: Set(perfect),
+                    grade: Set(grade),
+                    client_flags: Set(client_flags),
+                    client_version: Set(client_version),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(|err| match err {
+                ScoresError::DuplicateScore => {
+                    BanchoServiceError::DuplicateScore
+                },
+                err => err.into(),
+            })?;
+
+        if passed {
+            if let Some(replay) = replay {
+                self.replay_store.save_replay(score_id, &replay).await?;
+            }
+        }
+
+        let score_pp = if passed {
+            self.calculate_score_pp(
+                score_id,
+                db_mode.clone(),
+                CalculatePerformanceRequest {
+                    beatmap_id: beatmap.bid,
+                    mode: mode.as_vanilla().val() as i32,
+                    mods: mods.bits(),
+                    n300,
+                    n100,
+                    n50,
+                    geki,
+                    katu,
+                    miss,
+                    max_combo,
+                    passed_objects: None,
+                },
+            )
+            .await?
+        } else {
+            None
+        };
+
+        // only the stats of this play, they are added to the stored stats
+        // in one statement so concurrent submissions don't overwrite them
+        let mut play_stats = empty_user_stats(user.id, db_mode.clone());
+        play_stats.playcount = 1;
+        play_stats.total_score = score as i64;
+
+        if passed {
+            play_stats.total_hits = hits.total_hits(mode);
+            play_stats.max_combo = max_combo;
+            play_stats.count300 = n300;
+            play_stats.count100 = n100;
+            play_stats.count50 = n50;
+            play_stats.count_miss = miss;
+            play_stats.total_seconds_played = beatmap.length;
+        } else {
+            play_stats.total_seconds_played = fail_time / 1000;
+            if exited {
+                play_stats.count_quit = 1;
+            } else {
+                play_stats.count_failed = 1;
+            }
+        }
+
+        let is_ranked = matches!(
+            beatmap.rank_status,
+            RankStatus::Ranked | RankStatus::Approved
+        );
+
+        let previous_best_score =
+            previous_best.as_ref().map(|s| s.score).unwrap_or_default();
+        if passed && is_ranked && score > previous_best_score {
+            play_stats.ranked_score = (score - previous_best_score) as i64;
+        }
+
+        let mut stats =
+            self.users_repository.add_user_stats(play_stats).await?;
+
+        let (previous_best_pp, user_pp) = match score_pp {
+            Some(pp) => {
+                let previous_best_pp = self
+                    .leaderboard_repository
+                    .get_user_leaderboard_score(
+                        beatmap.bid,
+                        db_mode.clone(),
+                        RankingType::PpV2,
+                        &LeaderboardFilter::default(),
+                        user.id,
+                    )
+                    .await?
+                    .and_then(|entry| entry.pp);
+
+                if previous_best_pp.map(|before| pp > before).unwrap_or(true) {
+                    self.leaderboard_repository
+                        .update_leaderboard(
+                            beatmap.bid,
+                            db_mode.clone(),
+                            RankingType::PpV2,
+                            user.id,
+                            score_id,
+                        )
+                        .await?;
+                }
+
+                let user_pp_before = self
+                    .users_repository
+                    .get_user_pp(user.id, db_mode.clone(), PpVersion::V2)
+                    .await?
+                    .map(|user_pp| user_pp.pp);
+
+                let user_pp_after = if is_ranked {
+                    Some(
+                        update_user_performance(
+                            &self.users_repository,
+                            &self.leaderboard_repository,
+                            &mut stats,
+                        )
+                        .await?,
+                    )
+                } else {
+                    user_pp_before
+                };
+
+                (previous_best_pp, (user_pp_before, user_pp_after))
+            },
+            None => (None, (None, None)),
+        };
+
+        if !passed {
+            return Ok(SubmitScoreResponse { chart: "error: no".to_owned() });
+        }
+
+        let ranking_type = match score_version {
+            ScoreVersion::V1 => RankingType::ScoreV1,
+            ScoreVersion::V2 => RankingType::ScoreV2,
+        };
+
+        let leaderboard_entry = self
+            .leaderboard_repository
+            .get_user_leaderboard_score(
+                beatmap.bid,
+                db_mode.clone(),
+                ranking_type.clone(),
+                &LeaderboardFilter::default(),
+                user.id,
+            )
+            .await?;
+
+        let rank_before = self
+            .user_leaderboard_rank(
+                beatmap.bid,
+                db_mode.clone(),
+                ranking_type.clone(),
+                user.id,
+            )
+            .await?;
+
+        if leaderboard_entry.map(|entry| score > entry.score).unwrap_or(true) {
+            self.leaderboard_repository
+                .update_leaderboard(
+                    beatmap.bid,
+                    db_mode.clone(),
+                    ranking_type.clone(),
+                    user.id,
+                    score_id,
+                )
+                .await?;
+        }
+
+        let rank_after = self
+            .user_leaderboard_rank(beatmap.bid, db_mode, ranking_type, user.id)
+            .await?;
+
+        Ok(SubmitScoreResponse {
+            chart: score_chart(
+                &beatmap,
+                score_id,
+                (rank_before, rank_after),
+                previous_best.as_ref(),
+                (score, max_combo, accuracy),
+                (previous_best_pp, score_pp),
+                &stats_before,
+                &stats,
+                user_pp,
+            ),
+        })
+    }
+}
+
+#[async_trait]
+impl GetBeatmapScores for BanchoServiceImpl {
+    async fn get_beatmap_scores(
+        &self,
+        request: GetBeatmapScoresRequest,
+    ) -> Result<GetBeatmapScoresResponse, BanchoServiceError> {
+        const LEADERBOARD_SIZE: u64 = 50;
+
+        let GetBeatmapScoresRequest {
+            username,
+            password,
+            beatmap_md5,
+            mode,
+            mods,
+            leaderboard_type,
+        } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let Some(beatmap) = self.get_or_fetch_beatmap(&beatmap_md5).await?
+        else {
+            return Ok(GetBeatmapScoresResponse {
+                scores: format!(
+                    "{}|false",
+                    BeatmapRankStatus::NotSubmitted.val()
+                ),
+            });
+        };
+
+        let mods = Mods::from(mods);
+        let mode = db_game_mode(
+            GameMode::from_params(mode as u8, mods).unwrap_or_default(),
+        );
+
+        // pp v1 is served as pp v2, see `scoreboard_ranking_type`
+        let ranking_type = self
+            .users_repository
+            .get_user_settings(user.id)
+            .await?
+            .map(|settings| {
+                scoreboard_ranking_type(settings.scoreboard_ranking_type)
+            })
+            .unwrap_or(RankingType::ScoreV1);
+
+        let filter = match LeaderboardType::from_i32(leaderboard_type)
+            .unwrap_or_default()
+        {
+            LeaderboardType::SelectedMods => LeaderboardFilter {
+                mods: Some(mods.bits() as i32),
+                ..Default::default()
+            },
+            LeaderboardType::Friends => {
+                let mut user_ids =
+                    self.followers_repository.get_friends(user.id).await?;
+                user_ids.push(user.id);
+
+                LeaderboardFilter {
+                    user_ids: Some(user_ids),
+                    ..Default::default()
+                }
+            },
+            LeaderboardType::Country => LeaderboardFilter {
+                country: user.country.clone(),
+                ..Default::default()
+            },
+            LeaderboardType::Local | LeaderboardType::Global => {
+                LeaderboardFilter::default()
+            },
+        };
+
+        let scores = self
+            .leaderboard_repository
+            .get_leaderboard(
+                beatmap.bid,
+                mode.clone(),
+                ranking_type.clone(),
+                &filter,
+                LEADERBOARD_SIZE,
+            )
+            .await?;
+
+        let personal_best = match self
+            .leaderboard_repository
+            .get_user_leaderboard_score(
+                beatmap.bid,
+                mode.clone(),
+                ranking_type.clone(),
+                &filter,
+                user.id,
+            )
+            .await?
+        {
+            Some(entry) => {
+                let rank = self
+                    .leaderboard_repository
+                    .get_leaderboard_rank(
+                        beatmap.bid,
+                        mode,
+                        ranking_type.clone(),
+                        &filter,
+                        &entry,
+                    )
+                    .await?;
+
+                Some((entry, rank))
+            },
+            None => None,
+        };
+
+        let rating = self
+            .beatmap_ratings_repository
+            .get_average_rating(&beatmap.md5)
+            .await?;
+
+        Ok(GetBeatmapScoresResponse {
+            scores: beatmap_scores(
+                &beatmap,
+                &ranking_type,
+                rating,
+                personal_best,
+                &scores,
+            ),
+        })
+    }
+}
+
+#[async_trait]
+impl GetReplay for BanchoServiceImpl {
+    async fn get_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError> {
+        let (_, _, replay) = self.replay_score(request).await?;
+
+        Ok(GetReplayResponse { replay })
+    }
+}
+
+#[async_trait]
+impl ExportReplay for BanchoServiceImpl {
+    async fn export_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError> {
+        let (score, classic, frames) = self.replay_score(request).await?;
+
+        let user = self.users_repository.get_user_by_id(score.user_id).await?;
+
+        Ok(GetReplayResponse {
+            replay: build_osr(&score, &classic, &user.name, frames),
+        })
+    }
+}
+
+#[async_trait]
+impl RequestBeatmapInfo for BanchoServiceImpl {
+    async fn request_beatmap_info(
+        &self,
+        request: BeatmapInfoRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        let BeatmapInfoRequest { user_id, file_names, beatmap_ids } = request;
+
+        let infos =
+            self.beatmap_infos(user_id, &file_names, &beatmap_ids).await?;
+
+        Ok(HandleCompleted {
+            packets: Some(server::BeatmapInfoReply::pack(infos)),
+        })
+    }
+}
+
+#[async_trait]
+impl GetBeatmapInfo for BanchoServiceImpl {
+    async fn get_beatmap_info(
+        &self,
+        request: GetBeatmapInfoRequest,
+    ) -> Result<GetBeatmapInfoResponse, BanchoServiceError> {
+        let GetBeatmapInfoRequest {
+            username,
+            password,
+            file_names,
+            beatmap_ids,
+        } = request;
+
+        let user = self.authenticate(&username, &password).await?;
+
+        let infos =
+            self.beatmap_infos(user.id, &file_names, &beatmap_ids).await?;
+
+        Ok(GetBeatmapInfoResponse { beatmaps: beatmap_info_lines(&infos) })
+    }
+}
+
+/// The board served for the user's `scoreboard_ranking_type`.
+///
+/// pp v1 is not supported: the pp service only calculates pp v2, so no score
+/// has pp v1 and its board would always be empty. The users who chose pp v1
+/// are served the pp v2 board instead, every other ranking type is served as
+/// chosen.
+#[inline]
+fn scoreboard_ranking_type(ranking_type: RankingType) -> RankingType {
+    match ranking_type {
+        RankingType::PpV1 => RankingType::PpV2,
+        ranking_type => ranking_type,
+    }
+}
+
+#[inline]
+fn db_game_mode(mode: GameMode) -> DbGameMode {
+    match mode {
+        GameMode::Standard => DbGameMode::Standard,
+        GameMode::Taiko => DbGameMode::Taiko,
+        GameMode::Fruits => DbGameMode::Fruits,
+        GameMode::Mania => DbGameMode::Mania,
+        GameMode::StandardRelax => DbGameMode::StandardRelax,
+        GameMode::TaikoRelax => DbGameMode::TaikoRelax,
+        GameMode::FruitsRelax => DbGameMode::FruitsRelax,
+        GameMode::StandardAutopilot => DbGameMode::StandardAutopilot,
+        GameMode::StandardScoreV2 => DbGameMode::StandardScoreV2,
+    }
+}
+
+#[inline]
+fn empty_user_stats(user_id: i32, mode: DbGameMode) -> user_stats::Model {
+    user_stats::Model {
+        user_id,
+        mode,
+        total_score: 0,
+        ranked_score: 0,
+        playcount: 0,
+        total_hits: 0,
+        accuracy: Decimal::ZERO,
+        max_combo: 0,
+        total_seconds_played: 0,
+        count300: 0,
+        count100: 0,
+        count50: 0,
+        count_miss: 0,
+        count_failed: 0,
+        count_quit: 0,
+        updated_at: Utc::now().into(),
+    }
+}
+
+#[inline]
+fn client_rank_status(status: &RankStatus) -> BeatmapRankStatus {
+    match status {
+        RankStatus::Ranked => BeatmapRankStatus::Ranked,
+        RankStatus::Approved => BeatmapRankStatus::Approved,
+        RankStatus::Qualified => BeatmapRankStatus::Qualified,
+        RankStatus::Loved => BeatmapRankStatus::Loved,
+        RankStatus::Pending | RankStatus::Wip | RankStatus::Graveyard => {
+            BeatmapRankStatus::Pending
+        },
+    }
+}
+
+#[inline]
+fn client_grade(grade: &ScoreGrade) -> Grade {
+    match grade {
+        ScoreGrade::Xh => Grade::XH,
+        ScoreGrade::Sh => Grade::SH,
+        ScoreGrade::X => Grade::X,
+        ScoreGrade::S => Grade::S,
+        ScoreGrade::A => Grade::A,
+        ScoreGrade::B => Grade::B,
+        ScoreGrade::C => Grade::C,
+        ScoreGrade::D => Grade::D,
+        ScoreGrade::F => Grade::F,
+    }
+}
+
+/// Build the beatmap infos in the format of `/web/osu-getbeatmapinfo.php`,
+/// one beatmap per line.
+fn beatmap_info_lines(infos: &[BeatmapInfo]) -> String {
+    let letter = |grade: u8| match Grade::from_u8(grade).unwrap_or_default() {
+        Grade::XH => "XH",
+        Grade::SH => "SH",
+        Grade::X => "X",
+        Grade::S => "S",
+        Grade::A => "A",
+        Grade::B => "B",
+        Grade::C => "C",
+        Grade::D => "D",
+        Grade::F => "F",
+        Grade::N => "N",
+    };
+
+    infos
+        .iter()
+        .map(|info| {
+            format!(
+                "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+                info.index,
+                info.beatmap_id,
+                info.beatmapset_id,
+                info.beatmap_md5,
+                info.rank_status,
+                letter(info.osu_grade),
+                letter(info.taiko_grade),
+                letter(info.fruits_grade),
+                letter(info.mania_grade),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Build the leaderboard of a beatmap in the format of
+/// `/web/osu-osz2-getscores.php`, the score column shows pp when ranking by
+/// pp.
+fn beatmap_scores(
+    beatmap: &beatmaps::Model,
+    ranking_type: &RankingType,
+    rating: Option<f64>,
+    personal_best: Option<(LeaderboardScore, u64)>,
+    scores: &[LeaderboardScore],
+) -> String {
+    let by_pp = matches!(ranking_type, RankingType::PpV1 | RankingType::PpV2);
+
+    let score_line = |s: &LeaderboardScore, rank: u64| {
+        let value = if by_pp {
+            s.pp.map(|pp| pp.round().to_string()).unwrap_or("0".to_owned())
+        } else {
+            s.score.to_string()
+        };
+
+        format!(
+            "{}|{}|{value}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{rank}|{}|1",
+            s.score_id,
+            s.username,
+            s.combo,
+            s.n50,
+            s.n100,
+            s.n300,
+            s.miss,
+            s.katu,
+            s.geki,
+            s.perfect as i32,
+            s.mods,
+            s.user_id,
+            s.created_at.timestamp(),
+        )
+    };
+
+    let mut lines = vec![
+        format!(
+            "{}|false|{}|{}|{}|0|",
+            client_rank_status(&beatmap.rank_status).val(),
+            beatmap.bid,
+            beatmap.sid,
+            scores.len()
+        ),
+        // offset
+        "0".to_owned(),
+        format!(
+            "{} - {} [{}]",
+            beatmap.artist, beatmap.title, beatmap.diff_name
+        ),
+        format!("{:.1}", rating.unwrap_or_default()),
+        personal_best
+            .map(|(score, rank)| score_line(&score, rank))
+            .unwrap_or_default(),
+    ];
+
+    lines.extend(
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| score_line(score, i as u64 + 1)),
+    );
+
+    lines.join("\n")
+}
+
+/// Build the ranking charts displayed by the client after a submission,
+/// the overall rank and pp are left empty.
+#[allow(clippy::too_many_arguments)]
+fn score_chart(
+    beatmap: &beatmaps::Model,
+    score_id: i64,
+    (rank_before, rank_after): (Option<u64>, Option<u64>),
+    previous_best: Option<&scores_classic::Model>,
+    (score, max_combo, accuracy): (i32, i32, Decimal),
+    (pp_before, pp_after): (Option<Decimal>, Option<Decimal>),
+    stats_before: &user_stats::Model,
+    stats_after: &user_stats::Model,
+    (user_pp_before, user_pp_after): (Option<Decimal>, Option<Decimal>),
+) -> String {
+    fn entry<T: std::fmt::Display>(name: &str, before: T, after: T) -> String {
+        format!("{name}Before:{before}|{name}After:{after}")
+    }
+
+    let opt = |v: Option<String>| v.unwrap_or_default();
+
+    [
+        format!(
+            "beatmapId:{}|beatmapSetId:{}|beatmapPlaycount:0|beatmapPasscount:0|approvedDate:{}",
+            beatmap.bid,
+            beatmap.sid,
+            beatmap.last_update.format("%Y-%m-%d %H:%M:%S")
+        ),
+        [
+            "chartId:beatmap|chartUrl:|chartName:Beatmap Ranking".to_owned(),
+            entry(
+                "rank",
+                opt(rank_before.map(|r| r.to_string())),
+                opt(rank_after.map(|r| r.to_string())),
+            ),
+            entry(
+                "rankedScore",
+                opt(previous_best.map(|s| s.score.to_string())),
+                score.to_string(),
+            ),
+            entry(
+                "totalScore",
+                opt(previous_best.map(|s| s.score.to_string())),
+                score.to_string(),
+            ),
+            entry(
+                "maxCombo",
+                opt(previous_best.map(|s| s.combo.to_string())),
+                max_combo.to_string(),
+            ),
+            entry(
+                "accuracy",
+                opt(previous_best.map(|s| s.accuracy.to_string())),
+                accuracy.to_string(),
+            ),
+            entry(
+                "pp",
+                opt(pp_before.map(|pp| pp.to_string())),
+                opt(pp_after.map(|pp| pp.to_string())),
+            ),
+            format!("onlineScoreId:{score_id}"),
+        ]
+        .join("|"),
+        [
+            "chartId:overall|chartUrl:|chartName:Overall Ranking".to_owned(),
+            entry("rank", String::new(), String::new()),
+            entry(
+                "rankedScore",
+                stats_before.ranked_score,
+                stats_after.ranked_score,
+            ),
+            entry(
+                "totalScore",
+                stats_before.total_score,
+                stats_after.total_score,
+            ),
+            entry(
+                "maxCombo",
+                stats_before.max_combo,
+                stats_after.max_combo,
+            ),
+            entry("accuracy", stats_before.accuracy, stats_after.accuracy),
+            entry(
+                "pp",
+                opt(user_pp_before.map(|pp| pp.to_string())),
+                opt(user_pp_after.map(|pp| pp.to_string())),
+            ),
+            "achievements-new:".to_owned(),
+        ]
+        .join("|"),
+    ]
+    .join("\n")
+}
+
+#[derive(Clone)]
+pub struct BanchoServiceRemote(BanchoRpcClient<Channel>);
+
+impl BanchoService for BanchoServiceRemote {}
+
+impl IntoService<DynBanchoService> for BanchoServiceRemote {
+    #[inline]
+    fn into_service(self) -> DynBanchoService {
+        Arc::new(self) as DynBanchoService
+    }
+}
+
+impl FromRpcClient for BanchoServiceRemote {
+    #[inline]
+    fn from_client(client: Self::Client) -> Self {
+        Self(client)
+    }
+}
+
+impl RpcClient for BanchoServiceRemote {
+    type Client = BanchoRpcClient<Channel>;
+
+    fn client(&self) -> Self::Client {
+        self.0.clone()
+    }
+}
+
+#[async_trait]
+impl Login for BanchoServiceRemote {
+    async fn login(
+        &self,
+        client_ip: IpAddr,
+        request: LoginRequest,
+    ) -> Result<LoginSuccess, BanchoServiceError> {
+        Ok(self
+            .client()
+            .login(RawRequest::add_client_ip(request, client_ip))
+            .await?
+            .into_inner())
+    }
+}
+#[async_trait]
+impl BatchProcessPackets for BanchoServiceRemote {
+    async fn batch_process_bancho_packets(
+        &self,
+        request: BatchProcessBanchoPacketsRequest,
     ) -> Result<HandleCompleted, ProcessBanchoPacketError> {
         Ok(self
             .client()
@@ -733,6 +2791,16 @@ impl RequestPresence for BanchoServiceRemote {
     }
 }
 
+#[async_trait]
+impl SpectateStart for BanchoServiceRemote {
+    async fn spectate_start(
+        &self,
+        request: SpectateRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().spectate_start(request).await?.into_inner())
+    }
+}
+
 #[async_trait]
 impl SpectateStop for BanchoServiceRemote {
     async fn spectate_stop(
@@ -761,6 +2829,16 @@ impl SpectateCant for BanchoServiceRemote {
     }
 }
 
+#[async_trait]
+impl SpectateFrames for BanchoServiceRemote {
+    async fn spectate_frames(
+        &self,
+        request: SpectateFramesRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().spectate_frames(request).await?.into_inner())
+    }
+}
+
 #[async_trait]
 impl LobbyPart for BanchoServiceRemote {
     async fn lobby_part(
@@ -788,3 +2866,260 @@ impl LobbyJoin for BanchoServiceRemote {
             .into_inner())
     }
 }
+
+#[async_trait]
+impl MatchCreate for BanchoServiceRemote {
+    async fn match_create(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().match_create(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl MatchJoin for BanchoServiceRemote {
+    async fn match_join(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().match_join(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl MatchPart for BanchoServiceRemote {
+    async fn match_part(
+        &self,
+        user_query: UserQuery,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self
+            .client()
+            .match_part(Into::<RawUserQuery>::into(user_query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl MatchUpdate for BanchoServiceRemote {
+    async fn match_update(
+        &self,
+        request: UpdateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().match_update(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl FriendAdd for BanchoServiceRemote {
+    async fn friend_add(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().friend_add(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl FriendRemove for BanchoServiceRemote {
+    async fn friend_remove(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().friend_remove(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetFriends for BanchoServiceRemote {
+    async fn get_friends(
+        &self,
+        request: GetFriendsRequest,
+    ) -> Result<GetFriendsResponse, BanchoServiceError> {
+        Ok(self.client().get_friends(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl SubmitScore for BanchoServiceRemote {
+    async fn submit_score(
+        &self,
+        request: SubmitScoreRequest,
+    ) -> Result<SubmitScoreResponse, BanchoServiceError> {
+        Ok(self.client().submit_score(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetBeatmapScores for BanchoServiceRemote {
+    async fn get_beatmap_scores(
+        &self,
+        request: GetBeatmapScoresRequest,
+    ) -> Result<GetBeatmapScoresResponse, BanchoServiceError> {
+        Ok(self.client().get_beatmap_scores(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetReplay for BanchoServiceRemote {
+    async fn get_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError> {
+        Ok(self.client().get_replay(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl ExportReplay for BanchoServiceRemote {
+    async fn export_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError> {
+        Ok(self.client().export_replay(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl RequestBeatmapInfo for BanchoServiceRemote {
+    async fn request_beatmap_info(
+        &self,
+        request: BeatmapInfoRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError> {
+        Ok(self.client().request_beatmap_info(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetBeatmapInfo for BanchoServiceRemote {
+    async fn get_beatmap_info(
+        &self,
+        request: GetBeatmapInfoRequest,
+    ) -> Result<GetBeatmapInfoResponse, BanchoServiceError> {
+        Ok(self.client().get_beatmap_info(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl VerifyCredentials for BanchoServiceRemote {
+    async fn verify_credentials(
+        &self,
+        request: VerifyCredentialsRequest,
+    ) -> Result<VerifyCredentialsResponse, BanchoServiceError> {
+        Ok(self.client().verify_credentials(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl Register for BanchoServiceRemote {
+    async fn register(
+        &self,
+        client_ip: IpAddr,
+        request: RegisterRequest,
+    ) -> Result<RegisterResponse, BanchoServiceError> {
+        Ok(self
+            .client()
+            .register(RawRequest::add_client_ip(request, client_ip))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl BanchoConnect for BanchoServiceRemote {
+    async fn bancho_connect(
+        &self,
+        client_ip: IpAddr,
+        request: BanchoConnectRequest,
+    ) -> Result<BanchoConnectResponse, BanchoServiceError> {
+        Ok(self
+            .client()
+            .bancho_connect(RawRequest::add_client_ip(request, client_ip))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl UploadScreenshot for BanchoServiceRemote {
+    async fn upload_screenshot(
+        &self,
+        request: UploadScreenshotRequest,
+    ) -> Result<UploadScreenshotResponse, BanchoServiceError> {
+        Ok(self.client().upload_screenshot(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetScreenshot for BanchoServiceRemote {
+    async fn get_screenshot(
+        &self,
+        request: GetScreenshotRequest,
+    ) -> Result<GetScreenshotResponse, BanchoServiceError> {
+        Ok(self.client().get_screenshot(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetFavouriteBeatmaps for BanchoServiceRemote {
+    async fn get_favourite_beatmaps(
+        &self,
+        request: GetFavouriteBeatmapsRequest,
+    ) -> Result<GetFavouriteBeatmapsResponse, BanchoServiceError> {
+        Ok(self.client().get_favourite_beatmaps(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl AddFavouriteBeatmap for BanchoServiceRemote {
+    async fn add_favourite_beatmap(
+        &self,
+        request: AddFavouriteBeatmapRequest,
+    ) -> Result<AddFavouriteBeatmapResponse, BanchoServiceError> {
+        Ok(self.client().add_favourite_beatmap(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl RateBeatmap for BanchoServiceRemote {
+    async fn rate_beatmap(
+        &self,
+        request: RateBeatmapRequest,
+    ) -> Result<RateBeatmapResponse, BanchoServiceError> {
+        Ok(self.client().rate_beatmap(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl SearchBeatmapsets for BanchoServiceRemote {
+    async fn search_beatmapsets(
+        &self,
+        request: SearchBeatmapsetsRequest,
+    ) -> Result<SearchBeatmapsetsResponse, BanchoServiceError> {
+        Ok(self.client().search_beatmapsets(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetBeatmapsetInfo for BanchoServiceRemote {
+    async fn get_beatmapset_info(
+        &self,
+        request: GetBeatmapsetInfoRequest,
+    ) -> Result<GetBeatmapsetInfoResponse, BanchoServiceError> {
+        Ok(self.client().get_beatmapset_info(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl DownloadBeatmapset for BanchoServiceRemote {
+    async fn download_beatmapset(
+        &self,
+        request: DownloadBeatmapsetRequest,
+    ) -> Result<BeatmapsetChunkStream, BanchoServiceError> {
+        let stream =
+            self.client().download_beatmapset(request).await?.into_inner();
+
+        Ok(Box::pin(stream.map_err(BanchoServiceError::from)))
+    }
+}
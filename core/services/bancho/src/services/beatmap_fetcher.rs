@@ -0,0 +1,1128 @@
+use crate::{
+    BeatmapFetchError, BeatmapFetcher, BeatmapMirror, BeatmapMirrorError,
+    DynBeatmapFetcher, DynBeatmapMirror,
+};
+use chrono::{NaiveDateTime, Utc};
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use infra_services::IntoService;
+use peace_db::{
+    peace::entity::{
+        beatmaps,
+        sea_orm_active_enums::{GameMode, RankStatus},
+    },
+    prelude::{DateTimeWithTimeZone, Decimal},
+};
+use peace_repositories::{beatmaps::DynBeatmapsRepository, GetBeatmapError};
+use rosu_pp::{AnyStars, Beatmap};
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    future::Future,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::{Mutex, RwLock};
+use tonic::async_trait;
+use tools::Timestamp;
+
+const DEFAULT_OSU_FILES_DIR: &str = "./.data/beatmaps";
+
+/// The `.osu` files larger than this are rejected by the http mirrors.
+pub const MAX_OSU_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Min interval between the rescans of the local mirror directory.
+const LOCAL_MIRROR_RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Identifies a beatmap to fetch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BeatmapQuery {
+    Md5(String),
+    BeatmapId(i32),
+    /// Any beatmap of the beatmapset.
+    BeatmapsetId(i32),
+}
+
+impl Display for BeatmapQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Md5(md5) => write!(f, "md5 \"{md5}\""),
+            Self::BeatmapId(bid) => write!(f, "beatmap id {bid}"),
+            Self::BeatmapsetId(sid) => write!(f, "beatmapset id {sid}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliBeatmapFetcherConfigs {
+    /// Base urls of the osu! api v1 compatible mirrors, tried in order.
+    #[arg(long, value_delimiter = ',')]
+    pub beatmap_mirrors: Vec<String>,
+
+    #[arg(long)]
+    pub beatmap_mirror_api_key: Option<String>,
+
+    /// A directory of `.osu` files, tried before the http mirrors.
+    #[arg(long)]
+    pub local_beatmap_mirror_dir: Option<String>,
+
+    #[default(2)]
+    #[arg(long, default_value = "2")]
+    pub beatmap_fetch_retries: u32,
+
+    #[default(500)]
+    #[arg(long, default_value = "500")]
+    pub beatmap_fetch_retry_interval_millis: u64,
+
+    /// How long the beatmaps not found on any mirror are not fetched again.
+    #[default(600)]
+    #[arg(long, default_value = "600")]
+    pub beatmap_missing_cache_secs: u64,
+}
+
+/// Fetches the beatmaps missing in the database from the mirrors in order,
+/// saves them into the `beatmaps` table and their `.osu` files into the local
+/// directory used for pp calculation.
+pub struct BeatmapFetcherImpl {
+    pub beatmaps_repository: DynBeatmapsRepository,
+    pub mirrors: Vec<DynBeatmapMirror>,
+    pub osu_files_dir: PathBuf,
+    pub retries: u32,
+    pub retry_interval: Duration,
+    pub missing_cache_secs: u64,
+    /// The queries not found on any mirror, with the time they expire.
+    pub missing: RwLock<HashMap<BeatmapQuery, u64>>,
+}
+
+impl BeatmapFetcherImpl {
+    pub fn new(
+        beatmaps_repository: DynBeatmapsRepository,
+        mirrors: Vec<DynBeatmapMirror>,
+        osu_files_dir: Option<&str>,
+        cfg: &CliBeatmapFetcherConfigs,
+    ) -> Self {
+        Self {
+            beatmaps_repository,
+            mirrors,
+            osu_files_dir: osu_files_dir
+                .unwrap_or(DEFAULT_OSU_FILES_DIR)
+                .into(),
+            retries: cfg.beatmap_fetch_retries,
+            retry_interval: Duration::from_millis(
+                cfg.beatmap_fetch_retry_interval_millis,
+            ),
+            missing_cache_secs: cfg.beatmap_missing_cache_secs,
+            missing: RwLock::default(),
+        }
+    }
+
+    /// Build the mirrors from the config, the local directory goes first.
+    pub fn build_mirrors(
+        cfg: &CliBeatmapFetcherConfigs,
+    ) -> Vec<DynBeatmapMirror> {
+        let mut mirrors = Vec::new();
+
+        if let Some(dir) = &cfg.local_beatmap_mirror_dir {
+            mirrors.push(LocalBeatmapMirror::new(dir).into_service());
+        }
+
+        for base_url in &cfg.beatmap_mirrors {
+            mirrors.push(
+                HttpBeatmapMirror::new(
+                    base_url,
+                    cfg.beatmap_mirror_api_key.clone(),
+                )
+                .into_service(),
+            );
+        }
+
+        mirrors
+    }
+
+    #[inline]
+    pub fn osu_file_path(&self, beatmap_id: i32) -> PathBuf {
+        self.osu_files_dir.join(format!("{beatmap_id}.osu"))
+    }
+
+    async fn retry<T, F, Fut>(
+        &self,
+        mirror: &DynBeatmapMirror,
+        mut f: F,
+    ) -> Result<T, BeatmapMirrorError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BeatmapMirrorError>>,
+    {
+        let mut attempts = 0;
+        loop {
+            match f().await {
+                Err(err) if attempts < self.retries => {
+                    attempts += 1;
+                    warn!(
+                        "mirror \"{}\" failed ({attempts}/{}): {err}",
+                        mirror.name(),
+                        self.retries
+                    );
+                    tokio::time::sleep(self.retry_interval).await;
+                },
+                res => return res,
+            }
+        }
+    }
+
+    async fn is_missing(&self, query: &BeatmapQuery) -> bool {
+        self.missing
+            .read()
+            .await
+            .get(query)
+            .map(|expires| *expires > Timestamp::now())
+            .unwrap_or(false)
+    }
+
+    async fn mark_missing(&self, query: BeatmapQuery) {
+        let now = Timestamp::now();
+        let mut missing = self.missing.write().await;
+        missing.retain(|_, expires| *expires > now);
+        missing.insert(query, now + self.missing_cache_secs);
+    }
+
+    /// Download the `.osu` file, preferring the mirror the beatmap comes
+    /// from, only the file matching the md5 is written.
+    async fn download_osu_file(
+        &self,
+        beatmap_id: i32,
+        md5: Option<&str>,
+        preferred: Option<&DynBeatmapMirror>,
+    ) -> Result<bool, BeatmapFetchError> {
+        let mirrors =
+            preferred.into_iter().chain(self.mirrors.iter().filter(|m| {
+                preferred.map(|p| !Arc::ptr_eq(p, m)).unwrap_or(true)
+            }));
+
+        for mirror in mirrors {
+            let osu_file = match self
+                .retry(mirror, || mirror.get_osu_file(beatmap_id))
+                .await
+            {
+                Ok(Some(osu_file)) => osu_file,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(
+                        "failed to download .osu file of beatmap {beatmap_id} \
+                        from \"{}\": {err}",
+                        mirror.name()
+                    );
+                    continue;
+                },
+            };
+
+            if let Some(md5) = md5 {
+                if format!("{:x}", md5::compute(&osu_file)) != md5 {
+                    warn!(
+                        "md5 of .osu file of beatmap {beatmap_id} from \"{}\" \
+                        mismatched",
+                        mirror.name()
+                    );
+                    continue;
+                }
+            }
+
+            tokio::fs::create_dir_all(&self.osu_files_dir).await?;
+            tokio::fs::write(self.osu_file_path(beatmap_id), osu_file).await?;
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+impl IntoService<DynBeatmapFetcher> for BeatmapFetcherImpl {
+    #[inline]
+    fn into_service(self) -> DynBeatmapFetcher {
+        Arc::new(self) as DynBeatmapFetcher
+    }
+}
+
+#[async_trait]
+impl BeatmapFetcher for BeatmapFetcherImpl {
+    async fn fetch_beatmap(
+        &self,
+        query: BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BeatmapFetchError> {
+        let stored = match &query {
+            BeatmapQuery::Md5(md5) => {
+                self.beatmaps_repository.get_beatmap_by_md5(md5).await
+            },
+            BeatmapQuery::BeatmapId(bid) => {
+                self.beatmaps_repository.get_beatmap_by_bid(*bid).await
+            },
+            BeatmapQuery::BeatmapsetId(sid) => self
+                .beatmaps_repository
+                .get_beatmaps_by_sid(*sid)
+                .await
+                .and_then(|beatmaps| {
+                    beatmaps
+                        .into_iter()
+                        .next()
+                        .ok_or(GetBeatmapError::BeatmapNotExists)
+                }),
+        };
+
+        match stored {
+            Ok(beatmap) => return Ok(Some(beatmap)),
+            Err(GetBeatmapError::BeatmapNotExists) => {},
+            Err(err) => return Err(err.into()),
+        }
+
+        if self.is_missing(&query).await {
+            return Ok(None);
+        }
+
+        let mut last_err = None;
+        for mirror in self.mirrors.iter() {
+            let mut beatmap =
+                match self.retry(mirror, || mirror.get_beatmap(&query)).await {
+                    Ok(Some(beatmap)) => beatmap,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(
+                        "failed to fetch beatmap ({query}) from \"{}\": {err}",
+                        mirror.name()
+                    );
+                        last_err = Some(err);
+                        continue;
+                    },
+                };
+
+            beatmap.origin_server = mirror.name().to_owned();
+
+            if let Err(err) = self
+                .download_osu_file(
+                    beatmap.bid,
+                    Some(beatmap.md5.as_str()),
+                    Some(mirror),
+                )
+                .await
+            {
+                warn!(
+                    "failed to save .osu file of beatmap {}: {err}",
+                    beatmap.bid
+                );
+            }
+
+            self.beatmaps_repository.save_beatmap(beatmap.clone()).await?;
+
+            info!(
+                "fetched beatmap {} ({query}) from \"{}\"",
+                beatmap.bid, beatmap.origin_server
+            );
+
+            return Ok(Some(beatmap));
+        }
+
+        // Errors may be temporary, only cache the beatmaps that all mirrors
+        // have answered not found.
+        if let Some(err) = last_err {
+            return Err(err.into());
+        }
+
+        self.mark_missing(query).await;
+
+        Ok(None)
+    }
+
+    async fn fetch_osu_file(
+        &self,
+        beatmap_id: i32,
+    ) -> Result<bool, BeatmapFetchError> {
+        if tokio::fs::try_exists(self.osu_file_path(beatmap_id)).await? {
+            return Ok(true);
+        }
+
+        let md5 =
+            match self.beatmaps_repository.get_beatmap_by_bid(beatmap_id).await
+            {
+                Ok(beatmap) => Some(beatmap.md5),
+                Err(GetBeatmapError::BeatmapNotExists) => None,
+                Err(err) => return Err(err.into()),
+            };
+
+        self.download_osu_file(beatmap_id, md5.as_deref(), None).await
+    }
+}
+
+/// A mirror serving the osu! api v1 `get_beatmaps` endpoint and the `.osu`
+/// files at `/osu/{beatmap_id}`.
+#[derive(Debug, Clone)]
+pub struct HttpBeatmapMirror {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub client: reqwest::Client,
+}
+
+impl HttpBeatmapMirror {
+    #[inline]
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            api_key,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl IntoService<DynBeatmapMirror> for HttpBeatmapMirror {
+    #[inline]
+    fn into_service(self) -> DynBeatmapMirror {
+        Arc::new(self) as DynBeatmapMirror
+    }
+}
+
+#[async_trait]
+impl BeatmapMirror for HttpBeatmapMirror {
+    fn name(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    async fn get_beatmap(
+        &self,
+        query: &BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BeatmapMirrorError> {
+        let mut params = vec![match query {
+            BeatmapQuery::Md5(md5) => ("h", md5.to_owned()),
+            BeatmapQuery::BeatmapId(bid) => ("b", bid.to_string()),
+            BeatmapQuery::BeatmapsetId(sid) => ("s", sid.to_string()),
+        }];
+
+        if let Some(api_key) = &self.api_key {
+            params.push(("k", api_key.to_owned()));
+        }
+
+        let beatmaps = self
+            .client
+            .get(format!("{}/api/get_beatmaps", self.base_url))
+            .query(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<Map<String, Value>>>()
+            .await?;
+
+        beatmaps
+            .first()
+            .map(|beatmap| {
+                api_beatmap(beatmap).ok_or_else(|| {
+                    BeatmapMirrorError::InvalidResponse(
+                        "missing beatmap fields".to_owned(),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    async fn get_osu_file(
+        &self,
+        beatmap_id: i32,
+    ) -> Result<Option<Vec<u8>>, BeatmapMirrorError> {
+        let resp = self
+            .client
+            .get(format!("{}/osu/{beatmap_id}", self.base_url))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let mut resp = resp.error_for_status()?;
+        if resp.content_length().unwrap_or_default() > MAX_OSU_FILE_SIZE {
+            return Err(BeatmapMirrorError::TooLarge(MAX_OSU_FILE_SIZE));
+        }
+
+        let mut osu_file = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if (osu_file.len() + chunk.len()) as u64 > MAX_OSU_FILE_SIZE {
+                return Err(BeatmapMirrorError::TooLarge(MAX_OSU_FILE_SIZE));
+            }
+            osu_file.extend_from_slice(&chunk);
+        }
+
+        Ok(Some(osu_file).filter(|f| !f.is_empty()))
+    }
+}
+
+/// An indexed `.osu` file of the local mirror.
+#[derive(Debug, Clone)]
+pub struct IndexedOsuFile {
+    pub modified: Option<SystemTime>,
+    pub md5: String,
+    pub bid: Option<i32>,
+    pub sid: Option<i32>,
+}
+
+/// A directory of `.osu` files, the beatmaps are looked up by the md5 of the
+/// files and the `BeatmapID` and `BeatmapSetID` in them.
+#[derive(Debug, Default)]
+pub struct LocalBeatmapMirror {
+    pub dir: PathBuf,
+    pub name: String,
+    /// The files are indexed on the first query, then only the new and
+    /// modified ones are read when a query misses.
+    pub index: RwLock<HashMap<PathBuf, IndexedOsuFile>>,
+    scanned_at: Mutex<Option<Instant>>,
+}
+
+impl LocalBeatmapMirror {
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            name: format!("local:{}", dir.display()),
+            dir,
+            ..Default::default()
+        }
+    }
+
+    async fn find(&self, query: &BeatmapQuery) -> Option<PathBuf> {
+        self.index.read().await.iter().find_map(|(path, file)| {
+            let matched = match query {
+                BeatmapQuery::Md5(query_md5) => &file.md5 == query_md5,
+                BeatmapQuery::BeatmapId(query_bid) => {
+                    file.bid.as_ref() == Some(query_bid)
+                },
+                BeatmapQuery::BeatmapsetId(query_sid) => {
+                    file.sid.as_ref() == Some(query_sid)
+                },
+            };
+            matched.then(|| path.clone())
+        })
+    }
+
+    /// Sync the index with the directory, the files not modified since they
+    /// were indexed are not read again.
+    async fn update_index(&self) -> Result<(), BeatmapMirrorError> {
+        {
+            let mut scanned_at = self.scanned_at.lock().await;
+            if scanned_at.is_some_and(|scanned_at| {
+                scanned_at.elapsed() < LOCAL_MIRROR_RESCAN_INTERVAL
+            }) {
+                return Ok(());
+            }
+            *scanned_at = Some(Instant::now());
+        }
+
+        let indexed = self
+            .index
+            .read()
+            .await
+            .iter()
+            .map(|(path, file)| (path.clone(), file.modified))
+            .collect::<HashMap<_, _>>();
+
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                self.index.write().await.clear();
+                return Ok(());
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut existing = HashSet::new();
+        let mut updated = HashMap::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("osu") {
+                continue;
+            }
+
+            let modified = entry.metadata().await?.modified().ok();
+            existing.insert(path.clone());
+
+            if modified.is_some() && indexed.get(&path) == Some(&modified) {
+                continue;
+            }
+
+            let osu_file = tokio::fs::read(&path).await?;
+            let md5 = format!("{:x}", md5::compute(&osu_file));
+            let bid = osu_file_beatmap_id(&path, &osu_file);
+            let sid = osu_file_beatmapset_id(&osu_file);
+
+            updated.insert(path, IndexedOsuFile { modified, md5, bid, sid });
+        }
+
+        let mut index = self.index.write().await;
+        index.retain(|path, _| existing.contains(path));
+        index.extend(updated);
+
+        Ok(())
+    }
+
+    async fn find_or_update(
+        &self,
+        query: &BeatmapQuery,
+    ) -> Result<Option<PathBuf>, BeatmapMirrorError> {
+        if let Some(path) = self.find(query).await {
+            return Ok(Some(path));
+        }
+
+        self.update_index().await?;
+
+        Ok(self.find(query).await)
+    }
+}
+
+impl IntoService<DynBeatmapMirror> for LocalBeatmapMirror {
+    #[inline]
+    fn into_service(self) -> DynBeatmapMirror {
+        Arc::new(self) as DynBeatmapMirror
+    }
+}
+
+#[async_trait]
+impl BeatmapMirror for LocalBeatmapMirror {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    async fn get_beatmap(
+        &self,
+        query: &BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BeatmapMirrorError> {
+        let Some(path) = self.find_or_update(query).await? else {
+            return Ok(None);
+        };
+
+        let osu_file = tokio::fs::read(&path).await?;
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        tokio::task::spawn_blocking(move || {
+            parse_osu_file(&file_name, &osu_file)
+        })
+        .await
+        .map_err(|err| BeatmapMirrorError::InvalidResponse(err.to_string()))?
+        .map(Some)
+    }
+
+    async fn get_osu_file(
+        &self,
+        beatmap_id: i32,
+    ) -> Result<Option<Vec<u8>>, BeatmapMirrorError> {
+        let Some(path) =
+            self.find_or_update(&BeatmapQuery::BeatmapId(beatmap_id)).await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(tokio::fs::read(path).await?))
+    }
+}
+
+/// The `BeatmapID` in the `[Metadata]` section, or the file stem for the
+/// files named by the beatmap id.
+fn osu_file_beatmap_id(path: &Path, osu_file: &[u8]) -> Option<i32> {
+    osu_file_sections(&String::from_utf8_lossy(osu_file))
+        .get("BeatmapID")
+        .and_then(|bid| bid.parse::<i32>().ok())
+        .filter(|bid| *bid > 0)
+        .or_else(|| path.file_stem()?.to_str()?.parse::<i32>().ok())
+}
+
+/// The `BeatmapSetID` in the `[Metadata]` section.
+fn osu_file_beatmapset_id(osu_file: &[u8]) -> Option<i32> {
+    osu_file_sections(&String::from_utf8_lossy(osu_file))
+        .get("BeatmapSetID")
+        .and_then(|sid| sid.parse::<i32>().ok())
+        .filter(|sid| *sid > 0)
+}
+
+/// Collect the `key:value` lines of the `[General]`, `[Metadata]` and
+/// `[Difficulty]` sections.
+fn osu_file_sections(content: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_section = false;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section =
+                matches!(line, "[General]" | "[Metadata]" | "[Difficulty]");
+            continue;
+        }
+
+        if !in_section {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            values.insert(key.trim().to_owned(), value.trim().to_owned());
+        }
+    }
+
+    values
+}
+
+/// Build the beatmap row from a `.osu` file, the star rating and max combo
+/// are calculated in place.
+pub fn parse_osu_file(
+    file_name: &str,
+    osu_file: &[u8],
+) -> Result<beatmaps::Model, BeatmapMirrorError> {
+    let values = osu_file_sections(&String::from_utf8_lossy(osu_file));
+    let value = |key: &str| values.get(key).cloned().unwrap_or_default();
+    let optional =
+        |key: &str| values.get(key).filter(|v| !v.is_empty()).cloned();
+
+    let bid = osu_file_beatmap_id(Path::new(file_name), osu_file).ok_or(
+        BeatmapMirrorError::InvalidResponse(format!(
+            "{file_name}: missing beatmap id"
+        )),
+    )?;
+
+    let beatmap = Beatmap::from_bytes(osu_file)
+        .map_err(|err| BeatmapMirrorError::InvalidResponse(err.to_string()))?;
+    let attributes = AnyStars::new(&beatmap).calculate();
+
+    let length = match (beatmap.hit_objects.first(), beatmap.hit_objects.last())
+    {
+        (Some(first), Some(last)) => {
+            ((last.start_time - first.start_time) / 1000.0) as i32
+        },
+        _ => 0,
+    };
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+
+    Ok(beatmaps::Model {
+        bid,
+        sid: value("BeatmapSetID").parse().unwrap_or(-1),
+        md5: format!("{:x}", md5::compute(osu_file)),
+        title: value("Title"),
+        file_name: file_name.to_owned(),
+        artist: value("Artist"),
+        diff_name: value("Version"),
+        origin_server: String::new(),
+        mapper_name: value("Creator"),
+        mapper_id: String::new(),
+        rank_status: RankStatus::Pending,
+        game_mode: match beatmap.mode {
+            rosu_pp::GameMode::Osu => GameMode::Standard,
+            rosu_pp::GameMode::Taiko => GameMode::Taiko,
+            rosu_pp::GameMode::Catch => GameMode::Fruits,
+            rosu_pp::GameMode::Mania => GameMode::Mania,
+        },
+        stars: decimal(attributes.stars()),
+        bpm: decimal(beatmap.bpm()),
+        cs: decimal(beatmap.cs as f64),
+        od: decimal(beatmap.od as f64),
+        ar: decimal(beatmap.ar as f64),
+        hp: decimal(beatmap.hp as f64),
+        length,
+        length_drain: length,
+        source: optional("Source"),
+        tags: optional("Tags"),
+        genre_id: None,
+        language_id: None,
+        storyboard: None,
+        video: None,
+        object_count: Some(beatmap.n_circles as i32),
+        slider_count: Some(beatmap.n_sliders as i32),
+        spinner_count: Some(beatmap.n_spinners as i32),
+        max_combo: Some(attributes.max_combo() as i32),
+        immutable: false,
+        last_update: now,
+        upload_time: now,
+        approved_time: None,
+        updated_at: now,
+    })
+}
+
+/// Build the beatmap row from an osu! api v1 `get_beatmaps` entry, some
+/// mirrors serve the numbers as strings and the others as numbers.
+fn api_beatmap(beatmap: &Map<String, Value>) -> Option<beatmaps::Model> {
+    let text = |key: &str| match beatmap.get(key)? {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(if *b { "1" } else { "0" }.to_owned()),
+        _ => None,
+    };
+    let parse = |key: &str| text(key)?.parse::<f64>().ok();
+    let int = |key: &str| parse(key).map(|n| n as i32);
+    let flag = |key: &str| parse(key).map(|n| n > 0.0);
+    let time = |key: &str| {
+        NaiveDateTime::parse_from_str(&text(key)?, "%Y-%m-%d %H:%M:%S")
+            .ok()
+            .map(|t| DateTimeWithTimeZone::from(t.and_utc()))
+    };
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+
+    let artist = text("artist")?;
+    let title = text("title")?;
+    let diff_name = text("version")?;
+    let mapper_name = text("creator").unwrap_or_default();
+
+    Some(beatmaps::Model {
+        bid: int("beatmap_id")?,
+        sid: int("beatmapset_id")?,
+        md5: text("file_md5")?,
+        file_name: format!(
+            "{artist} - {title} ({mapper_name}) [{diff_name}].osu"
+        ),
+        title,
+        artist,
+        diff_name,
+        origin_server: String::new(),
+        mapper_name,
+        mapper_id: text("creator_id").unwrap_or_default(),
+        rank_status: match int("approved")? {
+            4 => RankStatus::Loved,
+            3 => RankStatus::Qualified,
+            2 => RankStatus::Approved,
+            1 => RankStatus::Ranked,
+            0 => RankStatus::Pending,
+            -1 => RankStatus::Wip,
+            _ => RankStatus::Graveyard,
+        },
+        game_mode: match int("mode")? {
+            1 => GameMode::Taiko,
+            2 => GameMode::Fruits,
+            3 => GameMode::Mania,
+            _ => GameMode::Standard,
+        },
+        stars: decimal(parse("difficultyrating").unwrap_or_default()),
+        bpm: decimal(parse("bpm").unwrap_or_default()),
+        cs: decimal(parse("diff_size").unwrap_or_default()),
+        od: decimal(parse("diff_overall").unwrap_or_default()),
+        ar: decimal(parse("diff_approach").unwrap_or_default()),
+        hp: decimal(parse("diff_drain").unwrap_or_default()),
+        length: int("total_length").unwrap_or_default(),
+        length_drain: int("hit_length").unwrap_or_default(),
+        source: text("source").filter(|s| !s.is_empty()),
+        tags: text("tags").filter(|s| !s.is_empty()),
+        genre_id: int("genre_id").map(|id| id as i16),
+        language_id: int("language_id").map(|id| id as i16),
+        storyboard: flag("storyboard"),
+        video: flag("video"),
+        object_count: int("count_normal"),
+        slider_count: int("count_slider"),
+        spinner_count: int("count_spinner"),
+        max_combo: int("max_combo"),
+        immutable: false,
+        last_update: time("last_update").unwrap_or(now),
+        upload_time: time("submit_date").unwrap_or(now),
+        approved_time: time("approved_date"),
+        updated_at: now,
+    })
+}
+
+#[inline]
+fn decimal(value: f64) -> Decimal {
+    Decimal::from_str(&format!("{value:.2}")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peace_repositories::beatmaps::BeatmapsRepository;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const OSU_FILE: &str = "osu file format v14\n\n[General]\nMode: 0\n\n\
+        [Metadata]\nTitle:Test\nArtist:Peace\nCreator:Tester\nVersion:Easy\n\
+        BeatmapID:1\nBeatmapSetID:1\n\n[Difficulty]\nHPDrainRate:5\n\
+        CircleSize:4\nOverallDifficulty:6\nApproachRate:7\n\
+        SliderMultiplier:1.4\nSliderTickRate:1\n\n[TimingPoints]\n\
+        0,500,4,2,0,100,1,0\n\n[HitObjects]\n256,192,1000,1,0,0:0:0:0:\n\
+        256,192,2000,1,0,0:0:0:0:\n";
+
+    #[derive(Default)]
+    struct MemoryBeatmapsRepository(Mutex<Vec<beatmaps::Model>>);
+
+    #[async_trait]
+    impl BeatmapsRepository for MemoryBeatmapsRepository {
+        async fn get_beatmap_by_md5(
+            &self,
+            md5: &str,
+        ) -> Result<beatmaps::Model, GetBeatmapError> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|b| b.md5 == md5)
+                .cloned()
+                .ok_or(GetBeatmapError::BeatmapNotExists)
+        }
+
+        async fn get_beatmap_by_bid(
+            &self,
+            bid: i32,
+        ) -> Result<beatmaps::Model, GetBeatmapError> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|b| b.bid == bid)
+                .cloned()
+                .ok_or(GetBeatmapError::BeatmapNotExists)
+        }
+
+        async fn get_beatmaps_by_sid(
+            &self,
+            sid: i32,
+        ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|b| b.sid == sid)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_beatmaps_by_file_names(
+            &self,
+            _file_names: &[String],
+        ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+            unimplemented!()
+        }
+
+        async fn get_beatmaps_by_bids(
+            &self,
+            _bids: &[i32],
+        ) -> Result<Vec<beatmaps::Model>, GetBeatmapError> {
+            unimplemented!()
+        }
+
+        async fn save_beatmap(
+            &self,
+            beatmap: beatmaps::Model,
+        ) -> Result<(), GetBeatmapError> {
+            self.0.lock().unwrap().push(beatmap);
+            Ok(())
+        }
+    }
+
+    /// A stand-in of the osu! api serving the beatmap 1 only, counts the
+    /// requests it has received.
+    async fn serve_mirror() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let md5 = format!("{:x}", md5::compute(OSU_FILE));
+
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = req.split_whitespace().nth(1).unwrap_or_default();
+
+                let body = if path.starts_with("/osu/1") {
+                    OSU_FILE.to_owned()
+                } else if path.contains("b=1")
+                    || path.contains("s=1")
+                    || path.contains(&md5)
+                {
+                    format!(
+                        r#"[{{"beatmapset_id":"1","beatmap_id":"1",
+                        "approved":"1","total_length":"2","hit_length":"1",
+                        "version":"Easy","file_md5":"{md5}","mode":"0",
+                        "diff_size":"4","artist":"Peace","title":"Test",
+                        "creator":"Tester","creator_id":2,"bpm":"120",
+                        "submit_date":"2023-01-02 03:04:05",
+                        "approved_date":null,"difficultyrating":"1.23"}}]"#
+                    )
+                } else {
+                    "[]".to_owned()
+                };
+
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                    Connection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_http_mirror() {
+        let (base_url, _) = serve_mirror().await;
+        let mirror = HttpBeatmapMirror::new(&base_url, None);
+
+        let beatmap = mirror
+            .get_beatmap(&BeatmapQuery::BeatmapId(1))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(beatmap.bid, 1);
+        assert_eq!(beatmap.rank_status, RankStatus::Ranked);
+        assert_eq!(beatmap.mapper_id, "2");
+        assert_eq!(beatmap.file_name, "Peace - Test (Tester) [Easy].osu");
+        assert_eq!(beatmap.stars, Decimal::from_str("1.23").unwrap());
+        assert!(beatmap.approved_time.is_none());
+
+        let beatmap = mirror
+            .get_beatmap(&BeatmapQuery::BeatmapsetId(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(beatmap.sid, 1);
+
+        assert!(mirror
+            .get_beatmap(&BeatmapQuery::BeatmapId(2))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_mirror_rejects_large_osu_file() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                Connection: close\r\n\r\n",
+                MAX_OSU_FILE_SIZE + 1
+            );
+            stream.write_all(resp.as_bytes()).await.unwrap();
+        });
+
+        assert!(matches!(
+            HttpBeatmapMirror::new(&base_url, None).get_osu_file(1).await,
+            Err(BeatmapMirrorError::TooLarge(MAX_OSU_FILE_SIZE))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_mirror() {
+        let dir = std::env::temp_dir()
+            .join(format!("peace-local-mirror-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("1.osu"), OSU_FILE).await.unwrap();
+
+        let mirror = LocalBeatmapMirror::new(&dir);
+        let md5 = format!("{:x}", md5::compute(OSU_FILE));
+
+        let beatmap =
+            mirror.get_beatmap(&BeatmapQuery::Md5(md5)).await.unwrap().unwrap();
+        assert_eq!(beatmap.bid, 1);
+
+        let beatmap = mirror
+            .get_beatmap(&BeatmapQuery::BeatmapsetId(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(beatmap.bid, 1);
+
+        // the files written later are indexed on the next scan
+        let osu_file = OSU_FILE.replace("BeatmapID:1", "BeatmapID:2");
+        tokio::fs::write(dir.join("2.osu"), &osu_file).await.unwrap();
+        tokio::fs::remove_file(dir.join("1.osu")).await.unwrap();
+        *mirror.scanned_at.lock().await = None;
+
+        assert_eq!(
+            mirror.get_osu_file(2).await.unwrap().as_deref(),
+            Some(osu_file.as_bytes())
+        );
+        assert!(mirror.get_osu_file(1).await.unwrap().is_none());
+        assert_eq!(mirror.index.read().await.len(), 1);
+
+        let _ = tokio::fs::remove_dir_all(dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_fetcher() {
+        let (base_url, requests) = serve_mirror().await;
+        let osu_files_dir = std::env::temp_dir()
+            .join(format!("peace-beatmap-fetcher-{}", std::process::id()));
+        let repository = Arc::new(MemoryBeatmapsRepository::default());
+
+        let fetcher = BeatmapFetcherImpl::new(
+            repository.clone(),
+            vec![HttpBeatmapMirror::new(&base_url, None).into_service()],
+            osu_files_dir.to_str(),
+            &CliBeatmapFetcherConfigs::default(),
+        );
+
+        let md5 = format!("{:x}", md5::compute(OSU_FILE));
+        let beatmap = fetcher
+            .fetch_beatmap(BeatmapQuery::Md5(md5.clone()))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(beatmap.origin_server, base_url);
+        assert_eq!(repository.get_beatmap_by_md5(&md5).await.unwrap().bid, 1);
+        assert_eq!(
+            tokio::fs::read_to_string(fetcher.osu_file_path(1)).await.unwrap(),
+            OSU_FILE
+        );
+
+        // The stored beatmap is not fetched again.
+        let fetched = requests.load(Ordering::SeqCst);
+        fetcher.fetch_beatmap(BeatmapQuery::BeatmapId(1)).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+        fetcher.fetch_beatmap(BeatmapQuery::BeatmapsetId(1)).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+
+        // Neither is the missing one until the negative cache expires.
+        let missing = BeatmapQuery::BeatmapId(2);
+        assert!(fetcher
+            .fetch_beatmap(missing.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(fetcher.fetch_beatmap(missing).await.unwrap().is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), fetched + 1);
+
+        let _ = tokio::fs::remove_dir_all(osu_files_dir).await;
+    }
+
+    #[test]
+    fn test_parse_osu_file() {
+        let beatmap = parse_osu_file("test.osu", OSU_FILE.as_bytes()).unwrap();
+
+        assert_eq!(beatmap.bid, 1);
+        assert_eq!(beatmap.sid, 1);
+        assert_eq!(beatmap.diff_name, "Easy");
+        assert_eq!(beatmap.game_mode, GameMode::Standard);
+        assert_eq!(beatmap.object_count, Some(2));
+        assert_eq!(beatmap.max_combo, Some(2));
+        assert_eq!(beatmap.length, 1);
+    }
+}
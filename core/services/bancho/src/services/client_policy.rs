@@ -0,0 +1,141 @@
+use crate::BanchoServiceError;
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliBanchoClientConfigs {
+    /// Min build date of the osu! clients allowed to connect, such as
+    /// `20230326`, 0 to allow all builds.
+    #[default(0)]
+    #[arg(long, default_value = "0")]
+    pub min_client_build: u32,
+
+    /// Country codes (ISO 3166-1 alpha-2) of the clients not allowed to
+    /// connect.
+    #[arg(long, value_delimiter = ',')]
+    pub blocked_countries: Vec<String>,
+}
+
+/// Build date of the osu! client version, such as `20230326` of
+/// `b20230326.2` and `b20230326cuttingedge`.
+pub fn client_build(client_version: &str) -> Option<u32> {
+    let build = client_version.strip_prefix('b')?;
+    let end = build.find(|c: char| !c.is_ascii_digit()).unwrap_or(build.len());
+
+    build[..end].parse().ok()
+}
+
+/// Decides which osu! clients are allowed to connect to the server.
+#[derive(Debug, Clone, Default)]
+pub struct ClientPolicy {
+    pub min_client_build: u32,
+    pub blocked_countries: HashSet<String>,
+}
+
+impl ClientPolicy {
+    #[inline]
+    pub fn new(cfg: &CliBanchoClientConfigs) -> Self {
+        Self {
+            min_client_build: cfg.min_client_build,
+            blocked_countries: cfg
+                .blocked_countries
+                .iter()
+                .map(|code| code.trim().to_uppercase())
+                .filter(|code| !code.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The unknown versions are rejected only if a min build is set.
+    #[inline]
+    pub fn check_client_version(
+        &self,
+        client_version: &str,
+    ) -> Result<(), BanchoServiceError> {
+        if self.min_client_build == 0 {
+            return Ok(());
+        }
+
+        match client_build(client_version) {
+            Some(build) if build >= self.min_client_build => Ok(()),
+            _ => Err(BanchoServiceError::OutdatedClient(
+                client_version.to_owned(),
+            )),
+        }
+    }
+
+    /// The clients with an unknown country are always allowed.
+    #[inline]
+    pub fn check_country(
+        &self,
+        country_code: Option<&str>,
+    ) -> Result<(), BanchoServiceError> {
+        match country_code {
+            Some(code)
+                if self.blocked_countries.contains(&code.to_uppercase()) =>
+            {
+                Err(BanchoServiceError::CountryNotAllowed(code.to_owned()))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy(
+        min_client_build: u32,
+        blocked_countries: &[&str],
+    ) -> ClientPolicy {
+        ClientPolicy::new(&CliBanchoClientConfigs {
+            min_client_build,
+            blocked_countries: blocked_countries
+                .iter()
+                .map(|code| code.to_string())
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn test_client_build() {
+        assert_eq!(client_build("b20230326"), Some(20230326));
+        assert_eq!(client_build("b20230326.2"), Some(20230326));
+        assert_eq!(client_build("b20230326cuttingedge"), Some(20230326));
+        assert_eq!(client_build("20230326"), None);
+        assert_eq!(client_build("b"), None);
+        assert_eq!(client_build("bcuttingedge"), None);
+        assert_eq!(client_build(""), None);
+    }
+
+    #[test]
+    fn test_check_client_version() {
+        // all builds are allowed without a min build
+        assert!(policy(0, &[]).check_client_version("unknown").is_ok());
+
+        let policy = policy(20230326, &[]);
+        assert!(policy.check_client_version("b20230326").is_ok());
+        assert!(policy.check_client_version("b20230401.1").is_ok());
+        assert!(matches!(
+            policy.check_client_version("b20230325"),
+            Err(BanchoServiceError::OutdatedClient(version)) if version == "b20230325"
+        ));
+        assert!(policy.check_client_version("unknown").is_err());
+    }
+
+    #[test]
+    fn test_check_country() {
+        let policy = policy(0, &[" kp ", "", "XX"]);
+        assert_eq!(policy.blocked_countries.len(), 2);
+
+        assert!(matches!(
+            policy.check_country(Some("KP")),
+            Err(BanchoServiceError::CountryNotAllowed(code)) if code == "KP"
+        ));
+        assert!(policy.check_country(Some("xx")).is_err());
+        assert!(policy.check_country(Some("US")).is_ok());
+        assert!(policy.check_country(None).is_ok());
+    }
+}
@@ -0,0 +1,874 @@
+use crate::{BeatmapMirrorError, DirectMirror, DynDirectMirror};
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use infra_services::IntoService;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Mutex, MutexGuard},
+};
+use tonic::async_trait;
+
+/// Beatmapsets per page of the osu!direct search.
+pub const DIRECT_PAGE_SIZE: i32 = 100;
+
+/// The search keywords of the osu!direct tabs, they are not real queries.
+const DIRECT_KEYWORDS: [&str; 3] = ["Newest", "Top Rated", "Most Played"];
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliBeatmapDirectConfigs {
+    /// Base url of the cheesegull compatible mirror used by osu!direct,
+    /// osu!direct is disabled if not set.
+    #[arg(long)]
+    pub direct_mirror: Option<String>,
+
+    #[default(10)]
+    #[arg(long, default_value = "10")]
+    pub direct_search_timeout_secs: u64,
+
+    #[default(120)]
+    #[arg(long, default_value = "120")]
+    pub beatmapset_download_timeout_secs: u64,
+
+    /// A directory to cache the downloaded `.osz` files.
+    #[default("./.data/beatmapsets".to_owned())]
+    #[arg(long, default_value = "./.data/beatmapsets")]
+    pub beatmapset_cache_dir: String,
+
+    /// Max size in bytes of a downloaded `.osz` file.
+    #[default(104857600)]
+    #[arg(long, default_value = "104857600")]
+    pub beatmapset_max_bytes: u64,
+
+    /// Cached `.osz` files older than this are downloaded again, as the
+    /// beatmapsets may be updated.
+    #[default(604800)]
+    #[arg(long, default_value = "604800")]
+    pub beatmapset_cache_secs: u64,
+
+    /// Max total size in bytes of the cached `.osz` files, the least
+    /// recently used files are removed first.
+    #[default(10737418240)]
+    #[arg(long, default_value = "10737418240")]
+    pub beatmapset_cache_max_bytes: u64,
+}
+
+/// The osu!direct search query, the `ranked_status` is the tab selected in
+/// the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectSearchQuery {
+    pub query: Option<String>,
+    pub mode: Option<i32>,
+    pub ranked_status: Option<i32>,
+    pub page: i32,
+}
+
+impl DirectSearchQuery {
+    /// Build the query from the params of `/web/osu-search.php`.
+    pub fn from_client(
+        query: String,
+        mode: i32,
+        ranked_status: i32,
+        page: i32,
+    ) -> Self {
+        let query = query.trim();
+
+        Self {
+            query: (!query.is_empty() && !DIRECT_KEYWORDS.contains(&query))
+                .then(|| query.to_owned()),
+            mode: (0..=3).contains(&mode).then_some(mode),
+            ranked_status: direct_ranked_status(ranked_status),
+            page: page.max(0),
+        }
+    }
+}
+
+/// Convert the ranked status tab of osu!direct to the status of the osu!
+/// api, `None` for all statuses.
+#[inline]
+pub fn direct_ranked_status(status: i32) -> Option<i32> {
+    match status {
+        // ranked, ranked played
+        0 | 7 => Some(1),
+        // pending
+        2 => Some(0),
+        // qualified
+        3 => Some(3),
+        // graveyard
+        5 => Some(-2),
+        // loved
+        8 => Some(4),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DirectBeatmap {
+    #[serde(rename = "BeatmapID", default)]
+    pub beatmap_id: i32,
+    #[serde(default)]
+    pub diff_name: String,
+    #[serde(default)]
+    pub difficulty_rating: f64,
+    #[serde(rename = "CS", default)]
+    pub cs: f64,
+    #[serde(rename = "OD", default)]
+    pub od: f64,
+    #[serde(rename = "AR", default)]
+    pub ar: f64,
+    #[serde(rename = "HP", default)]
+    pub hp: f64,
+    #[serde(default)]
+    pub mode: i32,
+}
+
+/// A beatmapset in the cheesegull format.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DirectBeatmapset {
+    #[serde(rename = "SetID")]
+    pub set_id: i32,
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub creator: String,
+    #[serde(default)]
+    pub ranked_status: i32,
+    #[serde(default)]
+    pub last_update: String,
+    #[serde(default)]
+    pub has_video: bool,
+    #[serde(default)]
+    pub children_beatmaps: Vec<DirectBeatmap>,
+}
+
+impl DirectBeatmapset {
+    /// A line of the `/web/osu-search.php` response.
+    pub fn search_line(&self) -> String {
+        let diffs = self
+            .children_beatmaps
+            .iter()
+            .map(|b| {
+                format!(
+                    "[{:.2}⭐] {} {{cs: {} / od: {} / ar: {} / hp: {}}}@{}",
+                    b.difficulty_rating,
+                    b.diff_name.replace([',', '|', '\r', '\n'], ""),
+                    b.cs,
+                    b.od,
+                    b.ar,
+                    b.hp,
+                    b.mode
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{}|{}|{}|{}|{}|10.0|{}|{}|0|{}|0|0|0|{diffs}",
+            self.file_name(),
+            direct_field(&self.artist),
+            direct_field(&self.title),
+            direct_field(&self.creator),
+            self.ranked_status,
+            self.last_update,
+            self.set_id,
+            self.has_video as i32,
+        )
+    }
+
+    /// The response of `/web/osu-search-set.php`.
+    pub fn search_set_line(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|10.0|{}|{}|0|0|0|0|0",
+            self.file_name(),
+            direct_field(&self.artist),
+            direct_field(&self.title),
+            direct_field(&self.creator),
+            self.ranked_status,
+            self.last_update,
+            self.set_id,
+        )
+    }
+
+    #[inline]
+    pub fn file_name(&self) -> String {
+        format!("{}.osz", self.set_id)
+    }
+}
+
+/// Remove the separators of the osu!direct response from a field.
+#[inline]
+fn direct_field(value: &str) -> String {
+    value.replace(['|', '\r', '\n'], "")
+}
+
+/// Render the `/web/osu-search.php` response, the client requests the next
+/// page if the count is over the page size.
+pub fn direct_search_response(beatmapsets: &[DirectBeatmapset]) -> String {
+    let count = if beatmapsets.len() as i32 >= DIRECT_PAGE_SIZE {
+        DIRECT_PAGE_SIZE + 1
+    } else {
+        beatmapsets.len() as i32
+    };
+
+    std::iter::once(count.to_string())
+        .chain(beatmapsets.iter().map(DirectBeatmapset::search_line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A cheesegull compatible mirror, serving `/api/search`, `/api/s/{set_id}`
+/// and the `.osz` files at `/d/{set_id}`.
+#[derive(Debug, Clone)]
+pub struct HttpDirectMirror {
+    pub base_url: String,
+    pub client: reqwest::Client,
+    pub download_client: reqwest::Client,
+}
+
+impl HttpDirectMirror {
+    #[inline]
+    pub fn new(
+        base_url: &str,
+        search_timeout: Duration,
+        download_timeout: Duration,
+    ) -> Self {
+        let client = |timeout| {
+            reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .unwrap_or_default()
+        };
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client: client(search_timeout),
+            download_client: client(download_timeout),
+        }
+    }
+
+    /// Build the mirror from the config, `None` if osu!direct is disabled.
+    pub fn from_cfg(cfg: &CliBeatmapDirectConfigs) -> Option<DynDirectMirror> {
+        cfg.direct_mirror.as_deref().map(|base_url| {
+            Self::new(
+                base_url,
+                Duration::from_secs(cfg.direct_search_timeout_secs),
+                Duration::from_secs(cfg.beatmapset_download_timeout_secs),
+            )
+            .into_service()
+        })
+    }
+}
+
+impl IntoService<DynDirectMirror> for HttpDirectMirror {
+    #[inline]
+    fn into_service(self) -> DynDirectMirror {
+        Arc::new(self) as DynDirectMirror
+    }
+}
+
+#[async_trait]
+impl DirectMirror for HttpDirectMirror {
+    fn name(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    async fn search(
+        &self,
+        query: &DirectSearchQuery,
+    ) -> Result<Vec<DirectBeatmapset>, BeatmapMirrorError> {
+        // no mirror has this many beatmapsets
+        let Some(offset) = query.page.checked_mul(DIRECT_PAGE_SIZE) else {
+            return Ok(Vec::new());
+        };
+
+        let mut params = vec![
+            ("amount", DIRECT_PAGE_SIZE.to_string()),
+            ("offset", offset.to_string()),
+        ];
+
+        if let Some(q) = &query.query {
+            params.push(("query", q.to_owned()));
+        }
+
+        if let Some(mode) = query.mode {
+            params.push(("mode", mode.to_string()));
+        }
+
+        if let Some(status) = query.ranked_status {
+            params.push(("status", status.to_string()));
+        }
+
+        Ok(self
+            .client
+            .get(format!("{}/api/search", self.base_url))
+            .query(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Option<Vec<DirectBeatmapset>>>()
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn get_beatmapset(
+        &self,
+        beatmapset_id: i32,
+    ) -> Result<Option<DirectBeatmapset>, BeatmapMirrorError> {
+        let resp = self
+            .client
+            .get(format!("{}/api/s/{beatmapset_id}", self.base_url))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(resp.error_for_status()?.json::<Option<DirectBeatmapset>>().await?)
+    }
+
+    async fn download_beatmapset(
+        &self,
+        beatmapset_id: i32,
+        no_video: bool,
+        max_bytes: u64,
+        path: &Path,
+    ) -> Result<bool, BeatmapMirrorError> {
+        let suffix = if no_video { "n" } else { "" };
+
+        let mut resp = self
+            .download_client
+            .get(format!("{}/d/{beatmapset_id}{suffix}", self.base_url))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+
+        resp = resp.error_for_status()?;
+
+        if resp.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(BeatmapMirrorError::TooLarge(max_bytes));
+        }
+
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut written = 0;
+
+        while let Some(chunk) = resp.chunk().await? {
+            written += chunk.len() as u64;
+            if written > max_bytes {
+                return Err(BeatmapMirrorError::TooLarge(max_bytes));
+            }
+
+            file.write_all(&chunk).await?;
+        }
+
+        file.flush().await?;
+
+        Ok(written > 0)
+    }
+}
+
+/// A cached `.osz` file.
+#[derive(Debug, Clone, Copy)]
+pub struct CachedBeatmapset {
+    pub size: u64,
+    pub used_at: SystemTime,
+}
+
+/// The cached `.osz` files with their total size, indexed from the cache
+/// directory on the first use.
+#[derive(Debug, Default)]
+pub struct BeatmapsetCacheIndex {
+    pub loaded: bool,
+    pub total_bytes: u64,
+    pub files: HashMap<PathBuf, CachedBeatmapset>,
+}
+
+impl BeatmapsetCacheIndex {
+    /// Index the `.osz` files in the directory, the files are considered
+    /// used when they were downloaded.
+    async fn load(&mut self, dir: &Path) {
+        self.loaded = true;
+
+        let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+            return;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "osz") {
+                continue;
+            }
+
+            let Ok(meta) = entry.metadata().await else { continue };
+            if !meta.is_file() {
+                continue;
+            }
+
+            self.insert(
+                path,
+                meta.len(),
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            );
+        }
+    }
+
+    pub fn insert(&mut self, path: PathBuf, size: u64, used_at: SystemTime) {
+        if let Some(old) =
+            self.files.insert(path, CachedBeatmapset { size, used_at })
+        {
+            self.total_bytes -= old.size;
+        }
+
+        self.total_bytes += size;
+    }
+
+    #[inline]
+    pub fn touch(&mut self, path: &Path) {
+        if let Some(file) = self.files.get_mut(path) {
+            file.used_at = SystemTime::now();
+        }
+    }
+
+    /// Remove the least recently used files until the total size is within
+    /// `max_bytes`, the `keep` file is never removed. Returns the paths of
+    /// the removed files.
+    pub fn evict(&mut self, max_bytes: u64, keep: &Path) -> Vec<PathBuf> {
+        if self.total_bytes <= max_bytes {
+            return Vec::new();
+        }
+
+        let mut files = self
+            .files
+            .iter()
+            .filter(|(path, _)| path.as_path() != keep)
+            .map(|(path, file)| (file.used_at, path.clone()))
+            .collect::<Vec<_>>();
+        files.sort_unstable();
+
+        let mut evicted = Vec::new();
+        for (_, path) in files {
+            if self.total_bytes <= max_bytes {
+                break;
+            }
+
+            if let Some(file) = self.files.remove(&path) {
+                self.total_bytes -= file.size;
+                evicted.push(path);
+            }
+        }
+
+        evicted
+    }
+}
+
+/// Caches the downloaded `.osz` files in a local directory, the files are
+/// downloaded into a temporary file first so a failed download is never
+/// served.
+///
+/// The concurrent requests of the same file wait for a single download, and
+/// the least recently used files are removed when the cache is over its
+/// total size.
+#[derive(Debug)]
+pub struct BeatmapsetCache {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+    pub max_total_bytes: u64,
+    pub expires: Duration,
+    pub download_seq: AtomicU64,
+    pub index: Mutex<BeatmapsetCacheIndex>,
+    /// The locks of the files being downloaded.
+    pub downloads: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl BeatmapsetCache {
+    #[inline]
+    pub fn new(cfg: &CliBeatmapDirectConfigs) -> Self {
+        Self {
+            dir: cfg.beatmapset_cache_dir.as_str().into(),
+            max_bytes: cfg.beatmapset_max_bytes,
+            max_total_bytes: cfg.beatmapset_cache_max_bytes,
+            expires: Duration::from_secs(cfg.beatmapset_cache_secs),
+            download_seq: AtomicU64::default(),
+            index: Mutex::default(),
+            downloads: std::sync::Mutex::default(),
+        }
+    }
+
+    #[inline]
+    pub fn beatmapset_path(
+        &self,
+        beatmapset_id: i32,
+        no_video: bool,
+    ) -> PathBuf {
+        let suffix = if no_video { "n" } else { "" };
+        self.dir.join(format!("{beatmapset_id}{suffix}.osz"))
+    }
+
+    async fn is_fresh(&self, path: &Path) -> bool {
+        tokio::fs::metadata(path)
+            .await
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| {
+                SystemTime::now().duration_since(modified).ok()
+            })
+            .is_some_and(|age| age < self.expires)
+    }
+
+    async fn index(&self) -> MutexGuard<'_, BeatmapsetCacheIndex> {
+        let mut index = self.index.lock().await;
+        if !index.loaded {
+            index.load(&self.dir).await;
+        }
+
+        index
+    }
+
+    /// Add the downloaded file to the index, and remove the least recently
+    /// used files if the cache is over its total size.
+    async fn add_cached(&self, path: &Path, size: u64) {
+        let mut index = self.index().await;
+        index.insert(path.to_owned(), size, SystemTime::now());
+
+        for evicted in index.evict(self.max_total_bytes, path) {
+            if let Err(err) = tokio::fs::remove_file(&evicted).await {
+                warn!(
+                    "failed to remove cached beatmapset \"{}\": {err}",
+                    evicted.display()
+                );
+            }
+        }
+    }
+
+    /// Get the path of the cached `.osz` file, downloads it from the mirror
+    /// if it's missing or expired. Returns `None` if the mirror doesn't have
+    /// the beatmapset.
+    pub async fn get_or_download(
+        &self,
+        mirror: &DynDirectMirror,
+        beatmapset_id: i32,
+        no_video: bool,
+    ) -> Result<Option<PathBuf>, BeatmapMirrorError> {
+        let path = self.beatmapset_path(beatmapset_id, no_video);
+
+        if self.is_fresh(&path).await {
+            self.index().await.touch(&path);
+            return Ok(Some(path));
+        }
+
+        let lock = {
+            let mut downloads = self.downloads.lock().unwrap();
+            // the locks left by the cancelled requests
+            downloads.retain(|_, lock| Arc::strong_count(lock) > 1);
+            downloads.entry(path.clone()).or_default().clone()
+        };
+
+        let res = {
+            let _downloading = lock.lock().await;
+            self.download(mirror, beatmapset_id, no_video, &path).await
+        };
+
+        let mut downloads = self.downloads.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            downloads.remove(&path);
+        }
+
+        res
+    }
+
+    async fn download(
+        &self,
+        mirror: &DynDirectMirror,
+        beatmapset_id: i32,
+        no_video: bool,
+        path: &Path,
+    ) -> Result<Option<PathBuf>, BeatmapMirrorError> {
+        // downloaded by the request we waited for
+        if self.is_fresh(path).await {
+            self.index().await.touch(path);
+            return Ok(Some(path.to_owned()));
+        }
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        let tmp_path = self.dir.join(format!(
+            "{beatmapset_id}.{}.part",
+            self.download_seq.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let res = mirror
+            .download_beatmapset(
+                beatmapset_id,
+                no_video,
+                self.max_bytes,
+                &tmp_path,
+            )
+            .await;
+
+        match res {
+            Ok(true) => {
+                let size = tokio::fs::metadata(&tmp_path).await?.len();
+                tokio::fs::rename(&tmp_path, path).await?;
+                self.add_cached(path, size).await;
+
+                info!(
+                    "downloaded beatmapset {beatmapset_id} from \"{}\"",
+                    mirror.name()
+                );
+
+                Ok(Some(path.to_owned()))
+            },
+            res => {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+
+                // serve the expired file if the mirror is unavailable
+                match res {
+                    Err(err) if tokio::fs::try_exists(path).await? => {
+                        warn!(
+                            "failed to update beatmapset {beatmapset_id} from \
+                            \"{}\": {err}",
+                            mirror.name()
+                        );
+                        self.index().await.touch(path);
+                        Ok(Some(path.to_owned()))
+                    },
+                    Err(err) => Err(err),
+                    Ok(_) => Ok(None),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Writes `size` bytes for every beatmapset, the beatmapset `0` is
+    /// missing.
+    #[derive(Default)]
+    struct MemoryDirectMirror {
+        size: usize,
+        downloads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DirectMirror for MemoryDirectMirror {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        async fn search(
+            &self,
+            _query: &DirectSearchQuery,
+        ) -> Result<Vec<DirectBeatmapset>, BeatmapMirrorError> {
+            Ok(Vec::new())
+        }
+
+        async fn get_beatmapset(
+            &self,
+            _beatmapset_id: i32,
+        ) -> Result<Option<DirectBeatmapset>, BeatmapMirrorError> {
+            Ok(None)
+        }
+
+        async fn download_beatmapset(
+            &self,
+            beatmapset_id: i32,
+            _no_video: bool,
+            _max_bytes: u64,
+            path: &Path,
+        ) -> Result<bool, BeatmapMirrorError> {
+            if beatmapset_id == 0 {
+                return Ok(false);
+            }
+
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            tokio::fs::write(path, vec![0; self.size]).await?;
+
+            Ok(true)
+        }
+    }
+
+    fn beatmapset(set_id: i32) -> DirectBeatmapset {
+        DirectBeatmapset {
+            set_id,
+            artist: "Artist".to_owned(),
+            title: "Title".to_owned(),
+            creator: "Creator".to_owned(),
+            ranked_status: 1,
+            ..Default::default()
+        }
+    }
+
+    fn cache(name: &str, max_total_bytes: u64) -> BeatmapsetCache {
+        let dir = std::env::temp_dir()
+            .join(format!("peace-beatmapsets-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        BeatmapsetCache {
+            dir,
+            max_bytes: 100,
+            max_total_bytes,
+            expires: Duration::from_secs(60),
+            download_seq: AtomicU64::default(),
+            index: Mutex::default(),
+            downloads: std::sync::Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn test_direct_ranked_status() {
+        assert_eq!(direct_ranked_status(0), Some(1));
+        assert_eq!(direct_ranked_status(7), Some(1));
+        assert_eq!(direct_ranked_status(2), Some(0));
+        assert_eq!(direct_ranked_status(3), Some(3));
+        assert_eq!(direct_ranked_status(5), Some(-2));
+        assert_eq!(direct_ranked_status(8), Some(4));
+        // all statuses
+        assert_eq!(direct_ranked_status(4), None);
+        assert_eq!(direct_ranked_status(-1), None);
+    }
+
+    #[test]
+    fn test_direct_search_query() {
+        assert_eq!(
+            DirectSearchQuery::from_client(" camellia ".to_owned(), 1, 0, 2),
+            DirectSearchQuery {
+                query: Some("camellia".to_owned()),
+                mode: Some(1),
+                ranked_status: Some(1),
+                page: 2,
+            }
+        );
+
+        // the tabs and invalid params are not queried
+        assert_eq!(
+            DirectSearchQuery::from_client("Newest".to_owned(), -1, 4, -3),
+            DirectSearchQuery::default()
+        );
+        assert_eq!(
+            DirectSearchQuery::from_client("  ".to_owned(), 4, 4, 0),
+            DirectSearchQuery::default()
+        );
+    }
+
+    #[test]
+    fn test_direct_search_response() {
+        assert_eq!(direct_search_response(&[]), "0");
+
+        let resp = direct_search_response(&[beatmapset(1), beatmapset(2)]);
+        let lines = resp.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "2");
+        assert!(lines[1].starts_with("1.osz|Artist|Title|Creator|1|"));
+
+        // the client requests the next page of a full page
+        let page = (1..=DIRECT_PAGE_SIZE).map(beatmapset).collect::<Vec<_>>();
+        let resp = direct_search_response(&page);
+        assert_eq!(resp.lines().next(), Some("101"));
+        assert_eq!(resp.lines().count(), DIRECT_PAGE_SIZE as usize + 1);
+    }
+
+    #[test]
+    fn test_search_line_separators_removed() {
+        let beatmapset = DirectBeatmapset {
+            artist: "Art|ist\r\n".to_owned(),
+            title: "Ti\ntle".to_owned(),
+            creator: "Crea|tor".to_owned(),
+            children_beatmaps: vec![DirectBeatmap {
+                diff_name: "Ea,sy\n".to_owned(),
+                ..Default::default()
+            }],
+            ..beatmapset(1)
+        };
+
+        let line = beatmapset.search_line();
+        assert!(!line.contains('\n') && !line.contains('\r'));
+        assert!(line.starts_with("1.osz|Artist|Title|Creator|"));
+        assert!(line.contains("] Easy {"));
+
+        let line = beatmapset.search_set_line();
+        assert!(line.starts_with("1.osz|Artist|Title|Creator|"));
+    }
+
+    #[tokio::test]
+    async fn test_search_page_overflow() {
+        let mirror = HttpDirectMirror::new(
+            "http://127.0.0.1:0",
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        );
+        let query = DirectSearchQuery { page: i32::MAX, ..Default::default() };
+
+        assert!(mirror.search(&query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_downloads_deduplicated() {
+        let cache = cache("dedupe", 1000);
+        let mirror =
+            Arc::new(MemoryDirectMirror { size: 10, ..Default::default() });
+        let dyn_mirror = mirror.clone() as DynDirectMirror;
+
+        let paths = futures::future::join_all(
+            (0..4).map(|_| cache.get_or_download(&dyn_mirror, 1, false)),
+        )
+        .await;
+
+        for path in paths {
+            assert_eq!(path.unwrap(), Some(cache.beatmapset_path(1, false)));
+        }
+        assert_eq!(mirror.downloads.load(Ordering::SeqCst), 1);
+        assert!(cache.downloads.lock().unwrap().is_empty());
+
+        assert_eq!(
+            cache.get_or_download(&dyn_mirror, 0, false).await.unwrap(),
+            None
+        );
+
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_evicted() {
+        let cache = cache("evict", 25);
+        let mirror =
+            Arc::new(MemoryDirectMirror { size: 10, ..Default::default() })
+                as DynDirectMirror;
+
+        cache.get_or_download(&mirror, 1, false).await.unwrap();
+        cache.get_or_download(&mirror, 2, false).await.unwrap();
+        // the beatmapset 2 becomes the least recently used
+        cache.get_or_download(&mirror, 1, false).await.unwrap();
+        cache.get_or_download(&mirror, 3, false).await.unwrap();
+
+        assert!(cache.beatmapset_path(1, false).exists());
+        assert!(!cache.beatmapset_path(2, false).exists());
+        assert!(cache.beatmapset_path(3, false).exists());
+        assert_eq!(cache.index.lock().await.total_bytes, 20);
+
+        // the existing files are indexed by a new cache
+        let reloaded = BeatmapsetCache { index: Mutex::default(), ..cache };
+        assert_eq!(reloaded.index().await.total_bytes, 20);
+
+        std::fs::remove_dir_all(&reloaded.dir).unwrap();
+    }
+}
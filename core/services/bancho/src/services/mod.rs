@@ -1,9 +1,21 @@
 pub mod background;
 pub mod bancho;
+pub mod beatmap_fetcher;
+pub mod client_policy;
+pub mod direct;
 pub mod password;
+pub mod registration;
+pub mod replay;
+pub mod screenshot;
 pub mod traits;
 
 pub use background::*;
 pub use bancho::*;
+pub use beatmap_fetcher::*;
+pub use client_policy::*;
+pub use direct::*;
 pub use password::*;
+pub use registration::*;
+pub use replay::*;
+pub use screenshot::*;
 pub use traits::*;
@@ -0,0 +1,274 @@
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use domain_users::{Email, UsernameAscii};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    ops::RangeInclusive,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const USERNAME_LENGTH: RangeInclusive<usize> = 2..=15;
+pub const PASSWORD_LENGTH: RangeInclusive<usize> = 8..=32;
+pub const PASSWORD_MIN_UNIQUE_CHARS: usize = 4;
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliBanchoRegistrationConfigs {
+    /// Disable the account registration of the osu! clients.
+    #[arg(long)]
+    pub registration_disabled: bool,
+
+    /// Max registration attempts from an ip in the window, including the
+    /// form checks of the osu! clients, 0 to disable.
+    #[default(10)]
+    #[arg(long, default_value = "10")]
+    pub registration_rate_limit: u32,
+
+    /// Seconds of the registration rate limit window.
+    #[default(3600)]
+    #[arg(long, default_value = "3600")]
+    pub registration_rate_limit_secs: u64,
+}
+
+/// Errors of the registration form fields, named as the fields of the form
+/// sent by the osu! clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationErrors {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub username: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_email: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub password: Vec<String>,
+}
+
+impl RegistrationErrors {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.username.is_empty()
+            && self.user_email.is_empty()
+            && self.password.is_empty()
+    }
+
+    /// Validate the form fields, the availability of the username and email
+    /// is checked with the database later.
+    pub fn validate(
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> (Option<UsernameAscii>, Option<Email>, Self) {
+        let mut errors = Self::default();
+
+        let username = match UsernameAscii::new(username) {
+            Ok(username) if !USERNAME_LENGTH.contains(&username.len()) => {
+                errors.username.push(format!(
+                    "Must be {}-{} characters in length.",
+                    USERNAME_LENGTH.start(),
+                    USERNAME_LENGTH.end()
+                ));
+                None
+            },
+            Ok(username) => Some(username),
+            Err(err) => {
+                errors.username.push(err.to_string());
+                None
+            },
+        };
+
+        let email = Email::new(email)
+            .map_err(|err| errors.user_email.push(err.to_string()))
+            .ok();
+
+        if !PASSWORD_LENGTH.contains(&password.chars().count()) {
+            errors.password.push(format!(
+                "Must be {}-{} characters in length.",
+                PASSWORD_LENGTH.start(),
+                PASSWORD_LENGTH.end()
+            ));
+        } else {
+            let mut chars = password.chars().collect::<Vec<_>>();
+            chars.sort_unstable();
+            chars.dedup();
+
+            if chars.len() < PASSWORD_MIN_UNIQUE_CHARS {
+                errors.password.push(format!(
+                    "Must have at least {PASSWORD_MIN_UNIQUE_CHARS} unique \
+                     characters."
+                ));
+            }
+        }
+
+        (username, email, errors)
+    }
+}
+
+/// Limits the registration attempts from an ip in a sliding window.
+#[derive(Debug)]
+pub struct RegistrationGuard {
+    pub disabled: bool,
+    pub limit: u32,
+    pub window: Duration,
+    pub records: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl RegistrationGuard {
+    #[inline]
+    pub fn new(cfg: &CliBanchoRegistrationConfigs) -> Self {
+        Self {
+            disabled: cfg.registration_disabled,
+            limit: cfg.registration_rate_limit,
+            window: Duration::from_secs(cfg.registration_rate_limit_secs),
+            records: Mutex::default(),
+        }
+    }
+
+    /// Record an attempt of the ip if it is under the limit in the window,
+    /// returns `false` if the ip has too many attempts. The expired records
+    /// of all ips are removed.
+    pub fn try_acquire(&self, ip: IpAddr) -> bool {
+        if self.limit == 0 {
+            return true;
+        }
+
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+
+        records.retain(|_, attempts| {
+            while attempts
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= self.window)
+            {
+                attempts.pop_front();
+            }
+            !attempts.is_empty()
+        });
+
+        let attempts = records.entry(ip).or_default();
+        if attempts.len() >= self.limit as usize {
+            return false;
+        }
+
+        attempts.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_valid_form() {
+        let (username, email, errors) = RegistrationErrors::validate(
+            " peppy ",
+            "Peppy@Example.com",
+            "abcd1234",
+        );
+
+        assert!(errors.is_empty());
+        assert_eq!(username.unwrap().as_ref(), "peppy");
+        assert_eq!(email.unwrap(), Email::new("peppy@example.com").unwrap());
+    }
+
+    #[test]
+    fn test_validate_username() {
+        let (username, _, errors) =
+            RegistrationErrors::validate("a", "a@b.com", "abcd1234");
+        assert!(username.is_none());
+        assert_eq!(errors.username.len(), 1);
+
+        let (username, _, errors) = RegistrationErrors::validate(
+            &"a".repeat(*USERNAME_LENGTH.end() + 1),
+            "a@b.com",
+            "abcd1234",
+        );
+        assert!(username.is_none());
+        assert_eq!(errors.username.len(), 1);
+
+        for invalid in ["ペッピー", "a_b c"] {
+            let (username, _, errors) =
+                RegistrationErrors::validate(invalid, "a@b.com", "abcd1234");
+            assert!(username.is_none());
+            assert_eq!(errors.username.len(), 1);
+            assert!(errors.user_email.is_empty() && errors.password.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_validate_email() {
+        let (username, email, errors) =
+            RegistrationErrors::validate("peppy", "not an email", "abcd1234");
+
+        assert!(username.is_some());
+        assert!(email.is_none());
+        assert_eq!(errors.user_email, ["Invalid email."]);
+    }
+
+    #[test]
+    fn test_validate_password() {
+        let (_, _, errors) =
+            RegistrationErrors::validate("peppy", "a@b.com", "short");
+        assert_eq!(errors.password.len(), 1);
+
+        let (_, _, errors) = RegistrationErrors::validate(
+            "peppy",
+            "a@b.com",
+            &"a".repeat(*PASSWORD_LENGTH.end() + 1),
+        );
+        assert_eq!(errors.password.len(), 1);
+
+        // the length is counted in characters
+        let (_, _, errors) =
+            RegistrationErrors::validate("peppy", "a@b.com", "пароль12");
+        assert!(errors.password.is_empty());
+
+        let (_, _, errors) =
+            RegistrationErrors::validate("peppy", "a@b.com", "aaabbbccc");
+        assert_eq!(errors.password.len(), 1);
+    }
+
+    #[test]
+    fn test_errors_serialized_by_field() {
+        let (_, _, errors) = RegistrationErrors::validate("a", "x", "abcd1234");
+
+        assert_eq!(
+            serde_json::to_value(&errors).unwrap(),
+            serde_json::json!({
+                "username": ["Must be 2-15 characters in length."],
+                "user_email": ["Invalid email."],
+            })
+        );
+    }
+
+    fn guard(limit: u32, window: Duration) -> RegistrationGuard {
+        RegistrationGuard {
+            disabled: false,
+            limit,
+            window,
+            records: Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn test_guard_limits_attempts() {
+        let guard = guard(2, Duration::from_secs(60));
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert!(guard.try_acquire(ip));
+        assert!(guard.try_acquire(ip));
+        assert!(!guard.try_acquire(ip));
+        assert!(guard.try_acquire(IpAddr::from([127, 0, 0, 2])));
+    }
+
+    #[test]
+    fn test_guard_evicts_stale_ips() {
+        let guard = guard(1, Duration::ZERO);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert!(guard.try_acquire(ip));
+        assert!(guard.try_acquire(IpAddr::from([127, 0, 0, 2])));
+
+        assert!(!guard.records.lock().unwrap().contains_key(&ip));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{DynReplayStore, ReplayStore, ReplayStoreError};
+use bancho_packets::BanchoPacketWrite;
+use infra_services::IntoService;
+use peace_db::{
+    peace::entity::{scores, scores_classic, sea_orm_active_enums::GameMode},
+    ActiveEnum,
+};
+use std::{io::ErrorKind, path::PathBuf, sync::Arc};
+use tonic::async_trait;
+
+/// Ticks (100ns) between `0001-01-01` and the unix epoch, the `.osr`
+/// timestamp is in Windows ticks.
+const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+
+/// Stores the raw replay data of the scores as files in a local directory,
+/// named by the score id.
+#[derive(Debug, Clone)]
+pub struct LocalReplayStore {
+    pub dir: PathBuf,
+}
+
+impl LocalReplayStore {
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    #[inline]
+    pub fn replay_path(&self, score_id: i64) -> PathBuf {
+        self.dir.join(format!("{score_id}.osr"))
+    }
+}
+
+impl IntoService<DynReplayStore> for LocalReplayStore {
+    #[inline]
+    fn into_service(self) -> DynReplayStore {
+        Arc::new(self) as DynReplayStore
+    }
+}
+
+#[async_trait]
+impl ReplayStore for LocalReplayStore {
+    async fn save_replay(
+        &self,
+        score_id: i64,
+        replay: &[u8],
+    ) -> Result<(), ReplayStoreError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.replay_path(score_id), replay).await?;
+
+        Ok(())
+    }
+
+    async fn load_replay(
+        &self,
+        score_id: i64,
+    ) -> Result<Option<Vec<u8>>, ReplayStoreError> {
+        match tokio::fs::read(self.replay_path(score_id)).await {
+            Ok(replay) => Ok(Some(replay)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Whether the user can get the replay of the score, the replays of the
+/// invisible scores are only available to their owners and the staff.
+///
+/// The submitted scores are not verified by anything yet, so `verified_at`
+/// doesn't restrict the replays.
+pub fn can_access_replay(
+    score: &scores::Model,
+    user_id: Option<i32>,
+    is_staff: bool,
+) -> bool {
+    !score.invisible || user_id == Some(score.user_id) || is_staff
+}
+
+/// Build a full `.osr` file from the score and its stored replay frames.
+pub fn build_osr(
+    score: &scores::Model,
+    classic: &scores_classic::Model,
+    username: &str,
+    frames: Vec<u8>,
+) -> Vec<u8> {
+    let mode: u8 = match classic.mode {
+        GameMode::Standard
+        | GameMode::StandardRelax
+        | GameMode::StandardAutopilot
+        | GameMode::StandardScoreV2 => 0,
+        GameMode::Taiko | GameMode::TaikoRelax => 1,
+        GameMode::Fruits | GameMode::FruitsRelax => 2,
+        GameMode::Mania => 3,
+    };
+
+    let python_bool = |b: bool| if b { "True" } else { "False" };
+    let replay_md5 = format!(
+        "{:x}",
+        md5::compute(format!(
+            "{}p{}o{}o{}t{}a{}r{}e{}y{username}o{}u{}{}True",
+            classic.n100 + classic.n300,
+            classic.n50,
+            classic.geki,
+            classic.katu,
+            classic.miss,
+            score.map_hash,
+            classic.combo,
+            python_bool(classic.perfect),
+            classic.score,
+            classic.grade.to_value(),
+            classic.mods,
+        ))
+    );
+
+    let timestamp =
+        score.created_at.timestamp() * 10_000_000 + UNIX_EPOCH_TICKS;
+
+    let mut buf = Vec::with_capacity(frames.len() + 128);
+
+    mode.write_into_buf(&mut buf);
+    classic
+        .client_version
+        .parse::<i32>()
+        .unwrap_or_default()
+        .write_into_buf(&mut buf);
+    score.map_hash.as_str().write_into_buf(&mut buf);
+    username.write_into_buf(&mut buf);
+    replay_md5.write_into_buf(&mut buf);
+    (classic.n300 as i16).write_into_buf(&mut buf);
+    (classic.n100 as i16).write_into_buf(&mut buf);
+    (classic.n50 as i16).write_into_buf(&mut buf);
+    (classic.geki as i16).write_into_buf(&mut buf);
+    (classic.katu as i16).write_into_buf(&mut buf);
+    (classic.miss as i16).write_into_buf(&mut buf);
+    classic.score.write_into_buf(&mut buf);
+    (classic.combo as i16).write_into_buf(&mut buf);
+    classic.perfect.write_into_buf(&mut buf);
+    classic.mods.write_into_buf(&mut buf);
+    // life bar graph
+    "".write_into_buf(&mut buf);
+    timestamp.write_into_buf(&mut buf);
+    (frames.len() as i32).write_into_buf(&mut buf);
+    frames.write_into_buf(&mut buf);
+    score.id.write_into_buf(&mut buf);
+
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peace_db::{
+        peace::entity::sea_orm_active_enums::{
+            ScoreGrade, ScoreKind, ScoreVersion,
+        },
+        prelude::Decimal,
+    };
+
+    fn score() -> (scores::Model, scores_classic::Model) {
+        let created_at =
+            chrono::DateTime::parse_from_rfc3339("2023-03-26T12:00:00+00:00")
+                .unwrap();
+
+        (
+            scores::Model {
+                id: 42,
+                map_hash: "beatmap_md5".to_owned(),
+                user_id: 1000,
+                cksm: "checksum".to_owned(),
+                kind: ScoreKind::Classic,
+                playtime: 90,
+                completed: true,
+                invisible: false,
+                verified_at: None,
+                created_at,
+                updated_at: created_at,
+            },
+            scores_classic::Model {
+                id: 42,
+                mode: GameMode::TaikoRelax,
+                score_version: ScoreVersion::V1,
+                score: 123456,
+                accuracy: Decimal::new(9250, 2),
+                combo: 321,
+                mods: 128,
+                n300: 100,
+                n100: 10,
+                n50: 1,
+                miss: 2,
+                geki: 20,
+                katu: 5,
+                perfect: false,
+                grade: ScoreGrade::Sh,
+                client_flags: 0,
+                client_version: "20230326".to_owned(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_build_osr() {
+        let (score, classic) = score();
+        let frames = b"frames".to_vec();
+        let osr = build_osr(&score, &classic, "peace", frames.clone());
+
+        // relax variants are stored as the vanilla mode
+        assert_eq!(osr[0], 1);
+        assert_eq!(&osr[1..5], 20230326i32.to_le_bytes());
+
+        let replay_md5 = format!(
+            "{:x}",
+            md5::compute(
+                "110p1o20o5t2abeatmap_md5r321eFalseypeaceo123456uSH128True"
+            )
+        );
+        let md5_at = osr
+            .windows(replay_md5.len())
+            .position(|window| window == replay_md5.as_bytes());
+        assert!(md5_at.is_some(), "replay md5 must include the grade");
+
+        assert!(osr.ends_with(
+            &[6i32.to_le_bytes().as_slice(), &frames, &42i64.to_le_bytes()]
+                .concat()
+        ));
+    }
+
+    #[test]
+    fn test_can_access_replay() {
+        let (mut score, _) = score();
+        let other_user = Some(score.user_id + 1);
+
+        // submitted scores are not verified, their replays are still public
+        assert!(score.verified_at.is_none());
+        assert!(can_access_replay(&score, None, false));
+        assert!(can_access_replay(&score, other_user, false));
+
+        score.invisible = true;
+        assert!(!can_access_replay(&score, None, false));
+        assert!(!can_access_replay(&score, other_user, false));
+        assert!(can_access_replay(&score, Some(score.user_id), false));
+        assert!(can_access_replay(&score, other_user, true));
+    }
+
+    #[tokio::test]
+    async fn test_local_replay_store() {
+        let store = LocalReplayStore::new(
+            std::env::temp_dir()
+                .join(format!("peace-replays-{}", std::process::id())),
+        );
+
+        assert_eq!(store.load_replay(1).await.unwrap(), None);
+
+        store.save_replay(1, b"replay").await.unwrap();
+        assert_eq!(
+            store.load_replay(1).await.unwrap().as_deref(),
+            Some(b"replay".as_slice())
+        );
+
+        let _ = tokio::fs::remove_dir_all(&store.dir).await;
+    }
+}
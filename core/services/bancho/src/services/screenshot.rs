@@ -0,0 +1,215 @@
+use crate::{DynScreenshotStore, ScreenshotStore, ScreenshotStoreError};
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use infra_services::IntoService;
+use std::{
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tonic::async_trait;
+
+/// Length of the hash prefix used in the short name of the screenshots.
+pub const SCREENSHOT_NAME_HASH_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliBanchoScreenshotConfigs {
+    /// Max size in bytes of an uploaded screenshot.
+    #[default(3145728)]
+    #[arg(long, default_value = "3145728")]
+    pub screenshot_max_bytes: u64,
+
+    /// Max screenshots uploaded by a user in the quota window, 0 to disable.
+    #[default(100)]
+    #[arg(long, default_value = "100")]
+    pub screenshot_quota_count: u64,
+
+    /// Max total bytes uploaded by a user in the quota window, 0 to
+    /// disable.
+    #[default(104857600)]
+    #[arg(long, default_value = "104857600")]
+    pub screenshot_quota_bytes: u64,
+
+    /// Seconds of the screenshot quota window.
+    #[default(86400)]
+    #[arg(long, default_value = "86400")]
+    pub screenshot_quota_window_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    Png,
+    Jpeg,
+}
+
+impl ScreenshotFormat {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    const JPEG_SIGNATURE: &[u8] = b"\xff\xd8\xff";
+
+    /// Detect the format by the magic bytes, the extension sent by the
+    /// clients is not trusted.
+    #[inline]
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(Self::PNG_SIGNATURE) {
+            Some(Self::Png)
+        } else if data.starts_with(Self::JPEG_SIGNATURE) {
+            Some(Self::Jpeg)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+
+    #[inline]
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// Stores the screenshots as files in a local directory, addressed by the
+/// sha256 of their content so duplicated uploads share the same file.
+#[derive(Debug, Clone)]
+pub struct LocalScreenshotStore {
+    pub dir: PathBuf,
+}
+
+impl LocalScreenshotStore {
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    #[inline]
+    pub fn screenshot_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash.get(..2).unwrap_or_default()).join(hash)
+    }
+}
+
+impl IntoService<DynScreenshotStore> for LocalScreenshotStore {
+    #[inline]
+    fn into_service(self) -> DynScreenshotStore {
+        Arc::new(self) as DynScreenshotStore
+    }
+}
+
+#[async_trait]
+impl ScreenshotStore for LocalScreenshotStore {
+    async fn save_screenshot(
+        &self,
+        hash: &str,
+        data: &[u8],
+    ) -> Result<(), ScreenshotStoreError> {
+        let path = self.screenshot_path(hash);
+
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // the partially written files are never served
+        static TEMP_ID: AtomicU64 = AtomicU64::new(0);
+        let temp_path = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        if let Err(err) = tokio::fs::write(&temp_path, data).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(err.into());
+        }
+
+        if let Err(err) = tokio::fs::rename(&temp_path, &path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn load_screenshot(
+        &self,
+        hash: &str,
+    ) -> Result<Option<Vec<u8>>, ScreenshotStoreError> {
+        match tokio::fs::read(self.screenshot_path(hash)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            ScreenshotFormat::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(ScreenshotFormat::Png)
+        );
+        assert_eq!(
+            ScreenshotFormat::detect(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some(ScreenshotFormat::Jpeg)
+        );
+    }
+
+    #[test]
+    fn test_detect_invalid_format() {
+        assert_eq!(ScreenshotFormat::detect(b""), None);
+        // truncated signatures
+        assert_eq!(ScreenshotFormat::detect(b"\x89PNG"), None);
+        assert_eq!(ScreenshotFormat::detect(b"\xff\xd8"), None);
+        // the extension or content type is not trusted
+        assert_eq!(ScreenshotFormat::detect(b"<html>.png"), None);
+        assert_eq!(ScreenshotFormat::detect(b"GIF89a"), None);
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(ScreenshotFormat::Png.extension(), "png");
+        assert_eq!(ScreenshotFormat::Png.content_type(), "image/png");
+        assert_eq!(ScreenshotFormat::Jpeg.extension(), "jpg");
+        assert_eq!(ScreenshotFormat::Jpeg.content_type(), "image/jpeg");
+    }
+
+    #[tokio::test]
+    async fn test_save_screenshot() {
+        let dir = std::env::temp_dir()
+            .join(format!("peace-screenshots-{}", std::process::id()));
+        let store = LocalScreenshotStore::new(&dir);
+        let hash = "ab".repeat(32);
+
+        store.save_screenshot(&hash, b"data").await.unwrap();
+        // the existing file is kept
+        store.save_screenshot(&hash, b"other").await.unwrap();
+
+        assert_eq!(
+            store.load_screenshot(&hash).await.unwrap().as_deref(),
+            Some(&b"data"[..])
+        );
+        assert_eq!(store.load_screenshot("missing").await.unwrap(), None);
+
+        // no temp files are left
+        let files = std::fs::read_dir(dir.join("ab")).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, 1);
+    }
+}
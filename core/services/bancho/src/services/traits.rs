@@ -1,9 +1,14 @@
 use crate::*;
 use bancho_packets::Packet;
 use domain_users::PasswordError;
+use futures::Stream;
 use pb_bancho::*;
-use pb_bancho_state::UserQuery;
-use std::{net::IpAddr, sync::Arc};
+use pb_bancho_state::{
+    update_match_request::MatchAction, CreateMatchRequest, JoinMatchRequest,
+    UpdateMatchRequest, UserQuery,
+};
+use peace_db::peace::entity::beatmaps;
+use std::{net::IpAddr, path::Path, pin::Pin, sync::Arc};
 use tonic::async_trait;
 use tools::async_collections::{
     BackgroundTask, BackgroundTaskError, CommonRecycleBackgroundTaskConfig,
@@ -13,6 +18,14 @@ pub type DynBanchoService = Arc<dyn BanchoService + Send + Sync>;
 pub type DynBanchoBackgroundService =
     Arc<dyn BanchoBackgroundService + Send + Sync>;
 pub type DynPasswordService = Arc<dyn PasswordService + Send + Sync>;
+pub type DynReplayStore = Arc<dyn ReplayStore + Send + Sync>;
+pub type DynScreenshotStore = Arc<dyn ScreenshotStore + Send + Sync>;
+pub type DynBeatmapMirror = Arc<dyn BeatmapMirror + Send + Sync>;
+pub type DynBeatmapFetcher = Arc<dyn BeatmapFetcher + Send + Sync>;
+pub type DynDirectMirror = Arc<dyn DirectMirror + Send + Sync>;
+pub type BeatmapsetChunkStream = Pin<
+    Box<dyn Stream<Item = Result<BeatmapsetChunk, BanchoServiceError>> + Send>,
+>;
 
 #[async_trait]
 pub trait PasswordBackgroundService {
@@ -38,6 +51,93 @@ pub trait PasswordService {
     ) -> Result<(), PasswordError>;
 }
 
+#[async_trait]
+pub trait ReplayStore {
+    async fn save_replay(
+        &self,
+        score_id: i64,
+        replay: &[u8],
+    ) -> Result<(), ReplayStoreError>;
+
+    async fn load_replay(
+        &self,
+        score_id: i64,
+    ) -> Result<Option<Vec<u8>>, ReplayStoreError>;
+}
+
+#[async_trait]
+pub trait ScreenshotStore {
+    async fn save_screenshot(
+        &self,
+        hash: &str,
+        data: &[u8],
+    ) -> Result<(), ScreenshotStoreError>;
+
+    async fn load_screenshot(
+        &self,
+        hash: &str,
+    ) -> Result<Option<Vec<u8>>, ScreenshotStoreError>;
+}
+
+/// An upstream source of beatmap metadata and `.osu` files.
+#[async_trait]
+pub trait BeatmapMirror {
+    fn name(&self) -> &str;
+
+    async fn get_beatmap(
+        &self,
+        query: &BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BeatmapMirrorError>;
+
+    async fn get_osu_file(
+        &self,
+        beatmap_id: i32,
+    ) -> Result<Option<Vec<u8>>, BeatmapMirrorError>;
+}
+
+/// An upstream source of the osu!direct search and the `.osz` files.
+#[async_trait]
+pub trait DirectMirror {
+    fn name(&self) -> &str;
+
+    async fn search(
+        &self,
+        query: &DirectSearchQuery,
+    ) -> Result<Vec<DirectBeatmapset>, BeatmapMirrorError>;
+
+    async fn get_beatmapset(
+        &self,
+        beatmapset_id: i32,
+    ) -> Result<Option<DirectBeatmapset>, BeatmapMirrorError>;
+
+    /// Download the `.osz` file into the path, returns `false` if the mirror
+    /// doesn't have the beatmapset. Downloads larger than `max_bytes` are
+    /// aborted.
+    async fn download_beatmapset(
+        &self,
+        beatmapset_id: i32,
+        no_video: bool,
+        max_bytes: u64,
+        path: &Path,
+    ) -> Result<bool, BeatmapMirrorError>;
+}
+
+#[async_trait]
+pub trait BeatmapFetcher {
+    /// Get the beatmap from the database, or fetch it from the mirrors if
+    /// it's unknown.
+    async fn fetch_beatmap(
+        &self,
+        query: BeatmapQuery,
+    ) -> Result<Option<beatmaps::Model>, BeatmapFetchError>;
+
+    /// Make sure the `.osu` file of the beatmap exists locally.
+    async fn fetch_osu_file(
+        &self,
+        beatmap_id: i32,
+    ) -> Result<bool, BeatmapFetchError>;
+}
+
 #[async_trait]
 pub trait BanchoBackgroundService: PasswordBackgroundService {
     fn start_all(&self, configs: BanchoBackgroundServiceConfigs);
@@ -56,10 +156,36 @@ pub trait BanchoService:
     + ToggleBlockNonFriendDms
     + UserLogout
     + RequestPresence
+    + SpectateStart
     + SpectateStop
     + SpectateCant
+    + SpectateFrames
     + LobbyPart
     + LobbyJoin
+    + MatchCreate
+    + MatchJoin
+    + MatchPart
+    + MatchUpdate
+    + FriendAdd
+    + FriendRemove
+    + GetFriends
+    + SubmitScore
+    + GetBeatmapScores
+    + GetReplay
+    + ExportReplay
+    + RequestBeatmapInfo
+    + GetBeatmapInfo
+    + VerifyCredentials
+    + Register
+    + BanchoConnect
+    + UploadScreenshot
+    + GetScreenshot
+    + GetFavouriteBeatmaps
+    + AddFavouriteBeatmap
+    + RateBeatmap
+    + SearchBeatmapsets
+    + GetBeatmapsetInfo
+    + DownloadBeatmapset
 {
 }
 
@@ -158,6 +284,14 @@ pub trait RequestPresence {
     ) -> Result<HandleCompleted, BanchoServiceError>;
 }
 
+#[async_trait]
+pub trait SpectateStart {
+    async fn spectate_start(
+        &self,
+        request: SpectateRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
 #[async_trait]
 pub trait SpectateStop {
     async fn spectate_stop(
@@ -174,6 +308,14 @@ pub trait SpectateCant {
     ) -> Result<HandleCompleted, BanchoServiceError>;
 }
 
+#[async_trait]
+pub trait SpectateFrames {
+    async fn spectate_frames(
+        &self,
+        request: SpectateFramesRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
 #[async_trait]
 pub trait LobbyPart {
     async fn lobby_part(
@@ -190,6 +332,140 @@ pub trait LobbyJoin {
     ) -> Result<HandleCompleted, BanchoServiceError>;
 }
 
+#[async_trait]
+pub trait MatchCreate {
+    async fn match_create(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait MatchJoin {
+    async fn match_join(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait MatchPart {
+    async fn match_part(
+        &self,
+        user_query: UserQuery,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait MatchUpdate {
+    async fn match_update(
+        &self,
+        request: UpdateMatchRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait FriendAdd {
+    async fn friend_add(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait FriendRemove {
+    async fn friend_remove(
+        &self,
+        request: FriendRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetFriends {
+    async fn get_friends(
+        &self,
+        request: GetFriendsRequest,
+    ) -> Result<GetFriendsResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait SubmitScore {
+    async fn submit_score(
+        &self,
+        request: SubmitScoreRequest,
+    ) -> Result<SubmitScoreResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetBeatmapScores {
+    async fn get_beatmap_scores(
+        &self,
+        request: GetBeatmapScoresRequest,
+    ) -> Result<GetBeatmapScoresResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetReplay {
+    async fn get_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait ExportReplay {
+    async fn export_replay(
+        &self,
+        request: GetReplayRequest,
+    ) -> Result<GetReplayResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait RequestBeatmapInfo {
+    async fn request_beatmap_info(
+        &self,
+        request: BeatmapInfoRequest,
+    ) -> Result<HandleCompleted, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetBeatmapInfo {
+    async fn get_beatmap_info(
+        &self,
+        request: GetBeatmapInfoRequest,
+    ) -> Result<GetBeatmapInfoResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait VerifyCredentials {
+    async fn verify_credentials(
+        &self,
+        request: VerifyCredentialsRequest,
+    ) -> Result<VerifyCredentialsResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait Register {
+    /// Register an account with the raw password, only the form is
+    /// validated if `check` is set.
+    async fn register(
+        &self,
+        client_ip: IpAddr,
+        request: RegisterRequest,
+    ) -> Result<RegisterResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait BanchoConnect {
+    /// Check the credentials, version and country of the client before it
+    /// connects to bancho, returns the country code of the client.
+    async fn bancho_connect(
+        &self,
+        client_ip: IpAddr,
+        request: BanchoConnectRequest,
+    ) -> Result<BanchoConnectResponse, BanchoServiceError>;
+}
+
 pub trait BanchoPacketProcessor:
     ProcessSendPublicMessage
     + ProcessSendPrivateMessage
@@ -203,6 +479,19 @@ pub trait BanchoPacketProcessor:
     + ProcessUserToggleBlockNonFriendDms
     + ProcessUserLogout
     + ProcessUserPresenceRequest
+    + ProcessSpectateStart
+    + ProcessSpectateStop
+    + ProcessSpectateCant
+    + ProcessSpectateFrames
+    + ProcessLobbyPart
+    + ProcessLobbyJoin
+    + ProcessMatchCreate
+    + ProcessMatchJoin
+    + ProcessMatchPart
+    + ProcessMatchUpdate
+    + ProcessUserFriendAdd
+    + ProcessUserFriendRemove
+    + ProcessBeatmapInfoRequest
 {
 }
 
@@ -289,3 +578,163 @@ pub trait ProcessUserPresenceRequest {
         &self,
     ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
 }
+
+#[async_trait]
+pub trait ProcessSpectateStart {
+    async fn spectate_start(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessSpectateStop {
+    async fn spectate_stop(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessSpectateCant {
+    async fn spectate_cant(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessSpectateFrames {
+    async fn spectate_frames(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessLobbyPart {
+    async fn lobby_part(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessLobbyJoin {
+    async fn lobby_join(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessMatchCreate {
+    async fn match_create(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessMatchJoin {
+    async fn match_join(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessMatchPart {
+    async fn match_part(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessMatchUpdate {
+    async fn match_update(
+        &self,
+        action: MatchAction,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessUserFriendAdd {
+    async fn user_friend_add(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessUserFriendRemove {
+    async fn user_friend_remove(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait ProcessBeatmapInfoRequest {
+    async fn beatmap_info_request(
+        &self,
+    ) -> Result<HandleCompleted, ProcessBanchoPacketError>;
+}
+
+#[async_trait]
+pub trait UploadScreenshot {
+    async fn upload_screenshot(
+        &self,
+        request: UploadScreenshotRequest,
+    ) -> Result<UploadScreenshotResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetScreenshot {
+    async fn get_screenshot(
+        &self,
+        request: GetScreenshotRequest,
+    ) -> Result<GetScreenshotResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetFavouriteBeatmaps {
+    async fn get_favourite_beatmaps(
+        &self,
+        request: GetFavouriteBeatmapsRequest,
+    ) -> Result<GetFavouriteBeatmapsResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait AddFavouriteBeatmap {
+    async fn add_favourite_beatmap(
+        &self,
+        request: AddFavouriteBeatmapRequest,
+    ) -> Result<AddFavouriteBeatmapResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait RateBeatmap {
+    /// Rate the beatmap if the `rating` is present, otherwise only check
+    /// whether the user has rated it.
+    async fn rate_beatmap(
+        &self,
+        request: RateBeatmapRequest,
+    ) -> Result<RateBeatmapResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait SearchBeatmapsets {
+    async fn search_beatmapsets(
+        &self,
+        request: SearchBeatmapsetsRequest,
+    ) -> Result<SearchBeatmapsetsResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait GetBeatmapsetInfo {
+    async fn get_beatmapset_info(
+        &self,
+        request: GetBeatmapsetInfoRequest,
+    ) -> Result<GetBeatmapsetInfoResponse, BanchoServiceError>;
+}
+
+#[async_trait]
+pub trait DownloadBeatmapset {
+    /// Stream the `.osz` file of the beatmapset, it's downloaded from the
+    /// mirror into the local cache first.
+    async fn download_beatmapset(
+        &self,
+        request: DownloadBeatmapsetRequest,
+    ) -> Result<BeatmapsetChunkStream, BanchoServiceError>;
+}
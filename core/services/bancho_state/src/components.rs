@@ -1,19 +1,28 @@
 use async_trait::async_trait;
-use bancho_packets::server::{UserPresence, UserStats};
+use bancho_packets::{
+    packet,
+    server::{UserPresence, UserStats},
+    BanchoPacketLength, BanchoPacketWrite, MatchData, MatchUpdate, PacketId,
+    MATCH_SLOTS,
+};
 use clap_serde_derive::ClapSerde;
 use domain_bancho::{
-    BanchoPrivileges, GameMode, Mods, PresenceFilter, UserOnlineStatus,
+    BanchoPrivileges, GameMode, MatchTeam, MatchTeamType, Mods, PresenceFilter,
+    SlotStatus, UserOnlineStatus,
 };
 use domain_bancho_state::ConnectionInfo;
 use infra_packets::{Packet, PacketsQueue};
 use infra_users::CreateSessionDto;
 use infra_users::{BaseSession, BaseSessionData, UserIndexes, UserStore};
+use num_traits::FromPrimitive;
 use peace_snapshot::{cli_snapshot_config, CreateSnapshot, SnapshotType};
 use peace_unique_id::Ulid;
 use std::{
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
     sync::Arc,
 };
+use tokio::sync::RwLock;
 use tools::atomic::{Atomic, AtomicOption, AtomicValue, Bool, F32, U32, U64};
 
 pub type SessionIndexes = UserIndexes<BanchoSession>;
@@ -81,6 +90,8 @@ pub struct BanchoExtend {
     pub utc_offset: u8,
     pub presence_filter: Atomic<PresenceFilter>,
     pub display_city: bool,
+    /// Mirrors the setting of the chat session, the private messages from
+    /// non-friends are rejected by the chat service when sending them.
     pub only_friend_pm_allowed: Bool,
     pub bancho_status: BanchoStatus,
     pub bancho_privileges: Atomic<BanchoPrivileges>,
@@ -89,6 +100,14 @@ pub struct BanchoExtend {
     pub connection_info: ConnectionInfo,
     pub country_code: u8,
     pub notify_index: Atomic<Ulid>,
+    /// The user id of the host this user is spectating.
+    pub spectating: AtomicOption<i32>,
+    /// The user ids of users who are spectating this user.
+    pub spectators: Atomic<HashSet<i32>>,
+    /// The id of the multiplayer match this user is in.
+    pub match_id: AtomicOption<i32>,
+    /// The user ids of this user's friends.
+    pub friends: Atomic<HashSet<i32>>,
 }
 
 impl From<BanchoExtendData> for BanchoExtend {
@@ -106,6 +125,10 @@ impl From<BanchoExtendData> for BanchoExtend {
             connection_info: data.connection_info,
             country_code: data.country_code,
             notify_index: data.notify_index.into(),
+            spectating: data.spectating.into(),
+            spectators: data.spectators.into(),
+            match_id: data.match_id.into(),
+            friends: data.friends.into(),
         }
     }
 }
@@ -126,6 +149,10 @@ impl CreateSnapshot<BanchoExtendData> for BanchoExtend {
             connection_info: self.connection_info.clone(),
             country_code: self.country_code,
             notify_index: *self.notify_index.load().as_ref(),
+            spectating: self.spectating.load().as_deref().copied(),
+            spectators: self.spectators.load().as_ref().clone(),
+            match_id: self.match_id.load().as_deref().copied(),
+            friends: self.friends.load().as_ref().clone(),
         }
     }
 }
@@ -142,6 +169,7 @@ impl BanchoExtend {
         bancho_privileges: BanchoPrivileges,
         connection_info: ConnectionInfo,
         country_code: u8,
+        friends: HashSet<i32>,
     ) -> Self {
         let packets_queue =
             initial_packets.map(PacketsQueue::from).unwrap_or_default();
@@ -155,9 +183,65 @@ impl BanchoExtend {
             packets_queue,
             connection_info,
             country_code,
+            friends: friends.into(),
             ..Default::default()
         }
     }
+
+    /// Add a spectator, returns the spectators before adding.
+    #[inline]
+    pub fn add_spectator(&self, user_id: i32) -> Arc<HashSet<i32>> {
+        self.spectators.rcu(|spectators| {
+            let mut spectators = HashSet::clone(spectators);
+            spectators.insert(user_id);
+            spectators
+        })
+    }
+
+    /// Remove a spectator, returns the spectators after removing.
+    #[inline]
+    pub fn remove_spectator(&self, user_id: i32) -> Arc<HashSet<i32>> {
+        self.spectators.rcu(|spectators| {
+            let mut spectators = HashSet::clone(spectators);
+            spectators.remove(&user_id);
+            spectators
+        });
+
+        self.spectators.load_full()
+    }
+
+    #[inline]
+    pub fn is_friend(&self, user_id: i32) -> bool {
+        self.friends.load().contains(&user_id)
+    }
+
+    #[inline]
+    pub fn add_friend(&self, user_id: i32) {
+        self.friends.rcu(|friends| {
+            let mut friends = HashSet::clone(friends);
+            friends.insert(user_id);
+            friends
+        });
+    }
+
+    #[inline]
+    pub fn remove_friend(&self, user_id: i32) {
+        self.friends.rcu(|friends| {
+            let mut friends = HashSet::clone(friends);
+            friends.remove(&user_id);
+            friends
+        });
+    }
+
+    /// Returns `true` if this user should receive the presence of the target
+    /// user, users with the `Friends` filter only receive their friends.
+    #[inline]
+    pub fn presence_visible(&self, target_id: i32) -> bool {
+        match self.presence_filter.load().as_ref() {
+            PresenceFilter::Friends => self.is_friend(target_id),
+            _ => true,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -298,6 +382,546 @@ pub struct BanchoExtendData {
     pub connection_info: ConnectionInfo,
     pub country_code: u8,
     pub notify_index: Ulid,
+    pub spectating: Option<i32>,
+    pub spectators: HashSet<i32>,
+    pub match_id: Option<i32>,
+    #[serde(default)]
+    pub friends: HashSet<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MatchSlot {
+    pub status: SlotStatus,
+    pub team: MatchTeam,
+    pub user_id: Option<i32>,
+    pub mods: u32,
+    pub loaded: bool,
+    pub skipped: bool,
+}
+
+impl MatchSlot {
+    #[inline]
+    pub fn has_player(&self) -> bool {
+        self.status.has_player()
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.status.contains(SlotStatus::Playing)
+    }
+
+    /// Move the player and its state into another slot.
+    #[inline]
+    pub fn take(&mut self) -> Self {
+        std::mem::replace(
+            self,
+            Self { status: SlotStatus::Open, ..Default::default() },
+        )
+    }
+}
+
+/// A bancho multiplayer match room.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BanchoMatch {
+    pub id: i32,
+    pub name: String,
+    pub password: Option<String>,
+    pub in_progress: bool,
+    pub match_type: i8,
+    pub mods: u32,
+    pub beatmap_name: String,
+    pub beatmap_id: i32,
+    pub beatmap_md5: String,
+    pub host_user_id: i32,
+    pub mode: u8,
+    pub win_condition: u8,
+    pub team_type: MatchTeamType,
+    pub freemods: bool,
+    pub seed: i32,
+    pub slots: Vec<MatchSlot>,
+}
+
+impl BanchoMatch {
+    /// Create a match from the bancho client's `MatchData`,
+    /// the host will be placed in the first slot.
+    pub fn new(id: i32, host_user_id: i32, data: MatchData) -> Self {
+        let mut slots = (0..MATCH_SLOTS)
+            .map(|i| {
+                let locked = data
+                    .slot_status
+                    .get(i)
+                    .map(|s| SlotStatus::from(*s).contains(SlotStatus::Locked))
+                    .unwrap_or_default();
+
+                MatchSlot {
+                    status: if locked {
+                        SlotStatus::Locked
+                    } else {
+                        SlotStatus::Open
+                    },
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<MatchSlot>>();
+
+        slots[0] = MatchSlot {
+            status: SlotStatus::NotReady,
+            user_id: Some(host_user_id),
+            ..Default::default()
+        };
+
+        let mut m = Self {
+            id,
+            name: data.match_name,
+            password: data.password,
+            match_type: data.match_type,
+            mods: data.play_mods,
+            beatmap_name: data.beatmap_name,
+            beatmap_id: data.beatmap_id,
+            beatmap_md5: data.beatmap_md5,
+            host_user_id,
+            mode: data.match_game_mode,
+            win_condition: data.win_condition,
+            seed: data.match_seed,
+            slots,
+            ..Default::default()
+        };
+
+        m.set_team_type(
+            MatchTeamType::from_u8(data.team_type).unwrap_or_default(),
+        );
+        m.set_freemods(data.freemods);
+
+        m
+    }
+
+    #[inline]
+    pub fn match_data(&self) -> MatchData {
+        let mut slot_players = Vec::with_capacity(MATCH_SLOTS);
+        for slot in self.slots.iter() {
+            if let Some(user_id) = slot.user_id.filter(|_| slot.has_player()) {
+                slot_players.push(user_id);
+            }
+        }
+
+        MatchData {
+            match_id: self.id,
+            in_progress: self.in_progress,
+            match_type: self.match_type,
+            play_mods: self.mods,
+            match_name: self.name.clone(),
+            password: self.password.clone(),
+            beatmap_name: self.beatmap_name.clone(),
+            beatmap_id: self.beatmap_id,
+            beatmap_md5: self.beatmap_md5.clone(),
+            slot_status: self.slots.iter().map(|s| s.status.bits()).collect(),
+            slot_teams: self.slots.iter().map(|s| s.team.val()).collect(),
+            slot_players,
+            host_player_id: self.host_user_id,
+            match_game_mode: self.mode,
+            win_condition: self.win_condition,
+            team_type: self.team_type.val(),
+            freemods: self.freemods,
+            player_mods: self.slots.iter().map(|s| s.mods as i32).collect(),
+            match_seed: self.seed,
+        }
+    }
+
+    /// `BANCHO_UPDATE_MATCH` packet, password is only visible to members.
+    #[inline]
+    pub fn update_packet(&self, send_password: bool) -> Vec<u8> {
+        packet!(
+            PacketId::BANCHO_UPDATE_MATCH,
+            MatchUpdate { data: self.match_data(), send_password }
+        )
+    }
+
+    #[inline]
+    pub fn user_ids(&self) -> Vec<i32> {
+        self.slots
+            .iter()
+            .filter(|s| s.has_player())
+            .filter_map(|s| s.user_id)
+            .collect()
+    }
+
+    #[inline]
+    pub fn playing_user_ids(&self) -> Vec<i32> {
+        self.slots
+            .iter()
+            .filter(|s| s.is_playing())
+            .filter_map(|s| s.user_id)
+            .collect()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !self.slots.iter().any(|s| s.has_player())
+    }
+
+    #[inline]
+    pub fn slot_id(&self, user_id: i32) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|s| s.has_player() && s.user_id == Some(user_id))
+    }
+
+    #[inline]
+    pub fn slot_mut(&mut self, user_id: i32) -> Option<&mut MatchSlot> {
+        let slot_id = self.slot_id(user_id)?;
+        self.slots.get_mut(slot_id)
+    }
+
+    #[inline]
+    pub fn free_slot_id(&self) -> Option<usize> {
+        self.slots.iter().position(|s| s.status == SlotStatus::Open)
+    }
+
+    #[inline]
+    pub fn is_host(&self, user_id: i32) -> bool {
+        self.host_user_id == user_id
+    }
+
+    #[inline]
+    pub fn check_password(&self, password: &str) -> bool {
+        match self.password.as_deref() {
+            Some(pw) if !pw.is_empty() => pw == password,
+            _ => true,
+        }
+    }
+
+    /// Add a player into a free slot, returns the slot id.
+    pub fn add_player(&mut self, user_id: i32) -> Option<usize> {
+        let slot_id = self.free_slot_id()?;
+        let team = if self.team_type.is_team_mode() {
+            MatchTeam::Red
+        } else {
+            MatchTeam::Neutral
+        };
+
+        self.slots[slot_id] = MatchSlot {
+            status: SlotStatus::NotReady,
+            team,
+            user_id: Some(user_id),
+            ..Default::default()
+        };
+
+        Some(slot_id)
+    }
+
+    /// Remove a player, if the host leaves, the host will be transferred to
+    /// the next player. Returns the new host id when the host changed.
+    pub fn remove_player(&mut self, user_id: i32) -> Option<i32> {
+        let slot = self.slot_mut(user_id)?;
+        slot.take();
+
+        if !self.is_host(user_id) {
+            return None;
+        }
+
+        let new_host = self
+            .slots
+            .iter()
+            .find(|s| s.has_player())
+            .and_then(|s| s.user_id)?;
+
+        self.host_user_id = new_host;
+
+        Some(new_host)
+    }
+
+    /// Move player to another open slot.
+    pub fn move_player(&mut self, user_id: i32, target: usize) -> bool {
+        let slot_id = match self.slot_id(user_id) {
+            Some(slot_id) => slot_id,
+            None => return false,
+        };
+
+        if self.slots.get(target).map(|s| s.status) != Some(SlotStatus::Open) {
+            return false;
+        }
+
+        self.slots[target] = self.slots[slot_id].take();
+
+        true
+    }
+
+    /// Lock or unlock a slot, slots with players in them can not be locked.
+    pub fn toggle_lock(&mut self, slot_id: usize) -> bool {
+        let slot = match self.slots.get_mut(slot_id) {
+            Some(slot) => slot,
+            None => return false,
+        };
+
+        slot.status = match slot.status {
+            SlotStatus::Locked => SlotStatus::Open,
+            SlotStatus::Open => SlotStatus::Locked,
+            _ => return false,
+        };
+
+        true
+    }
+
+    /// Transfer the host to the player in the slot, returns the new host id.
+    pub fn transfer_host(&mut self, slot_id: usize) -> Option<i32> {
+        let slot = self.slots.get(slot_id).filter(|s| s.has_player())?;
+        let new_host = slot.user_id?;
+
+        self.host_user_id = new_host;
+
+        Some(new_host)
+    }
+
+    /// With freemods, players choose their own mods while the host controls
+    /// the speed changing mods, otherwise only the host can change mods.
+    pub fn change_mods(&mut self, user_id: i32, mods: u32) -> bool {
+        let speed_changing = Mods::SpeedChanging.bits();
+
+        if !self.freemods {
+            if !self.is_host(user_id) {
+                return false;
+            }
+            self.mods = mods;
+            return true;
+        }
+
+        if self.is_host(user_id) {
+            self.mods = mods & speed_changing;
+        }
+
+        match self.slot_mut(user_id) {
+            Some(slot) => {
+                slot.mods = mods & !speed_changing;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Switch the player between the red and blue team.
+    pub fn change_team(&mut self, user_id: i32) -> bool {
+        if !self.team_type.is_team_mode() {
+            return false;
+        }
+
+        match self.slot_mut(user_id) {
+            Some(slot) => {
+                slot.team = match slot.team {
+                    MatchTeam::Red => MatchTeam::Blue,
+                    _ => MatchTeam::Red,
+                };
+                true
+            },
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn set_team_type(&mut self, team_type: MatchTeamType) {
+        if self.team_type == team_type && !self.slots.is_empty() {
+            return;
+        }
+
+        self.team_type = team_type;
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            slot.team = if team_type.is_team_mode() {
+                if i % 2 == 0 {
+                    MatchTeam::Red
+                } else {
+                    MatchTeam::Blue
+                }
+            } else {
+                MatchTeam::Neutral
+            };
+        }
+    }
+
+    #[inline]
+    pub fn set_freemods(&mut self, freemods: bool) {
+        if self.freemods == freemods {
+            return;
+        }
+
+        self.freemods = freemods;
+        let speed_changing = Mods::SpeedChanging.bits();
+
+        if freemods {
+            // speed changing mods are still controlled by the host
+            for slot in self.slots.iter_mut().filter(|s| s.has_player()) {
+                slot.mods = self.mods & !speed_changing;
+            }
+            self.mods &= speed_changing;
+        } else {
+            let host_mods = self
+                .slot_id(self.host_user_id)
+                .map(|i| self.slots[i].mods)
+                .unwrap_or_default();
+
+            self.mods = (self.mods & speed_changing) | host_mods;
+            for slot in self.slots.iter_mut() {
+                slot.mods = 0;
+            }
+        }
+    }
+
+    /// Set all ready players to not ready.
+    #[inline]
+    pub fn unready_players(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if slot.status == SlotStatus::Ready {
+                slot.status = SlotStatus::NotReady;
+            }
+        }
+    }
+
+    /// Apply new match settings from the bancho client's `MatchData`.
+    pub fn apply_settings(&mut self, data: MatchData) {
+        if data.beatmap_md5 != self.beatmap_md5 {
+            self.unready_players();
+        }
+
+        self.name = data.match_name;
+        self.password = data.password;
+        self.match_type = data.match_type;
+        self.beatmap_name = data.beatmap_name;
+        self.beatmap_id = data.beatmap_id;
+        self.beatmap_md5 = data.beatmap_md5;
+        self.mode = data.match_game_mode;
+        self.win_condition = data.win_condition;
+        self.seed = data.match_seed;
+
+        self.set_team_type(
+            MatchTeamType::from_u8(data.team_type).unwrap_or_default(),
+        );
+        self.set_freemods(data.freemods);
+    }
+
+    /// Start the match, returns the user ids of players.
+    pub fn start(&mut self) -> Vec<i32> {
+        self.in_progress = true;
+
+        for slot in self.slots.iter_mut() {
+            if slot.has_player() && slot.status != SlotStatus::NoMap {
+                slot.status = SlotStatus::Playing;
+                slot.loaded = false;
+                slot.skipped = false;
+            }
+        }
+
+        self.playing_user_ids()
+    }
+
+    #[inline]
+    pub fn all_loaded(&self) -> bool {
+        self.slots.iter().filter(|s| s.is_playing()).all(|s| s.loaded)
+    }
+
+    #[inline]
+    pub fn all_skipped(&self) -> bool {
+        self.slots.iter().filter(|s| s.is_playing()).all(|s| s.skipped)
+    }
+
+    #[inline]
+    pub fn all_completed(&self) -> bool {
+        !self.slots.iter().any(|s| s.is_playing())
+    }
+
+    /// Finish the match, all players will be set to not ready.
+    pub fn finish(&mut self) {
+        self.in_progress = false;
+
+        for slot in self.slots.iter_mut() {
+            if slot
+                .status
+                .intersects(SlotStatus::Playing | SlotStatus::Complete)
+            {
+                slot.status = SlotStatus::NotReady;
+            }
+            slot.loaded = false;
+            slot.skipped = false;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MatchesData {
+    pub matches: Vec<BanchoMatch>,
+    pub lobby: HashSet<i32>,
+}
+
+/// Multiplayer matches and the users in the lobby.
+#[derive(Debug, Default)]
+pub struct Matches {
+    pub matches: RwLock<HashMap<i32, Arc<RwLock<BanchoMatch>>>>,
+    pub lobby: RwLock<HashSet<i32>>,
+}
+
+impl From<MatchesData> for Matches {
+    fn from(data: MatchesData) -> Self {
+        Self {
+            matches: RwLock::new(HashMap::from_iter(
+                data.matches
+                    .into_iter()
+                    .map(|m| (m.id, Arc::new(RwLock::new(m)))),
+            )),
+            lobby: RwLock::new(data.lobby),
+        }
+    }
+}
+
+#[async_trait]
+impl CreateSnapshot<MatchesData> for Matches {
+    async fn create_snapshot(&self) -> MatchesData {
+        let mut matches = Vec::new();
+        for m in self.matches.read().await.values() {
+            matches.push(m.read().await.clone());
+        }
+
+        MatchesData { matches, lobby: self.lobby.read().await.clone() }
+    }
+}
+
+impl Matches {
+    /// Create a match with the next available id, `None` if all the ids are
+    /// in use.
+    pub async fn create(
+        &self,
+        host_user_id: i32,
+        data: MatchData,
+    ) -> Option<Arc<RwLock<BanchoMatch>>> {
+        let mut matches = self.matches.write().await;
+
+        // match id is sent as u16 in bancho packets
+        let id = (1..=u16::MAX as i32).find(|id| !matches.contains_key(id))?;
+
+        let m = Arc::new(RwLock::new(BanchoMatch::new(id, host_user_id, data)));
+        matches.insert(id, m.clone());
+
+        Some(m)
+    }
+
+    #[inline]
+    pub async fn get(&self, match_id: i32) -> Option<Arc<RwLock<BanchoMatch>>> {
+        self.matches.read().await.get(&match_id).cloned()
+    }
+
+    #[inline]
+    pub async fn remove(
+        &self,
+        match_id: i32,
+    ) -> Option<Arc<RwLock<BanchoMatch>>> {
+        self.matches.write().await.remove(&match_id)
+    }
+
+    #[inline]
+    pub async fn all(&self) -> Vec<Arc<RwLock<BanchoMatch>>> {
+        self.matches.read().await.values().cloned().collect()
+    }
+
+    #[inline]
+    pub async fn lobby_users(&self) -> Vec<i32> {
+        self.lobby.read().await.iter().copied().collect()
+    }
 }
 
 cli_snapshot_config!(service: BanchoState);
@@ -15,6 +15,12 @@ pub enum BanchoStateError {
     InvalidArgument,
     #[error("bancho session not exists")]
     SessionNotExists,
+    #[error("match not exists")]
+    MatchNotExists,
+    #[error("failed to join match")]
+    JoinMatchFailed,
+    #[error("no match id available")]
+    MatchIdExhausted,
     #[error(transparent)]
     SignatureError(#[from] SignatureError),
     #[error(transparent)]
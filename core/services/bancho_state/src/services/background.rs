@@ -3,6 +3,7 @@ use crate::{
     NotifyMessagesCleaner, UserSessionsCleaner,
 };
 use async_trait::async_trait;
+use clap::Parser;
 use clap_serde_derive::ClapSerde;
 use peace_unique_id::Ulid;
 use std::{
@@ -113,6 +114,16 @@ impl BanchoStateBackgroundServiceImpl {
                                 }
                             };
 
+                            // unlink spectators, hosts and matches of removed sessions
+                            for session in sessions_deactive.iter() {
+                                user_sessions_service
+                                    .clear_spectate_links(session)
+                                    .await;
+                                user_sessions_service
+                                    .clear_match_links(session)
+                                    .await;
+                            }
+
                             sessions_deactive.len()
                         },
                         None => 0,
@@ -1,16 +1,20 @@
 use crate::*;
 use async_trait::async_trait;
+use bancho_packets::{
+    packet, server, BanchoPacketLength, BanchoPacketWrite, MatchData, PacketId,
+    PayloadReader,
+};
 use chrono::{DateTime, Utc};
 use core_signature::DynSignatureService;
 use domain_bancho::{
     BanchoClientToken, BanchoPrivileges, GameMode, Mods, PresenceFilter,
-    UserOnlineStatus,
+    SlotStatus, UserOnlineStatus,
 };
 use infra_packets::Packet;
 use infra_services::{IntoService, ServiceSnapshot};
 use infra_users::{CreateSessionDto, SessionFilter};
 use num_traits::FromPrimitive;
-use pb_bancho_state::*;
+use pb_bancho_state::{update_match_request::MatchAction, *};
 use pb_base::ExecSuccess;
 use peace_message_queue::ReceivedMessages;
 use peace_snapshot::{
@@ -18,6 +22,7 @@ use peace_snapshot::{
     SnapshotConfig, SnapshotExpired, SnapshotTime, SnapshotType,
 };
 use std::{path::Path, sync::Arc};
+use tokio::sync::RwLock;
 use tools::atomic::AtomicValue;
 
 pub struct BanchoStateServiceSnapshotLoader;
@@ -75,6 +80,8 @@ impl BanchoStateServiceSnapshotLoader {
 pub struct BanchoStateServiceSnapshot {
     pub user_sessions: Vec<BanchoSessionData>,
     pub notify_queue: Vec<BanchoMessageData>,
+    #[serde(default)]
+    pub matches: MatchesData,
     pub create_time: DateTime<Utc>,
 }
 
@@ -118,8 +125,10 @@ impl BanchoStateServiceImpl {
         let notify_queue =
             Arc::new(BanchoMessageQueue::from(snapshot.notify_queue));
 
+        let matches = Arc::new(Matches::from(snapshot.matches));
+
         let user_sessions_service =
-            UserSessionsServiceImpl { user_sessions, notify_queue }
+            UserSessionsServiceImpl { user_sessions, notify_queue, matches }
                 .into_service();
 
         Self { user_sessions_service, signature_service }
@@ -173,6 +182,11 @@ impl CreateSnapshot<BanchoStateServiceSnapshot> for BanchoStateServiceImpl {
                 .notify_queue()
                 .create_snapshot()
                 .await,
+            matches: self
+                .user_sessions_service
+                .matches()
+                .create_snapshot()
+                .await,
             create_time: Utc::now(),
         }
     }
@@ -181,6 +195,734 @@ impl CreateSnapshot<BanchoStateServiceSnapshot> for BanchoStateServiceImpl {
 #[async_trait]
 impl BanchoStateService for BanchoStateServiceImpl {}
 
+impl BanchoStateServiceImpl {
+    #[inline]
+    async fn user_match(
+        &self,
+        session: &BanchoSession,
+    ) -> Result<Arc<RwLock<BanchoMatch>>, BanchoStateError> {
+        let match_id = session
+            .extends
+            .match_id
+            .load()
+            .as_deref()
+            .copied()
+            .ok_or(BanchoStateError::MatchNotExists)?;
+
+        self.user_sessions_service
+            .matches()
+            .get(match_id)
+            .await
+            .ok_or(BanchoStateError::MatchNotExists)
+    }
+
+    #[inline]
+    async fn join_match_failed(
+        &self,
+        session: &BanchoSession,
+    ) -> BanchoStateError {
+        session
+            .extends
+            .packets_queue
+            .push_packet(server::MatchJoinFail::pack().into())
+            .await;
+
+        BanchoStateError::JoinMatchFailed
+    }
+}
+
+#[async_trait]
+impl AddUserFriend for BanchoStateServiceImpl {
+    async fn add_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let UserFriendRequest { user_query, friend_id } = request;
+
+        let session = self
+            .user_sessions_service
+            .get(
+                &user_query
+                    .ok_or(BanchoStateError::InvalidArgument)?
+                    .into_user_query()?,
+            )
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        session.extends.add_friend(friend_id);
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl RemoveUserFriend for BanchoStateServiceImpl {
+    async fn remove_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let UserFriendRequest { user_query, friend_id } = request;
+
+        let session = self
+            .user_sessions_service
+            .get(
+                &user_query
+                    .ok_or(BanchoStateError::InvalidArgument)?
+                    .into_user_query()?,
+            )
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        session.extends.remove_friend(friend_id);
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl GetUserMatch for BanchoStateServiceImpl {
+    async fn get_user_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<UserMatch, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        Ok(UserMatch {
+            match_id: session.extends.match_id.load().as_deref().copied(),
+        })
+    }
+}
+
+#[async_trait]
+impl UpdateMatch for BanchoStateServiceImpl {
+    async fn update_match(
+        &self,
+        request: UpdateMatchRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let UpdateMatchRequest {
+            user_query,
+            action,
+            int_val,
+            string_val,
+            bytes_val,
+        } = request;
+
+        let query = user_query.ok_or(BanchoStateError::InvalidArgument)?;
+        let action = MatchAction::from_i32(action)
+            .ok_or(BanchoStateError::InvalidArgument)?;
+
+        let session = self
+            .user_sessions_service
+            .get(&query.into_user_query()?)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        let m = self.user_match(&session).await?;
+        let mut m = m.write().await;
+
+        let user_id = session.user_id;
+        let slot_id =
+            m.slot_id(user_id).ok_or(BanchoStateError::MatchNotExists)?;
+        let is_host = m.is_host(user_id);
+        let svc = self.user_sessions_service.as_ref();
+
+        let int_val = || int_val.ok_or(BanchoStateError::InvalidArgument);
+
+        let updated = match action {
+            MatchAction::ChangeSettings => {
+                let data = bytes_val
+                    .as_deref()
+                    .and_then(|b| PayloadReader::new(b).read::<MatchData>())
+                    .ok_or(BanchoStateError::InvalidArgument)?;
+
+                is_host && {
+                    m.apply_settings(data);
+                    true
+                }
+            },
+            MatchAction::ChangeSlot => {
+                !m.in_progress && m.move_player(user_id, int_val()? as usize)
+            },
+            MatchAction::Lock => is_host && m.toggle_lock(int_val()? as usize),
+            MatchAction::Ready
+            | MatchAction::NotReady
+            | MatchAction::NoBeatmap
+            | MatchAction::HasBeatmap => {
+                let slot = &mut m.slots[slot_id];
+                if slot.is_playing() {
+                    false
+                } else {
+                    slot.status = match action {
+                        MatchAction::Ready => SlotStatus::Ready,
+                        MatchAction::NoBeatmap => SlotStatus::NoMap,
+                        _ => SlotStatus::NotReady,
+                    };
+                    true
+                }
+            },
+            MatchAction::ChangeMods => {
+                m.change_mods(user_id, int_val()? as u32)
+            },
+            MatchAction::ChangeTeam => m.change_team(user_id),
+            MatchAction::TransferHost => {
+                let new_host = if is_host {
+                    m.transfer_host(int_val()? as usize)
+                } else {
+                    None
+                };
+
+                if let Some(new_host) = new_host {
+                    svc.enqueue_packet_to_users(
+                        &[new_host],
+                        server::MatchTransferHost::pack().into(),
+                    )
+                    .await;
+                }
+
+                new_host.is_some()
+            },
+            MatchAction::ChangePassword => {
+                if is_host {
+                    let password = string_val.unwrap_or_default();
+
+                    svc.enqueue_packet_to_users(
+                        &m.user_ids(),
+                        Packet::new_ptr(server::MatchChangePassword::pack(
+                            password.as_str().into(),
+                        )),
+                    )
+                    .await;
+
+                    m.password =
+                        if password.is_empty() { None } else { Some(password) };
+                }
+
+                is_host
+            },
+            MatchAction::Start => {
+                if is_host && !m.in_progress {
+                    let players = m.start();
+
+                    svc.enqueue_packet_to_users(
+                        &players,
+                        Packet::new_ptr(server::MatchStart::pack(
+                            m.match_data(),
+                        )),
+                    )
+                    .await;
+
+                    true
+                } else {
+                    false
+                }
+            },
+            MatchAction::LoadComplete => {
+                m.slots[slot_id].loaded = true;
+
+                if m.all_loaded() {
+                    svc.enqueue_packet_to_users(
+                        &m.playing_user_ids(),
+                        Packet::new_ptr(server::MatchAllPlayerLoaded::pack()),
+                    )
+                    .await;
+                }
+
+                false
+            },
+            MatchAction::SkipRequest => {
+                m.slots[slot_id].skipped = true;
+                let players = m.playing_user_ids();
+
+                svc.enqueue_packet_to_users(
+                    &players,
+                    Packet::new_ptr(server::MatchPlayerSkipped::pack(
+                        slot_id as i32,
+                    )),
+                )
+                .await;
+
+                if m.all_skipped() {
+                    svc.enqueue_packet_to_users(
+                        &players,
+                        Packet::new_ptr(server::MatchSkip::pack()),
+                    )
+                    .await;
+                }
+
+                false
+            },
+            MatchAction::Failed => {
+                svc.enqueue_packet_to_users(
+                    &m.playing_user_ids(),
+                    Packet::new_ptr(server::MatchPlayerFailed::pack(
+                        slot_id as i32,
+                    )),
+                )
+                .await;
+
+                false
+            },
+            MatchAction::ScoreUpdate => {
+                let mut frame =
+                    bytes_val.ok_or(BanchoStateError::InvalidArgument)?;
+
+                // the 5th byte of the score frame is the slot id
+                *frame.get_mut(4).ok_or(BanchoStateError::InvalidArgument)? =
+                    slot_id as u8;
+
+                svc.enqueue_packet_to_users(
+                    &m.playing_user_ids(),
+                    Packet::new_ptr(packet!(
+                        PacketId::BANCHO_MATCH_SCORE_UPDATE,
+                        frame
+                    )),
+                )
+                .await;
+
+                false
+            },
+            MatchAction::Complete => {
+                m.slots[slot_id].status = SlotStatus::Complete;
+
+                if m.in_progress && m.all_completed() {
+                    m.finish();
+
+                    svc.enqueue_packet_to_users(
+                        &m.user_ids(),
+                        Packet::new_ptr(server::MatchComplete::pack()),
+                    )
+                    .await;
+
+                    true
+                } else {
+                    false
+                }
+            },
+            MatchAction::Invite => {
+                svc.enqueue_packet_to_users(
+                    &[int_val()?],
+                    server::MatchInvite::pack(
+                        format!(
+                            "Come join my multiplayer match: \"{}\"",
+                            m.name
+                        )
+                        .into(),
+                        m.id,
+                        m.password.as_deref().map(|pw| pw.into()),
+                    )
+                    .into(),
+                )
+                .await;
+
+                false
+            },
+        };
+
+        if updated {
+            svc.broadcast_match_update(&m).await;
+        }
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl LeaveMatch for BanchoStateServiceImpl {
+    async fn leave_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<LeaveMatchResponse, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        Ok(UserSessionsMultiplayer::leave_match(
+            self.user_sessions_service.as_ref(),
+            &session,
+        )
+        .await)
+    }
+}
+
+#[async_trait]
+impl JoinMatch for BanchoStateServiceImpl {
+    async fn join_match(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError> {
+        const LOG_TARGET: &str = "bancho_state::multiplayer::join_match";
+
+        let JoinMatchRequest { user_query, match_id, password } = request;
+
+        let query = user_query.ok_or(BanchoStateError::InvalidArgument)?;
+
+        let session = self
+            .user_sessions_service
+            .get(&query.into_user_query()?)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        if session.extends.match_id.load().as_deref() == Some(&match_id) {
+            return Ok(JoinMatchResponse { match_id, previous: None });
+        }
+
+        let m = match self.user_sessions_service.matches().get(match_id).await {
+            Some(m) => m,
+            None => return Err(self.join_match_failed(&session).await),
+        };
+
+        if !m.read().await.check_password(&password) {
+            return Err(self.join_match_failed(&session).await);
+        }
+
+        let previous = match session.extends.match_id.load().is_some() {
+            true => Some(
+                UserSessionsMultiplayer::leave_match(
+                    self.user_sessions_service.as_ref(),
+                    &session,
+                )
+                .await,
+            ),
+            false => None,
+        };
+
+        let mut m = m.write().await;
+
+        if m.add_player(session.user_id).is_none() {
+            drop(m);
+            return Err(self.join_match_failed(&session).await);
+        }
+
+        session.extends.match_id.set(Some(match_id.into()));
+
+        session
+            .extends
+            .packets_queue
+            .push_packet(server::MatchJoinSuccess::pack(m.match_data()).into())
+            .await;
+
+        self.user_sessions_service.broadcast_match_update(&m).await;
+
+        info!(
+            target: LOG_TARGET,
+            "{} [{}] joined match {} ({})",
+            session.username.load(),
+            session.user_id,
+            m.name,
+            match_id,
+        );
+
+        Ok(JoinMatchResponse { match_id, previous })
+    }
+}
+
+#[async_trait]
+impl CreateMatch for BanchoStateServiceImpl {
+    async fn create_match(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError> {
+        const LOG_TARGET: &str = "bancho_state::multiplayer::create_match";
+
+        let CreateMatchRequest { user_query, match_data } = request;
+
+        let query = user_query.ok_or(BanchoStateError::InvalidArgument)?;
+        let match_data = PayloadReader::new(&match_data)
+            .read::<MatchData>()
+            .ok_or(BanchoStateError::InvalidArgument)?;
+
+        let session = self
+            .user_sessions_service
+            .get(&query.into_user_query()?)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        // create the match first, so the user stays in the previous match
+        // if it fails
+        let matches = self.user_sessions_service.matches();
+        let m = matches
+            .create(session.user_id, match_data)
+            .await
+            .ok_or(BanchoStateError::MatchIdExhausted)?;
+
+        let previous = match session.extends.match_id.load().is_some() {
+            true => Some(
+                UserSessionsMultiplayer::leave_match(
+                    self.user_sessions_service.as_ref(),
+                    &session,
+                )
+                .await,
+            ),
+            false => None,
+        };
+
+        let m = m.read().await;
+
+        session.extends.match_id.set(Some(m.id.into()));
+
+        session
+            .extends
+            .packets_queue
+            .push_packet(server::MatchJoinSuccess::pack(m.match_data()).into())
+            .await;
+
+        self.user_sessions_service
+            .enqueue_packet_to_users(
+                &matches.lobby_users().await,
+                Packet::new_ptr(server::NewMatch::pack(m.match_data())),
+            )
+            .await;
+
+        info!(
+            target: LOG_TARGET,
+            "{} [{}] created match {} ({})",
+            session.username.load(),
+            session.user_id,
+            m.name,
+            m.id,
+        );
+
+        Ok(JoinMatchResponse { match_id: m.id, previous })
+    }
+}
+
+#[async_trait]
+impl LeaveLobby for BanchoStateServiceImpl {
+    async fn leave_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        self.user_sessions_service
+            .matches()
+            .lobby
+            .write()
+            .await
+            .remove(&session.user_id);
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl JoinLobby for BanchoStateServiceImpl {
+    async fn join_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        let matches = self.user_sessions_service.matches();
+        matches.lobby.write().await.insert(session.user_id);
+
+        let mut packets = Vec::new();
+        for m in matches.all().await {
+            packets.extend(server::NewMatch::pack(m.read().await.match_data()));
+        }
+
+        if !packets.is_empty() {
+            session.extends.packets_queue.push_packet(packets.into()).await;
+        }
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl GetSpectateState for BanchoStateServiceImpl {
+    async fn get_spectate_state(
+        &self,
+        query: UserQuery,
+    ) -> Result<SpectateState, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        Ok(SpectateState {
+            user_id: session.user_id,
+            spectating: session.extends.spectating.load().as_deref().copied(),
+            spectators: session
+                .extends
+                .spectators
+                .load()
+                .iter()
+                .copied()
+                .collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl BroadcastSpectateFrames for BanchoStateServiceImpl {
+    async fn broadcast_spectate_frames(
+        &self,
+        request: BroadcastSpectateFramesRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let BroadcastSpectateFramesRequest { user_query, frames } = request;
+
+        let query = user_query.ok_or(BanchoStateError::InvalidArgument)?;
+
+        let host = self
+            .user_sessions_service
+            .get(&query.into_user_query()?)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        let spectators = host.extends.spectators.load_full();
+        if spectators.is_empty() {
+            return Ok(ExecSuccess::default());
+        }
+
+        let packet = Packet::new_ptr(
+            bancho_packets::server::SpectatorFrames::pack(frames),
+        );
+
+        let indexes = self.user_sessions_service.user_sessions().read().await;
+
+        for user_id in spectators.iter() {
+            if let Some(spectator) =
+                UserSessions::get_inner(&indexes, &UserQuery::UserId(*user_id))
+            {
+                spectator
+                    .extends
+                    .packets_queue
+                    .push_packet(packet.clone())
+                    .await;
+            }
+        }
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl CantSpectate for BanchoStateServiceImpl {
+    async fn cant_spectate(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        let host_user_id = match session.extends.spectating.load_full() {
+            Some(host_user_id) => *host_user_id,
+            None => return Ok(ExecSuccess::default()),
+        };
+
+        let packet = Packet::new_ptr(
+            bancho_packets::server::SpectatorCantSpectate::pack(
+                session.user_id,
+            ),
+        );
+
+        let indexes = self.user_sessions_service.user_sessions().read().await;
+
+        let host = match UserSessions::get_inner(
+            &indexes,
+            &UserQuery::UserId(host_user_id),
+        ) {
+            Some(host) => host,
+            None => return Ok(ExecSuccess::default()),
+        };
+
+        host.extends.packets_queue.push_packet(packet.clone()).await;
+
+        for user_id in host.extends.spectators.load().iter() {
+            if let Some(spectator) =
+                UserSessions::get_inner(&indexes, &UserQuery::UserId(*user_id))
+            {
+                spectator
+                    .extends
+                    .packets_queue
+                    .push_packet(packet.clone())
+                    .await;
+            }
+        }
+
+        Ok(ExecSuccess::default())
+    }
+}
+
+#[async_trait]
+impl StopSpectating for BanchoStateServiceImpl {
+    async fn stop_spectating(
+        &self,
+        query: UserQuery,
+    ) -> Result<StopSpectatingResponse, BanchoStateError> {
+        let session = self
+            .user_sessions_service
+            .get(&query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        Ok(UserSessionsSpectate::stop_spectating(
+            self.user_sessions_service.as_ref(),
+            &session,
+        )
+        .await)
+    }
+}
+
+#[async_trait]
+impl StartSpectating for BanchoStateServiceImpl {
+    async fn start_spectating(
+        &self,
+        request: StartSpectatingRequest,
+    ) -> Result<StartSpectatingResponse, BanchoStateError> {
+        let StartSpectatingRequest { user_query, target_query } = request;
+
+        let user_query = user_query
+            .ok_or(BanchoStateError::InvalidArgument)?
+            .into_user_query()?;
+        let target_query = target_query
+            .ok_or(BanchoStateError::InvalidArgument)?
+            .into_user_query()?;
+
+        let spectator = self
+            .user_sessions_service
+            .get(&user_query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        let host = self
+            .user_sessions_service
+            .get(&target_query)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        if spectator.user_id == host.user_id {
+            return Err(BanchoStateError::InvalidArgument);
+        }
+
+        Ok(UserSessionsSpectate::start_spectating(
+            self.user_sessions_service.as_ref(),
+            &spectator,
+            &host,
+        )
+        .await)
+    }
+}
+
 #[async_trait]
 impl UpdateUserBanchoStatus for BanchoStateServiceImpl {
     async fn update_user_bancho_status(
@@ -222,10 +964,12 @@ impl UpdateUserBanchoStatus for BanchoStateServiceImpl {
 
         // todo update stats from database
 
-        self.broadcast_bancho_packets(BroadcastBanchoPacketsRequest {
-            packets: session.user_stats_packet(),
-        })
-        .await?;
+        let packets = Packet::new_ptr(session.user_stats_packet());
+
+        session.extends.packets_queue.push_packet(packets.clone()).await;
+        self.user_sessions_service
+            .broadcast_user_packets(session.user_id, packets)
+            .await;
 
         Ok(ExecSuccess::default())
     }
@@ -277,6 +1021,9 @@ impl BatchSendPresences for BanchoStateServiceImpl {
             let indexes =
                 self.user_sessions_service.user_sessions().read().await;
 
+            let target = UserSessions::get_inner(&indexes, &to)
+                .ok_or(BanchoStateError::SessionNotExists)?;
+
             for raw_query in request.user_queries {
                 let query = raw_query.into_user_query()?;
                 let session = match &query {
@@ -301,6 +1048,10 @@ impl BatchSendPresences for BanchoStateServiceImpl {
                     continue;
                 };
 
+                if !target.extends.presence_visible(session.user_id) {
+                    continue;
+                }
+
                 presences_packets.extend(session.user_presence_packet());
             }
 
@@ -334,11 +1085,18 @@ impl SendAllPresences for BanchoStateServiceImpl {
             let user_sessions =
                 self.user_sessions_service.user_sessions().read().await;
 
+            let target = UserSessions::get_inner(&user_sessions, &to)
+                .ok_or(BanchoStateError::SessionNotExists)?;
+
             for session in user_sessions.values() {
                 if SessionFilter::session_is_target(session, &to) {
                     continue;
                 };
 
+                if !target.extends.presence_visible(session.user_id) {
+                    continue;
+                }
+
                 presences_packets.extend(session.user_presence_packet());
             }
 
@@ -375,6 +1133,9 @@ impl BatchSendUserStatsPacket for BanchoStateServiceImpl {
             let indexes =
                 self.user_sessions_service.user_sessions().read().await;
 
+            let target = UserSessions::get_inner(&indexes, &to)
+                .ok_or(BanchoStateError::SessionNotExists)?;
+
             for raw_query in request.user_queries {
                 let query = raw_query.into_user_query()?;
                 let session = match &query {
@@ -399,6 +1160,10 @@ impl BatchSendUserStatsPacket for BanchoStateServiceImpl {
                     continue;
                 };
 
+                if !target.extends.presence_visible(session.user_id) {
+                    continue;
+                }
+
                 user_stats_packets.extend(session.user_stats_packet());
             }
 
@@ -421,7 +1186,10 @@ impl SendUserStatsPacket for BanchoStateServiceImpl {
         &self,
         request: SendUserStatsPacketRequest,
     ) -> Result<ExecSuccess, BanchoStateError> {
-        let to = request.to.ok_or(BanchoStateError::InvalidArgument)?;
+        let to = request
+            .to
+            .ok_or(BanchoStateError::InvalidArgument)?
+            .into_user_query()?;
         let query =
             request.user_query.ok_or(BanchoStateError::InvalidArgument)?;
 
@@ -432,11 +1200,23 @@ impl SendUserStatsPacket for BanchoStateServiceImpl {
             .await
             .ok_or(BanchoStateError::SessionNotExists)?;
 
-        self.enqueue_bancho_packets(EnqueueBanchoPacketsRequest {
-            user_query: Some(to),
-            packets: session.user_stats_packet(),
-        })
-        .await?;
+        let target = self
+            .user_sessions_service
+            .get(&to)
+            .await
+            .ok_or(BanchoStateError::SessionNotExists)?;
+
+        if session.user_id != target.user_id
+            && !target.extends.presence_visible(session.user_id)
+        {
+            return Ok(ExecSuccess::default());
+        }
+
+        target
+            .extends
+            .packets_queue
+            .push_packet(session.user_stats_packet().into())
+            .await;
 
         Ok(ExecSuccess::default())
     }
@@ -632,6 +1412,7 @@ impl CreateUserSession for BanchoStateServiceImpl {
             bancho_privileges,
             connection_info,
             country_code,
+            friends,
         } = request;
 
         let connection_info = connection_info
@@ -655,6 +1436,7 @@ impl CreateUserSession for BanchoStateServiceImpl {
                     BanchoPrivileges::from(bancho_privileges),
                     connection_info,
                     country_code as u8,
+                    friends.into_iter().collect(),
                 ),
             })
             .await;
@@ -250,3 +250,171 @@ impl UpdateUserBanchoStatus for BanchoStateServiceRemote {
         Ok(self.client().update_user_bancho_status(request).await?.into_inner())
     }
 }
+
+#[async_trait]
+impl StartSpectating for BanchoStateServiceRemote {
+    async fn start_spectating(
+        &self,
+        request: StartSpectatingRequest,
+    ) -> Result<StartSpectatingResponse, BanchoStateError> {
+        Ok(self.client().start_spectating(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl StopSpectating for BanchoStateServiceRemote {
+    async fn stop_spectating(
+        &self,
+        query: UserQuery,
+    ) -> Result<StopSpectatingResponse, BanchoStateError> {
+        Ok(self
+            .client()
+            .stop_spectating(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl CantSpectate for BanchoStateServiceRemote {
+    async fn cant_spectate(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self
+            .client()
+            .cant_spectate(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl BroadcastSpectateFrames for BanchoStateServiceRemote {
+    async fn broadcast_spectate_frames(
+        &self,
+        request: BroadcastSpectateFramesRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self.client().broadcast_spectate_frames(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetSpectateState for BanchoStateServiceRemote {
+    async fn get_spectate_state(
+        &self,
+        query: UserQuery,
+    ) -> Result<SpectateState, BanchoStateError> {
+        Ok(self
+            .client()
+            .get_spectate_state(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl JoinLobby for BanchoStateServiceRemote {
+    async fn join_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self
+            .client()
+            .join_lobby(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl LeaveLobby for BanchoStateServiceRemote {
+    async fn leave_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self
+            .client()
+            .leave_lobby(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl CreateMatch for BanchoStateServiceRemote {
+    async fn create_match(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError> {
+        Ok(self.client().create_match(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl JoinMatch for BanchoStateServiceRemote {
+    async fn join_match(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError> {
+        Ok(self.client().join_match(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl LeaveMatch for BanchoStateServiceRemote {
+    async fn leave_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<LeaveMatchResponse, BanchoStateError> {
+        Ok(self
+            .client()
+            .leave_match(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl UpdateMatch for BanchoStateServiceRemote {
+    async fn update_match(
+        &self,
+        request: UpdateMatchRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self.client().update_match(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl GetUserMatch for BanchoStateServiceRemote {
+    async fn get_user_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<UserMatch, BanchoStateError> {
+        Ok(self
+            .client()
+            .get_user_match(Into::<RawUserQuery>::into(query))
+            .await?
+            .into_inner())
+    }
+}
+
+#[async_trait]
+impl AddUserFriend for BanchoStateServiceRemote {
+    async fn add_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self.client().add_user_friend(request).await?.into_inner())
+    }
+}
+
+#[async_trait]
+impl RemoveUserFriend for BanchoStateServiceRemote {
+    async fn remove_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError> {
+        Ok(self.client().remove_user_friend(request).await?.into_inner())
+    }
+}
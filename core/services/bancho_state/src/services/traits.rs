@@ -1,5 +1,6 @@
 use crate::*;
 use async_trait::async_trait;
+use bancho_packets::server;
 use domain_bancho::BanchoClientToken;
 use infra_packets::Packet;
 use infra_services::ServiceSnapshot;
@@ -14,6 +15,7 @@ use tools::async_collections::{
     BackgroundTask, BackgroundTaskError, CommonRecycleBackgroundTaskConfig,
     LoopBackgroundTaskConfig,
 };
+use tools::atomic::AtomicValue;
 
 pub type BanchoMessageQueue = MessageQueue<Packet, i32, Ulid>;
 pub type BanchoMessageData = MessageData<Packet, i32, Ulid>;
@@ -63,14 +65,21 @@ pub trait NotifyMessagesQueue {
     fn notify_queue(&self) -> &Arc<BanchoMessageQueue>;
 }
 
+pub trait MatchesStore {
+    fn matches(&self) -> &Arc<Matches>;
+}
+
 #[async_trait]
 pub trait UserSessionsService:
     UserSessionsCreate
+    + UserSessionsBroadcast
     + UserSessionsDelete
     + UserSessionsGet
     + UserSessionsExists
     + UserSessionsClear
     + UserSessionsCount
+    + UserSessionsSpectate
+    + UserSessionsMultiplayer
 {
 }
 
@@ -98,13 +107,303 @@ pub trait UserSessionsGet: UserSessionsStore {
 }
 
 #[async_trait]
-pub trait UserSessionsDelete: UserSessionsStore + NotifyMessagesQueue {
+pub trait UserSessionsSpectate: UserSessionsStore {
+    /// Link the spectator to the host, if the spectator is already spectating
+    /// another user, that link will be removed first.
+    async fn start_spectating(
+        &self,
+        spectator: &BanchoSession,
+        host: &BanchoSession,
+    ) -> StartSpectatingResponse {
+        const LOG_TARGET: &str = "bancho_state::user_sessions::spectate";
+
+        let spectating = spectator.extends.spectating.load_full();
+
+        let previous = match spectating.as_deref() {
+            Some(host_user_id) if *host_user_id == host.user_id => None,
+            Some(_) => Some(self.stop_spectating(spectator).await),
+            None => None,
+        };
+
+        spectator.extends.spectating.set(Some(host.user_id.into()));
+
+        let fellow_spectators = host.extends.add_spectator(spectator.user_id);
+
+        if !fellow_spectators.contains(&spectator.user_id) {
+            host.extends
+                .packets_queue
+                .push_packet(
+                    server::SpectatorJoined::pack(spectator.user_id).into(),
+                )
+                .await;
+
+            let fellow_joined = Packet::new_ptr(
+                server::FellowSpectatorJoined::pack(spectator.user_id),
+            );
+
+            let mut fellow_packets = Vec::new();
+            let indexes = self.user_sessions().read().await;
+
+            for user_id in fellow_spectators.iter() {
+                if let Some(fellow) = UserSessions::get_inner(
+                    &indexes,
+                    &UserQuery::UserId(*user_id),
+                ) {
+                    fellow
+                        .extends
+                        .packets_queue
+                        .push_packet(fellow_joined.clone())
+                        .await;
+                }
+
+                fellow_packets
+                    .extend(server::FellowSpectatorJoined::pack(*user_id));
+            }
+
+            if !fellow_packets.is_empty() {
+                spectator
+                    .extends
+                    .packets_queue
+                    .push_packet(fellow_packets.into())
+                    .await;
+            }
+
+            info!(
+                target: LOG_TARGET,
+                "{} [{}] started spectating {} [{}]",
+                spectator.username.load(),
+                spectator.user_id,
+                host.username.load(),
+                host.user_id,
+            );
+        }
+
+        StartSpectatingResponse { host_user_id: host.user_id, previous }
+    }
+
+    /// Remove the link between the spectator and its host.
+    async fn stop_spectating(
+        &self,
+        spectator: &BanchoSession,
+    ) -> StopSpectatingResponse {
+        const LOG_TARGET: &str = "bancho_state::user_sessions::spectate";
+
+        let host_user_id = match spectator.extends.spectating.swap(None) {
+            Some(host_user_id) => *host_user_id,
+            None => return StopSpectatingResponse::default(),
+        };
+
+        let indexes = self.user_sessions().read().await;
+
+        let host = match UserSessions::get_inner(
+            &indexes,
+            &UserQuery::UserId(host_user_id),
+        ) {
+            Some(host) => host,
+            None => {
+                return StopSpectatingResponse {
+                    host_user_id: Some(host_user_id),
+                    remaining_spectators: 0,
+                }
+            },
+        };
+
+        let remaining_spectators =
+            host.extends.remove_spectator(spectator.user_id);
+
+        host.extends
+            .packets_queue
+            .push_packet(server::SpectatorLeft::pack(spectator.user_id).into())
+            .await;
+
+        let fellow_left = Packet::new_ptr(server::FellowSpectatorLeft::pack(
+            spectator.user_id,
+        ));
+
+        for user_id in remaining_spectators.iter() {
+            if let Some(fellow) =
+                UserSessions::get_inner(&indexes, &UserQuery::UserId(*user_id))
+            {
+                fellow
+                    .extends
+                    .packets_queue
+                    .push_packet(fellow_left.clone())
+                    .await;
+            }
+        }
+
+        info!(
+            target: LOG_TARGET,
+            "{} [{}] stopped spectating {} [{}]",
+            spectator.username.load(),
+            spectator.user_id,
+            host.username.load(),
+            host.user_id,
+        );
+
+        StopSpectatingResponse {
+            host_user_id: Some(host_user_id),
+            remaining_spectators: remaining_spectators.len() as u32,
+        }
+    }
+
+    /// Remove all spectate links of the session, both as a spectator and
+    /// as a host. Should be called after the session is removed.
+    async fn clear_spectate_links(&self, session: &BanchoSession) {
+        self.stop_spectating(session).await;
+
+        let spectators = session.extends.spectators.swap(Default::default());
+        if spectators.is_empty() {
+            return;
+        }
+
+        let indexes = self.user_sessions().read().await;
+
+        for user_id in spectators.iter() {
+            if let Some(spectator) =
+                UserSessions::get_inner(&indexes, &UserQuery::UserId(*user_id))
+            {
+                if spectator.extends.spectating.load().as_deref()
+                    == Some(&session.user_id)
+                {
+                    spectator.extends.spectating.set(None);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+pub trait UserSessionsMultiplayer: UserSessionsStore + MatchesStore {
+    /// Push the packet to the packets queue of each online user.
+    async fn enqueue_packet_to_users(&self, user_ids: &[i32], packet: Packet) {
+        if user_ids.is_empty() {
+            return;
+        }
+
+        let indexes = self.user_sessions().read().await;
+
+        for user_id in user_ids {
+            if let Some(session) =
+                UserSessions::get_inner(&indexes, &UserQuery::UserId(*user_id))
+            {
+                session.extends.packets_queue.push_packet(packet.clone()).await;
+            }
+        }
+    }
+
+    /// Send the match state to its members and users in the lobby,
+    /// the password is only visible to the members.
+    async fn broadcast_match_update(&self, m: &BanchoMatch) {
+        self.enqueue_packet_to_users(
+            &m.user_ids(),
+            Packet::new_ptr(m.update_packet(true)),
+        )
+        .await;
+
+        self.enqueue_packet_to_users(
+            &self.matches().lobby_users().await,
+            Packet::new_ptr(server::UpdateMatch::pack(m.match_data())),
+        )
+        .await;
+    }
+
+    /// Remove the user from its match, the match will be disbanded
+    /// if there are no players left.
+    async fn leave_match(&self, session: &BanchoSession) -> LeaveMatchResponse {
+        const LOG_TARGET: &str = "bancho_state::user_sessions::multiplayer";
+
+        let match_id = match session.extends.match_id.swap(None) {
+            Some(match_id) => *match_id,
+            None => return LeaveMatchResponse::default(),
+        };
+
+        let m = match self.matches().get(match_id).await {
+            Some(m) => m,
+            None => {
+                return LeaveMatchResponse {
+                    match_id: Some(match_id),
+                    disbanded: true,
+                }
+            },
+        };
+
+        let mut m = m.write().await;
+        let new_host = m.remove_player(session.user_id);
+
+        info!(
+            target: LOG_TARGET,
+            "{} [{}] left match {} ({})",
+            session.username.load(),
+            session.user_id,
+            m.name,
+            match_id,
+        );
+
+        if m.is_empty() {
+            drop(m);
+            self.matches().remove(match_id).await;
+
+            self.enqueue_packet_to_users(
+                &self.matches().lobby_users().await,
+                Packet::new_ptr(server::DisbandMatch::pack(match_id)),
+            )
+            .await;
+
+            info!(target: LOG_TARGET, "Match disbanded: {match_id}");
+
+            return LeaveMatchResponse {
+                match_id: Some(match_id),
+                disbanded: true,
+            };
+        }
+
+        if let Some(new_host) = new_host {
+            self.enqueue_packet_to_users(
+                &[new_host],
+                server::MatchTransferHost::pack().into(),
+            )
+            .await;
+        }
+
+        if m.in_progress && m.all_completed() {
+            m.finish();
+            self.enqueue_packet_to_users(
+                &m.user_ids(),
+                Packet::new_ptr(server::MatchComplete::pack()),
+            )
+            .await;
+        }
+
+        self.broadcast_match_update(&m).await;
+
+        LeaveMatchResponse { match_id: Some(match_id), disbanded: false }
+    }
+
+    /// Remove the user from its match and the lobby.
+    /// Should be called after the session is removed.
+    async fn clear_match_links(&self, session: &BanchoSession) {
+        self.leave_match(session).await;
+        self.matches().lobby.write().await.remove(&session.user_id);
+    }
+}
+
+#[async_trait]
+pub trait UserSessionsDelete:
+    UserSessionsStore
+    + NotifyMessagesQueue
+    + UserSessionsSpectate
+    + UserSessionsMultiplayer
+{
     #[inline]
     async fn delete(&self, query: &UserQuery) -> Option<Arc<BanchoSession>> {
         const LOG_TARGET: &str = "bancho_state::user_sessions::delete_session";
 
         let session = self.user_sessions().delete(query).await?;
 
+        self.clear_spectate_links(&session).await;
+        self.clear_match_links(&session).await;
+
         self.notify_queue().write().await.push_message(
             bancho_packets::server::UserLogout::pack(session.user_id).into(),
             None,
@@ -123,7 +422,26 @@ pub trait UserSessionsDelete: UserSessionsStore + NotifyMessagesQueue {
 }
 
 #[async_trait]
-pub trait UserSessionsCreate: UserSessionsStore + NotifyMessagesQueue {
+pub trait UserSessionsBroadcast: UserSessionsStore {
+    /// Enqueue the presence or stats packets of the user to the other online
+    /// sessions, only the ones whose presence filter shows the user get them.
+    async fn broadcast_user_packets(&self, user_id: i32, packets: Packet) {
+        let user_sessions = self.user_sessions().read().await;
+
+        for session in user_sessions.values() {
+            if session.user_id == user_id
+                || !session.extends.presence_visible(user_id)
+            {
+                continue;
+            }
+
+            session.extends.packets_queue.push_packet(packets.clone()).await;
+        }
+    }
+}
+
+#[async_trait]
+pub trait UserSessionsCreate: UserSessionsBroadcast {
     #[inline]
     async fn create(
         &self,
@@ -137,14 +455,12 @@ pub trait UserSessionsCreate: UserSessionsStore + NotifyMessagesQueue {
             .create(BanchoSession::new(create_session).into())
             .await;
 
-        let weak = Arc::downgrade(&session);
-
-        self.notify_queue().write().await.push_message_excludes(
+        self.broadcast_user_packets(
+            session.user_id,
             bancho_packets::server::UserPresenceSingle::pack(session.user_id)
                 .into(),
-            [session.user_id],
-            Some(Arc::new(move |_| weak.upgrade().is_some())),
-        );
+        )
+        .await;
 
         let online_users = {
             self.user_sessions()
@@ -200,7 +516,21 @@ pub trait UserSessionsExists: UserSessionsStore {
 
 #[async_trait]
 pub trait BanchoStateService:
-    UpdateUserBanchoStatus
+    AddUserFriend
+    + RemoveUserFriend
+    + GetUserMatch
+    + UpdateMatch
+    + LeaveMatch
+    + JoinMatch
+    + CreateMatch
+    + LeaveLobby
+    + JoinLobby
+    + GetSpectateState
+    + BroadcastSpectateFrames
+    + CantSpectate
+    + StopSpectating
+    + StartSpectating
+    + UpdateUserBanchoStatus
     + UpdatePresenceFilter
     + BatchSendPresences
     + SendAllPresences
@@ -223,6 +553,118 @@ pub trait BanchoStateService:
 {
 }
 
+#[async_trait]
+pub trait AddUserFriend {
+    async fn add_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait RemoveUserFriend {
+    async fn remove_user_friend(
+        &self,
+        request: UserFriendRequest,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait GetUserMatch {
+    async fn get_user_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<UserMatch, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait UpdateMatch {
+    async fn update_match(
+        &self,
+        request: UpdateMatchRequest,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait LeaveMatch {
+    async fn leave_match(
+        &self,
+        query: UserQuery,
+    ) -> Result<LeaveMatchResponse, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait JoinMatch {
+    async fn join_match(
+        &self,
+        request: JoinMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait CreateMatch {
+    async fn create_match(
+        &self,
+        request: CreateMatchRequest,
+    ) -> Result<JoinMatchResponse, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait LeaveLobby {
+    async fn leave_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait JoinLobby {
+    async fn join_lobby(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait GetSpectateState {
+    async fn get_spectate_state(
+        &self,
+        query: UserQuery,
+    ) -> Result<SpectateState, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait BroadcastSpectateFrames {
+    async fn broadcast_spectate_frames(
+        &self,
+        request: BroadcastSpectateFramesRequest,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait CantSpectate {
+    async fn cant_spectate(
+        &self,
+        query: UserQuery,
+    ) -> Result<ExecSuccess, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait StopSpectating {
+    async fn stop_spectating(
+        &self,
+        query: UserQuery,
+    ) -> Result<StopSpectatingResponse, BanchoStateError>;
+}
+
+#[async_trait]
+pub trait StartSpectating {
+    async fn start_spectating(
+        &self,
+        request: StartSpectatingRequest,
+    ) -> Result<StartSpectatingResponse, BanchoStateError>;
+}
+
 #[async_trait]
 pub trait UpdateUserBanchoStatus {
     async fn update_user_bancho_status(
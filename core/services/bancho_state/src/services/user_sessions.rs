@@ -1,5 +1,5 @@
 use super::traits::*;
-use crate::{BanchoSessionData, UserSessions};
+use crate::{BanchoSessionData, Matches, MatchesData, UserSessions};
 use async_trait::async_trait;
 use infra_services::IntoService;
 use peace_snapshot::CreateSnapshot;
@@ -9,6 +9,7 @@ use std::sync::Arc;
 pub struct UserSessionsServiceImpl {
     pub user_sessions: Arc<UserSessions>,
     pub notify_queue: Arc<BanchoMessageQueue>,
+    pub matches: Arc<Matches>,
 }
 
 impl UserSessionsServiceImpl {
@@ -22,6 +23,7 @@ impl UserSessionsServiceImpl {
 pub struct UserSessionsServiceSnapshot {
     pub user_sessions: Vec<BanchoSessionData>,
     pub notify_queue: Vec<BanchoMessageData>,
+    pub matches: MatchesData,
 }
 
 #[async_trait]
@@ -30,6 +32,7 @@ impl CreateSnapshot<UserSessionsServiceSnapshot> for UserSessionsServiceImpl {
         UserSessionsServiceSnapshot {
             user_sessions: self.user_sessions.create_snapshot().await,
             notify_queue: self.notify_queue.create_snapshot().await,
+            matches: self.matches.create_snapshot().await,
         }
     }
 }
@@ -39,6 +42,7 @@ impl Default for UserSessionsServiceImpl {
         Self {
             user_sessions: Arc::new(UserSessions::new()),
             notify_queue: Arc::new(BanchoMessageQueue::default()),
+            matches: Arc::new(Matches::default()),
         }
     }
 }
@@ -64,6 +68,13 @@ impl NotifyMessagesQueue for UserSessionsServiceImpl {
     }
 }
 
+impl MatchesStore for UserSessionsServiceImpl {
+    #[inline]
+    fn matches(&self) -> &Arc<Matches> {
+        &self.matches
+    }
+}
+
 #[async_trait]
 impl UserSessionsCount for UserSessionsServiceImpl {}
 
@@ -79,8 +90,95 @@ impl UserSessionsDelete for UserSessionsServiceImpl {}
 #[async_trait]
 impl UserSessionsCreate for UserSessionsServiceImpl {}
 
+#[async_trait]
+impl UserSessionsBroadcast for UserSessionsServiceImpl {}
+
 #[async_trait]
 impl UserSessionsExists for UserSessionsServiceImpl {}
 
+#[async_trait]
+impl UserSessionsSpectate for UserSessionsServiceImpl {}
+
+#[async_trait]
+impl UserSessionsMultiplayer for UserSessionsServiceImpl {}
+
 #[async_trait]
 impl UserSessionsService for UserSessionsServiceImpl {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BanchoExtend, BanchoSession};
+    use domain_bancho::PresenceFilter;
+    use infra_packets::Packet;
+    use infra_users::CreateSessionDto;
+    use std::collections::HashSet;
+
+    const ALICE: i32 = 1;
+    const BOB: i32 = 2;
+    const CAROL: i32 = 3;
+    const DAVE: i32 = 4;
+
+    async fn login(
+        service: &UserSessionsServiceImpl,
+        user_id: i32,
+        presence_filter: PresenceFilter,
+        friends: &[i32],
+    ) -> Arc<BanchoSession> {
+        service
+            .create(CreateSessionDto {
+                user_id,
+                username: format!("user{user_id}"),
+                username_unicode: None,
+                privileges: 1,
+                extends: BanchoExtend {
+                    presence_filter: presence_filter.into(),
+                    friends: HashSet::from_iter(friends.iter().copied()).into(),
+                    ..Default::default()
+                },
+            })
+            .await
+    }
+
+    async fn received(session: &BanchoSession) -> Vec<u8> {
+        session.extends.packets_queue.dequeue_all_packets(None).await
+    }
+
+    #[tokio::test]
+    async fn test_login_presence_respects_filter() {
+        let service = UserSessionsServiceImpl::new();
+        let alice =
+            login(&service, ALICE, PresenceFilter::Friends, &[DAVE]).await;
+        let bob = login(&service, BOB, PresenceFilter::All, &[]).await;
+        received(&alice).await;
+        received(&bob).await;
+
+        login(&service, CAROL, PresenceFilter::All, &[]).await;
+        assert!(received(&alice).await.is_empty());
+        assert!(!received(&bob).await.is_empty());
+
+        login(&service, DAVE, PresenceFilter::All, &[]).await;
+        assert!(!received(&alice).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stats_broadcast_respects_filter() {
+        let service = UserSessionsServiceImpl::new();
+        let alice =
+            login(&service, ALICE, PresenceFilter::Friends, &[DAVE]).await;
+        let bob = login(&service, BOB, PresenceFilter::All, &[]).await;
+        let carol = login(&service, CAROL, PresenceFilter::All, &[]).await;
+        for session in [&alice, &bob, &carol] {
+            received(session).await;
+        }
+
+        let stats = carol.user_stats_packet();
+        service
+            .broadcast_user_packets(CAROL, Packet::new_ptr(stats.clone()))
+            .await;
+
+        assert!(received(&alice).await.is_empty());
+        assert_eq!(received(&bob).await, stats);
+        assert!(received(&carol).await.is_empty());
+    }
+}
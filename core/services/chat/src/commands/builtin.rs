@@ -0,0 +1,612 @@
+use super::*;
+use crate::{update_user_performance, ChatError, MAX_PUNISHMENT_SECS};
+use bancho_packets::server;
+use domain_bancho::{BanchoPrivileges, UserPrivileges};
+use infra_packets::Packet;
+use pb_bancho_state::UserQuery;
+use peace_db::{
+    peace::entity::{
+        sea_orm_active_enums::{GameMode, PpVersion},
+        users,
+    },
+    Iterable,
+};
+use peace_repositories::{leaderboard::DynLeaderboardRepository, GetUserError};
+use rand::Rng;
+
+const STAFF: BanchoPrivileges = BanchoPrivileges::Moderator
+    .or(BanchoPrivileges::Administrator)
+    .or(BanchoPrivileges::Developer);
+
+const ADMIN: BanchoPrivileges =
+    BanchoPrivileges::Administrator.or(BanchoPrivileges::Developer);
+
+impl ChatCommandRegistryImpl {
+    /// The registry with all built-in commands.
+    pub fn with_builtin_commands(
+        cfg: &CliChatCommandConfigs,
+        leaderboard_repository: DynLeaderboardRepository,
+    ) -> Self {
+        Self::new(cfg)
+            .register(HelpCommand)
+            .register(RollCommand)
+            .register(StatsCommand)
+            .register(BlockCommand)
+            .register(UnblockCommand)
+            .register(SilenceCommand)
+            .register(RestrictCommand)
+            .register(AlertCommand)
+            .register(RecalcCommand { leaderboard_repository })
+    }
+}
+
+async fn find_user(
+    ctx: &CommandContext<'_>,
+    username: &str,
+) -> Result<users::Model, CommandError> {
+    match ctx
+        .chat_service
+        .get_user(&UserQuery::Username(username.to_owned()))
+        .await
+    {
+        Ok(user) => Ok(user),
+        Err(ChatError::GetUserError(GetUserError::UserNotExists)) => {
+            Err(CommandError::UserNotExists)
+        },
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The bot and the staff can not be silenced or restricted.
+async fn ensure_punishable(
+    ctx: &CommandContext<'_>,
+    user: &users::Model,
+) -> Result<(), CommandError> {
+    if user.id == ChatBot::USER_ID {
+        return Err(CommandError::PermissionDenied);
+    }
+
+    let privileges = UserPrivileges::from_names(
+        ctx.chat_service
+            .users_repository
+            .get_user_privileges(user.id)
+            .await
+            .map_err(ChatError::from)?
+            .iter()
+            .map(|p| p.name.as_str()),
+    );
+
+    if privileges.is_staff() {
+        return Err(CommandError::TargetIsStaff);
+    }
+
+    Ok(())
+}
+
+/// Parse `std`, `taiko`, `ctb` or `mania` (and some aliases), with the
+/// `rx`, `ap` and `v2` variants.
+fn parse_mode(mode: &str) -> Option<GameMode> {
+    Some(match mode.to_ascii_lowercase().as_str() {
+        "std" | "osu" | "standard" => GameMode::Standard,
+        "taiko" => GameMode::Taiko,
+        "ctb" | "catch" | "fruits" => GameMode::Fruits,
+        "mania" => GameMode::Mania,
+        "rx" | "std_rx" | "relax" => GameMode::StandardRelax,
+        "taiko_rx" => GameMode::TaikoRelax,
+        "ctb_rx" | "fruits_rx" => GameMode::FruitsRelax,
+        "ap" | "std_ap" | "autopilot" => GameMode::StandardAutopilot,
+        "v2" | "std_v2" | "scorev2" => GameMode::StandardScoreV2,
+        _ => return None,
+    })
+}
+
+/// Parse durations like `30s`, `10m`, `2h` or `7d` into seconds, plain
+/// numbers are seconds.
+fn parse_duration(duration: &str) -> Option<u64> {
+    let (value, unit) = match duration.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => duration.split_at(index),
+        None => (duration, "s"),
+    };
+
+    let value = value.parse::<u64>().ok()?;
+
+    value.checked_mul(match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => return None,
+    })
+}
+
+pub struct HelpCommand;
+
+#[async_trait]
+impl ChatCommand for HelpCommand {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[command]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Show the commands you are able to use."
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let prefix = ctx.prefix();
+        let privileges = ctx.sender_privileges().bancho_privileges();
+
+        let commands = ctx.chat_service.commands.commands();
+        let commands = commands.into_iter().filter(|command| {
+            privileges.intersects(command.required_privileges())
+                && args.get(0).is_none_or(|name| {
+                    name.trim_start_matches(prefix)
+                        .eq_ignore_ascii_case(command.name())
+                })
+        });
+
+        let lines = commands
+            .map(|command| {
+                format!(
+                    "{} - {}",
+                    command_usage(prefix, command.as_ref()),
+                    command.help()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if lines.is_empty() {
+            return Ok("No such command.".to_owned());
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+pub struct RollCommand;
+
+#[async_trait]
+impl ChatCommand for RollCommand {
+    fn name(&self) -> &'static str {
+        "roll"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[max]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Roll a random number between 0 and max (100 by default)."
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let max = args.parse_at::<u64>(0)?.unwrap_or(100);
+        let point = rand::thread_rng().gen_range(0..=max);
+
+        Ok(format!("{} rolls {point} point(s)", ctx.sender.username.load()))
+    }
+}
+
+pub struct StatsCommand;
+
+#[async_trait]
+impl ChatCommand for StatsCommand {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[username] [std|taiko|ctb|mania]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Show the stats of the user, yours by default."
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let (user_id, username) = match args.get(0) {
+            Some(username) => {
+                let user = find_user(ctx, username).await?;
+                (user.id, user.name)
+            },
+            None => {
+                (ctx.sender.user_id, ctx.sender.username.load().to_string())
+            },
+        };
+
+        let mode = match args.get(1) {
+            Some(mode) => {
+                parse_mode(mode).ok_or(CommandError::InvalidArguments)?
+            },
+            None => GameMode::Standard,
+        };
+
+        let users_repository = &ctx.chat_service.users_repository;
+
+        let Some(stats) =
+            users_repository.get_user_stats(user_id, mode.clone()).await?
+        else {
+            return Ok(format!("{username} has not played {mode:?} yet."));
+        };
+
+        let pp = users_repository
+            .get_user_pp(user_id, mode.clone(), PpVersion::V2)
+            .await?
+            .map(|user_pp| user_pp.pp)
+            .unwrap_or_default();
+
+        Ok(format!(
+            "{username} ({mode:?}): {pp}pp, {}% accuracy, {} plays, {} ranked \
+            score, {} total score",
+            stats.accuracy.round_dp(2),
+            stats.playcount,
+            stats.ranked_score,
+            stats.total_score,
+        ))
+    }
+}
+
+pub struct BlockCommand;
+
+#[async_trait]
+impl ChatCommand for BlockCommand {
+    fn name(&self) -> &'static str {
+        "block"
+    }
+
+    fn usage(&self) -> &'static str {
+        "<username>"
+    }
+
+    fn help(&self) -> &'static str {
+        "Ignore the private messages from the user."
+    }
+
+    fn min_args(&self) -> usize {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let user = find_user(ctx, args.get(0).unwrap_or_default()).await?;
+
+        ctx.chat_service
+            .followers_repository
+            .block_user(ctx.sender.user_id, user.id)
+            .await?;
+
+        Ok(format!("{} has been blocked.", user.name))
+    }
+}
+
+pub struct UnblockCommand;
+
+#[async_trait]
+impl ChatCommand for UnblockCommand {
+    fn name(&self) -> &'static str {
+        "unblock"
+    }
+
+    fn usage(&self) -> &'static str {
+        "<username>"
+    }
+
+    fn help(&self) -> &'static str {
+        "Receive the private messages from the blocked user again."
+    }
+
+    fn min_args(&self) -> usize {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let user = find_user(ctx, args.get(0).unwrap_or_default()).await?;
+
+        ctx.chat_service
+            .followers_repository
+            .unblock_user(ctx.sender.user_id, user.id)
+            .await?;
+
+        Ok(format!("{} has been unblocked.", user.name))
+    }
+}
+
+pub struct SilenceCommand;
+
+#[async_trait]
+impl ChatCommand for SilenceCommand {
+    fn name(&self) -> &'static str {
+        "silence"
+    }
+
+    fn usage(&self) -> &'static str {
+        "<username> <duration> [reason]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Silence the user, the duration is like 30s, 10m, 2h or 7d."
+    }
+
+    fn required_privileges(&self) -> BanchoPrivileges {
+        STAFF
+    }
+
+    fn min_args(&self) -> usize {
+        2
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        const LOG_TARGET: &str = "chat::commands::silence";
+
+        let user = find_user(ctx, args.get(0).unwrap_or_default()).await?;
+        let secs = args
+            .get(1)
+            .and_then(parse_duration)
+            .filter(|secs| *secs > 0)
+            .ok_or(CommandError::InvalidArguments)?;
+        let reason = args.rest(2).unwrap_or_default();
+
+        if secs > MAX_PUNISHMENT_SECS {
+            return Err(CommandError::DurationTooLong);
+        }
+
+        ensure_punishable(ctx, &user).await?;
+
+        ctx.chat_service.silence_user(user.id, secs, &reason).await?;
+
+        info!(
+            target: LOG_TARGET,
+            "{}({}) silenced {}({}) for {secs}s, reason: {reason}",
+            ctx.sender.username.load(),
+            ctx.sender.user_id,
+            user.name,
+            user.id
+        );
+
+        Ok(format!("{} has been silenced for {}.", user.name, args.0[1]))
+    }
+}
+
+pub struct RestrictCommand;
+
+#[async_trait]
+impl ChatCommand for RestrictCommand {
+    fn name(&self) -> &'static str {
+        "restrict"
+    }
+
+    fn usage(&self) -> &'static str {
+        "<username> [reason]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Restrict the user."
+    }
+
+    fn required_privileges(&self) -> BanchoPrivileges {
+        ADMIN
+    }
+
+    fn min_args(&self) -> usize {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        const LOG_TARGET: &str = "chat::commands::restrict";
+
+        let user = find_user(ctx, args.get(0).unwrap_or_default()).await?;
+        let reason = args.rest(1).unwrap_or_default();
+
+        ensure_punishable(ctx, &user).await?;
+
+        ctx.chat_service
+            .users_repository
+            .grant_user_privilege(user.id, "restricted", ctx.sender.user_id)
+            .await?;
+
+        if let Some(session) = ctx
+            .chat_service
+            .user_sessions
+            .get(&UserQuery::UserId(user.id))
+            .await
+        {
+            session.privileges.set(
+                (UserPrivileges::from(session.privileges.val())
+                    | UserPrivileges::Restricted)
+                    .bits(),
+            );
+
+            session
+                .extends
+                .push_packet(server::AccountRestricted::pack().into())
+                .await;
+        }
+
+        info!(
+            target: LOG_TARGET,
+            "{}({}) restricted {}({}), reason: {reason}",
+            ctx.sender.username.load(),
+            ctx.sender.user_id,
+            user.name,
+            user.id
+        );
+
+        Ok(format!("{} has been restricted.", user.name))
+    }
+}
+
+pub struct AlertCommand;
+
+#[async_trait]
+impl ChatCommand for AlertCommand {
+    fn name(&self) -> &'static str {
+        "alert"
+    }
+
+    fn usage(&self) -> &'static str {
+        "<message>"
+    }
+
+    fn help(&self) -> &'static str {
+        "Send a notification to all online users."
+    }
+
+    fn required_privileges(&self) -> BanchoPrivileges {
+        ADMIN
+    }
+
+    fn min_args(&self) -> usize {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let message = args.rest(0).unwrap_or_default();
+
+        ctx.chat_service
+            .notify_queue
+            .push_message(
+                Packet::Ptr(server::Notification::pack(message.into()).into()),
+                None,
+            )
+            .await;
+
+        Ok("Alert sent.".to_owned())
+    }
+}
+
+/// Recalculate the user's total pp and accuracy of all modes from the best
+/// scores on the ranked beatmaps.
+pub struct RecalcCommand {
+    pub leaderboard_repository: DynLeaderboardRepository,
+}
+
+#[async_trait]
+impl ChatCommand for RecalcCommand {
+    fn name(&self) -> &'static str {
+        "recalc"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[username]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Recalculate the total pp and accuracy of the user, yours by default."
+    }
+
+    fn required_privileges(&self) -> BanchoPrivileges {
+        ADMIN
+    }
+
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError> {
+        let (user_id, username) = match args.get(0) {
+            Some(username) => {
+                let user = find_user(ctx, username).await?;
+                (user.id, user.name)
+            },
+            None => {
+                (ctx.sender.user_id, ctx.sender.username.load().to_string())
+            },
+        };
+
+        let users_repository = &ctx.chat_service.users_repository;
+        let mut results = Vec::new();
+
+        for mode in GameMode::iter() {
+            let Some(mut stats) =
+                users_repository.get_user_stats(user_id, mode.clone()).await?
+            else {
+                continue;
+            };
+
+            let pp = update_user_performance(
+                users_repository,
+                &self.leaderboard_repository,
+                &mut stats,
+            )
+            .await?;
+
+            results.push(format!("{mode:?} {pp}pp"));
+        }
+
+        if results.is_empty() {
+            return Ok(format!("{username} has no stats to recalculate."));
+        }
+
+        Ok(format!("Recalculated {username}: {}", results.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30"), Some(30));
+        assert_eq!(parse_duration("30s"), Some(30));
+        assert_eq!(parse_duration("10m"), Some(600));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("7d"), Some(604800));
+        assert_eq!(parse_duration("1w"), Some(604800));
+
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("10y"), None);
+        assert_eq!(parse_duration("10mm"), None);
+        assert_eq!(parse_duration("-10m"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+
+        // overflows
+        assert_eq!(parse_duration(&format!("{}w", u64::MAX)), None);
+        assert_eq!(parse_duration("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("STD"), Some(GameMode::Standard));
+        assert_eq!(parse_mode("ctb"), Some(GameMode::Fruits));
+        assert_eq!(parse_mode("rx"), Some(GameMode::StandardRelax));
+        assert_eq!(parse_mode("v2"), Some(GameMode::StandardScoreV2));
+        assert_eq!(parse_mode("mania_rx"), None);
+    }
+}
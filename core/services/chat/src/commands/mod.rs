@@ -0,0 +1,326 @@
+pub mod builtin;
+
+pub use builtin::*;
+
+use crate::{Channel, ChatServiceImpl, ChatSession, CommandError};
+use async_trait::async_trait;
+use bancho_packets::server;
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use domain_bancho::{BanchoPrivileges, UserPrivileges};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+use tools::atomic::AtomicValue;
+
+pub type DynChatCommand = Arc<dyn ChatCommand + Send + Sync>;
+pub type DynChatCommandRegistry = Arc<dyn ChatCommandRegistry + Send + Sync>;
+
+/// The bot answering the commands, it is the seeded `system` user.
+pub struct ChatBot;
+
+impl ChatBot {
+    pub const USER_ID: i32 = 0;
+    pub const USERNAME: &'static str = "system";
+
+    #[inline]
+    pub fn privileges() -> UserPrivileges {
+        UserPrivileges::Normal | UserPrivileges::Developer
+    }
+
+    /// Presence and stats packets of the bot, the bot is always online.
+    #[inline]
+    pub fn presence_packets() -> Vec<u8> {
+        let mut packets = server::UserPresence::pack(
+            Self::USER_ID,
+            Self::USERNAME.into(),
+            0,
+            0,
+            Self::privileges().bancho_privileges().bits(),
+            0.0,
+            0.0,
+            0,
+        );
+
+        packets.extend(server::UserStats::pack(
+            Self::USER_ID,
+            0,
+            "".into(),
+            "".into(),
+            0,
+            0,
+            0,
+            0,
+            0.0,
+            0,
+            0,
+            0,
+            0,
+        ));
+
+        packets
+    }
+}
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliChatCommandConfigs {
+    /// Messages starting with the prefix are handled as bot commands.
+    #[default("!".to_owned())]
+    #[arg(long, default_value = "!")]
+    pub chat_command_prefix: String,
+}
+
+/// Arguments of a command, split by whitespaces, double quotes group the
+/// words into one argument.
+#[derive(Debug, Default, Clone)]
+pub struct CommandArgs(pub Vec<String>);
+
+impl CommandArgs {
+    pub fn parse(input: &str) -> Self {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+
+        for c in input.chars() {
+            match c {
+                '"' => {
+                    if quoted {
+                        args.push(std::mem::take(&mut current));
+                    }
+                    quoted = !quoted;
+                },
+                c if c.is_whitespace() && !quoted => {
+                    if !current.is_empty() {
+                        args.push(std::mem::take(&mut current));
+                    }
+                },
+                c => current.push(c),
+            }
+        }
+
+        if !current.is_empty() {
+            args.push(current);
+        }
+
+        Self(args)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.0.get(index).map(|s| s.as_str())
+    }
+
+    /// Parse the argument, returns `None` if it is not given.
+    #[inline]
+    pub fn parse_at<T: FromStr>(
+        &self,
+        index: usize,
+    ) -> Result<Option<T>, CommandError> {
+        self.get(index)
+            .map(|s| s.parse::<T>().map_err(|_| CommandError::InvalidArguments))
+            .transpose()
+    }
+
+    /// Join the arguments from the index, for the trailing free text.
+    #[inline]
+    pub fn rest(&self, index: usize) -> Option<String> {
+        self.0.get(index..).filter(|s| !s.is_empty()).map(|s| s.join(" "))
+    }
+}
+
+/// Split a message starting with the prefix into the lowercase command name
+/// and its arguments.
+pub fn parse_command(
+    prefix: &str,
+    message: &str,
+) -> Option<(String, CommandArgs)> {
+    let input = message.strip_prefix(prefix)?;
+    let (name, args) =
+        input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name.to_ascii_lowercase(), CommandArgs::parse(args)))
+}
+
+pub struct CommandContext<'a> {
+    pub chat_service: &'a ChatServiceImpl,
+    pub sender: &'a ChatSession,
+    /// The channel the command is sent to, `None` if it is sent to the bot.
+    pub channel: Option<&'a Channel>,
+}
+
+impl<'a> CommandContext<'a> {
+    #[inline]
+    pub fn sender_privileges(&self) -> UserPrivileges {
+        UserPrivileges::from(self.sender.privileges.val())
+    }
+
+    #[inline]
+    pub fn prefix(&self) -> &str {
+        self.chat_service.commands.prefix()
+    }
+}
+
+#[async_trait]
+pub trait ChatCommand {
+    /// Name of the command without the prefix.
+    fn name(&self) -> &'static str;
+
+    /// Arguments of the command, `<required> [optional]`.
+    fn usage(&self) -> &'static str {
+        ""
+    }
+
+    fn help(&self) -> &'static str;
+
+    /// Users with any of the privileges are able to use the command.
+    fn required_privileges(&self) -> BanchoPrivileges {
+        BanchoPrivileges::Normal
+    }
+
+    fn min_args(&self) -> usize {
+        0
+    }
+
+    /// Run the command, returns the reply of the bot.
+    async fn execute(
+        &self,
+        ctx: &CommandContext<'_>,
+        args: &CommandArgs,
+    ) -> Result<String, CommandError>;
+}
+
+pub trait ChatCommandRegistry {
+    fn prefix(&self) -> &str;
+
+    fn get(&self, name: &str) -> Option<&DynChatCommand>;
+
+    /// All commands ordered by name.
+    fn commands(&self) -> Vec<&DynChatCommand>;
+}
+
+#[derive(Clone, Default)]
+pub struct ChatCommandRegistryImpl {
+    pub prefix: String,
+    pub commands: BTreeMap<&'static str, DynChatCommand>,
+}
+
+impl ChatCommandRegistryImpl {
+    #[inline]
+    pub fn new(cfg: &CliChatCommandConfigs) -> Self {
+        Self { prefix: cfg.chat_command_prefix.clone(), ..Default::default() }
+    }
+
+    /// Register the command, replaces the command with the same name.
+    #[inline]
+    pub fn register<C>(mut self, command: C) -> Self
+    where
+        C: ChatCommand + Send + Sync + 'static,
+    {
+        self.commands.insert(command.name(), Arc::new(command));
+        self
+    }
+
+    #[inline]
+    pub fn into_service(self) -> DynChatCommandRegistry {
+        Arc::new(self) as DynChatCommandRegistry
+    }
+}
+
+impl ChatCommandRegistry for ChatCommandRegistryImpl {
+    #[inline]
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    #[inline]
+    fn get(&self, name: &str) -> Option<&DynChatCommand> {
+        self.commands.get(name)
+    }
+
+    #[inline]
+    fn commands(&self) -> Vec<&DynChatCommand> {
+        self.commands.values().collect()
+    }
+}
+
+/// Format the usage of the command with the prefix.
+#[inline]
+pub fn command_usage(
+    prefix: &str,
+    command: &(dyn ChatCommand + Send + Sync),
+) -> String {
+    match command.usage() {
+        "" => format!("{prefix}{}", command.name()),
+        usage => format!("{prefix}{} {usage}", command.name()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(input: &str) -> Vec<String> {
+        CommandArgs::parse(input).0
+    }
+
+    #[test]
+    fn test_command_args_parse() {
+        assert!(args("").is_empty());
+        assert!(args("   ").is_empty());
+        assert_eq!(args("a b  c"), ["a", "b", "c"]);
+        assert_eq!(args(" a\tb\n"), ["a", "b"]);
+        assert_eq!(
+            args(r#"alice "spam in #osu" now"#),
+            ["alice", "spam in #osu", "now"]
+        );
+        assert_eq!(args(r#""""#), [""]);
+
+        // the unclosed quote groups the rest
+        assert_eq!(args(r#"a "b c"#), ["a", "b c"]);
+        assert_eq!(args("ユーザー 理由"), ["ユーザー", "理由"]);
+    }
+
+    #[test]
+    fn test_command_args_helpers() {
+        let args = CommandArgs::parse("alice 10 some reason");
+        assert_eq!(args.get(0), Some("alice"));
+        assert_eq!(args.get(4), None);
+        assert_eq!(args.parse_at::<u64>(1).unwrap(), Some(10));
+        assert!(args.parse_at::<u64>(0).is_err());
+        assert_eq!(args.parse_at::<u64>(4).unwrap(), None);
+        assert_eq!(args.rest(2).as_deref(), Some("some reason"));
+        assert_eq!(args.rest(4), None);
+        assert_eq!(args.rest(5), None);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let (name, args) = parse_command("!", "!Silence alice 10m").unwrap();
+        assert_eq!(name, "silence");
+        assert_eq!(args.0, ["alice", "10m"]);
+
+        let (name, args) = parse_command("!", "!help").unwrap();
+        assert_eq!(name, "help");
+        assert!(args.is_empty());
+
+        let (name, _) = parse_command("$$", "$$roll 10").unwrap();
+        assert_eq!(name, "roll");
+
+        assert!(parse_command("!", "hello").is_none());
+        assert!(parse_command("!", "!").is_none());
+        assert!(parse_command("!", "! help").is_none());
+        assert!(parse_command("!", " !help").is_none());
+    }
+}
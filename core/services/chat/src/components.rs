@@ -1,28 +1,47 @@
 use crate::{BanchoMessageData, BanchoMessageQueue};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Parser;
 use clap_serde_derive::ClapSerde;
-use domain_chat::{ChannelType, Platform};
+use domain_bancho::UserPrivileges;
+use domain_chat::{ChannelType, MessageToken, ParsedMessage, Platform};
 use infra_packets::{Packet, PacketsQueue};
 use infra_users::{
     BaseSession, BaseSessionData, CreateSessionDto, UserIndexes, UserStore,
 };
-use pb_chat::ChannelQuery;
+use pb_chat::{chat_message_token::TokenType, ChannelQuery, ChatMessageToken};
+use peace_db::{
+    peace::entity::{
+        channel_moderation_logs, chat_messages,
+        sea_orm_active_enums::{ChannelHandleType, ChannelModerationAction},
+    },
+    Set,
+};
+use peace_repositories::{
+    chat_messages::DynChatMessagesRepository, ChatMessagesError,
+};
 use peace_snapshot::{cli_snapshot_config, CreateSnapshot, SnapshotType};
 use peace_unique_id::Ulid;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
     ops::{Deref, DerefMut},
-    sync::{Arc, Weak},
+    sync::{Arc, Mutex as StdMutex, Weak},
+    time::{Duration, Instant},
 };
 use tokio::sync::{Mutex, RwLock};
 use tools::atomic::{
-    Atomic, AtomicOperation, AtomicOption, AtomicValue, Usize, U32,
+    Atomic, AtomicOperation, AtomicOption, AtomicValue, Bool, Usize, I64, U32,
 };
 
 pub type SessionIndexes = UserIndexes<ChatSession>;
 pub type UserSessions = UserStore<ChatSession>;
 
+const MAX_PENDING_CHAT_MESSAGES: usize = 100_000;
+
+/// Mutes and silences last 1 year at most.
+pub const MAX_PUNISHMENT_SECS: u64 = 365 * 24 * 60 * 60;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ChatSessionData {
     pub base: BaseSessionData,
@@ -91,14 +110,29 @@ impl ChatSession {
 pub struct JoinedChannel {
     pub ptr: Atomic<Weak<Channel>>,
     pub message_index: Atomic<Ulid>,
+    /// The irc connection receives the channel messages separately.
+    pub irc_message_index: Atomic<Ulid>,
     pub joined_time: DateTime<Utc>,
 }
 
+impl JoinedChannel {
+    /// Index of the channel messages received by the bancho or irc client.
+    #[inline]
+    pub fn platform_message_index(&self, platform: Platform) -> &Atomic<Ulid> {
+        if platform.contains(Platform::Irc) {
+            &self.irc_message_index
+        } else {
+            &self.message_index
+        }
+    }
+}
+
 impl From<Weak<Channel>> for JoinedChannel {
     fn from(ptr: Weak<Channel>) -> Self {
         Self {
             ptr: ptr.into(),
             message_index: Default::default(),
+            irc_message_index: Default::default(),
             joined_time: Utc::now(),
         }
     }
@@ -108,6 +142,8 @@ impl From<Weak<Channel>> for JoinedChannel {
 pub struct JoinedChannelData {
     pub channel_id: u64,
     pub message_index: Ulid,
+    #[serde(default)]
+    pub irc_message_index: Ulid,
     pub joined_time: DateTime<Utc>,
 }
 
@@ -154,8 +190,13 @@ impl CreateSnapshot<BanchoChatExtData> for BanchoChatExt {
 pub struct ChatSessionExtend {
     pub platforms: Atomic<Platform>,
     pub bancho_ext: AtomicOption<BanchoChatExt>,
+    /// The irc clients receive the bancho packets from their own queue.
+    pub irc_ext: AtomicOption<BanchoChatExt>,
     pub joined_channels: RwLock<HashMap<u64, Arc<JoinedChannel>>>,
     pub channel_count: U32,
+    /// Unix timestamp of the end of user's silence.
+    pub silence_end: I64,
+    pub only_friend_pm_allowed: Bool,
 }
 
 impl From<ChatSessionExtendData> for ChatSessionExtend {
@@ -164,6 +205,7 @@ impl From<ChatSessionExtendData> for ChatSessionExtend {
         Self {
             platforms: Platform::from(data.platforms).into(),
             bancho_ext: data.bancho_ext.map(|d| d.into()).into(),
+            irc_ext: data.irc_ext.map(|d| d.into()).into(),
             joined_channels: RwLock::new(HashMap::from_iter(
                 data.joined_channels.into_iter().map(|j| {
                     (
@@ -171,12 +213,15 @@ impl From<ChatSessionExtendData> for ChatSessionExtend {
                         Arc::new(JoinedChannel {
                             ptr: Weak::new().into(),
                             message_index: j.message_index.into(),
+                            irc_message_index: j.irc_message_index.into(),
                             joined_time: j.joined_time,
                         }),
                     )
                 }),
             )),
             channel_count,
+            silence_end: data.silence_end.into(),
+            only_friend_pm_allowed: data.only_friend_pm_allowed.into(),
         }
     }
 }
@@ -193,8 +238,49 @@ impl ChatSessionExtend {
         Self {
             platforms: platforms.into(),
             bancho_ext: bancho_ext.into(),
+            irc_ext: None.into(),
             joined_channels: RwLock::new(joined_channels),
             channel_count: U32::from(channel_count as u32),
+            silence_end: I64::default(),
+            only_friend_pm_allowed: Bool::default(),
+        }
+    }
+
+    /// Seconds left of user's silence, zero if the user is not silenced.
+    #[inline]
+    pub fn silence_left_secs(&self) -> i64 {
+        (self.silence_end.val() - Utc::now().timestamp()).max(0)
+    }
+
+    #[inline]
+    pub fn is_silenced(&self) -> bool {
+        self.silence_left_secs() > 0
+    }
+
+    /// The queue of the bancho or irc client of the session.
+    #[inline]
+    pub fn chat_ext(&self, platform: Platform) -> &AtomicOption<BanchoChatExt> {
+        if platform.contains(Platform::Irc) {
+            &self.irc_ext
+        } else {
+            &self.bancho_ext
+        }
+    }
+
+    /// Whether the session is connected with a bancho or irc client.
+    #[inline]
+    pub fn has_chat_ext(&self) -> bool {
+        self.bancho_ext.load().is_some() || self.irc_ext.load().is_some()
+    }
+
+    /// Push the packet to the bancho and irc clients of the session.
+    pub async fn push_packet(&self, packet: Packet) {
+        if let Some(irc_ext) = self.irc_ext.load().as_ref() {
+            irc_ext.packets_queue.push_packet(packet.clone()).await;
+        }
+
+        if let Some(bancho_ext) = self.bancho_ext.load().as_ref() {
+            bancho_ext.packets_queue.push_packet(packet).await;
         }
     }
 
@@ -206,6 +292,7 @@ impl ChatSessionExtend {
             channels.push(JoinedChannelData {
                 channel_id: *channel_id,
                 message_index: *channel.message_index.load().as_ref(),
+                irc_message_index: *channel.irc_message_index.load().as_ref(),
                 joined_time: channel.joined_time,
             });
         }
@@ -218,7 +305,13 @@ impl ChatSessionExtend {
 pub struct ChatSessionExtendData {
     pub platforms: i32,
     pub bancho_ext: Option<BanchoChatExtData>,
+    #[serde(default)]
+    pub irc_ext: Option<BanchoChatExtData>,
     pub joined_channels: Vec<JoinedChannelData>,
+    #[serde(default)]
+    pub silence_end: i64,
+    #[serde(default)]
+    pub only_friend_pm_allowed: bool,
 }
 
 #[async_trait]
@@ -230,7 +323,13 @@ impl CreateSnapshot<ChatSessionExtendData> for ChatSessionExtend {
                 Some(ext) => Some(ext.create_snapshot().await),
                 None => None,
             },
+            irc_ext: match self.irc_ext.load().as_deref() {
+                Some(ext) => Some(ext.create_snapshot().await),
+                None => None,
+            },
             joined_channels: self.collect_joined_channels().await,
+            silence_end: self.silence_end.val(),
+            only_friend_pm_allowed: self.only_friend_pm_allowed.val(),
         }
     }
 }
@@ -288,15 +387,80 @@ impl ChannelIndexes {
     }
 }
 
+/// The privileges required by the handles of a channel, loaded from the
+/// `channel_privileges` table.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChannelPrivileges {
+    pub join: Option<UserPrivileges>,
+    pub kick_user: Option<UserPrivileges>,
+    pub mute_user: Option<UserPrivileges>,
+    pub send_message: Option<UserPrivileges>,
+}
+
+impl ChannelPrivileges {
+    #[inline]
+    pub fn required(
+        &self,
+        handle: &ChannelHandleType,
+    ) -> Option<UserPrivileges> {
+        match handle {
+            ChannelHandleType::Join => self.join,
+            ChannelHandleType::KickUser => self.kick_user,
+            ChannelHandleType::MuteUser => self.mute_user,
+            ChannelHandleType::SendMessage => self.send_message,
+        }
+    }
+
+    #[inline]
+    pub fn set_required(
+        &mut self,
+        handle: &ChannelHandleType,
+        privileges: UserPrivileges,
+    ) {
+        *match handle {
+            ChannelHandleType::Join => &mut self.join,
+            ChannelHandleType::KickUser => &mut self.kick_user,
+            ChannelHandleType::MuteUser => &mut self.mute_user,
+            ChannelHandleType::SendMessage => &mut self.send_message,
+        } = Some(privileges);
+    }
+
+    /// Staff can do everything. Without a requirement, joining and sending
+    /// messages are open to everyone, kicking and muting are staff only.
+    #[inline]
+    pub fn is_allowed(
+        &self,
+        handle: &ChannelHandleType,
+        privileges: UserPrivileges,
+    ) -> bool {
+        if privileges.is_staff() {
+            return true;
+        }
+
+        match self.required(handle) {
+            Some(required) => privileges.contains(required),
+            None => matches!(
+                handle,
+                ChannelHandleType::Join | ChannelHandleType::SendMessage
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Channel {
     pub id: u64,
     pub name: Atomic<String>,
     pub channel_type: ChannelType,
     pub description: AtomicOption<String>,
+    pub privileges: Atomic<ChannelPrivileges>,
+    /// Users are joined into the channel automatically when they log in.
+    pub auto_join: Bool,
 
     pub users: Arc<RwLock<HashMap<i32, Option<Weak<ChatSession>>>>>,
     pub user_count: U32,
+    /// Muted users and the end time of their mutes.
+    pub mutes: RwLock<HashMap<i32, DateTime<Utc>>>,
 
     pub min_msg_index: AtomicOption<Ulid>,
     pub message_queue: Arc<BanchoMessageQueue>,
@@ -326,8 +490,11 @@ impl Channel {
             name: name.into(),
             channel_type,
             description: description.into(),
+            privileges: ChannelPrivileges::default().into(),
+            auto_join: Bool::default(),
             users: Arc::new(users.into()),
             user_count: user_count.into(),
+            mutes: RwLock::default(),
             min_msg_index: None.into(),
             message_queue: Arc::new(BanchoMessageQueue::default()),
             created_at: Utc::now(),
@@ -356,18 +523,14 @@ impl Channel {
                 JoinedChannel {
                     ptr: Arc::downgrade(channel).into(),
                     message_index: Ulid::default().into(),
+                    irc_message_index: Ulid::default().into(),
                     joined_time: Utc::now(),
                 }
                 .into()
             });
 
         // notify to user's bancho client if possible
-        if let Some(bancho_ext) = session.extends.bancho_ext.load().as_ref() {
-            bancho_ext
-                .packets_queue
-                .push_packet(channel.join_packets().into())
-                .await;
-        }
+        session.extends.push_packet(channel.join_packets().into()).await;
 
         info!(
             target: LOG_TARGET,
@@ -397,13 +560,8 @@ impl Channel {
             session.extends.channel_count.sub(1);
         }
 
-        // notify to user's bancho client if possible
-        if let Some(bancho_ext) = session.extends.bancho_ext.load().as_ref() {
-            bancho_ext
-                .packets_queue
-                .push_packet(channel.kick_packets().into())
-                .await;
-        }
+        // notify to user's bancho and irc clients if possible
+        session.extends.push_packet(channel.kick_packets().into()).await;
 
         info!(
             target: LOG_TARGET,
@@ -415,10 +573,52 @@ impl Channel {
         );
     }
 
+    #[inline]
+    pub fn is_allowed(
+        &self,
+        handle: &ChannelHandleType,
+        privileges: UserPrivileges,
+    ) -> bool {
+        self.privileges.load().is_allowed(handle, privileges)
+    }
+
+    /// Get the end time of the user's mute, the expired mute is removed.
+    pub async fn mute_end(&self, user_id: i32) -> Option<DateTime<Utc>> {
+        let mute_end = *self.mutes.read().await.get(&user_id)?;
+        if mute_end > Utc::now() {
+            return Some(mute_end);
+        }
+
+        self.mutes.write().await.remove(&user_id);
+        None
+    }
+
+    /// Mute the user until `mute_end`, or unmute the user if not set.
+    pub async fn set_mute(
+        &self,
+        user_id: i32,
+        mute_end: Option<DateTime<Utc>>,
+    ) {
+        let mut mutes = self.mutes.write().await;
+        match mute_end {
+            Some(mute_end) => mutes.insert(user_id, mute_end),
+            None => mutes.remove(&user_id),
+        };
+    }
+
+    /// The channel name displayed in bancho clients.
+    #[inline]
+    pub fn bancho_name(&self) -> String {
+        match self.channel_type.bancho_name() {
+            Some(name) => name.to_owned(),
+            None => self.name.to_string(),
+        }
+    }
+
     #[inline]
     pub fn info_packets(&self) -> Vec<u8> {
         bancho_packets::server::ChannelInfo::pack(
-            self.name.load().as_ref().into(),
+            self.bancho_name().into(),
             self.description
                 .load()
                 .as_deref()
@@ -431,16 +631,12 @@ impl Channel {
 
     #[inline]
     pub fn join_packets(&self) -> Vec<u8> {
-        bancho_packets::server::ChannelJoin::pack(
-            self.name.load().as_ref().into(),
-        )
+        bancho_packets::server::ChannelJoin::pack(self.bancho_name().into())
     }
 
     #[inline]
     pub fn kick_packets(&self) -> Vec<u8> {
-        bancho_packets::server::ChannelKick::pack(
-            self.name.load().as_ref().into(),
-        )
+        bancho_packets::server::ChannelKick::pack(self.bancho_name().into())
     }
 }
 
@@ -450,7 +646,11 @@ pub struct ChannelData {
     pub name: String,
     pub channel_type: ChannelType,
     pub description: Option<String>,
+    #[serde(default)]
+    pub auto_join: bool,
     pub users: Vec<i32>,
+    #[serde(default)]
+    pub mutes: HashMap<i32, DateTime<Utc>>,
     pub min_msg_index: Option<Ulid>,
     pub message_queue: Vec<BanchoMessageData>,
     pub created_at: DateTime<Utc>,
@@ -468,7 +668,9 @@ impl ChannelData {
                 .load()
                 .as_deref()
                 .map(|s| s.to_string()),
+            auto_join: ch.auto_join.val(),
             users: ch.users.read().await.keys().copied().collect(),
+            mutes: ch.mutes.read().await.clone(),
             min_msg_index: ch.min_msg_index.load().as_deref().copied(),
             message_queue: ch
                 .message_queue
@@ -477,7 +679,7 @@ impl ChannelData {
                 .create_snapshot()
                 .await,
             created_at: ch.created_at,
-            updated_at: ch.updated_at.load().as_ref().clone(),
+            updated_at: *ch.updated_at.load().as_ref(),
         }
     }
 }
@@ -642,4 +844,418 @@ impl Channels {
     }
 }
 
+/// The end of a mute or silence lasting `secs` from now, `None` if it is
+/// longer than [`MAX_PUNISHMENT_SECS`].
+#[inline]
+pub fn punishment_end(secs: u64) -> Option<DateTime<Utc>> {
+    if secs > MAX_PUNISHMENT_SECS {
+        return None;
+    }
+
+    TimeDelta::try_seconds(secs as i64)
+        .and_then(|duration| Utc::now().checked_add_signed(duration))
+}
+
+/// Build a chat message row sent just now.
+#[inline]
+pub fn new_chat_message(
+    sender_id: i32,
+    channel_id: Option<i64>,
+    target_user_id: Option<i32>,
+    content: &str,
+) -> chat_messages::ActiveModel {
+    let parsed = ParsedMessage::parse(content);
+
+    chat_messages::ActiveModel {
+        sender_id: Set(sender_id),
+        channel_id: Set(channel_id),
+        target_user_id: Set(target_user_id),
+        timestamp: Set(Utc::now().into()),
+        content_string: Set(content.to_owned()),
+        content_html: Set(Some(parsed.to_html())),
+        is_action: Set(parsed.is_action),
+        ..Default::default()
+    }
+}
+
+/// Tokens of the parsed message for the rpc responses.
+pub fn chat_message_tokens(parsed: &ParsedMessage) -> Vec<ChatMessageToken> {
+    parsed
+        .tokens
+        .iter()
+        .map(|token| {
+            let (token_type, id) = match token {
+                MessageToken::Text { .. } => (TokenType::Text, None),
+                MessageToken::Link { .. } => (TokenType::Link, None),
+                MessageToken::Beatmap { id, .. } => {
+                    (TokenType::Beatmap, Some(*id))
+                },
+                MessageToken::Beatmapset { id, .. } => {
+                    (TokenType::Beatmapset, Some(*id))
+                },
+                MessageToken::Mention { .. } => (TokenType::Mention, None),
+            };
+
+            ChatMessageToken {
+                token_type: token_type as i32,
+                text: token.text().to_owned(),
+                url: token.url().map(|url| url.to_owned()),
+                id,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliChatSpamConfigs {
+    /// Max messages a user can send in a burst.
+    #[default(10)]
+    #[arg(long, default_value = "10")]
+    pub chat_rate_limit_burst: u32,
+
+    /// Milliseconds to regain one message of the burst.
+    #[default(1000)]
+    #[arg(long, default_value = "1000")]
+    pub chat_rate_limit_refill_millis: u64,
+
+    /// Max times in a row a user can send the same message, 0 to disable.
+    #[default(5)]
+    #[arg(long, default_value = "5")]
+    pub chat_repeated_message_limit: u32,
+
+    /// Seconds to silence the users who spam, 1 year at most.
+    #[default(300)]
+    #[arg(long, default_value = "300")]
+    pub chat_spam_silence_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum SpamViolation {
+    #[error("sending messages too fast")]
+    RateLimited,
+    #[error("sending the same message repeatedly")]
+    RepeatedMessage,
+}
+
+#[derive(Debug)]
+pub struct SpamState {
+    pub tokens: f64,
+    pub last_refill: Instant,
+    pub last_message_hash: u64,
+    pub repeats: u32,
+}
+
+/// Per-user token-bucket rate limiting with repeated message detection.
+#[derive(Debug)]
+pub struct ChatSpamGuard {
+    pub burst: u32,
+    pub refill_interval: Duration,
+    pub repeated_message_limit: u32,
+    pub silence_secs: u64,
+    pub states: StdMutex<HashMap<i32, SpamState>>,
+}
+
+impl ChatSpamGuard {
+    #[inline]
+    pub fn new(cfg: &CliChatSpamConfigs) -> Self {
+        Self {
+            burst: cfg.chat_rate_limit_burst.max(1),
+            refill_interval: Duration::from_millis(
+                cfg.chat_rate_limit_refill_millis.max(1),
+            ),
+            repeated_message_limit: cfg.chat_repeated_message_limit,
+            silence_secs: cfg.chat_spam_silence_secs.min(MAX_PUNISHMENT_SECS),
+            states: StdMutex::default(),
+        }
+    }
+
+    /// Take a token of the user's bucket for the message, the state of the
+    /// user is reset on violations.
+    pub fn check(
+        &self,
+        user_id: i32,
+        message: &str,
+    ) -> Result<(), SpamViolation> {
+        let now = Instant::now();
+        let message_hash = {
+            let mut hasher = DefaultHasher::new();
+            message.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(user_id).or_insert_with(|| SpamState {
+            tokens: self.burst as f64,
+            last_refill: now,
+            last_message_hash: 0,
+            repeats: 0,
+        });
+
+        state.tokens = (state.tokens
+            + now.duration_since(state.last_refill).as_secs_f64()
+                / self.refill_interval.as_secs_f64())
+        .min(self.burst as f64);
+        state.last_refill = now;
+
+        if state.last_message_hash == message_hash {
+            state.repeats += 1;
+        } else {
+            state.last_message_hash = message_hash;
+            state.repeats = 1;
+        }
+
+        let violation = if state.tokens < 1.0 {
+            Some(SpamViolation::RateLimited)
+        } else if self.repeated_message_limit > 0
+            && state.repeats > self.repeated_message_limit
+        {
+            Some(SpamViolation::RepeatedMessage)
+        } else {
+            None
+        };
+
+        match violation {
+            Some(violation) => {
+                states.remove(&user_id);
+                Err(violation)
+            },
+            None => {
+                state.tokens -= 1.0;
+                Ok(())
+            },
+        }
+    }
+
+    #[inline]
+    pub fn remove(&self, user_id: i32) {
+        self.states.lock().unwrap().remove(&user_id);
+    }
+}
+
+/// Build an audit log row of a moderation action taken just now.
+#[inline]
+pub fn new_moderation_log(
+    channel_id: u64,
+    moderator_id: i32,
+    target_user_id: i32,
+    action: ChannelModerationAction,
+    reason: Option<String>,
+    mute_end: Option<DateTime<Utc>>,
+) -> channel_moderation_logs::ActiveModel {
+    channel_moderation_logs::ActiveModel {
+        channel_id: Set(channel_id as i64),
+        moderator_id: Set(moderator_id),
+        target_user_id: Set(target_user_id),
+        action: Set(action),
+        reason: Set(reason),
+        mute_end: Set(mute_end.map(|t| t.into())),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+}
+
+/// The chat messages are saved into the database in batches, never blocking
+/// the sending of messages.
+pub struct ChatMessagesPersister {
+    pub repository: DynChatMessagesRepository,
+    pub pending: StdMutex<Vec<chat_messages::ActiveModel>>,
+    /// Messages over this are dropped if the database is unavailable.
+    pub max_pending: usize,
+}
+
+impl ChatMessagesPersister {
+    #[inline]
+    pub fn new(repository: DynChatMessagesRepository) -> Self {
+        Self {
+            repository,
+            pending: StdMutex::default(),
+            max_pending: MAX_PENDING_CHAT_MESSAGES,
+        }
+    }
+
+    /// Queue a message to be saved, the oldest pending messages are dropped
+    /// if there are too many, e.g. while the database is unavailable.
+    pub fn push(
+        &self,
+        sender_id: i32,
+        channel_id: Option<i64>,
+        target_user_id: Option<i32>,
+        content: &str,
+    ) {
+        let message =
+            new_chat_message(sender_id, channel_id, target_user_id, content);
+
+        let mut pending = self.pending.lock().unwrap();
+
+        let overflow =
+            (pending.len() + 1).saturating_sub(self.max_pending.max(1));
+        if overflow > 0 {
+            pending.drain(..overflow);
+            warn!("{overflow} chat messages dropped, too many pending");
+        }
+
+        pending.push(message);
+    }
+
+    #[inline]
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// The messages not saved yet in the order they were sent, their ids are
+    /// `0`.
+    pub fn pending_messages(&self) -> Vec<chat_messages::Model> {
+        self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|message| chat_messages::Model {
+                id: 0,
+                sender_id: message.sender_id.clone().take().unwrap_or_default(),
+                channel_id: message.channel_id.clone().take().flatten(),
+                target_user_id: message.target_user_id.clone().take().flatten(),
+                timestamp: message
+                    .timestamp
+                    .clone()
+                    .take()
+                    .unwrap_or_else(|| Utc::now().into()),
+                content_string: message
+                    .content_string
+                    .clone()
+                    .take()
+                    .unwrap_or_default(),
+                content_html: message.content_html.clone().take().flatten(),
+                is_action: message.is_action.clone().take().unwrap_or_default(),
+                delivered: message.delivered.clone().take().unwrap_or(true),
+            })
+            .collect()
+    }
+
+    /// Save all pending messages, they are queued again if failed.
+    pub async fn flush(&self) -> Result<usize, ChatMessagesError> {
+        let messages = std::mem::take(&mut *self.pending.lock().unwrap());
+        if messages.is_empty() {
+            return Ok(0);
+        }
+
+        match self.repository.save_messages(messages.clone()).await {
+            Ok(count) => Ok(count),
+            Err(err) => {
+                let mut pending = self.pending.lock().unwrap();
+                let keep = self.max_pending.saturating_sub(pending.len());
+                let dropped = messages.len().saturating_sub(keep);
+                if dropped > 0 {
+                    warn!("{dropped} chat messages dropped, too many pending");
+                }
+
+                let mut requeued =
+                    messages.into_iter().rev().take(keep).collect::<Vec<_>>();
+                requeued.reverse();
+                requeued.append(&mut pending);
+                *pending = requeued;
+
+                Err(err)
+            },
+        }
+    }
+}
+
 cli_snapshot_config!(service: Chat);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spam_guard(burst: u32, repeated_message_limit: u32) -> ChatSpamGuard {
+        ChatSpamGuard::new(&CliChatSpamConfigs {
+            chat_rate_limit_burst: burst,
+            chat_rate_limit_refill_millis: 60_000,
+            chat_repeated_message_limit: repeated_message_limit,
+            chat_spam_silence_secs: 300,
+        })
+    }
+
+    #[test]
+    fn test_spam_guard_burst() {
+        let guard = spam_guard(3, 0);
+
+        for i in 0..3 {
+            assert!(guard.check(1, &i.to_string()).is_ok());
+        }
+        assert!(matches!(guard.check(1, "4"), Err(SpamViolation::RateLimited)));
+
+        // the buckets are per user
+        assert!(guard.check(2, "1").is_ok());
+
+        // the state is reset after the violation
+        assert!(guard.check(1, "5").is_ok());
+    }
+
+    #[test]
+    fn test_spam_guard_refill() {
+        let guard = spam_guard(2, 0);
+        assert!(guard.check(1, "1").is_ok());
+        assert!(guard.check(1, "2").is_ok());
+
+        guard.states.lock().unwrap().get_mut(&1).unwrap().last_refill -=
+            Duration::from_secs(60);
+
+        // one token is regained, not more than one
+        assert!(guard.check(1, "3").is_ok());
+        assert!(guard.check(1, "4").is_err());
+    }
+
+    #[test]
+    fn test_spam_guard_repeated_messages() {
+        let guard = spam_guard(10, 2);
+        assert!(guard.check(1, "hi").is_ok());
+        assert!(guard.check(1, "hi").is_ok());
+        assert!(matches!(
+            guard.check(1, "hi"),
+            Err(SpamViolation::RepeatedMessage)
+        ));
+
+        assert!(guard.check(1, "hi").is_ok());
+        assert!(guard.check(1, "hello").is_ok());
+        assert!(guard.check(1, "hi").is_ok());
+
+        // disabled
+        let guard = spam_guard(10, 0);
+        for _ in 0..5 {
+            assert!(guard.check(1, "hi").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_persister_drops_oldest_pending() {
+        let mut persister = ChatMessagesPersister::new(Arc::new(
+            crate::services::mock::MemoryChatMessages::default(),
+        ));
+        persister.max_pending = 2;
+
+        for content in ["1", "2", "3"] {
+            persister.push(1, Some(1), None, content);
+        }
+
+        assert_eq!(persister.pending_count(), 2);
+        assert_eq!(
+            persister
+                .pending_messages()
+                .into_iter()
+                .map(|message| message.content_string)
+                .collect::<Vec<_>>(),
+            ["2", "3"]
+        );
+    }
+
+    #[test]
+    fn test_spam_guard_silence_secs_capped() {
+        let guard = ChatSpamGuard::new(&CliChatSpamConfigs {
+            chat_spam_silence_secs: u64::MAX,
+            ..CliChatSpamConfigs::default()
+        });
+        assert_eq!(guard.silence_secs, MAX_PUNISHMENT_SECS);
+        assert!(punishment_end(guard.silence_secs).is_some());
+        assert!(punishment_end(MAX_PUNISHMENT_SECS + 1).is_none());
+    }
+}
@@ -1,5 +1,9 @@
+use peace_db::DbErr;
 use peace_pb::ConvertError;
-use peace_repositories::GetUserError;
+use peace_repositories::{
+    ChannelsError, ChatMessagesError, FollowersError, GetUserError,
+    LeaderboardError,
+};
 use peace_rpc_error::{RpcError, TonicError};
 use tonic::Status;
 
@@ -7,12 +11,30 @@ use tonic::Status;
 pub enum ChatError {
     #[error(transparent)]
     GetUserError(#[from] GetUserError),
+    #[error(transparent)]
+    ChatMessagesError(#[from] ChatMessagesError),
+    #[error(transparent)]
+    ChannelsError(#[from] ChannelsError),
+    #[error(transparent)]
+    FollowersError(#[from] FollowersError),
     #[error("invalid argument")]
     InvalidArgument,
     #[error("chat session not exists")]
     SessionNotExists,
     #[error("channel not exists")]
     ChannelNotExists,
+    #[error("channel already exists")]
+    ChannelExists,
+    #[error("permission denied")]
+    PermissionDenied,
+    #[error("user is muted in the channel")]
+    UserMuted,
+    #[error("user is silenced")]
+    UserSilenced,
+    #[error("the target only accepts private messages from friends")]
+    PrivateMessageBlocked,
+    #[error("the duration can not be longer than 1 year")]
+    DurationTooLong,
     #[error(transparent)]
     ConvertError(#[from] ConvertError),
     #[error("bancho state error: {0}")]
@@ -26,3 +48,31 @@ impl TonicError for ChatError {
         Self::TonicError(s.message().to_owned())
     }
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum CommandError {
+    #[error("invalid arguments")]
+    InvalidArguments,
+    #[error("You don't have permission to use this command.")]
+    PermissionDenied,
+    #[error("User not found.")]
+    UserNotExists,
+    #[error("Staff members can not be punished.")]
+    TargetIsStaff,
+    #[error("The duration can not be longer than 1 year.")]
+    DurationTooLong,
+    #[error(transparent)]
+    ChatError(#[from] ChatError),
+    #[error(transparent)]
+    LeaderboardError(#[from] LeaderboardError),
+    #[error(transparent)]
+    FollowersError(#[from] FollowersError),
+    #[error("database err: {0}")]
+    DbErr(String),
+}
+
+impl From<DbErr> for CommandError {
+    fn from(err: DbErr) -> Self {
+        Self::DbErr(err.to_string())
+    }
+}
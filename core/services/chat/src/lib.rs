@@ -8,10 +8,12 @@ extern crate anyhow;
 #[macro_use]
 extern crate serde;
 
+pub mod commands;
 pub mod components;
 pub mod error;
 pub mod services;
 
+pub use commands::*;
 pub use components::*;
 pub use error::*;
 pub use services::*;
@@ -1,9 +1,12 @@
 use crate::{
-    Channel, ChatBackgroundService, ChatSession, DynChatBackgroundService,
-    DynChatService,
+    Channel, ChatBackgroundService, ChatBot, ChatSession,
+    DynChatBackgroundService, DynChatService,
 };
 use async_trait::async_trait;
+use clap::Parser;
 use clap_serde_derive::ClapSerde;
+use domain_chat::ChannelType;
+use pb_chat::{MultiplayerChannelRequest, SpectatorChannelRequest};
 use peace_unique_id::Ulid;
 use std::{
     sync::Arc,
@@ -24,6 +27,7 @@ pub struct Tasks {
     pub user_sessions_recycle: BackgroundTaskManager,
     pub notify_messages_recycle: BackgroundTaskManager,
     pub channel_messages_recycle: BackgroundTaskManager,
+    pub chat_messages_persist: BackgroundTaskManager,
 }
 
 #[derive(Clone)]
@@ -48,10 +52,12 @@ impl ChatBackgroundServiceImpl {
         const LOG_TARGET: &str =
             "chat::background_tasks::user_sessions_recycling";
 
+        let chat_service = self.chat_service.clone();
         let user_sessions = self.chat_service.user_sessions().clone();
         let notify_queue = self.chat_service.notify_queue().clone();
 
         BackgroundTaskFactory::new(Arc::new(move |stop: SignalHandle| {
+            let chat_service = chat_service.clone();
             let user_sessions = user_sessions.clone();
             let notify_queue = notify_queue.clone();
             let cfg = config.clone();
@@ -77,41 +83,117 @@ impl ChatBackgroundServiceImpl {
                         let user_sessions = user_sessions.read().await;
 
                         for session in user_sessions.values() {
-                            if session.is_deactive(current_timestamp, deadline)
+                            // the bot is always online
+                            if session.user_id != ChatBot::USER_ID
+                                && session
+                                    .is_deactive(current_timestamp, deadline)
                             {
                                 lazy_init!(sessions_deactive => sessions_deactive.push(session.clone()), vec![session.clone()]);
                             }
 
                             // update min notify msg id
-                            if let Some(bancho_ext) =
-                                session.extends.bancho_ext.load().as_ref()
+                            for chat_ext in [
+                                session.extends.bancho_ext.load_full(),
+                                session.extends.irc_ext.load_full(),
+                            ]
+                            .into_iter()
+                            .flatten()
                             {
                                 lazy_init!(min_notify_msg_id_in_all_users, Some(val) => {
-                                    let notify_index = *bancho_ext.notify_index.val();
+                                    let notify_index = *chat_ext.notify_index.val();
                                     if val > notify_index {
                                         min_notify_msg_id_in_all_users = Some(notify_index);
                                     }
-                                }, *bancho_ext.notify_index.load().as_ref())
+                                }, *chat_ext.notify_index.load().as_ref())
                             }
                         }
                     }
 
                     let removed_deactive_sessions = match sessions_deactive {
                         Some(sessions_deactive) => {
-                            let mut indexes = user_sessions.write().await;
+                            {
+                                let mut indexes = user_sessions.write().await;
+
+                                for session in sessions_deactive.iter() {
+                                    user_sessions.delete_inner(
+                                        &mut indexes,
+                                        &session.user_id,
+                                        &session.username.load(),
+                                        &session.id,
+                                        session
+                                            .username_unicode
+                                            .load()
+                                            .as_deref()
+                                            .map(|s| s.as_str()),
+                                    );
+                                }
+                            }
 
                             for session in sessions_deactive.iter() {
-                                user_sessions.delete_inner(
-                                    &mut indexes,
-                                    &session.user_id,
-                                    &session.username.load(),
-                                    &session.id,
-                                    session
-                                        .username_unicode
-                                        .load()
-                                        .as_deref()
-                                        .map(|s| s.as_str()),
-                                );
+                                // remove spectator channel hosted by this user
+                                let _ = chat_service
+                                    .remove_spectator_channel(
+                                        SpectatorChannelRequest {
+                                            host_user_id: session.user_id,
+                                        },
+                                    )
+                                    .await;
+
+                                // leave all joined channels
+                                let joined_channels = session
+                                    .extends
+                                    .joined_channels
+                                    .read()
+                                    .await
+                                    .values()
+                                    .filter_map(|j| j.ptr.load().upgrade())
+                                    .collect::<Vec<Arc<Channel>>>();
+
+                                for channel in joined_channels.iter() {
+                                    Channel::remove(session, channel).await;
+
+                                    // multiplayer channels without members
+                                    // belong to matches that no longer exist
+                                    if channel.channel_type
+                                        == ChannelType::Multiplayer
+                                        && channel.users.read().await.is_empty()
+                                    {
+                                        let _ = chat_service
+                                            .remove_multiplayer_channel(
+                                                MultiplayerChannelRequest {
+                                                    match_id: channel.id as i32,
+                                                },
+                                            )
+                                            .await;
+                                    }
+
+                                    // spectator channels are removed with
+                                    // their last spectator, as `spectate_stop`
+                                    // does
+                                    if channel.channel_type
+                                        == ChannelType::Spectaor
+                                    {
+                                        let host_user_id = channel.id as i32;
+                                        let only_host_left = channel
+                                            .users
+                                            .read()
+                                            .await
+                                            .keys()
+                                            .all(|user_id| {
+                                                *user_id == host_user_id
+                                            });
+
+                                        if only_host_left {
+                                            let _ = chat_service
+                                                .remove_spectator_channel(
+                                                    SpectatorChannelRequest {
+                                                        host_user_id,
+                                                    },
+                                                )
+                                                .await;
+                                        }
+                                    }
+                                }
                             }
 
                             sessions_deactive.len()
@@ -295,6 +377,56 @@ impl ChatBackgroundServiceImpl {
             })
         }))
     }
+
+    pub fn chat_messages_persist_factory(
+        &self,
+        config: Arc<LoopBackgroundTaskConfig>,
+    ) -> BackgroundTaskFactory {
+        const LOG_TARGET: &str =
+            "chat::background_tasks::chat_messages_persist";
+
+        let chat_messages = self.chat_service.chat_messages().clone();
+
+        BackgroundTaskFactory::new(Arc::new(move |stop: SignalHandle| {
+            let chat_messages = chat_messages.clone();
+            let cfg = config.clone();
+
+            let task = async move {
+                loop {
+                    tokio::time::sleep(*cfg.loop_interval.load().as_ref())
+                        .await;
+                    let start = Instant::now();
+
+                    match chat_messages.flush().await {
+                        Ok(0) => {},
+                        Ok(saved) => debug!(
+                            target: LOG_TARGET,
+                            "Done in: {:?} ({saved} messages saved)",
+                            start.elapsed()
+                        ),
+                        Err(err) => warn!(
+                            target: LOG_TARGET,
+                            "Failed to save chat messages, err: {err}"
+                        ),
+                    }
+                }
+            };
+
+            info!(
+                target: LOG_TARGET,
+                "Service started! (sleep={:?})",
+                config.loop_interval.val()
+            );
+
+            Box::pin(async move {
+                tokio::select!(
+                    _ = task => {},
+                    _ = stop.wait_signal() => {}
+                );
+                warn!(target: LOG_TARGET, "Service stopped!");
+            })
+        }))
+    }
 }
 
 #[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
@@ -314,6 +446,10 @@ pub struct CliChatBackgroundServiceConfigs {
     #[default(300)]
     #[arg(long, default_value = "300")]
     pub channel_messages_recycle_interval_secs: u64,
+
+    #[default(5)]
+    #[arg(long, default_value = "5")]
+    pub chat_messages_persist_interval_secs: u64,
 }
 
 pub struct UserSessionsRecycleConfig;
@@ -380,11 +516,31 @@ impl ChannelMessagesRecycleConfig {
     }
 }
 
+pub struct ChatMessagesPersistConfig;
+
+impl ChatMessagesPersistConfig {
+    pub fn build(loop_interval: u64) -> Arc<LoopBackgroundTaskConfig> {
+        LoopBackgroundTaskConfig {
+            loop_interval: Atomic::new(Duration::from_secs(loop_interval)),
+            manual_stop: true.into(),
+        }
+        .into()
+    }
+
+    #[inline]
+    pub fn buid_with_cfg(
+        cfg: &CliChatBackgroundServiceConfigs,
+    ) -> Arc<LoopBackgroundTaskConfig> {
+        Self::build(cfg.chat_messages_persist_interval_secs)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ChatBackgroundServiceConfigs {
     pub user_sessions_recycle: Arc<CommonRecycleBackgroundTaskConfig>,
     pub notify_messages_recyce: Arc<LoopBackgroundTaskConfig>,
     pub channel_messages_recyce: Arc<LoopBackgroundTaskConfig>,
+    pub chat_messages_persist: Arc<LoopBackgroundTaskConfig>,
 }
 
 impl ChatBackgroundServiceConfigs {
@@ -393,11 +549,13 @@ impl ChatBackgroundServiceConfigs {
         user_sessions_recycle: Arc<CommonRecycleBackgroundTaskConfig>,
         notify_messages_recyce: Arc<LoopBackgroundTaskConfig>,
         channel_messages_recyce: Arc<LoopBackgroundTaskConfig>,
+        chat_messages_persist: Arc<LoopBackgroundTaskConfig>,
     ) -> Self {
         Self {
             user_sessions_recycle,
             notify_messages_recyce,
             channel_messages_recyce,
+            chat_messages_persist,
         }
     }
 
@@ -412,6 +570,9 @@ impl ChatBackgroundServiceConfigs {
             ),
             channel_messages_recyce:
                 ChannelMessagesRecycleConfig::buid_with_cfg(cfg),
+            chat_messages_persist: ChatMessagesPersistConfig::buid_with_cfg(
+                cfg,
+            ),
         }
     }
 }
@@ -439,5 +600,12 @@ impl ChatBackgroundService for ChatBackgroundServiceImpl {
             ),
             configs.channel_messages_recyce,
         );
+
+        self.tasks.chat_messages_persist.start(
+            self.chat_messages_persist_factory(
+                configs.chat_messages_persist.clone(),
+            ),
+            configs.chat_messages_persist,
+        );
     }
 }
@@ -2,20 +2,43 @@ use crate::*;
 use async_trait::async_trait;
 use bancho_packets::server;
 use chrono::{DateTime, Utc};
-use domain_chat::{ChannelType, Platform};
+use domain_bancho::UserPrivileges;
+use domain_chat::{
+    ChannelType, MultiplayerChannel, ParsedMessage, Platform, SpectatorChannel,
+};
 use infra_packets::{Packet, PacketsQueue};
 use infra_services::{FromRpcClient, IntoService, RpcClient, ServiceSnapshot};
 use infra_users::CreateSessionDto;
 use pb_bancho_state::{BanchoPackets, RawUserQuery, UserQuery};
 use pb_base::ExecSuccess;
 use pb_chat::{
-    chat_rpc_client::ChatRpcClient, ChannelInfo, ChatMessageTarget,
+    chat_rpc_client::ChatRpcClient, ChannelInfo, ChannelMember, ChannelMembers,
+    ChannelQuery, ChatHistory, ChatHistoryMessage, ChatMessageTarget,
+    CreateChannelRequest, DeleteChannelRequest, GetChannelHistoryRequest,
+    GetChannelMembersRequest, GetPrivateConversationRequest,
     GetPublicChannelsRequest, GetPublicChannelsResponse, JoinChannelRequest,
-    LeaveChannelRequest, LoadPublicChannelsRequest, LoginRequest,
-    LogoutRequest, SendMessageRequest, SendMessageResponse,
+    KickUserRequest, LeaveChannelRequest, LoadPublicChannelsRequest,
+    LoginRequest, LogoutRequest, MultiplayerChannelRequest, MuteUserRequest,
+    PullChatPacketsRequest, RawChannelQuery, ReloadChannelsRequest,
+    SendMessageRequest, SendMessageResponse, SetOnlyFriendPmAllowedRequest,
+    SpectatorChannelRequest, UpdateChannelRequest,
+};
+use peace_db::{
+    peace::entity::{
+        channels, chat_messages,
+        sea_orm_active_enums::{
+            ChannelHandleType, ChannelModerationAction,
+            ChannelType as DbChannelType,
+        },
+        users,
+    },
+    ActiveValue::{NotSet, Set, Unchanged},
 };
 use peace_message_queue::ReceivedMessages;
-use peace_repositories::users::DynUsersRepository;
+use peace_repositories::{
+    channels::DynChannelsRepository, chat_messages::DynChatMessagesRepository,
+    followers::DynFollowersRepository, users::DynUsersRepository, GetUserError,
+};
 use peace_snapshot::{
     CreateSnapshot, CreateSnapshotError, LoadSnapshotFrom, SaveSnapshotTo,
     SnapshotConfig, SnapshotExpired, SnapshotTime, SnapshotType,
@@ -30,22 +53,52 @@ use tokio::sync::RwLock;
 use tonic::{transport::Channel as RpcChannel, IntoRequest};
 use tools::atomic::{AtomicValue, U32};
 
+/// Private messages over this are not kept for an offline user.
+pub const MAX_UNDELIVERED_MESSAGES: u64 = 100;
+
+/// Messages per page of the chat history if the limit is not specified.
+pub const DEFAULT_CHAT_HISTORY_LIMIT: u32 = 50;
+
+/// Max messages per page of the chat history.
+pub const MAX_CHAT_HISTORY_LIMIT: u32 = 100;
+
+/// Max length of the public channel names, including the leading `#`.
+pub const MAX_CHANNEL_NAME_LENGTH: usize = 32;
+
 #[derive(Clone)]
 pub struct ChatServiceImpl {
     pub user_sessions: Arc<UserSessions>,
     pub notify_queue: Arc<BanchoMessageQueue>,
     pub channels: Arc<Channels>,
+    pub chat_messages: Arc<ChatMessagesPersister>,
+    pub spam_guard: Arc<ChatSpamGuard>,
     pub users_repository: DynUsersRepository,
+    pub channels_repository: DynChannelsRepository,
+    pub followers_repository: DynFollowersRepository,
+    pub commands: DynChatCommandRegistry,
 }
 
 impl ChatServiceImpl {
     #[inline]
-    pub fn new(users_repository: DynUsersRepository) -> Self {
+    pub fn new(
+        users_repository: DynUsersRepository,
+        chat_messages_repository: DynChatMessagesRepository,
+        channels_repository: DynChannelsRepository,
+        followers_repository: DynFollowersRepository,
+        commands: DynChatCommandRegistry,
+        spam_cfg: &CliChatSpamConfigs,
+    ) -> Self {
         Self {
             user_sessions: UserSessions::default().into(),
             notify_queue: Arc::new(BanchoMessageQueue::default()),
             channels: Channels::default().into(),
+            chat_messages: ChatMessagesPersister::new(chat_messages_repository)
+                .into(),
+            spam_guard: ChatSpamGuard::new(spam_cfg).into(),
             users_repository,
+            channels_repository,
+            followers_repository,
+            commands,
         }
     }
 
@@ -53,6 +106,11 @@ impl ChatServiceImpl {
     pub async fn from_snapshot(
         snapshot: ChatServiceSnapshot,
         users_repository: DynUsersRepository,
+        chat_messages_repository: DynChatMessagesRepository,
+        channels_repository: DynChannelsRepository,
+        followers_repository: DynFollowersRepository,
+        commands: DynChatCommandRegistry,
+        spam_cfg: &CliChatSpamConfigs,
     ) -> Self {
         let mut session_indexes =
             SessionIndexes::with_capacity(snapshot.user_sessions.len());
@@ -81,8 +139,11 @@ impl ChatServiceImpl {
                 name: ch.name.into(),
                 channel_type: ch.channel_type,
                 description: ch.description.into(),
+                privileges: ChannelPrivileges::default().into(),
+                auto_join: ch.auto_join.into(),
                 users,
                 user_count,
+                mutes: ch.mutes.into(),
                 min_msg_index: ch.min_msg_index.into(),
                 message_queue: Arc::new(ch.message_queue.into()),
                 created_at: ch.created_at,
@@ -116,7 +177,20 @@ impl ChatServiceImpl {
         let user_sessions =
             Arc::new(UserSessions::from_indexes(session_indexes));
 
-        Self { user_sessions, notify_queue, channels, users_repository }
+        let chat_messages =
+            ChatMessagesPersister::new(chat_messages_repository).into();
+
+        Self {
+            user_sessions,
+            notify_queue,
+            channels,
+            chat_messages,
+            spam_guard: ChatSpamGuard::new(spam_cfg).into(),
+            users_repository,
+            channels_repository,
+            followers_repository,
+            commands,
+        }
     }
 
     #[inline]
@@ -124,6 +198,26 @@ impl ChatServiceImpl {
         Arc::new(self) as DynChatService
     }
 
+    /// Create the session of the bot, so that users can message it like
+    /// online users.
+    pub async fn with_bot(self) -> Self {
+        if let Err(err) = self
+            .login_inner(
+                ChatBot::USER_ID,
+                ChatBot::USERNAME.to_owned(),
+                None,
+                ChatBot::privileges().bits(),
+                Platform::None,
+                None,
+            )
+            .await
+        {
+            warn!("Failed to create the session of the chat bot: {err}");
+        }
+
+        self
+    }
+
     #[inline]
     pub async fn login_inner(
         &self,
@@ -132,7 +226,21 @@ impl ChatServiceImpl {
         username_unicode: Option<String>,
         privileges: i32,
         platforms: Platform,
+        silence_end: Option<i64>,
     ) -> Result<Arc<ChatSession>, ChatError> {
+        let mut undelivered_ids = Vec::new();
+
+        let prev_session =
+            self.user_sessions.get(&UserQuery::UserId(user_id)).await;
+
+        // the irc client is added into the online session of the user
+        if let Some(session) = &prev_session {
+            if !platforms.contains(Platform::Bancho) {
+                self.add_platforms(session, platforms).await;
+                return Ok(session.clone());
+            }
+        }
+
         let bancho_chat_ext = if platforms.contains(Platform::Bancho) {
             // prepare bancho packets
             let mut channel_packets = VecDeque::new();
@@ -155,12 +263,36 @@ impl ChatServiceImpl {
 
             channel_packets.push_back(server::ChannelInfoEnd::pack().into());
 
+            let (packets, message_ids) =
+                self.undelivered_message_packets(user_id, &username).await;
+            channel_packets.extend(packets);
+            undelivered_ids = message_ids;
+
             Some(PacketsQueue::new(channel_packets).into())
         } else {
             None
         };
 
+        // irc clients receive the messages as bancho packets too
+        let irc_chat_ext = if platforms.contains(Platform::Irc) {
+            let (packets, message_ids) =
+                self.undelivered_message_packets(user_id, &username).await;
+            undelivered_ids.extend(message_ids);
+
+            Some(Arc::new(PacketsQueue::new(packets.into()).into()))
+        } else {
+            // keep the irc client connected to the replaced session
+            prev_session.and_then(|prev| prev.extends.irc_ext.load_full())
+        };
+
+        let mut platforms = platforms;
+        if irc_chat_ext.is_some() {
+            platforms.add(&Platform::Irc);
+        }
+
         let extends = ChatSessionExtend::new(platforms, bancho_chat_ext, None);
+        extends.irc_ext.set(irc_chat_ext);
+        extends.silence_end.set(silence_end.unwrap_or_default());
 
         let session = ChatSession::new(CreateSessionDto {
             user_id,
@@ -172,9 +304,210 @@ impl ChatServiceImpl {
 
         let session = self.user_sessions.create(session.into()).await;
 
+        // the messages are queued to the session now
+        if let Err(err) = self
+            .chat_messages
+            .repository
+            .mark_messages_delivered(undelivered_ids)
+            .await
+        {
+            warn!(
+                "Failed to mark the messages of user {user_id} delivered, \
+                err: {err}"
+            );
+        }
+
+        if session.extends.has_chat_ext() {
+            self.auto_join_channels(&session).await;
+        }
+
         Ok(session)
     }
 
+    /// Add the platforms into the online session, the irc client gets its
+    /// own delivery queue and skips the messages received by the session.
+    pub async fn add_platforms(
+        &self,
+        session: &Arc<ChatSession>,
+        platforms: Platform,
+    ) {
+        if platforms.contains(Platform::Irc) {
+            let irc_ext = BanchoChatExt {
+                notify_index: session
+                    .extends
+                    .bancho_ext
+                    .load()
+                    .as_ref()
+                    .map(|ext| *ext.notify_index.load().as_ref())
+                    .unwrap_or_default()
+                    .into(),
+                ..Default::default()
+            };
+
+            for joined_channel in
+                session.extends.joined_channels.read().await.values()
+            {
+                joined_channel
+                    .irc_message_index
+                    .set(joined_channel.message_index.val());
+            }
+
+            session.extends.irc_ext.set(Some(irc_ext.into()));
+        }
+
+        let mut curr_platforms = *session.extends.platforms.load().as_ref();
+        curr_platforms.add(&platforms);
+        session.extends.platforms.set(curr_platforms.into());
+        session.update_active();
+    }
+
+    /// Get the user from the database, the session id is not supported.
+    pub async fn get_user(
+        &self,
+        query: &UserQuery,
+    ) -> Result<users::Model, ChatError> {
+        Ok(match query {
+            UserQuery::SessionId(_) => return Err(ChatError::InvalidArgument),
+            UserQuery::UserId(user_id) => {
+                self.users_repository.get_user_by_id(*user_id).await
+            },
+            UserQuery::Username(username) => {
+                self.users_repository
+                    .get_user_by_username(username.as_str())
+                    .await
+            },
+            UserQuery::UsernameUnicode(username_unicode) => {
+                self.users_repository
+                    .get_user_by_username_unicode(username_unicode.as_str())
+                    .await
+            },
+        }?)
+    }
+
+    /// The `BANCHO_SEND_MESSAGE` packets of the private messages the user
+    /// received while offline, in the order they were sent, with the ids of
+    /// the messages to mark delivered once the packets are queued.
+    pub async fn undelivered_message_packets(
+        &self,
+        user_id: i32,
+        username: &str,
+    ) -> (Vec<Packet>, Vec<i64>) {
+        let messages = match self
+            .chat_messages
+            .repository
+            .get_undelivered_messages(user_id)
+            .await
+        {
+            Ok(messages) => messages,
+            Err(err) => {
+                warn!(
+                    "Failed to get undelivered messages of user {user_id}, \
+                    err: {err}"
+                );
+                return Default::default();
+            },
+        };
+
+        messages
+            .into_iter()
+            .map(|(message, sender)| {
+                let packet = server::SendMessage::pack(
+                    sender.map(|u| u.name).unwrap_or_default().into(),
+                    message.content_string.into(),
+                    username.into(),
+                    message.sender_id,
+                )
+                .into();
+
+                (packet, message.id)
+            })
+            .unzip()
+    }
+
+    /// Build a page of the chat history, the messages should be in the order
+    /// they were sent. The pending messages are newer than the saved ones,
+    /// only the latest `limit` messages are kept.
+    pub fn chat_history(
+        saved: Vec<(chat_messages::Model, Option<users::Model>)>,
+        pending: Vec<(chat_messages::Model, String)>,
+        limit: u32,
+    ) -> ChatHistory {
+        let latest_saved_id = saved.last().map(|(msg, _)| msg.id);
+
+        let mut messages = saved
+            .into_iter()
+            .map(|(msg, sender)| (msg, sender.map(|u| u.name)))
+            .chain(pending.into_iter().map(|(msg, name)| (msg, Some(name))))
+            .collect::<Vec<_>>();
+
+        let skip = messages.len().saturating_sub(limit as usize);
+        messages.drain(..skip);
+
+        // the pending messages have no id, continue from the latest saved one
+        let next_before_id = (messages.len() >= limit as usize)
+            .then(|| {
+                messages.first().and_then(|(msg, _)| match msg.id {
+                    0 => latest_saved_id.map(|id| id + 1),
+                    id => Some(id),
+                })
+            })
+            .flatten();
+
+        let messages = messages
+            .into_iter()
+            .map(|(message, sender_name)| {
+                let parsed = ParsedMessage::parse(&message.content_string);
+
+                ChatHistoryMessage {
+                    id: message.id,
+                    sender_id: message.sender_id,
+                    sender_name: sender_name.unwrap_or_default(),
+                    channel_id: message.channel_id.map(|id| id as u64),
+                    target_user_id: message.target_user_id,
+                    content: message.content_string,
+                    is_action: message.is_action,
+                    timestamp: message.timestamp.timestamp_millis(),
+                    content_html: message
+                        .content_html
+                        .unwrap_or_else(|| parsed.to_html()),
+                    tokens: chat_message_tokens(&parsed),
+                }
+            })
+            .collect();
+
+        ChatHistory { messages, next_before_id }
+    }
+
+    /// The messages not saved yet with their sender names, they are newer
+    /// than any saved message so there are none before a saved one.
+    pub async fn pending_history_messages(
+        &self,
+        before_id: Option<i64>,
+        filter: impl Fn(&chat_messages::Model) -> bool,
+    ) -> Vec<(chat_messages::Model, String)> {
+        if before_id.is_some() {
+            return Vec::new();
+        }
+
+        let mut messages = Vec::new();
+        for message in self.chat_messages.pending_messages() {
+            if !filter(&message) {
+                continue;
+            }
+
+            let sender_name = self
+                .user_sessions
+                .get(&UserQuery::UserId(message.sender_id))
+                .await
+                .map(|session| session.username.load().to_string())
+                .unwrap_or_default();
+
+            messages.push((message, sender_name));
+        }
+
+        messages
+    }
+
     pub async fn get_session(
         &self,
         query: &UserQuery,
@@ -187,26 +520,7 @@ impl ChatServiceImpl {
             },
             None => {
                 if let Some(platforms) = create_if_not_exists {
-                    let user = match query {
-                        UserQuery::SessionId(_) => {
-                            return Err(ChatError::InvalidArgument)
-                        },
-                        UserQuery::UserId(user_id) => {
-                            self.users_repository.get_user_by_id(*user_id).await
-                        },
-                        UserQuery::Username(username) => {
-                            self.users_repository
-                                .get_user_by_username(username.as_str())
-                                .await
-                        },
-                        UserQuery::UsernameUnicode(username_unicode) => {
-                            self.users_repository
-                                .get_user_by_username_unicode(
-                                    username_unicode.as_str(),
-                                )
-                                .await
-                        },
-                    }?;
+                    let user = self.get_user(query).await?;
 
                     self.login_inner(
                         user.id,
@@ -214,6 +528,7 @@ impl ChatServiceImpl {
                         user.name_unicode,
                         1, // todo
                         platforms,
+                        user.silence_end.map(|end| end.timestamp()),
                     )
                     .await
                 } else {
@@ -224,12 +539,77 @@ impl ChatServiceImpl {
     }
 }
 
+#[inline]
+fn channel_info(ch: &Channel) -> ChannelInfo {
+    ChannelInfo {
+        id: ch.id,
+        name: ch.name.to_string(),
+        channel_type: ch.channel_type as i32,
+        description: ch.description.load().as_deref().map(|s| s.to_string()),
+        online_users: ch.user_count.val(),
+        users: None,
+    }
+}
+
+/// Public channels without a name can not be joined, they are skipped.
+fn public_channel(model: channels::Model) -> Option<Channel> {
+    let channel = Channel::new(
+        model.id as u64,
+        model.name?,
+        ChannelType::Public,
+        model.description,
+        None,
+    );
+    channel.auto_join.set(model.auto_join);
+
+    Some(channel)
+}
+
+/// Public channel names start with `#`, the names of the instance channels
+/// are reserved.
+fn validate_channel_name(name: &str) -> Result<(), ChatError> {
+    let valid = name.len() > 1
+        && name.len() <= MAX_CHANNEL_NAME_LENGTH
+        && name.starts_with('#')
+        && !name.contains(|c: char| {
+            c.is_whitespace() || c.is_control() || c == ','
+        })
+        && !name.starts_with("#spec_")
+        && !name.starts_with("#mp_")
+        && name != SpectatorChannel::BANCHO_NAME
+        && name != MultiplayerChannel::BANCHO_NAME;
+
+    if !valid {
+        return Err(ChatError::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// The notice to the sender of a private message to an offline user.
+#[inline]
+fn offline_message_notice(target: &str, saved: bool) -> String {
+    if saved {
+        format!(
+            "{target} is offline, your message will be delivered when they \
+            come online."
+        )
+    } else {
+        format!("{target} is offline and can not receive more messages now.")
+    }
+}
+
 pub struct ChatServiceSnapshotLoader;
 
 impl ChatServiceSnapshotLoader {
     pub async fn load(
         cfg: &CliChatServiceSnapshotConfigs,
+        spam_cfg: &CliChatSpamConfigs,
         users_repository: DynUsersRepository,
+        chat_messages_repository: DynChatMessagesRepository,
+        channels_repository: DynChannelsRepository,
+        followers_repository: DynFollowersRepository,
+        commands: DynChatCommandRegistry,
     ) -> ChatServiceImpl {
         if cfg.should_load_snapshot() {
             let snapshot_path = Path::new(cfg.snapshot_path());
@@ -250,7 +630,14 @@ impl ChatServiceSnapshotLoader {
                             return ChatServiceImpl::from_snapshot(
                                 snapshot,
                                 users_repository,
+                                chat_messages_repository,
+                                channels_repository,
+                                followers_repository,
+                                commands,
+                                spam_cfg,
                             )
+                            .await
+                            .with_bot()
                             .await;
                         }
 
@@ -268,7 +655,16 @@ impl ChatServiceSnapshotLoader {
             }
         }
 
-        ChatServiceImpl::new(users_repository)
+        ChatServiceImpl::new(
+            users_repository,
+            chat_messages_repository,
+            channels_repository,
+            followers_repository,
+            commands,
+            spam_cfg,
+        )
+        .with_bot()
+        .await
     }
 }
 
@@ -319,6 +715,13 @@ impl ChannelStore for ChatServiceImpl {
     }
 }
 
+impl ChatMessagesStore for ChatServiceImpl {
+    #[inline]
+    fn chat_messages(&self) -> &Arc<ChatMessagesPersister> {
+        &self.chat_messages
+    }
+}
+
 #[async_trait]
 impl ServiceSnapshot for ChatServiceImpl {
     async fn save_service_snapshot(
@@ -340,163 +743,795 @@ impl ServiceSnapshot for ChatServiceImpl {
     }
 }
 
-#[async_trait]
-impl ChatService for ChatServiceImpl {
-    async fn login(
-        &self,
-        request: LoginRequest,
-    ) -> Result<ExecSuccess, ChatError> {
-        const LOG_TARGET: &str = "chat::login";
-
-        let LoginRequest {
-            user_id,
-            username,
-            username_unicode,
-            privileges,
-            platforms,
-        } = request;
+impl ChatServiceImpl {
+    /// Load the privileges required by the handles of the existing channels
+    /// from the database.
+    pub async fn load_channel_privileges(&self) -> Result<(), ChatError> {
+        const LOG_TARGET: &str = "chat::channel::load_channel_privileges";
 
-        let platforms = Platform::from(platforms);
+        let mut channel_privileges = HashMap::<u64, ChannelPrivileges>::new();
 
-        let session = self
-            .login_inner(
-                user_id,
-                username,
-                username_unicode,
-                privileges,
-                platforms,
-            )
-            .await?;
+        for (row, privilege) in
+            self.channels_repository.get_channel_privileges().await?
+        {
+            // unknown privileges can not be checked, keep the handle for
+            // staff only
+            let required = privilege
+                .as_ref()
+                .and_then(|p| UserPrivileges::from_name(p.name.as_str()))
+                .unwrap_or_else(|| {
+                    warn!(
+                        target: LOG_TARGET,
+                        "Unknown privilege {:?} required by {:?} of channel {}",
+                        privilege.as_ref().map(|p| p.name.as_str()),
+                        row.handle,
+                        row.channel_id
+                    );
+                    UserPrivileges::Moderator
+                });
+
+            channel_privileges
+                .entry(row.channel_id as u64)
+                .or_default()
+                .set_required(&row.handle, required);
+        }
 
-        info!(
-            target: LOG_TARGET,
-            "User {}({}) logged in",
-            session.username.load(),
-            session.user_id,
-        );
+        for channel in self.channels.read().await.values() {
+            channel.privileges.set(
+                channel_privileges
+                    .get(&channel.id)
+                    .copied()
+                    .unwrap_or_default()
+                    .into(),
+            );
+        }
 
-        Ok(ExecSuccess::default())
+        Ok(())
     }
 
-    async fn logout(
+    /// Sessions of the channel members, sessions loaded from the snapshot
+    /// are not linked to the channel and are looked up by the user id.
+    pub async fn channel_sessions(
         &self,
-        query: UserQuery,
-        remove_platforms: Platform,
-    ) -> Result<ExecSuccess, ChatError> {
-        const LOG_TARGET: &str = "chat::logout";
+        channel: &Channel,
+    ) -> Vec<Arc<ChatSession>> {
+        let users = channel
+            .users
+            .read()
+            .await
+            .iter()
+            .map(|(user_id, session)| {
+                (*user_id, session.as_ref().and_then(|s| s.upgrade()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut sessions = Vec::with_capacity(users.len());
+
+        for (user_id, session) in users {
+            let session = match session {
+                Some(session) => Some(session),
+                None => {
+                    self.user_sessions.get(&UserQuery::UserId(user_id)).await
+                },
+            };
 
-        let session = match self.user_sessions.get(&query).await {
-            Some(session) => session,
-            None => return Ok(ExecSuccess::default()),
-        };
+            if let Some(session) = session {
+                sessions.push(session);
+            }
+        }
 
-        let curr_platforms = session.extends.platforms.val();
+        sessions
+    }
 
-        // Logout from bancho
-        if curr_platforms.contains(Platform::Bancho)
-            && remove_platforms.contains(Platform::Bancho)
-        {
-            session.extends.bancho_ext.set(None);
+    /// Join the session into all of the auto join public channels.
+    pub async fn auto_join_channels(&self, session: &Arc<ChatSession>) {
+        let channels = self
+            .channels
+            .read()
+            .await
+            .public_channels
+            .values()
+            .filter(|ch| ch.auto_join.val())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for channel in channels {
+            Channel::join(session, &channel).await;
         }
+    }
 
-        // TODO: part from other platforms
-        if curr_platforms.contains(Platform::Lazer)
-            && remove_platforms.contains(Platform::Lazer)
-        {
-            todo!("Logout from Lazer")
-        }
+    /// Join the sessions connected with bancho or irc clients into the
+    /// channel.
+    pub async fn join_online_sessions(&self, channel: &Arc<Channel>) {
+        let sessions = self
+            .user_sessions
+            .read()
+            .await
+            .values()
+            .filter(|session| session.extends.has_chat_ext())
+            .cloned()
+            .collect::<Vec<_>>();
 
-        if curr_platforms.contains(Platform::Web)
-            && remove_platforms.contains(Platform::Web)
-        {
-            todo!("Logout from Web")
+        for session in sessions {
+            Channel::join(&session, channel).await;
         }
+    }
 
-        // do remove platforms
-        let platforms = curr_platforms.and(remove_platforms.not());
+    /// Remove the channel from the indexes and kick all of its members.
+    pub async fn close_channel(&self, channel: &Arc<Channel>) {
+        self.channels
+            .remove_channel(&ChannelQuery::ChannelId(channel.id))
+            .await;
 
-        if platforms.is_none() {
-            // leave all channels
-            for channel in session.extends.joined_channels.read().await.values()
-            {
-                if let Some(channel) = channel.ptr.load().upgrade() {
-                    // remove user from channel
-                    Channel::remove(&session, &channel).await;
+        for session in self.channel_sessions(channel).await {
+            Channel::remove(&session, channel).await;
+        }
+    }
 
-                    // update channel
-                    channel.updated_at.set(Utc::now().into());
-                }
-            }
+    /// Bancho clients know the channels by their names, the members are
+    /// moved to the renamed channel.
+    pub async fn rename_channel(&self, channel: &Arc<Channel>, name: String) {
+        if channel.name.load().as_str() == name {
+            return;
+        }
 
-            // delete user session
-            self.user_sessions.delete(&query).await;
+        let members = self.channel_sessions(channel).await;
+        let kick_packets = Packet::from(channel.kick_packets());
 
-            info!(
-                target: LOG_TARGET,
-                "User {}({}) logged out",
-                session.username.load(),
-                session.user_id,
+        let () = {
+            let mut indexes = self.channels.write().await;
+            self.channels.remove_channel_inner(
+                &mut indexes,
+                &channel.id,
+                &channel.name.load(),
+            );
+            channel.name.set(name.into());
+            self.channels.create_channel_inner(
+                &mut indexes,
+                channel.clone(),
+                false,
             );
+        };
 
-            return Ok(ExecSuccess::default());
+        let join_packets = Packet::from(channel.join_packets());
+        for session in members {
+            session.extends.push_packet(kick_packets.clone()).await;
+            session.extends.push_packet(join_packets.clone()).await;
         }
+    }
 
-        session.extends.platforms.set(platforms.into());
+    /// Get the public channel, other channels are managed by their owners.
+    pub async fn get_public_channel(
+        &self,
+        channel_query: Option<RawChannelQuery>,
+    ) -> Result<Arc<Channel>, ChatError> {
+        let channel_query = channel_query
+            .ok_or(ChatError::InvalidArgument)?
+            .into_channel_query()?;
 
-        info!(
-            target: LOG_TARGET,
-            "User {}({}) leaved from platforms: {:?} ",
-            session.username.load(),
-            session.user_id,
-            remove_platforms.platforms_array()
-        );
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
 
-        Ok(ExecSuccess::default())
+        if channel.channel_type != ChannelType::Public {
+            return Err(ChatError::InvalidArgument);
+        }
+
+        Ok(channel)
     }
 
-    async fn send_message(
+    /// Check the moderator's privileges of the channel handle, returns the
+    /// channel and the sessions of the moderator and the target user.
+    pub async fn moderation_context(
         &self,
-        request: SendMessageRequest,
-    ) -> Result<SendMessageResponse, ChatError> {
-        const LOG_TARGET: &str = "chat::send_message";
+        handle: ChannelHandleType,
+        channel_query: Option<RawChannelQuery>,
+        moderator: Option<RawUserQuery>,
+        target: Option<RawUserQuery>,
+    ) -> Result<(Arc<Channel>, Arc<ChatSession>, Arc<ChatSession>), ChatError>
+    {
+        let channel_query = channel_query
+            .ok_or(ChatError::InvalidArgument)?
+            .into_channel_query()?;
 
-        let SendMessageRequest { sender, message, target } = request;
+        let moderator_query =
+            moderator.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
 
-        let sender_query =
-            sender.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
+        let target_query =
+            target.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
 
-        let target =
-            target.ok_or(ChatError::InvalidArgument)?.into_message_target()?;
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
 
-        let sender =
-            self.get_session(&sender_query, Some(Platform::all())).await?;
+        let moderator = self.get_session(&moderator_query, None).await?;
 
-        match target {
-            ChatMessageTarget::Channel(channel_query) => {
-                // get channel
-                let channel =
-                    match self.channels.get_channel(&channel_query).await {
-                        Some(channel) => channel,
-                        None => {
-                            todo!("channel not exists")
-                        },
-                    };
+        if !channel.is_allowed(
+            &handle,
+            UserPrivileges::from(moderator.privileges.val()),
+        ) {
+            return Err(ChatError::PermissionDenied);
+        }
 
-                let message_packet = server::SendMessage::pack(
-                    sender.username.load().as_ref().into(),
-                    Cow::Borrowed(message.as_ref()),
-                    channel.name.load().as_ref().into(),
-                    sender.user_id,
-                )
-                .into();
+        let target = self.get_session(&target_query, None).await?;
 
-                // push msg into channel packets queue
+        Ok((channel, moderator, target))
+    }
+
+    /// Write the moderation action into the audit trail, the action already
+    /// taken is not reverted if it fails.
+    pub async fn save_moderation_log(
+        &self,
+        channel: &Channel,
+        moderator: &ChatSession,
+        target: &ChatSession,
+        action: ChannelModerationAction,
+        reason: Option<String>,
+        mute_end: Option<DateTime<Utc>>,
+    ) {
+        if let Err(err) = self
+            .channels_repository
+            .save_moderation_log(new_moderation_log(
+                channel.id,
+                moderator.user_id,
+                target.user_id,
+                action,
+                reason,
+                mute_end,
+            ))
+            .await
+        {
+            warn!(
+                "Failed to save moderation log of channel {}, err: {err}",
+                channel.id
+            );
+        }
+    }
+
+    /// Silence the user for `secs`, the silence is saved into the database
+    /// and all users are notified to clear the user's messages. The silence
+    /// can not be longer than [`MAX_PUNISHMENT_SECS`].
+    pub async fn silence_user(
+        &self,
+        user_id: i32,
+        secs: u64,
+        reason: &str,
+    ) -> Result<(), ChatError> {
+        const LOG_TARGET: &str = "chat::silence_user";
+
+        let silence_end =
+            punishment_end(secs).ok_or(ChatError::DurationTooLong)?;
+
+        if let Err(err) = self
+            .users_repository
+            .update_silence_end(user_id, Some(silence_end.into()))
+            .await
+        {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to save silence of user {user_id}, err: {err}",
+            );
+        }
+
+        if let Some(session) =
+            self.user_sessions.get(&UserQuery::UserId(user_id)).await
+        {
+            session.extends.silence_end.set(silence_end.timestamp());
+
+            session
+                .extends
+                .push_packet(
+                    server::SilenceEnd::pack(
+                        i32::try_from(secs).unwrap_or(i32::MAX),
+                    )
+                    .into(),
+                )
+                .await;
+        }
+
+        self.notify_queue
+            .push_message(
+                Packet::Ptr(server::UserSilenced::pack(user_id).into()),
+                None,
+            )
+            .await;
+
+        info!(
+            target: LOG_TARGET,
+            "User {user_id} silenced for {secs}s, reason: {reason}",
+        );
+
+        Ok(())
+    }
+
+    /// Run the bot command in the message, the reply of the bot is sent to
+    /// the channel, or to the sender if the command is sent to the bot.
+    pub async fn handle_command(
+        &self,
+        sender: &ChatSession,
+        channel: Option<&Channel>,
+        message: &str,
+    ) {
+        const LOG_TARGET: &str = "chat::handle_command";
+
+        let prefix = self.commands.prefix();
+
+        let Some((name, args)) = parse_command(prefix, message) else {
+            return;
+        };
+
+        let Some(command) = self.commands.get(&name) else {
+            if channel.is_none() {
+                self.bot_reply(
+                    sender,
+                    None,
+                    &format!("Unknown command, try {prefix}help."),
+                )
+                .await;
+            }
+            return;
+        };
+
+        let ctx = CommandContext { chat_service: self, sender, channel };
+
+        let result = if !ctx
+            .sender_privileges()
+            .bancho_privileges()
+            .intersects(command.required_privileges())
+        {
+            Err(CommandError::PermissionDenied)
+        } else if args.len() < command.min_args() {
+            Err(CommandError::InvalidArguments)
+        } else {
+            command.execute(&ctx, &args).await
+        };
+
+        let reply = match result {
+            Ok(reply) => reply,
+            Err(CommandError::InvalidArguments) => {
+                format!("Usage: {}", command_usage(prefix, command.as_ref()))
+            },
+            Err(err) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Command \"{message}\" of user {}({}) failed: {err}",
+                    sender.username.load(),
+                    sender.user_id,
+                );
+                err.to_string()
+            },
+        };
+
+        self.bot_reply(sender, channel, &reply).await;
+    }
+
+    /// Send the message from the bot to the channel, or to the user if the
+    /// channel is `None`.
+    pub async fn bot_reply(
+        &self,
+        user: &ChatSession,
+        channel: Option<&Channel>,
+        message: &str,
+    ) {
+        match channel {
+            Some(channel) => {
+                let packet = server::SendMessage::pack(
+                    ChatBot::USERNAME.into(),
+                    message.into(),
+                    channel.bancho_name().into(),
+                    ChatBot::USER_ID,
+                )
+                .into();
+
+                channel
+                    .message_queue
+                    .write()
+                    .await
+                    .push_message(Packet::Ptr(packet), None);
+
+                self.chat_messages.push(
+                    ChatBot::USER_ID,
+                    Some(channel.id as i64),
+                    None,
+                    message,
+                );
+            },
+            None => {
+                user.extends
+                    .push_packet(
+                        server::SendMessage::pack(
+                            ChatBot::USERNAME.into(),
+                            message.into(),
+                            user.username.load().as_ref().into(),
+                            ChatBot::USER_ID,
+                        )
+                        .into(),
+                    )
+                    .await;
+
+                self.chat_messages.push(
+                    ChatBot::USER_ID,
+                    None,
+                    Some(user.user_id),
+                    message,
+                );
+            },
+        }
+    }
+
+    /// Set the user's setting and save it, so it is still checked when the
+    /// user is offline.
+    pub async fn set_only_friend_pm_allowed_inner(
+        &self,
+        session: &ChatSession,
+        only_friend_pm_allowed: bool,
+    ) {
+        const LOG_TARGET: &str = "chat::set_only_friend_pm_allowed";
+
+        session.extends.only_friend_pm_allowed.set(only_friend_pm_allowed);
+
+        if let Err(err) = self
+            .users_repository
+            .update_only_friend_pm_allowed(
+                session.user_id,
+                only_friend_pm_allowed,
+            )
+            .await
+        {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to save the pm setting of user {}, err: {err}",
+                session.user_id
+            );
+        }
+    }
+
+    /// Returns `false` if the target only allows friends to pm and the sender
+    /// is not one of them, the sender is told with `BANCHO_USER_DM_BLOCKED`.
+    pub async fn accepts_private_message(
+        &self,
+        sender: &ChatSession,
+        target_id: i32,
+        target_name: &str,
+        only_friend_pm_allowed: bool,
+    ) -> Result<bool, ChatError> {
+        if !only_friend_pm_allowed
+            || UserPrivileges::from(sender.privileges.val()).is_staff()
+            || self
+                .followers_repository
+                .is_friend(target_id, sender.user_id)
+                .await?
+        {
+            return Ok(true);
+        }
+
+        sender
+            .extends
+            .push_packet(server::UserDmBlocked::pack(target_name.into()).into())
+            .await;
+
+        Ok(false)
+    }
+
+    /// Tell the sender the target of the private message is silenced.
+    pub async fn notify_target_silenced(
+        &self,
+        sender: &ChatSession,
+        target: &str,
+    ) {
+        sender
+            .extends
+            .push_packet(server::TargetSilenced::pack(target.into()).into())
+            .await;
+    }
+
+    /// Get the spectator or multiplayer channel, create it if not exists.
+    pub async fn get_or_create_instance_channel(
+        &self,
+        channel_id: u64,
+        channel_name: String,
+        channel_type: ChannelType,
+        description: String,
+    ) -> Arc<Channel> {
+        const LOG_TARGET: &str = "chat::channel::create_instance_channel";
+
+        let mut indexes = self.channels.write().await;
+        if let Some(channel) = self
+            .channels
+            .get_channel_inner(&indexes, &ChannelQuery::ChannelId(channel_id))
+        {
+            return channel;
+        }
+
+        let channel = Arc::new(Channel::new(
+            channel_id,
+            channel_name,
+            channel_type,
+            Some(description),
+            None,
+        ));
+
+        self.channels.create_channel_inner(
+            &mut indexes,
+            channel.clone(),
+            false,
+        );
+
+        info!(
+            target: LOG_TARGET,
+            "{:?} channel {}({}) created",
+            channel_type,
+            channel.name.load(),
+            channel.id
+        );
+
+        channel
+    }
+
+    /// Remove the spectator or multiplayer channel and kick all its members.
+    pub async fn remove_instance_channel(
+        &self,
+        channel_id: u64,
+    ) -> Option<Arc<Channel>> {
+        const LOG_TARGET: &str = "chat::channel::remove_instance_channel";
+
+        let channel = self
+            .channels
+            .remove_channel(&ChannelQuery::ChannelId(channel_id))
+            .await?;
+
+        let members = channel
+            .users
+            .read()
+            .await
+            .values()
+            .filter_map(|ptr| ptr.as_ref().and_then(|ptr| ptr.upgrade()))
+            .collect::<Vec<Arc<ChatSession>>>();
+
+        // kick all members
+        for session in members.iter() {
+            Channel::remove(session, &channel).await;
+        }
+
+        info!(
+            target: LOG_TARGET,
+            "{:?} channel {}({}) removed",
+            channel.channel_type,
+            channel.name.load(),
+            channel.id
+        );
+
+        Some(channel)
+    }
+}
+
+#[async_trait]
+impl ChatService for ChatServiceImpl {
+    async fn login(
+        &self,
+        request: LoginRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::login";
+
+        let LoginRequest {
+            user_id,
+            username,
+            username_unicode,
+            privileges,
+            platforms,
+            silence_end,
+            only_friend_pm_allowed,
+        } = request;
+
+        let platforms = Platform::from(platforms);
+
+        let session = self
+            .login_inner(
+                user_id,
+                username,
+                username_unicode,
+                privileges,
+                platforms,
+                silence_end,
+            )
+            .await?;
+
+        // only the osu! client sends the setting
+        if platforms.contains(Platform::Bancho) {
+            self.set_only_friend_pm_allowed_inner(
+                &session,
+                only_friend_pm_allowed,
+            )
+            .await;
+        }
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) logged in",
+            session.username.load(),
+            session.user_id,
+        );
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn logout(
+        &self,
+        query: UserQuery,
+        remove_platforms: Platform,
+    ) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::logout";
+
+        let session = match self.user_sessions.get(&query).await {
+            Some(session) => session,
+            None => return Ok(ExecSuccess::default()),
+        };
+
+        let curr_platforms = session.extends.platforms.val();
+
+        // Logout from bancho
+        if curr_platforms.contains(Platform::Bancho)
+            && remove_platforms.contains(Platform::Bancho)
+        {
+            session.extends.bancho_ext.set(None);
+        }
+
+        // Logout from irc, the bancho client keeps its queue
+        if curr_platforms.contains(Platform::Irc)
+            && remove_platforms.contains(Platform::Irc)
+        {
+            session.extends.irc_ext.set(None);
+        }
+
+        // TODO: part from other platforms
+        if curr_platforms.contains(Platform::Lazer)
+            && remove_platforms.contains(Platform::Lazer)
+        {
+            todo!("Logout from Lazer")
+        }
+
+        if curr_platforms.contains(Platform::Web)
+            && remove_platforms.contains(Platform::Web)
+        {
+            todo!("Logout from Web")
+        }
+
+        // do remove platforms
+        let platforms = curr_platforms.and(remove_platforms.not());
+
+        if platforms.is_none() {
+            // leave all channels
+            for channel in session.extends.joined_channels.read().await.values()
+            {
+                if let Some(channel) = channel.ptr.load().upgrade() {
+                    // remove user from channel
+                    Channel::remove(&session, &channel).await;
+
+                    // update channel
+                    channel.updated_at.set(Utc::now().into());
+                }
+            }
+
+            // delete user session
+            self.user_sessions.delete(&query).await;
+
+            self.spam_guard.remove(session.user_id);
+
+            info!(
+                target: LOG_TARGET,
+                "User {}({}) logged out",
+                session.username.load(),
+                session.user_id,
+            );
+
+            return Ok(ExecSuccess::default());
+        }
+
+        session.extends.platforms.set(platforms.into());
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) leaved from platforms: {:?} ",
+            session.username.load(),
+            session.user_id,
+            remove_platforms.platforms_array()
+        );
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn send_message(
+        &self,
+        request: SendMessageRequest,
+    ) -> Result<SendMessageResponse, ChatError> {
+        const LOG_TARGET: &str = "chat::send_message";
+
+        let SendMessageRequest { sender, message, target } = request;
+
+        // `/me` from the web and lazer clients is sent as the stable action
+        let parsed = ParsedMessage::parse(&message);
+        let message = parsed.bancho_content();
+        let response = SendMessageResponse {
+            is_action: parsed.is_action,
+            content_html: parsed.to_html(),
+            tokens: chat_message_tokens(&parsed),
+            ..Default::default()
+        };
+
+        let sender_query =
+            sender.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
+
+        let target =
+            target.ok_or(ChatError::InvalidArgument)?.into_message_target()?;
+
+        let sender =
+            self.get_session(&sender_query, Some(Platform::all_bits())).await?;
+
+        if sender.extends.is_silenced() {
+            return Err(ChatError::UserSilenced);
+        }
+
+        if !UserPrivileges::from(sender.privileges.val()).is_staff() {
+            if let Err(violation) =
+                self.spam_guard.check(sender.user_id, &message)
+            {
+                self.silence_user(
+                    sender.user_id,
+                    self.spam_guard.silence_secs,
+                    &violation.to_string(),
+                )
+                .await?;
+
+                return Err(ChatError::UserSilenced);
+            }
+        }
+
+        match target {
+            ChatMessageTarget::Channel(channel_query) => {
+                // get channel
+                let channel = self
+                    .channels
+                    .get_channel(&channel_query)
+                    .await
+                    .ok_or(ChatError::ChannelNotExists)?;
+
+                if !channel.is_allowed(
+                    &ChannelHandleType::SendMessage,
+                    UserPrivileges::from(sender.privileges.val()),
+                ) {
+                    return Err(ChatError::PermissionDenied);
+                }
+
+                if channel.mute_end(sender.user_id).await.is_some() {
+                    return Err(ChatError::UserMuted);
+                }
+
+                let message_packet = server::SendMessage::pack(
+                    sender.username.load().as_ref().into(),
+                    Cow::Borrowed(message.as_ref()),
+                    channel.bancho_name().into(),
+                    sender.user_id,
+                )
+                .into();
+
+                // push msg into channel packets queue
                 channel.message_queue.write().await.push_message_excludes(
                     Packet::Ptr(message_packet),
                     [sender.user_id],
                     None,
                 );
 
+                self.chat_messages.push(
+                    sender.user_id,
+                    Some(channel.id as i64),
+                    None,
+                    &message,
+                );
+
                 info!(
                     target: LOG_TARGET,
                     "{}({}) @ {}({}): {}",
@@ -506,32 +1541,64 @@ impl ChatService for ChatServiceImpl {
                     channel.id,
                     message
                 );
+
+                self.handle_command(&sender, Some(&channel), &message).await;
             },
             ChatMessageTarget::User(target_query) => {
                 // get target user session
                 match self.get_session(&target_query, None).await.ok() {
+                    // drop the messages from blocked users silently
+                    Some(target_user)
+                        if self
+                            .followers_repository
+                            .is_blocked(target_user.user_id, sender.user_id)
+                            .await? =>
+                    {
+                        return Ok(response);
+                    },
+                    Some(target_user)
+                        if !self
+                            .accepts_private_message(
+                                &sender,
+                                target_user.user_id,
+                                target_user.username.load().as_ref(),
+                                target_user
+                                    .extends
+                                    .only_friend_pm_allowed
+                                    .val(),
+                            )
+                            .await? =>
+                    {
+                        return Err(ChatError::PrivateMessageBlocked);
+                    },
+                    Some(target_user) if target_user.extends.is_silenced() => {
+                        self.notify_target_silenced(
+                            &sender,
+                            target_user.username.load().as_ref(),
+                        )
+                        .await;
+                    },
                     Some(target_user) => {
-                        // push msg packet if target user's bancho packets queue is exists
-                        if let Some(bancho_ext) =
-                            target_user.extends.bancho_ext.load().as_ref()
-                        {
-                            bancho_ext
-                                .packets_queue
-                                .push_packet(
-                                    server::SendMessage::pack(
-                                        sender.username.load().as_ref().into(),
-                                        Cow::Borrowed(message.as_ref()),
-                                        target_user
-                                            .username
-                                            .load()
-                                            .as_ref()
-                                            .into(),
-                                        sender.user_id,
-                                    )
-                                    .into(),
+                        // push msg packet to the bancho and irc clients of target user
+                        target_user
+                            .extends
+                            .push_packet(
+                                server::SendMessage::pack(
+                                    sender.username.load().as_ref().into(),
+                                    Cow::Borrowed(message.as_ref()),
+                                    target_user.username.load().as_ref().into(),
+                                    sender.user_id,
                                 )
-                                .await;
-                        }
+                                .into(),
+                            )
+                            .await;
+
+                        self.chat_messages.push(
+                            sender.user_id,
+                            None,
+                            Some(target_user.user_id),
+                            &message,
+                        );
 
                         info!(
                             target: LOG_TARGET,
@@ -542,15 +1609,100 @@ impl ChatService for ChatServiceImpl {
                             target_user.user_id,
                             message
                         );
+
+                        if target_user.user_id == ChatBot::USER_ID {
+                            self.handle_command(&sender, None, &message).await;
+                        }
                     },
                     None => {
-                        todo!("offline msg handle")
+                        let target_user = self.get_user(&target_query).await?;
+
+                        if self
+                            .followers_repository
+                            .is_blocked(target_user.id, sender.user_id)
+                            .await?
+                        {
+                            return Ok(response);
+                        }
+
+                        let only_friend_pm_allowed = self
+                            .users_repository
+                            .get_user_settings(target_user.id)
+                            .await
+                            .map_err(GetUserError::from)?
+                            .is_some_and(|settings| {
+                                settings.only_friend_pm_allowed
+                            });
+
+                        if !self
+                            .accepts_private_message(
+                                &sender,
+                                target_user.id,
+                                &target_user.name,
+                                only_friend_pm_allowed,
+                            )
+                            .await?
+                        {
+                            return Err(ChatError::PrivateMessageBlocked);
+                        }
+
+                        if target_user
+                            .silence_end
+                            .is_some_and(|end| end > Utc::now())
+                        {
+                            self.notify_target_silenced(
+                                &sender,
+                                target_user.name.as_str(),
+                            )
+                            .await;
+
+                            return Ok(response);
+                        }
+
+                        let saved = self
+                            .chat_messages
+                            .repository
+                            .save_undelivered_message(
+                                new_chat_message(
+                                    sender.user_id,
+                                    None,
+                                    Some(target_user.id),
+                                    &message,
+                                ),
+                                MAX_UNDELIVERED_MESSAGES,
+                            )
+                            .await?;
+
+                        // tell the sender the target is offline, as stable
+                        sender
+                            .extends
+                            .push_packet(
+                                server::Notification::pack(
+                                    offline_message_notice(
+                                        &target_user.name,
+                                        saved,
+                                    )
+                                    .into(),
+                                )
+                                .into(),
+                            )
+                            .await;
+
+                        info!(
+                            target: LOG_TARGET,
+                            "{}({}) @ {}({}) (offline): {}",
+                            sender.username.load(),
+                            sender.user_id,
+                            target_user.name,
+                            target_user.id,
+                            message
+                        );
                     },
                 };
             },
         }
 
-        Ok(SendMessageResponse::default())
+        Ok(response)
     }
 
     async fn join_channel(
@@ -567,14 +1719,20 @@ impl ChatService for ChatServiceImpl {
             .into_channel_query()?;
 
         let session =
-            self.get_session(&user_query, Some(Platform::all())).await?;
+            self.get_session(&user_query, Some(Platform::all_bits())).await?;
 
-        let channel = match self.channels.get_channel(&channel_query).await {
-            Some(channel) => channel,
-            None => {
-                todo!("channel not exists")
-            },
-        };
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
+
+        if !channel.is_allowed(
+            &ChannelHandleType::Join,
+            UserPrivileges::from(session.privileges.val()),
+        ) {
+            return Err(ChatError::PermissionDenied);
+        }
 
         // add user into channel
         Channel::join(&session, &channel).await;
@@ -599,14 +1757,13 @@ impl ChatService for ChatServiceImpl {
             .into_channel_query()?;
 
         let session =
-            self.get_session(&user_query, Some(Platform::all())).await?;
+            self.get_session(&user_query, Some(Platform::all_bits())).await?;
 
-        let channel = match self.channels.get_channel(&channel_query).await {
-            Some(channel) => channel,
-            None => {
-                todo!("channel not exists")
-            },
-        };
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
 
         // remove user from channel
         Channel::remove(&session, &channel).await;
@@ -620,13 +1777,15 @@ impl ChatService for ChatServiceImpl {
     async fn dequeue_chat_packets(
         &self,
         query: UserQuery,
+        platform: Platform,
     ) -> Result<BanchoPackets, ChatError> {
-        let session = self.get_session(&query, Some(Platform::Bancho)).await?;
+        let session = self.get_session(&query, Some(platform)).await?;
 
-        let bancho_ext = match session.extends.bancho_ext.load_full() {
-            Some(bancho_ext) => bancho_ext,
-            None => todo!("invalid call"),
-        };
+        let bancho_ext = session
+            .extends
+            .chat_ext(platform)
+            .load_full()
+            .ok_or(ChatError::SessionNotExists)?;
 
         let mut data = Vec::new();
 
@@ -650,7 +1809,7 @@ impl ChatService for ChatServiceImpl {
         }
 
         // get user's joined channels
-        let joined_channels = {
+        let joined_channels: Vec<(u64, Arc<JoinedChannel>)> = {
             session
                 .extends
                 .joined_channels
@@ -658,9 +1817,12 @@ impl ChatService for ChatServiceImpl {
                 .await
                 .iter()
                 .map(|(channel_id, channel)| (*channel_id, channel.clone()))
-                .collect::<Vec<(u64, Arc<JoinedChannel>)>>()
+                .collect()
         };
 
+        let joined_channel_ids =
+            joined_channels.iter().map(|(id, _)| *id).collect::<Vec<u64>>();
+
         let mut invalid_channels = Vec::new();
 
         // receive msg from each channels, and mark invalid channels ptr
@@ -674,7 +1836,9 @@ impl ChatService for ChatServiceImpl {
                             .await
                             .receive_messages(
                                 &session.user_id,
-                                &joined_channel.message_index.load(),
+                                &joined_channel
+                                    .platform_message_index(platform)
+                                    .load(),
                                 None,
                             )
                             .await
@@ -696,7 +1860,9 @@ impl ChatService for ChatServiceImpl {
                                 .set(Some(last_msg_id.into())),
                         };
 
-                        joined_channel.message_index.set(last_msg_id.into());
+                        joined_channel
+                            .platform_message_index(platform)
+                            .set(last_msg_id.into());
                     }
                 },
                 None => invalid_channels.push(channel_id),
@@ -713,12 +1879,16 @@ impl ChatService for ChatServiceImpl {
             }
         }
 
-        // TODO: real accessable check
+        // only public channels and user's joined channels are accessable
         let accessable_channels = {
             self.channels
                 .read()
                 .await
                 .values()
+                .filter(|ch| {
+                    ch.channel_type == ChannelType::Public
+                        || joined_channel_ids.contains(&ch.id)
+                })
                 .cloned()
                 .collect::<Vec<Arc<Channel>>>()
         };
@@ -741,82 +1911,588 @@ impl ChatService for ChatServiceImpl {
 
                 // update receive
                 receive_channel_updates
-                    .insert(ch.id, ch.updated_at.load().as_ref().clone());
+                    .insert(ch.id, *ch.updated_at.load().as_ref());
+            }
+        };
+
+        // receive msg from session queue
+        data.extend(bancho_ext.packets_queue.dequeue_all_packets(None).await);
+
+        Ok(BanchoPackets { data })
+    }
+
+    async fn load_public_channels(&self) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::initialize_public_channels";
+
+        let public_channels = self
+            .channels_repository
+            .get_channels(DbChannelType::Public)
+            .await?
+            .into_iter()
+            .filter_map(public_channel);
+
+        let () = {
+            let mut indexes = self.channels.write().await;
+            for channel in public_channels {
+                self.channels.create_channel_inner(
+                    &mut indexes,
+                    channel.into(),
+                    false,
+                );
+            }
+        };
+
+        self.load_channel_privileges().await?;
+
+        info!(target: LOG_TARGET, "Public channels successfully initialized.",);
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn get_public_channels(
+        &self,
+    ) -> Result<GetPublicChannelsResponse, ChatError> {
+        let channel_indexes = self.channels.read().await;
+
+        let res = GetPublicChannelsResponse {
+            channels: channel_indexes
+                .public_channels
+                .values()
+                .map(|ch| channel_info(ch))
+                .collect(),
+        };
+
+        Ok(res)
+    }
+
+    async fn get_channel_members(
+        &self,
+        request: GetChannelMembersRequest,
+    ) -> Result<ChannelMembers, ChatError> {
+        let channel_query = request
+            .channel_query
+            .ok_or(ChatError::InvalidArgument)?
+            .into_channel_query()?;
+
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
+
+        let members = self
+            .channel_sessions(&channel)
+            .await
+            .into_iter()
+            .map(|session| ChannelMember {
+                user_id: session.user_id,
+                username: session.username.load().to_string(),
+                privileges: session.privileges.val(),
+            })
+            .collect();
+
+        Ok(ChannelMembers { channel: Some(channel_info(&channel)), members })
+    }
+
+    async fn create_channel(
+        &self,
+        request: CreateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::create_channel";
+
+        let CreateChannelRequest { name, description, auto_join, creator_id } =
+            request;
+
+        validate_channel_name(&name)?;
+
+        if self
+            .channels
+            .is_channel_exists(&ChannelQuery::ChannelName(name.to_owned()))
+            .await
+        {
+            return Err(ChatError::ChannelExists);
+        }
+
+        let model = self
+            .channels_repository
+            .create_channel(channels::ActiveModel {
+                channel_type: Set(DbChannelType::Public),
+                name: Set(Some(name)),
+                description: Set(description.filter(|d| !d.is_empty())),
+                auto_join: Set(auto_join),
+                creator_id: Set(creator_id.map(i64::from)),
+                ..Default::default()
+            })
+            .await?;
+
+        let channel = self
+            .channels
+            .create_channel(
+                public_channel(model).ok_or(ChatError::InvalidArgument)?,
+                false,
+            )
+            .await;
+
+        if channel.auto_join.val() {
+            self.join_online_sessions(&channel).await;
+        }
+
+        info!(
+            target: LOG_TARGET,
+            "Channel {}({}) created by {:?}",
+            channel.name.load(),
+            channel.id,
+            creator_id
+        );
+
+        Ok(channel_info(&channel))
+    }
+
+    async fn update_channel(
+        &self,
+        request: UpdateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::update_channel";
+
+        let UpdateChannelRequest {
+            channel_query,
+            name,
+            description,
+            auto_join,
+        } = request;
+
+        let channel = self.get_public_channel(channel_query).await?;
+
+        let name = name.filter(|name| name != channel.name.load().as_str());
+        if let Some(name) = &name {
+            validate_channel_name(name)?;
+
+            if self
+                .channels
+                .is_channel_exists(&ChannelQuery::ChannelName(name.to_owned()))
+                .await
+            {
+                return Err(ChatError::ChannelExists);
+            }
+        }
+
+        let model = self
+            .channels_repository
+            .update_channel(channels::ActiveModel {
+                id: Unchanged(channel.id as i64),
+                name: name.map(|name| Set(Some(name))).unwrap_or(NotSet),
+                description: description
+                    .map(|d| Set(Some(d).filter(|d| !d.is_empty())))
+                    .unwrap_or(NotSet),
+                auto_join: auto_join.map(Set).unwrap_or(NotSet),
+                ..Default::default()
+            })
+            .await?;
+
+        if let Some(name) = model.name {
+            self.rename_channel(&channel, name).await;
+        }
+
+        channel.description.set(model.description.map(Arc::new));
+
+        let was_auto_join = channel.auto_join.val();
+        channel.auto_join.set(model.auto_join);
+        if model.auto_join && !was_auto_join {
+            self.join_online_sessions(&channel).await;
+        }
+
+        // online users receive the channel info on the next pull
+        channel.updated_at.set(Utc::now().into());
+
+        info!(
+            target: LOG_TARGET,
+            "Channel {}({}) updated",
+            channel.name.load(),
+            channel.id
+        );
+
+        Ok(channel_info(&channel))
+    }
+
+    async fn delete_channel(
+        &self,
+        request: DeleteChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::delete_channel";
+
+        let channel = self.get_public_channel(request.channel_query).await?;
+
+        self.channels_repository.delete_channel(channel.id as i64).await?;
+
+        self.close_channel(&channel).await;
+
+        info!(
+            target: LOG_TARGET,
+            "Channel {}({}) deleted",
+            channel.name.load(),
+            channel.id
+        );
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn reload_channels(&self) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::reload_channels";
+
+        let public_channels = self
+            .channels_repository
+            .get_channels(DbChannelType::Public)
+            .await?
+            .into_iter()
+            .filter_map(public_channel)
+            .map(|channel| (channel.id, channel))
+            .collect::<HashMap<_, _>>();
+
+        let current_channels = self
+            .channels
+            .read()
+            .await
+            .public_channels
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // channels removed from the database
+        for channel in current_channels.iter() {
+            if !public_channels.contains_key(&channel.id) {
+                self.close_channel(channel).await;
+            }
+        }
+
+        for (channel_id, loaded) in public_channels {
+            let current =
+                current_channels.iter().find(|ch| ch.id == channel_id);
+
+            let Some(channel) = current else {
+                let channel = self.channels.create_channel(loaded, false).await;
+                if channel.auto_join.val() {
+                    self.join_online_sessions(&channel).await;
+                }
+                continue;
+            };
+
+            self.rename_channel(channel, loaded.name.to_string()).await;
+            channel.description.set(loaded.description.load_full());
+
+            let was_auto_join = channel.auto_join.val();
+            channel.auto_join.set(loaded.auto_join.val());
+            if channel.auto_join.val() && !was_auto_join {
+                self.join_online_sessions(channel).await;
+            }
+
+            channel.updated_at.set(Utc::now().into());
+        }
+
+        self.load_channel_privileges().await?;
+
+        info!(target: LOG_TARGET, "Public channels successfully reloaded.");
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn create_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        let SpectatorChannelRequest { host_user_id } = request;
+
+        let channel = self
+            .get_or_create_instance_channel(
+                SpectatorChannel::channel_id(host_user_id),
+                SpectatorChannel::channel_name(host_user_id),
+                ChannelType::Spectaor,
+                format!("spectator channel of {host_user_id}"),
+            )
+            .await;
+
+        // host should always be in the spectator channel
+        if let Ok(host) =
+            self.get_session(&UserQuery::UserId(host_user_id), None).await
+        {
+            if !channel.users.read().await.contains_key(&host.user_id) {
+                Channel::join(&host, &channel).await;
+                channel.updated_at.set(Utc::now().into());
             }
+        }
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn remove_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        let SpectatorChannelRequest { host_user_id } = request;
+
+        self.remove_instance_channel(SpectatorChannel::channel_id(
+            host_user_id,
+        ))
+        .await;
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn create_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        let MultiplayerChannelRequest { match_id } = request;
+
+        self.get_or_create_instance_channel(
+            MultiplayerChannel::channel_id(match_id),
+            MultiplayerChannel::channel_name(match_id),
+            ChannelType::Multiplayer,
+            format!("multiplayer channel of match {match_id}"),
+        )
+        .await;
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn remove_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        let MultiplayerChannelRequest { match_id } = request;
+
+        self.remove_instance_channel(MultiplayerChannel::channel_id(match_id))
+            .await;
+
+        Ok(ExecSuccess::default())
+    }
+
+    async fn get_channel_history(
+        &self,
+        request: GetChannelHistoryRequest,
+    ) -> Result<ChatHistory, ChatError> {
+        let GetChannelHistoryRequest {
+            channel_query,
+            before_id,
+            limit,
+            user_id,
+        } = request;
+
+        let channel_query = channel_query
+            .ok_or(ChatError::InvalidArgument)?
+            .into_channel_query()?;
+
+        let session = self
+            .user_sessions
+            .get(&UserQuery::UserId(user_id))
+            .await
+            .ok_or(ChatError::SessionNotExists)?;
+
+        let channel = self
+            .channels
+            .get_channel(&channel_query)
+            .await
+            .ok_or(ChatError::ChannelNotExists)?;
+
+        // only the members can read the channel, staff can read all of them
+        let privileges = UserPrivileges::from(session.privileges.val());
+        if !channel.is_allowed(&ChannelHandleType::Join, privileges)
+            || !(privileges.is_staff()
+                || channel.users.read().await.contains_key(&user_id))
+        {
+            return Err(ChatError::PermissionDenied);
+        }
+
+        let limit = match limit {
+            0 => DEFAULT_CHAT_HISTORY_LIMIT,
+            n => n.min(MAX_CHAT_HISTORY_LIMIT),
+        };
+
+        let channel_id = channel.id as i64;
+        let saved = self
+            .chat_messages
+            .repository
+            .get_channel_messages(channel_id, before_id, limit as u64)
+            .await?;
+
+        let pending = self
+            .pending_history_messages(before_id, |msg| {
+                msg.channel_id == Some(channel_id)
+            })
+            .await;
+
+        Ok(Self::chat_history(saved, pending, limit))
+    }
+
+    async fn get_private_conversation(
+        &self,
+        request: GetPrivateConversationRequest,
+    ) -> Result<ChatHistory, ChatError> {
+        let GetPrivateConversationRequest {
+            user_id,
+            other_user_id,
+            before_id,
+            limit,
+        } = request;
+
+        let limit = match limit {
+            0 => DEFAULT_CHAT_HISTORY_LIMIT,
+            n => n.min(MAX_CHAT_HISTORY_LIMIT),
         };
 
-        // receive msg from session queue
-        data.extend(bancho_ext.packets_queue.dequeue_all_packets(None).await);
+        let saved = self
+            .chat_messages
+            .repository
+            .get_private_messages(
+                user_id,
+                other_user_id,
+                before_id,
+                limit as u64,
+            )
+            .await?;
+
+        let pending = self
+            .pending_history_messages(before_id, |msg| {
+                (msg.sender_id == user_id
+                    && msg.target_user_id == Some(other_user_id))
+                    || (msg.sender_id == other_user_id
+                        && msg.target_user_id == Some(user_id))
+            })
+            .await;
+
+        Ok(Self::chat_history(saved, pending, limit))
+    }
+
+    async fn kick_user(
+        &self,
+        request: KickUserRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::kick_user";
+
+        let KickUserRequest { channel_query, moderator, target, reason } =
+            request;
+
+        let (channel, moderator, target) = self
+            .moderation_context(
+                ChannelHandleType::KickUser,
+                channel_query,
+                moderator,
+                target,
+            )
+            .await?;
+
+        // remove user from channel, the `BANCHO_CHANNEL_KICK` is sent to
+        // user's bancho client
+        Channel::remove(&target, &channel).await;
+
+        // update channel
+        channel.updated_at.set(Utc::now().into());
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) kicked {}({}) from channel {}({}), reason: {:?}",
+            moderator.username.load(),
+            moderator.user_id,
+            target.username.load(),
+            target.user_id,
+            channel.name.load(),
+            channel.id,
+            reason
+        );
 
-        Ok(BanchoPackets { data })
+        self.save_moderation_log(
+            &channel,
+            &moderator,
+            &target,
+            ChannelModerationAction::Kick,
+            reason,
+            None,
+        )
+        .await;
+
+        Ok(ExecSuccess::default())
     }
 
-    async fn load_public_channels(&self) -> Result<ExecSuccess, ChatError> {
-        const LOG_TARGET: &str = "chat::channel::initialize_public_channels";
+    async fn mute_user(
+        &self,
+        request: MuteUserRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        const LOG_TARGET: &str = "chat::channel::mute_user";
+
+        let MuteUserRequest {
+            channel_query,
+            moderator,
+            target,
+            duration_secs,
+            reason,
+        } = request;
 
-        // todo: load public channels from database
-        let public_channels = vec![
-            Channel::new(
-                0,
-                "#osu".to_string(),
-                ChannelType::Public,
-                Some("default channel".to_string()),
-                None,
-            ),
-            Channel::new(
-                1,
-                "#peace".to_string(),
-                ChannelType::Public,
-                Some("peace channel".to_string()),
-                None,
-            ),
-        ];
+        let (channel, moderator, target) = self
+            .moderation_context(
+                ChannelHandleType::MuteUser,
+                channel_query,
+                moderator,
+                target,
+            )
+            .await?;
 
-        let () = {
-            let mut indexes = self.channels.write().await;
-            for channel in public_channels {
-                self.channels.create_channel_inner(
-                    &mut indexes,
-                    channel.into(),
-                    false,
-                );
-            }
+        let mute_end = match duration_secs {
+            0 => None,
+            secs => {
+                Some(punishment_end(secs).ok_or(ChatError::DurationTooLong)?)
+            },
         };
 
-        info!(target: LOG_TARGET, "Public channels successfully initialized.",);
+        channel.set_mute(target.user_id, mute_end).await;
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) set mute of {}({}) in channel {}({}) to {:?}, \
+            reason: {:?}",
+            moderator.username.load(),
+            moderator.user_id,
+            target.username.load(),
+            target.user_id,
+            channel.name.load(),
+            channel.id,
+            mute_end,
+            reason
+        );
+
+        self.save_moderation_log(
+            &channel,
+            &moderator,
+            &target,
+            if mute_end.is_some() {
+                ChannelModerationAction::Mute
+            } else {
+                ChannelModerationAction::Unmute
+            },
+            reason,
+            mute_end,
+        )
+        .await;
 
         Ok(ExecSuccess::default())
     }
 
-    async fn get_public_channels(
+    async fn set_only_friend_pm_allowed(
         &self,
-    ) -> Result<GetPublicChannelsResponse, ChatError> {
-        fn to_channel_info(ch: &Arc<Channel>) -> ChannelInfo {
-            ChannelInfo {
-                id: ch.id,
-                name: ch.name.to_string(),
-                channel_type: ch.channel_type as i32,
-                description: ch
-                    .description
-                    .load()
-                    .as_deref()
-                    .map(|s| s.to_string()),
-                online_users: ch.user_count.val(),
-                users: None,
-            }
-        }
+        request: SetOnlyFriendPmAllowedRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        let SetOnlyFriendPmAllowedRequest {
+            user_query,
+            only_friend_pm_allowed,
+        } = request;
 
-        let channel_indexes = self.channels.read().await;
+        let user_query =
+            user_query.ok_or(ChatError::InvalidArgument)?.into_user_query()?;
 
-        let res = GetPublicChannelsResponse {
-            channels: channel_indexes
-                .public_channels
-                .values()
-                .map(to_channel_info)
-                .collect(),
-        };
+        let session = self.get_session(&user_query, None).await?;
 
-        Ok(res)
+        self.set_only_friend_pm_allowed_inner(&session, only_friend_pm_allowed)
+            .await;
+
+        Ok(ExecSuccess::default())
     }
 }
 
@@ -854,6 +2530,8 @@ impl NotifyMessagesQueue for ChatServiceRemote {}
 
 impl ChannelStore for ChatServiceRemote {}
 
+impl ChatMessagesStore for ChatServiceRemote {}
+
 #[async_trait]
 impl CreateSnapshot<ChatServiceSnapshot> for ChatServiceRemote {
     async fn create_snapshot(&self) -> ChatServiceSnapshot {
@@ -931,12 +2609,15 @@ impl ChatService for ChatServiceRemote {
     async fn dequeue_chat_packets(
         &self,
         query: UserQuery,
+        platform: Platform,
     ) -> Result<BanchoPackets, ChatError> {
-        Ok(self
-            .client()
-            .pull_chat_packets(Into::<RawUserQuery>::into(query))
-            .await?
-            .into_inner())
+        let req = PullChatPacketsRequest {
+            user_query: Some(query.into()),
+            platform: platform.bits(),
+        }
+        .into_request();
+
+        Ok(self.client().pull_chat_packets(req).await?.into_inner())
     }
 
     async fn load_public_channels(&self) -> Result<ExecSuccess, ChatError> {
@@ -956,4 +2637,585 @@ impl ChatService for ChatServiceRemote {
             .await?
             .into_inner())
     }
+
+    async fn get_channel_members(
+        &self,
+        request: GetChannelMembersRequest,
+    ) -> Result<ChannelMembers, ChatError> {
+        Ok(self
+            .client()
+            .get_channel_members(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn create_channel(
+        &self,
+        request: CreateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError> {
+        Ok(self
+            .client()
+            .create_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn update_channel(
+        &self,
+        request: UpdateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError> {
+        Ok(self
+            .client()
+            .update_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn delete_channel(
+        &self,
+        request: DeleteChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .delete_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn reload_channels(&self) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .reload_channels(ReloadChannelsRequest::default())
+            .await?
+            .into_inner())
+    }
+
+    async fn create_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .create_spectator_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn remove_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .remove_spectator_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn create_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .create_multiplayer_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn remove_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .remove_multiplayer_channel(request.into_request())
+            .await?
+            .into_inner())
+    }
+    async fn get_channel_history(
+        &self,
+        request: GetChannelHistoryRequest,
+    ) -> Result<ChatHistory, ChatError> {
+        Ok(self
+            .client()
+            .get_channel_history(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn get_private_conversation(
+        &self,
+        request: GetPrivateConversationRequest,
+    ) -> Result<ChatHistory, ChatError> {
+        Ok(self
+            .client()
+            .get_private_conversation(request.into_request())
+            .await?
+            .into_inner())
+    }
+
+    async fn kick_user(
+        &self,
+        request: KickUserRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self.client().kick_user(request.into_request()).await?.into_inner())
+    }
+
+    async fn mute_user(
+        &self,
+        request: MuteUserRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self.client().mute_user(request.into_request()).await?.into_inner())
+    }
+
+    async fn set_only_friend_pm_allowed(
+        &self,
+        request: SetOnlyFriendPmAllowedRequest,
+    ) -> Result<ExecSuccess, ChatError> {
+        Ok(self
+            .client()
+            .set_only_friend_pm_allowed(request.into_request())
+            .await?
+            .into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::*;
+    use bancho_packets::PacketId;
+    use peace_repositories::chat_messages::ChatMessagesRepository;
+
+    fn to_bob() -> ChatMessageTarget {
+        ChatMessageTarget::User(UserQuery::UserId(BOB))
+    }
+
+    #[tokio::test]
+    async fn test_offline_message_delivered_on_login() {
+        let chat = TestChat::default();
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.packets(ALICE).await;
+
+        chat.send(ALICE, to_bob(), "first").await.unwrap();
+        chat.send(ALICE, to_bob(), "second").await.unwrap();
+
+        // the sender is told with a notice, not a message from the target
+        assert_eq!(
+            packet_ids(&chat.packets(ALICE).await),
+            [PacketId::BANCHO_NOTIFICATION, PacketId::BANCHO_NOTIFICATION]
+        );
+
+        let saved = chat.messages.0.lock().unwrap().clone();
+        assert_eq!(saved.len(), 2);
+        assert!(saved.iter().all(|m| !m.delivered));
+
+        chat.login(BOB, Platform::Bancho).await;
+
+        let packets = chat.packets(BOB).await;
+        let messages = packets
+            .iter()
+            .filter(|p| packet_id(p) == PacketId::BANCHO_SEND_MESSAGE)
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].windows(5).any(|w| w == b"first"));
+        assert!(messages[1].windows(6).any(|w| w == b"second"));
+
+        assert!(chat.messages.0.lock().unwrap().iter().all(|m| m.delivered));
+    }
+
+    #[tokio::test]
+    async fn test_offline_messages_are_capped() {
+        let chat = TestChat::default();
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.packets(ALICE).await;
+
+        for _ in 0..MAX_UNDELIVERED_MESSAGES {
+            chat.messages
+                .save_undelivered_message(
+                    new_chat_message(ALICE, None, Some(BOB), "hi"),
+                    MAX_UNDELIVERED_MESSAGES,
+                )
+                .await
+                .unwrap();
+        }
+
+        chat.send(ALICE, to_bob(), "one more").await.unwrap();
+
+        assert_eq!(
+            chat.messages.0.lock().unwrap().len() as u64,
+            MAX_UNDELIVERED_MESSAGES
+        );
+
+        let notice = offline_message_notice("bob", false);
+        let packets = chat.packets(ALICE).await;
+        assert_eq!(packet_ids(&packets), [PacketId::BANCHO_NOTIFICATION]);
+        assert!(packets[0].ends_with(notice.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_irc_shares_the_online_session() {
+        let chat = TestChat::default();
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.login(BOB, Platform::Bancho).await;
+        chat.packets(BOB).await;
+
+        let session = chat
+            .service
+            .user_sessions
+            .get(&UserQuery::UserId(BOB))
+            .await
+            .unwrap();
+
+        chat.login(BOB, Platform::Irc).await;
+        let irc_session = chat
+            .service
+            .user_sessions
+            .get(&UserQuery::UserId(BOB))
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(&session, &irc_session));
+        assert_eq!(
+            *session.extends.platforms.load().as_ref(),
+            Platform::Bancho | Platform::Irc
+        );
+
+        // both clients receive the message from their own queues
+        chat.send(ALICE, to_bob(), "hi").await.unwrap();
+        assert_eq!(
+            packet_ids(&chat.packets(BOB).await),
+            [PacketId::BANCHO_SEND_MESSAGE]
+        );
+        assert_eq!(
+            packet_ids(&chat.platform_packets(BOB, Platform::Irc).await),
+            [PacketId::BANCHO_SEND_MESSAGE]
+        );
+
+        // the osu! client stays online after the irc client quits
+        chat.service
+            .logout(UserQuery::UserId(BOB), Platform::Irc)
+            .await
+            .unwrap();
+        assert!(session.extends.irc_ext.load().is_none());
+        assert!(session.extends.bancho_ext.load().is_some());
+        assert_eq!(
+            *session.extends.platforms.load().as_ref(),
+            Platform::Bancho
+        );
+
+        chat.send(ALICE, to_bob(), "again").await.unwrap();
+        assert_eq!(
+            packet_ids(&chat.packets(BOB).await),
+            [PacketId::BANCHO_SEND_MESSAGE]
+        );
+    }
+
+    async fn test_channel(chat: &TestChat) -> Arc<Channel> {
+        chat.service
+            .channels
+            .create_channel(
+                Channel::new(
+                    1,
+                    "#test".to_owned(),
+                    ChannelType::Public,
+                    None,
+                    None,
+                ),
+                false,
+            )
+            .await
+    }
+
+    fn channel_history_request(user_id: i32) -> GetChannelHistoryRequest {
+        GetChannelHistoryRequest {
+            channel_query: Some(
+                ChannelQuery::ChannelName("#test".to_owned()).into(),
+            ),
+            before_id: None,
+            limit: 0,
+            user_id,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channel_history_requires_membership() {
+        let chat = TestChat::default();
+        let channel = test_channel(&chat).await;
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.login(BOB, Platform::Bancho).await;
+
+        let alice = chat
+            .service
+            .user_sessions
+            .get(&UserQuery::UserId(ALICE))
+            .await
+            .unwrap();
+        Channel::join(&alice, &channel).await;
+
+        assert!(chat
+            .service
+            .get_channel_history(channel_history_request(ALICE))
+            .await
+            .is_ok());
+        assert!(matches!(
+            chat.service
+                .get_channel_history(channel_history_request(BOB))
+                .await,
+            Err(ChatError::PermissionDenied)
+        ));
+
+        // no session
+        assert!(matches!(
+            chat.service.get_channel_history(channel_history_request(42)).await,
+            Err(ChatError::SessionNotExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_channel_history_includes_pending_messages() {
+        let chat = TestChat::default();
+        let channel = test_channel(&chat).await;
+        chat.login(ALICE, Platform::Bancho).await;
+
+        let alice = chat
+            .service
+            .user_sessions
+            .get(&UserQuery::UserId(ALICE))
+            .await
+            .unwrap();
+        Channel::join(&alice, &channel).await;
+
+        let to_channel =
+            || ChatMessageTarget::Channel(ChannelQuery::ChannelId(1));
+        chat.send(ALICE, to_channel(), "saved").await.unwrap();
+        chat.service.chat_messages.flush().await.unwrap();
+        chat.send(ALICE, to_channel(), "pending").await.unwrap();
+
+        let history = chat
+            .service
+            .get_channel_history(channel_history_request(ALICE))
+            .await
+            .unwrap();
+
+        // reading the history does not save the pending messages
+        assert_eq!(chat.service.chat_messages.pending_count(), 1);
+        assert_eq!(chat.messages.0.lock().unwrap().len(), 1);
+
+        let contents = history
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, ["saved", "pending"]);
+        assert_eq!(history.messages[1].id, 0);
+        assert_eq!(history.messages[1].sender_name, "alice");
+
+        // older pages only have the saved messages
+        let history = chat
+            .service
+            .get_channel_history(GetChannelHistoryRequest {
+                before_id: Some(2),
+                ..channel_history_request(ALICE)
+            })
+            .await
+            .unwrap();
+        assert_eq!(history.messages.len(), 1);
+        assert_eq!(history.messages[0].content, "saved");
+    }
+
+    #[tokio::test]
+    async fn test_mute_duration_is_checked() {
+        let chat = TestChat::default();
+        test_channel(&chat).await;
+        chat.login_as(ALICE, Platform::Bancho, UserPrivileges::Moderator).await;
+        chat.login(BOB, Platform::Bancho).await;
+
+        let mute = |duration_secs| MuteUserRequest {
+            channel_query: Some(ChannelQuery::ChannelId(1).into()),
+            moderator: Some(UserQuery::UserId(ALICE).into()),
+            target: Some(UserQuery::UserId(BOB).into()),
+            duration_secs,
+            reason: None,
+        };
+
+        for secs in [MAX_PUNISHMENT_SECS + 1, i64::MAX as u64 + 1, u64::MAX] {
+            assert!(matches!(
+                chat.service.mute_user(mute(secs)).await,
+                Err(ChatError::DurationTooLong)
+            ));
+        }
+
+        let channel =
+            chat.service.channels.get_channel(&ChannelQuery::ChannelId(1));
+        assert!(channel.await.unwrap().mute_end(BOB).await.is_none());
+
+        chat.service.mute_user(mute(MAX_PUNISHMENT_SECS)).await.unwrap();
+        let channel =
+            chat.service.channels.get_channel(&ChannelQuery::ChannelId(1));
+        assert!(channel.await.unwrap().mute_end(BOB).await.is_some());
+    }
+
+    #[test]
+    fn test_chat_history_keeps_latest_messages() {
+        let message = |id, content: &str| chat_messages::Model {
+            id,
+            sender_id: ALICE,
+            channel_id: Some(1),
+            target_user_id: None,
+            timestamp: Utc::now().into(),
+            content_string: content.to_owned(),
+            content_html: None,
+            is_action: false,
+            delivered: true,
+        };
+
+        let saved = vec![(message(7, "a"), None), (message(8, "b"), None)];
+        let pending = vec![
+            (message(0, "c"), "alice".to_owned()),
+            (message(0, "d"), "alice".to_owned()),
+        ];
+
+        let history = ChatServiceImpl::chat_history(saved.clone(), pending, 3);
+        let contents = history
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, ["b", "c", "d"]);
+        assert_eq!(history.next_before_id, Some(8));
+
+        // all pending, continue from the latest saved message
+        let pending = vec![
+            (message(0, "c"), "alice".to_owned()),
+            (message(0, "d"), "alice".to_owned()),
+        ];
+        let history = ChatServiceImpl::chat_history(saved.clone(), pending, 2);
+        assert_eq!(history.next_before_id, Some(9));
+
+        let history = ChatServiceImpl::chat_history(saved, Vec::new(), 3);
+        assert_eq!(history.messages.len(), 2);
+        assert_eq!(history.next_before_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_offline_friend_only_message_blocked() {
+        let chat = TestChat::default();
+        chat.login(BOB, Platform::Bancho).await;
+        chat.service
+            .set_only_friend_pm_allowed(SetOnlyFriendPmAllowedRequest {
+                user_query: Some(UserQuery::UserId(BOB).into()),
+                only_friend_pm_allowed: true,
+            })
+            .await
+            .unwrap();
+        chat.service
+            .logout(UserQuery::UserId(BOB), Platform::all_bits())
+            .await
+            .unwrap();
+
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.packets(ALICE).await;
+
+        // the setting is saved, so it is still checked when bob is offline
+        assert!(matches!(
+            chat.send(ALICE, to_bob(), "hi").await,
+            Err(ChatError::PrivateMessageBlocked)
+        ));
+        assert_eq!(
+            packet_ids(&chat.packets(ALICE).await),
+            [PacketId::BANCHO_USER_DM_BLOCKED]
+        );
+        assert!(chat.messages.0.lock().unwrap().is_empty());
+
+        chat.followers.friends.lock().unwrap().push((BOB, ALICE));
+        chat.send(ALICE, to_bob(), "hi").await.unwrap();
+        assert_eq!(chat.messages.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_commands() {
+        let mut chat = TestChat::default();
+        chat.service = chat.service.with_bot().await;
+        chat.login(ALICE, Platform::Bancho).await;
+        chat.login(BOB, Platform::Bancho).await;
+        chat.packets(ALICE).await;
+
+        let to_bot =
+            || ChatMessageTarget::User(UserQuery::UserId(ChatBot::USER_ID));
+        let to_alice = || ChatMessageTarget::User(UserQuery::UserId(ALICE));
+
+        chat.send(ALICE, to_bot(), "!block bob").await.unwrap();
+        assert_eq!(*chat.followers.blocks.lock().unwrap(), [(ALICE, BOB)]);
+        chat.packets(ALICE).await;
+
+        // the messages from bob are dropped silently
+        chat.send(BOB, to_alice(), "hi").await.unwrap();
+        assert!(packet_ids(&chat.packets(ALICE).await).is_empty());
+
+        chat.send(ALICE, to_bot(), "!unblock bob").await.unwrap();
+        assert!(chat.followers.blocks.lock().unwrap().is_empty());
+        chat.packets(ALICE).await;
+
+        chat.send(BOB, to_alice(), "hi").await.unwrap();
+        assert_eq!(
+            packet_ids(&chat.packets(ALICE).await),
+            [PacketId::BANCHO_SEND_MESSAGE]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_silence_command() {
+        let mut chat = TestChat::default();
+        chat.service = chat.service.with_bot().await;
+        chat.login_as(ALICE, Platform::Bancho, UserPrivileges::Administrator)
+            .await;
+        chat.login(BOB, Platform::Bancho).await;
+        chat.packets(ALICE).await;
+
+        let to_bot =
+            || ChatMessageTarget::User(UserQuery::UserId(ChatBot::USER_ID));
+        let bob_silenced = || {
+            chat.users
+                .users
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|u| u.id == BOB && u.silence_end.is_some())
+        };
+        let last_reply = |packets: Vec<Packet>| {
+            let packet = packets.last().unwrap().to_vec();
+            String::from_utf8_lossy(&packet).into_owned()
+        };
+
+        chat.send(ALICE, to_bot(), "!silence bob 53w").await.unwrap();
+        assert!(!bob_silenced());
+        assert!(last_reply(chat.packets(ALICE).await)
+            .contains(&CommandError::DurationTooLong.to_string()));
+
+        // staff can not be punished
+        chat.users
+            .privileges
+            .lock()
+            .unwrap()
+            .push((BOB, "moderator".to_owned()));
+        chat.send(ALICE, to_bot(), "!silence bob 10m").await.unwrap();
+        chat.send(ALICE, to_bot(), "!restrict bob").await.unwrap();
+        assert!(!bob_silenced());
+        assert_eq!(chat.users.privileges.lock().unwrap().len(), 1);
+        assert!(last_reply(chat.packets(ALICE).await)
+            .contains(&CommandError::TargetIsStaff.to_string()));
+
+        chat.users.privileges.lock().unwrap().clear();
+        chat.send(ALICE, to_bot(), "!silence bob 10m").await.unwrap();
+        assert!(bob_silenced());
+    }
+
+    #[test]
+    fn test_offline_message_notice() {
+        assert!(offline_message_notice("bob", true).contains("delivered"));
+        assert!(
+            offline_message_notice("bob", false).contains("can not receive")
+        );
+    }
 }
@@ -0,0 +1,664 @@
+use crate::*;
+use async_trait::async_trait;
+use bancho_packets::PacketId;
+use chrono::Utc;
+use domain_bancho::UserPrivileges;
+use domain_chat::Platform;
+use infra_packets::Packet;
+use num_traits::FromPrimitive;
+use pb_bancho_state::UserQuery;
+use pb_chat::{ChatMessageTarget, LoginRequest, SendMessageRequest};
+use peace_db::{
+    peace::entity::{
+        channel_moderation_logs, channel_privileges, channels, chat_messages,
+        privileges,
+        sea_orm_active_enums::{ChannelType, GameMode, PpVersion, RankingType},
+        user_pp, user_settings, user_stats, users,
+    },
+    prelude::{DateTimeWithTimeZone, Decimal},
+    DbErr, InsertResult,
+};
+use peace_repositories::{
+    channels::ChannelsRepository, chat_messages::ChatMessagesRepository,
+    followers::FollowersRepository, users::UsersRepository, ChannelsError,
+    ChatMessagesError, FollowersError, GetUserError,
+};
+use std::sync::{Arc, Mutex};
+
+pub const ALICE: i32 = 1000;
+pub const BOB: i32 = 1001;
+
+#[derive(Default)]
+pub struct MemoryUsers {
+    pub users: Mutex<Vec<users::Model>>,
+    pub settings: Mutex<Vec<user_settings::Model>>,
+    /// The user ids with the names of their privileges.
+    pub privileges: Mutex<Vec<(i32, String)>>,
+}
+
+impl MemoryUsers {
+    fn find(
+        &self,
+        f: impl Fn(&users::Model) -> bool,
+    ) -> Result<users::Model, GetUserError> {
+        self.users
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|u| f(u))
+            .cloned()
+            .ok_or(GetUserError::UserNotExists)
+    }
+}
+
+#[async_trait]
+impl UsersRepository for MemoryUsers {
+    async fn get_user(
+        &self,
+        user_id: Option<i32>,
+        username: Option<&str>,
+        username_unicode: Option<&str>,
+    ) -> Result<users::Model, GetUserError> {
+        self.find(|u| {
+            Some(u.id) == user_id
+                || Some(u.name.as_str()) == username
+                || u.name_unicode
+                    .as_deref()
+                    .is_some_and(|name| Some(name) == username_unicode)
+        })
+    }
+
+    async fn get_user_by_id(
+        &self,
+        user_id: i32,
+    ) -> Result<users::Model, GetUserError> {
+        self.find(|u| u.id == user_id)
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+    ) -> Result<users::Model, GetUserError> {
+        self.find(|u| u.name == username)
+    }
+
+    async fn get_user_by_username_unicode(
+        &self,
+        username_unicode: &str,
+    ) -> Result<users::Model, GetUserError> {
+        self.find(|u| u.name_unicode.as_deref() == Some(username_unicode))
+    }
+
+    async fn get_user_by_email(
+        &self,
+        _email: &str,
+    ) -> Result<users::Model, GetUserError> {
+        unimplemented!()
+    }
+
+    async fn get_user_privileges(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<privileges::Model>, GetUserError> {
+        Ok(self
+            .privileges
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| *id == user_id)
+            .enumerate()
+            .map(|(index, (_, name))| privileges::Model {
+                id: index as i64,
+                name: name.clone(),
+                description: None,
+                priority: 0,
+                creator_id: None,
+                created_at: Utc::now().into(),
+            })
+            .collect())
+    }
+
+    async fn get_user_settings(
+        &self,
+        user_id: i32,
+    ) -> Result<Option<user_settings::Model>, DbErr> {
+        Ok(self
+            .settings
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|settings| settings.user_id == user_id)
+            .cloned())
+    }
+
+    async fn get_user_stats(
+        &self,
+        _user_id: i32,
+        _mode: GameMode,
+    ) -> Result<Option<user_stats::Model>, DbErr> {
+        Ok(None)
+    }
+
+    async fn add_user_stats(
+        &self,
+        _stats: user_stats::Model,
+    ) -> Result<user_stats::Model, DbErr> {
+        unimplemented!()
+    }
+
+    async fn save_user_accuracy(
+        &self,
+        _user_id: i32,
+        _mode: GameMode,
+        _accuracy: Decimal,
+    ) -> Result<(), DbErr> {
+        unimplemented!()
+    }
+
+    async fn get_user_pp(
+        &self,
+        _user_id: i32,
+        _mode: GameMode,
+        _pp_version: PpVersion,
+    ) -> Result<Option<user_pp::Model>, DbErr> {
+        Ok(None)
+    }
+
+    async fn save_user_pp(&self, _pp: user_pp::Model) -> Result<(), DbErr> {
+        unimplemented!()
+    }
+
+    async fn create_user(
+        &self,
+        _creat_user: domain_users::CreateUser,
+    ) -> Result<InsertResult<users::ActiveModel>, DbErr> {
+        unimplemented!()
+    }
+
+    async fn change_user_password(
+        &self,
+        _user_id: Option<i32>,
+        _username: Option<domain_users::UsernameSafe>,
+        _username_unicode: Option<domain_users::UsernameSafe>,
+        _password: String,
+    ) -> Result<InsertResult<users::ActiveModel>, DbErr> {
+        unimplemented!()
+    }
+
+    async fn update_silence_end(
+        &self,
+        user_id: i32,
+        silence_end: Option<DateTimeWithTimeZone>,
+    ) -> Result<(), DbErr> {
+        for user in self.users.lock().unwrap().iter_mut() {
+            if user.id == user_id {
+                user.silence_end = silence_end;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_only_friend_pm_allowed(
+        &self,
+        user_id: i32,
+        only_friend_pm_allowed: bool,
+    ) -> Result<(), DbErr> {
+        let mut settings = self.settings.lock().unwrap();
+        match settings.iter_mut().find(|settings| settings.user_id == user_id) {
+            Some(settings) => {
+                settings.only_friend_pm_allowed = only_friend_pm_allowed
+            },
+            None => settings.push(user_settings::Model {
+                user_id,
+                display_unicode_name: false,
+                scoreboard_ranking_type: RankingType::ScoreV1,
+                invisible_online: false,
+                only_friend_pm_allowed,
+            }),
+        }
+        Ok(())
+    }
+
+    async fn grant_user_privilege(
+        &self,
+        user_id: i32,
+        privilege_name: &str,
+        _grantor_id: i32,
+    ) -> Result<(), DbErr> {
+        self.privileges
+            .lock()
+            .unwrap()
+            .push((user_id, privilege_name.to_owned()));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryChannels;
+
+#[async_trait]
+impl ChannelsRepository for MemoryChannels {
+    async fn get_channels(
+        &self,
+        _channel_type: ChannelType,
+    ) -> Result<Vec<channels::Model>, ChannelsError> {
+        Ok(Vec::new())
+    }
+
+    async fn create_channel(
+        &self,
+        _channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError> {
+        unimplemented!()
+    }
+
+    async fn update_channel(
+        &self,
+        _channel: channels::ActiveModel,
+    ) -> Result<channels::Model, ChannelsError> {
+        unimplemented!()
+    }
+
+    async fn delete_channel(
+        &self,
+        _channel_id: i64,
+    ) -> Result<(), ChannelsError> {
+        unimplemented!()
+    }
+
+    async fn get_channel_privileges(
+        &self,
+    ) -> Result<
+        Vec<(channel_privileges::Model, Option<privileges::Model>)>,
+        ChannelsError,
+    > {
+        Ok(Vec::new())
+    }
+
+    async fn save_moderation_log(
+        &self,
+        _log: channel_moderation_logs::ActiveModel,
+    ) -> Result<(), ChannelsError> {
+        Ok(())
+    }
+}
+
+/// Friends and blocks as `(user_id, other_id)`.
+#[derive(Default)]
+pub struct MemoryFollowers {
+    pub friends: Mutex<Vec<(i32, i32)>>,
+    pub blocks: Mutex<Vec<(i32, i32)>>,
+}
+
+#[async_trait]
+impl FollowersRepository for MemoryFollowers {
+    async fn get_friends(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError> {
+        Ok(self
+            .friends
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| *id == user_id)
+            .map(|(_, friend_id)| *friend_id)
+            .collect())
+    }
+
+    async fn is_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<bool, FollowersError> {
+        Ok(self.friends.lock().unwrap().contains(&(user_id, friend_id)))
+    }
+
+    async fn add_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError> {
+        self.friends.lock().unwrap().push((user_id, friend_id));
+        Ok(())
+    }
+
+    async fn remove_friend(
+        &self,
+        user_id: i32,
+        friend_id: i32,
+    ) -> Result<(), FollowersError> {
+        self.friends.lock().unwrap().retain(|f| *f != (user_id, friend_id));
+        Ok(())
+    }
+
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<i32>, FollowersError> {
+        Ok(self
+            .blocks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| *id == user_id)
+            .map(|(_, block_id)| *block_id)
+            .collect())
+    }
+
+    async fn is_blocked(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<bool, FollowersError> {
+        Ok(self.blocks.lock().unwrap().contains(&(user_id, block_id)))
+    }
+
+    async fn block_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError> {
+        if user_id == block_id {
+            return Err(FollowersError::BlockSelf);
+        }
+        self.blocks.lock().unwrap().push((user_id, block_id));
+        Ok(())
+    }
+
+    async fn unblock_user(
+        &self,
+        user_id: i32,
+        block_id: i32,
+    ) -> Result<(), FollowersError> {
+        self.blocks.lock().unwrap().retain(|b| *b != (user_id, block_id));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryChatMessages(pub Mutex<Vec<chat_messages::Model>>);
+
+impl MemoryChatMessages {
+    fn model(
+        mut message: chat_messages::ActiveModel,
+        id: i64,
+    ) -> chat_messages::Model {
+        chat_messages::Model {
+            id,
+            sender_id: message.sender_id.unwrap(),
+            channel_id: message.channel_id.unwrap(),
+            target_user_id: message.target_user_id.unwrap(),
+            timestamp: message.timestamp.unwrap(),
+            content_string: message.content_string.unwrap(),
+            content_html: message.content_html.unwrap(),
+            is_action: message.is_action.unwrap(),
+            delivered: message.delivered.take().unwrap_or(true),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatMessagesRepository for MemoryChatMessages {
+    async fn save_messages(
+        &self,
+        messages: Vec<chat_messages::ActiveModel>,
+    ) -> Result<usize, ChatMessagesError> {
+        let mut saved = self.0.lock().unwrap();
+        let count = messages.len();
+        for message in messages {
+            let id = saved.len() as i64 + 1;
+            saved.push(Self::model(message, id));
+        }
+        Ok(count)
+    }
+
+    async fn save_undelivered_message(
+        &self,
+        mut message: chat_messages::ActiveModel,
+        max_undelivered: u64,
+    ) -> Result<bool, ChatMessagesError> {
+        let mut saved = self.0.lock().unwrap();
+        let target_user_id = message.target_user_id.clone().unwrap();
+        let undelivered = saved
+            .iter()
+            .filter(|m| m.target_user_id == target_user_id && !m.delivered)
+            .count() as u64;
+
+        if undelivered >= max_undelivered {
+            return Ok(false);
+        }
+
+        message.delivered = peace_db::ActiveValue::Set(false);
+        let id = saved.len() as i64 + 1;
+        saved.push(Self::model(message, id));
+        Ok(true)
+    }
+
+    async fn get_undelivered_messages(
+        &self,
+        target_user_id: i32,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        Ok(self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.target_user_id == Some(target_user_id))
+            .filter(|m| !m.delivered)
+            .map(|m| (m.clone(), Some(user(m.sender_id))))
+            .collect())
+    }
+
+    async fn mark_messages_delivered(
+        &self,
+        message_ids: Vec<i64>,
+    ) -> Result<(), ChatMessagesError> {
+        for message in self.0.lock().unwrap().iter_mut() {
+            if message_ids.contains(&message.id) {
+                message.delivered = true;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_channel_messages(
+        &self,
+        channel_id: i64,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        let messages = self.0.lock().unwrap();
+        let mut messages = messages
+            .iter()
+            .rev()
+            .filter(|m| m.channel_id == Some(channel_id))
+            .filter(|m| before_id.map(|id| m.id < id).unwrap_or(true))
+            .take(limit as usize)
+            .map(|m| (m.clone(), Some(user(m.sender_id))))
+            .collect::<Vec<_>>();
+        messages.reverse();
+        Ok(messages)
+    }
+
+    async fn get_private_messages(
+        &self,
+        user_id: i32,
+        other_user_id: i32,
+        before_id: Option<i64>,
+        limit: u64,
+    ) -> Result<
+        Vec<(chat_messages::Model, Option<users::Model>)>,
+        ChatMessagesError,
+    > {
+        let messages = self.0.lock().unwrap();
+        let mut messages = messages
+            .iter()
+            .rev()
+            .filter(|m| {
+                (m.sender_id == user_id
+                    && m.target_user_id == Some(other_user_id))
+                    || (m.sender_id == other_user_id
+                        && m.target_user_id == Some(user_id))
+            })
+            .filter(|m| before_id.map(|id| m.id < id).unwrap_or(true))
+            .take(limit as usize)
+            .map(|m| (m.clone(), Some(user(m.sender_id))))
+            .collect::<Vec<_>>();
+        messages.reverse();
+        Ok(messages)
+    }
+}
+
+pub fn user(id: i32) -> users::Model {
+    let now = Utc::now().into();
+    let name = match id {
+        ALICE => "alice".to_owned(),
+        BOB => "bob".to_owned(),
+        id => format!("user{id}"),
+    };
+
+    users::Model {
+        id,
+        name_safe: name.clone(),
+        name,
+        name_unicode: None,
+        name_unicode_safe: None,
+        password: String::new(),
+        email: format!("{id}@peace.test"),
+        country: None,
+        created_at: now,
+        updated_at: now,
+        silence_end: None,
+    }
+}
+
+/// The chat service backed by the in-memory repositories, with the users
+/// alice and bob.
+pub struct TestChat {
+    pub service: ChatServiceImpl,
+    pub messages: Arc<MemoryChatMessages>,
+    pub followers: Arc<MemoryFollowers>,
+    pub users: Arc<MemoryUsers>,
+}
+
+impl Default for TestChat {
+    fn default() -> Self {
+        let users = Arc::new(MemoryUsers {
+            users: Mutex::new(vec![user(ALICE), user(BOB)]),
+            ..Default::default()
+        });
+        let messages = Arc::new(MemoryChatMessages::default());
+        let followers = Arc::new(MemoryFollowers::default());
+
+        let service = ChatServiceImpl::new(
+            users.clone(),
+            messages.clone(),
+            Arc::new(MemoryChannels),
+            followers.clone(),
+            ChatCommandRegistryImpl::new(&CliChatCommandConfigs::default())
+                .register(BlockCommand)
+                .register(UnblockCommand)
+                .register(SilenceCommand)
+                .register(RestrictCommand)
+                .into_service(),
+            &CliChatSpamConfigs::default(),
+        );
+
+        Self { service, messages, followers, users }
+    }
+}
+
+impl TestChat {
+    pub async fn login(&self, user_id: i32, platforms: Platform) {
+        self.login_as(user_id, platforms, UserPrivileges::Normal).await
+    }
+
+    pub async fn login_as(
+        &self,
+        user_id: i32,
+        platforms: Platform,
+        privileges: UserPrivileges,
+    ) {
+        self.service
+            .login(LoginRequest {
+                user_id,
+                username: user(user_id).name,
+                privileges: privileges.bits(),
+                platforms: platforms.bits(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    pub async fn send(
+        &self,
+        sender: i32,
+        target: ChatMessageTarget,
+        message: &str,
+    ) -> Result<pb_chat::SendMessageResponse, ChatError> {
+        self.service
+            .send_message(SendMessageRequest {
+                sender: Some(UserQuery::UserId(sender).into()),
+                message: message.to_owned(),
+                target: Some(target.into()),
+            })
+            .await
+    }
+
+    /// Dequeue the bancho packets of the user.
+    pub async fn packets(&self, user_id: i32) -> Vec<Packet> {
+        self.platform_packets(user_id, Platform::Bancho).await
+    }
+
+    /// Dequeue the packets queued to the bancho or irc client of the user.
+    pub async fn platform_packets(
+        &self,
+        user_id: i32,
+        platform: Platform,
+    ) -> Vec<Packet> {
+        let session = self
+            .service
+            .user_sessions
+            .get(&UserQuery::UserId(user_id))
+            .await
+            .unwrap();
+        let chat_ext = session.extends.chat_ext(platform).load();
+        let queue = &chat_ext.as_ref().unwrap().packets_queue;
+
+        let mut packets = Vec::new();
+        while let Some(packet) = queue.dequeue_packet(None).await {
+            packets.push(packet);
+        }
+        packets
+    }
+}
+
+/// The packet id of the packet.
+pub fn packet_id(packet: &Packet) -> PacketId {
+    PacketId::from_u16(u16::from_le_bytes([packet[0], packet[1]]))
+        .unwrap_or_default()
+}
+
+/// The ids of the packets, without the channel infos sent on login.
+pub fn packet_ids(packets: &[Packet]) -> Vec<PacketId> {
+    packets
+        .iter()
+        .map(packet_id)
+        .filter(|id| {
+            !matches!(
+                id,
+                PacketId::BANCHO_CHANNEL_INFO
+                    | PacketId::BANCHO_CHANNEL_INFO_END
+                    | PacketId::BANCHO_CHANNEL_JOIN_SUCCESS
+            )
+        })
+        .collect()
+}
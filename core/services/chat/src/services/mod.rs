@@ -1,7 +1,12 @@
 pub mod background;
 pub mod chat;
+pub mod performance;
 pub mod traits;
 
+#[cfg(test)]
+pub mod mock;
+
 pub use background::*;
 pub use chat::*;
+pub use performance::*;
 pub use traits::*;
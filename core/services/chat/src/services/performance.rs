@@ -0,0 +1,62 @@
+use domain_pp::PP_WEIGHTED_SCORES;
+use num_traits::ToPrimitive;
+use peace_db::{
+    peace::entity::{
+        sea_orm_active_enums::{PpVersion, RankingType},
+        user_pp, user_stats,
+    },
+    prelude::Decimal,
+};
+use peace_repositories::{
+    leaderboard::DynLeaderboardRepository, users::DynUsersRepository,
+    LeaderboardError,
+};
+
+/// Recalculate the user's total pp and accuracy from the best scores on the
+/// ranked beatmaps, both are saved, the total pp is returned and the accuracy
+/// is set on the stats.
+pub async fn update_user_performance(
+    users_repository: &DynUsersRepository,
+    leaderboard_repository: &DynLeaderboardRepository,
+    stats: &mut user_stats::Model,
+) -> Result<Decimal, LeaderboardError> {
+    let top_scores = leaderboard_repository
+        .get_user_top_scores(
+            stats.user_id,
+            stats.mode.clone(),
+            RankingType::PpV2,
+            PP_WEIGHTED_SCORES,
+        )
+        .await?;
+
+    let pp_values = top_scores
+        .iter()
+        .map(|s| s.pp.and_then(|pp| pp.to_f64()).unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let pp = Decimal::from_f64_retain(domain_pp::total_pp(&pp_values))
+        .unwrap_or_default()
+        .round_dp(2);
+
+    stats.accuracy = Decimal::from_f64_retain(domain_pp::weighted_average(
+        top_scores.iter().map(|s| s.accuracy.to_f64().unwrap_or_default()),
+    ))
+    .unwrap_or_default()
+    .round_dp(2);
+
+    users_repository
+        .save_user_accuracy(stats.user_id, stats.mode.clone(), stats.accuracy)
+        .await?;
+
+    users_repository
+        .save_user_pp(user_pp::Model {
+            user_id: stats.user_id,
+            mode: stats.mode.clone(),
+            pp_version: PpVersion::V2,
+            pp,
+            raw_pp: None,
+        })
+        .await?;
+
+    Ok(pp)
+}
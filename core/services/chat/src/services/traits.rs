@@ -37,11 +37,18 @@ pub trait NotifyMessagesQueue {
     }
 }
 
+pub trait ChatMessagesStore {
+    fn chat_messages(&self) -> &Arc<ChatMessagesPersister> {
+        unimplemented!()
+    }
+}
+
 #[async_trait]
 pub trait ChatService:
     UserSessionsStore
     + NotifyMessagesQueue
     + ChannelStore
+    + ChatMessagesStore
     + CreateSnapshot<ChatServiceSnapshot>
     + SaveSnapshotTo<ChatServiceSnapshot>
     + ServiceSnapshot
@@ -72,9 +79,12 @@ pub trait ChatService:
         request: LeaveChannelRequest,
     ) -> Result<ExecSuccess, ChatError>;
 
+    /// Dequeue the packets delivered to the platform of the session,
+    /// the bancho and irc clients have their own queues.
     async fn dequeue_chat_packets(
         &self,
         query: UserQuery,
+        platform: Platform,
     ) -> Result<BanchoPackets, ChatError>;
 
     async fn load_public_channels(&self) -> Result<ExecSuccess, ChatError>;
@@ -82,6 +92,73 @@ pub trait ChatService:
     async fn get_public_channels(
         &self,
     ) -> Result<GetPublicChannelsResponse, ChatError>;
+
+    async fn get_channel_members(
+        &self,
+        request: GetChannelMembersRequest,
+    ) -> Result<ChannelMembers, ChatError>;
+
+    async fn create_channel(
+        &self,
+        request: CreateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError>;
+
+    async fn update_channel(
+        &self,
+        request: UpdateChannelRequest,
+    ) -> Result<ChannelInfo, ChatError>;
+
+    async fn delete_channel(
+        &self,
+        request: DeleteChannelRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn reload_channels(&self) -> Result<ExecSuccess, ChatError>;
+
+    async fn create_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn remove_spectator_channel(
+        &self,
+        request: SpectatorChannelRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn create_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn remove_multiplayer_channel(
+        &self,
+        request: MultiplayerChannelRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn get_channel_history(
+        &self,
+        request: GetChannelHistoryRequest,
+    ) -> Result<ChatHistory, ChatError>;
+
+    async fn get_private_conversation(
+        &self,
+        request: GetPrivateConversationRequest,
+    ) -> Result<ChatHistory, ChatError>;
+
+    async fn kick_user(
+        &self,
+        request: KickUserRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn mute_user(
+        &self,
+        request: MuteUserRequest,
+    ) -> Result<ExecSuccess, ChatError>;
+
+    async fn set_only_friend_pm_allowed(
+        &self,
+        request: SetOnlyFriendPmAllowedRequest,
+    ) -> Result<ExecSuccess, ChatError>;
 }
 
 #[async_trait]
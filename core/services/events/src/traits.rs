@@ -7,6 +7,7 @@ use std::sync::Arc;
 pub type DynEventsService = Arc<dyn EventsService + Send + Sync>;
 
 #[async_trait]
+#[allow(clippy::ptr_arg)]
 pub trait EventsService {
     async fn create_subscription(
         &self,
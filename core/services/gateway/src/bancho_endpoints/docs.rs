@@ -29,7 +29,10 @@ use super::routes::{bancho, debug};
     bancho::osu_getseasonal,
     bancho::bancho_connect,
     bancho::check_updates,
-    bancho::update_beatmap
+    bancho::update_beatmap,
+    bancho::download_replay,
+    bancho::get_channel_messages,
+    bancho::get_private_messages
 ))]
 pub struct BanchoEndpointsDocs;
 
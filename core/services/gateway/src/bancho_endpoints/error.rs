@@ -2,10 +2,15 @@ use super::{CHO_PROTOCOL, CHO_TOKEN};
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
 use bancho_packets::{server, PacketBuilder};
-use core_bancho::{BanchoServiceError, ProcessBanchoPacketError};
+use core_bancho::{
+    BanchoServiceError, ProcessBanchoPacketError, RegistrationErrors,
+};
 use core_bancho_state::BanchoStateError;
+use core_chat::ChatError;
+use serde_json::json;
 use std::string::FromUtf8Error;
 
 #[derive(thiserror::Error, Debug)]
@@ -34,10 +39,38 @@ pub enum LoginError {
     BanchoServiceError(#[from] BanchoServiceError),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum SubmitScoreError {
+    #[error("missing form field: {0}")]
+    MissingField(&'static str),
+    #[error("failed to decrypt score data")]
+    DecryptFailed,
+    #[error("invalid score data")]
+    InvalidScoreData,
+    #[error("mismatched score checksum")]
+    MismatchedChecksum,
+    #[error("mismatched client version")]
+    MismatchedClientVersion,
+    #[error(transparent)]
+    BanchoServiceError(#[from] BanchoServiceError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RegisterError {
+    #[error("missing form field: {0}")]
+    MissingField(&'static str),
+    #[error(transparent)]
+    BanchoServiceError(#[from] BanchoServiceError),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum BanchoHttpError {
     #[error(transparent)]
     LoginFailed(#[from] LoginError),
+    #[error(transparent)]
+    SubmitScoreFailed(#[from] SubmitScoreError),
+    #[error(transparent)]
+    RegisterFailed(#[from] RegisterError),
     #[error("errors occured while handling packet: {0}")]
     PacketHandlingError(#[source] anyhow::Error),
     #[error("errors occured while dequeueing packets: {0}")]
@@ -56,11 +89,52 @@ pub enum BanchoHttpError {
     FailedToProcessBanchoPackets(#[from] ProcessBanchoPacketError),
     #[error(transparent)]
     BanchoStateError(#[from] BanchoStateError),
+    #[error(transparent)]
+    BanchoServiceError(#[from] BanchoServiceError),
+    #[error(transparent)]
+    ChatError(#[from] ChatError),
 }
 
 impl BanchoHttpError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+        match self {
+            Self::BanchoServiceError(
+                BanchoServiceError::PasswordError(..)
+                | BanchoServiceError::UserNotExists(..),
+            ) => StatusCode::UNAUTHORIZED,
+            Self::BanchoServiceError(
+                BanchoServiceError::ScoreNotExists
+                | BanchoServiceError::ReplayNotExists
+                | BanchoServiceError::ScreenshotNotExists,
+            ) => StatusCode::NOT_FOUND,
+            Self::BanchoServiceError(
+                BanchoServiceError::InvalidScreenshot
+                | BanchoServiceError::InvalidRating(..),
+            ) => StatusCode::BAD_REQUEST,
+            Self::BanchoServiceError(
+                BanchoServiceError::ScreenshotTooLarge,
+            ) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::BanchoServiceError(
+                BanchoServiceError::ScreenshotQuotaExceeded,
+            ) => StatusCode::TOO_MANY_REQUESTS,
+            Self::BanchoServiceError(
+                BanchoServiceError::BeatmapsetNotExists,
+            ) => StatusCode::NOT_FOUND,
+            Self::BanchoServiceError(BanchoServiceError::DirectDisabled) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            },
+            Self::BanchoServiceError(
+                BanchoServiceError::BeatmapMirrorError(..),
+            ) => StatusCode::BAD_GATEWAY,
+            Self::ParseRequestError => StatusCode::BAD_REQUEST,
+            Self::BanchoServiceError(
+                BanchoServiceError::ReplayAccessDenied,
+            ) => StatusCode::FORBIDDEN,
+            Self::ChatError(ChatError::ChannelNotExists) => {
+                StatusCode::NOT_FOUND
+            },
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
     }
 }
 
@@ -80,6 +154,12 @@ impl IntoResponse for BanchoHttpError {
                         | BanchoServiceError::ChatError(..)
                         | BanchoServiceError::BanchoStateError(..),
                     ) => server::LoginReply::failed_server_error(),
+                    LoginError::BanchoServiceError(
+                        BanchoServiceError::UserBanned,
+                    ) => server::LoginReply::failed_user_banned(),
+                    LoginError::BanchoServiceError(
+                        BanchoServiceError::OutdatedClient(..),
+                    ) => server::LoginReply::failed_outdated_client(),
                     _ => server::LoginReply::failed_invalid_credentials(),
                 };
 
@@ -91,6 +171,79 @@ impl IntoResponse for BanchoHttpError {
                 ([(CHO_TOKEN, "failed"), CHO_PROTOCOL], packets).into_response()
             },
 
+            Self::SubmitScoreFailed(err) => {
+                let reply = match err {
+                    SubmitScoreError::MismatchedClientVersion => {
+                        "error: oldver"
+                    },
+                    SubmitScoreError::BanchoServiceError(
+                        BanchoServiceError::PasswordError(..)
+                        | BanchoServiceError::UserNotExists(..),
+                    ) => "error: pass",
+                    SubmitScoreError::BanchoServiceError(
+                        BanchoServiceError::BeatmapError(..),
+                    ) => "error: beatmap",
+                    SubmitScoreError::BanchoServiceError(
+                        BanchoServiceError::UserBanned,
+                    ) => "error: disabled",
+                    _ => {
+                        warn!("[SubmitScoreError] {err}");
+                        "error: no"
+                    },
+                };
+
+                (StatusCode::OK, reply).into_response()
+            },
+
+            Self::RegisterFailed(err) => {
+                let errors = match err {
+                    RegisterError::MissingField(field) => {
+                        let mut errors = RegistrationErrors::default();
+                        let message = vec!["Required.".to_owned()];
+                        match field {
+                            "user_email" => errors.user_email = message,
+                            "password" => errors.password = message,
+                            _ => errors.username = message,
+                        };
+                        errors
+                    },
+                    RegisterError::BanchoServiceError(
+                        BanchoServiceError::InvalidRegistration(errors),
+                    ) => errors,
+                    RegisterError::BanchoServiceError(
+                        BanchoServiceError::RegistrationDisabled,
+                    ) => RegistrationErrors {
+                        username: vec!["In-game registration is disabled, \
+                                        please register on the website."
+                            .to_owned()],
+                        ..Default::default()
+                    },
+                    RegisterError::BanchoServiceError(
+                        BanchoServiceError::RegistrationRateLimited,
+                    ) => RegistrationErrors {
+                        username: vec!["Too many registrations from your \
+                                        network, please try again later."
+                            .to_owned()],
+                        ..Default::default()
+                    },
+                    err => {
+                        warn!("[RegisterError] {err}");
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "error: server",
+                        )
+                            .into_response();
+                    },
+                };
+
+                // the osu! clients display the errors of each form field
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "form_error": { "user": errors } })),
+                )
+                    .into_response()
+            },
+
             Self::BanchoStateError(
                 BanchoStateError::SessionNotExists
                 | BanchoStateError::SignatureError(..),
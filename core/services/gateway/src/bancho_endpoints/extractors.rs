@@ -2,7 +2,7 @@ use super::{parser, BanchoHttpError};
 use axum::{
     async_trait,
     body::Bytes,
-    extract::{FromRequest, FromRequestParts},
+    extract::{FromRequest, FromRequestParts, Multipart},
     headers::HeaderName,
     http::{request::Parts, Request},
 };
@@ -100,6 +100,336 @@ where
     }
 }
 
+/// The `u` (username) and `h` (password md5) query parameters sent by the
+/// osu! client to authenticate `/web` requests.
+#[derive(Debug, Deserialize)]
+pub struct OsuClientCredentials {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "h")]
+    pub password_md5: String,
+}
+
+/// The pagination query parameters of the chat history endpoints, `before` is
+/// the id of the oldest message already loaded.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChatHistoryQuery {
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// The query parameters of `/web/osu-getreplay.php`.
+#[derive(Debug, Deserialize)]
+pub struct OsuGetReplayQuery {
+    #[serde(rename = "c")]
+    pub score_id: i64,
+    #[serde(rename = "u")]
+    pub username: Option<String>,
+    #[serde(rename = "h")]
+    pub password_md5: Option<String>,
+}
+
+/// The query parameters of `/web/osu-addfavourite.php`.
+#[derive(Debug, Deserialize)]
+pub struct OsuAddFavouriteQuery {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "h")]
+    pub password_md5: String,
+    #[serde(rename = "a")]
+    pub beatmapset_id: i32,
+}
+
+/// The query parameters of `/web/osu-rate.php`, the client only checks
+/// whether the user has voted if `v` is missing.
+#[derive(Debug, Deserialize)]
+pub struct OsuRateQuery {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "p")]
+    pub password_md5: String,
+    #[serde(rename = "c")]
+    pub beatmap_md5: String,
+    #[serde(rename = "v")]
+    pub rating: Option<i32>,
+}
+
+/// The query parameters of `/web/bancho_connect.php`, sent by the client
+/// before it connects to bancho.
+#[derive(Debug, Deserialize)]
+pub struct OsuBanchoConnectQuery {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "h")]
+    pub password_md5: String,
+    #[serde(rename = "v")]
+    pub client_version: String,
+}
+
+/// The query parameters of `/web/check-updates.php`.
+#[derive(Debug, Deserialize)]
+pub struct OsuCheckUpdatesQuery {
+    #[serde(default = "OsuCheckUpdatesQuery::default_action")]
+    pub action: String,
+    #[serde(default = "OsuCheckUpdatesQuery::default_stream")]
+    pub stream: String,
+}
+
+impl OsuCheckUpdatesQuery {
+    #[inline]
+    fn default_action() -> String {
+        "check".to_owned()
+    }
+
+    #[inline]
+    fn default_stream() -> String {
+        "stable40".to_owned()
+    }
+}
+
+/// The query parameters of `/web/osu-search.php`, `r` is the ranked status
+/// tab of osu!direct.
+#[derive(Debug, Deserialize)]
+pub struct OsuSearchQuery {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "h")]
+    pub password_md5: String,
+    #[serde(rename = "q", default)]
+    pub query: String,
+    #[serde(rename = "m", default = "OsuSearchQuery::all_modes")]
+    pub mode: i32,
+    #[serde(rename = "r", default)]
+    pub ranked_status: i32,
+    #[serde(rename = "p", default)]
+    pub page: i32,
+}
+
+impl OsuSearchQuery {
+    #[inline]
+    fn all_modes() -> i32 {
+        -1
+    }
+}
+
+/// The query parameters of `/web/osu-search-set.php`, the beatmapset is
+/// queried by its id, or the id or md5 of one of its beatmaps.
+#[derive(Debug, Deserialize)]
+pub struct OsuSearchSetQuery {
+    #[serde(rename = "u")]
+    pub username: String,
+    #[serde(rename = "h")]
+    pub password_md5: String,
+    #[serde(rename = "s")]
+    pub beatmapset_id: Option<i32>,
+    #[serde(rename = "b")]
+    pub beatmap_id: Option<i32>,
+    #[serde(rename = "c")]
+    pub beatmap_md5: Option<String>,
+}
+
+/// The json body of `/web/osu-getbeatmapinfo.php`, the beatmaps are queried
+/// by their `.osu` file names and ids.
+#[derive(Debug, Default, Deserialize)]
+pub struct OsuGetBeatmapInfoBody {
+    #[serde(rename = "Filenames", default)]
+    pub file_names: Vec<String>,
+    #[serde(rename = "Ids", default)]
+    pub beatmap_ids: Vec<i32>,
+}
+
+/// The query parameters of `/web/osu-osz2-getscores.php`.
+#[derive(Debug, Deserialize)]
+pub struct OsuGetScoresQuery {
+    #[serde(rename = "us")]
+    pub username: String,
+    #[serde(rename = "ha")]
+    pub password_md5: String,
+    #[serde(rename = "c")]
+    pub beatmap_md5: String,
+    #[serde(rename = "m")]
+    pub mode: i32,
+    pub mods: u32,
+    #[serde(rename = "v")]
+    pub leaderboard_type: i32,
+}
+
+/// The multipart form sent by the osu! client to
+/// `/web/osu-submit-modular-selector.php`.
+///
+/// The form contains two `score` fields, the first one is the encrypted
+/// score data and the second one is the replay file.
+#[derive(Debug, Default)]
+pub struct OsuScoreSubmissionForm {
+    pub score_data: String,
+    pub iv: String,
+    pub client_hash: String,
+    pub osu_version: String,
+    pub password_md5: String,
+    pub storyboard_checksum: String,
+    pub exited: bool,
+    pub fail_time: i32,
+    pub replay: Option<Vec<u8>>,
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for OsuScoreSubmissionForm
+where
+    Multipart: FromRequest<S, B>,
+    B: Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = BanchoHttpError;
+
+    async fn from_request(
+        req: Request<B>,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+        let mut form = Self::default();
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?
+        {
+            let name = field.name().unwrap_or_default().to_owned();
+            let value = field
+                .bytes()
+                .await
+                .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+            let text = || String::from_utf8_lossy(&value).into_owned();
+
+            match name.as_str() {
+                "score" if form.score_data.is_empty() => {
+                    form.score_data = text()
+                },
+                "score" => form.replay = Some(value.to_vec()),
+                "iv" => form.iv = text(),
+                "s" => form.client_hash = text(),
+                "osuver" => form.osu_version = text(),
+                "pass" => form.password_md5 = text(),
+                "sbk" => form.storyboard_checksum = text(),
+                "x" => form.exited = text() == "1",
+                "ft" => form.fail_time = text().parse().unwrap_or(0),
+                _ => {},
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+/// The multipart form sent by the osu! client to `/web/osu-screenshot.php`,
+/// the image is in the `ss` field.
+#[derive(Debug, Default)]
+pub struct OsuScreenshotForm {
+    pub username: String,
+    pub password_md5: String,
+    pub screenshot: Option<Vec<u8>>,
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for OsuScreenshotForm
+where
+    Multipart: FromRequest<S, B>,
+    B: Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = BanchoHttpError;
+
+    async fn from_request(
+        req: Request<B>,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+        let mut form = Self::default();
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?
+        {
+            let name = field.name().unwrap_or_default().to_owned();
+            let value = field
+                .bytes()
+                .await
+                .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+            let text = || String::from_utf8_lossy(&value).into_owned();
+
+            match name.as_str() {
+                "u" => form.username = text(),
+                "p" => form.password_md5 = text(),
+                "ss" => form.screenshot = Some(value.to_vec()),
+                _ => {},
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+/// The multipart form sent by the osu! client to `/users`, the form is only
+/// validated if `check` is `1`.
+#[derive(Debug, Default)]
+pub struct OsuRegistrationForm {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub password: Option<String>,
+    pub check: bool,
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for OsuRegistrationForm
+where
+    Multipart: FromRequest<S, B>,
+    B: Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = BanchoHttpError;
+
+    async fn from_request(
+        req: Request<B>,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+        let mut form = Self::default();
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| BanchoHttpError::ParseRequestError)?
+        {
+            let name = field.name().unwrap_or_default().to_owned();
+            let value = field
+                .text()
+                .await
+                .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+            match name.as_str() {
+                "user[username]" => form.username = Some(value),
+                "user[user_email]" => form.email = Some(value),
+                "user[password]" => form.password = Some(value),
+                "check" => form.check = value == "1",
+                _ => {},
+            }
+        }
+
+        Ok(form)
+    }
+}
+
 /// A wrapper around the body of a Bancho request.
 #[derive(Debug, Deref)]
 pub struct BanchoRequestBody(pub Bytes);
@@ -1,5 +1,9 @@
-use super::ParseLoginDataError;
-use pb_bancho::{ClientHashes, LoginRequest};
+use super::{
+    extractors::OsuScoreSubmissionForm, ParseLoginDataError, SubmitScoreError,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pb_bancho::{ClientHashes, LoginRequest, SubmitScoreRequest};
+use simple_rijndael::{impls::RijndaelCbc, paddings::Pkcs7Padding};
 
 pub fn parse_osu_login_request_body(
     body: Vec<u8>,
@@ -71,3 +75,242 @@ pub fn parse_osu_login_request_body(
         }),
     })
 }
+
+/// Decrypt and parse the score data submitted by the osu! client, the
+/// checksum and the client version of the score are verified here.
+pub fn parse_osu_score_submission(
+    form: OsuScoreSubmissionForm,
+) -> Result<SubmitScoreRequest, SubmitScoreError> {
+    let OsuScoreSubmissionForm {
+        score_data,
+        iv,
+        client_hash,
+        osu_version,
+        password_md5,
+        storyboard_checksum,
+        exited,
+        fail_time,
+        replay,
+    } = form;
+
+    for (field, value) in [
+        ("score", &score_data),
+        ("iv", &iv),
+        ("s", &client_hash),
+        ("osuver", &osu_version),
+        ("pass", &password_md5),
+    ] {
+        if value.is_empty() {
+            return Err(SubmitScoreError::MissingField(field));
+        }
+    }
+
+    let decode = |s: &str| {
+        STANDARD.decode(s).map_err(|_| SubmitScoreError::DecryptFailed)
+    };
+
+    let key = format!("osu!-scoreburgr---------{osu_version}");
+    let cipher = RijndaelCbc::<Pkcs7Padding>::new(key.as_bytes(), 32)
+        .map_err(|_| SubmitScoreError::DecryptFailed)?;
+
+    let iv = decode(&iv)?;
+    let decrypt = |s: &str| {
+        cipher
+            .decrypt(&iv, decode(s)?)
+            .ok()
+            .and_then(|data| String::from_utf8(data).ok())
+            .ok_or(SubmitScoreError::DecryptFailed)
+    };
+
+    let score_data = decrypt(&score_data)?;
+    let client_hash = decrypt(&client_hash)?;
+
+    let data = score_data.split(':').collect::<Vec<_>>();
+    if data.len() < 18 {
+        return Err(SubmitScoreError::InvalidScoreData);
+    }
+
+    #[inline]
+    fn parse<T: std::str::FromStr>(s: &str) -> Result<T, SubmitScoreError> {
+        s.parse().map_err(|_| SubmitScoreError::InvalidScoreData)
+    }
+
+    let beatmap_md5 = data[0].to_owned();
+    let username = data[1].trim().to_owned();
+    let checksum = data[2].to_owned();
+    let n300 = parse::<i32>(data[3])?;
+    let n100 = parse::<i32>(data[4])?;
+    let n50 = parse::<i32>(data[5])?;
+    let geki = parse::<i32>(data[6])?;
+    let katu = parse::<i32>(data[7])?;
+    let miss = parse::<i32>(data[8])?;
+    let score = parse::<i32>(data[9])?;
+    let max_combo = parse::<i32>(data[10])?;
+    let perfect = data[11] == "True";
+    let grade = data[12].to_owned();
+    let mods = parse::<u32>(data[13])?;
+    let passed = data[14] == "True";
+    let mode = parse::<i32>(data[15])?;
+    let client_time = data[16];
+
+    // The client version is padded with spaces, the count of which
+    // represents the client flags.
+    let client_version = data[17].trim();
+    let client_flags = data[17].matches(' ').count() as i32;
+
+    if client_version != osu_version {
+        return Err(SubmitScoreError::MismatchedClientVersion);
+    }
+
+    let python_bool = |b: bool| if b { "True" } else { "False" };
+    let expected_checksum = format!(
+        "{:x}",
+        md5::compute(format!(
+            "chickenmcnuggets{}o15{n50}{geki}smustard{katu}{miss}uu{beatmap_md5}{max_combo}{}{username}{score}{grade}{mods}Q{}{mode}{osu_version}{client_time}{client_hash}{storyboard_checksum}",
+            n100 + n300,
+            python_bool(perfect),
+            python_bool(passed),
+        ))
+    );
+
+    if checksum != expected_checksum {
+        return Err(SubmitScoreError::MismatchedChecksum);
+    }
+
+    Ok(SubmitScoreRequest {
+        username,
+        password: password_md5,
+        beatmap_md5,
+        checksum,
+        n300,
+        n100,
+        n50,
+        geki,
+        katu,
+        miss,
+        score,
+        max_combo,
+        perfect,
+        grade,
+        mods,
+        passed,
+        mode,
+        client_version: client_version.to_owned(),
+        client_flags,
+        fail_time,
+        exited,
+        replay,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OSU_VERSION: &str = "20230326";
+    const IV: [u8; 32] = [7; 32];
+
+    fn encrypt(data: &str) -> String {
+        let key = format!("osu!-scoreburgr---------{OSU_VERSION}");
+        let cipher =
+            RijndaelCbc::<Pkcs7Padding>::new(key.as_bytes(), 32).unwrap();
+
+        STANDARD.encode(cipher.encrypt(&IV, data.as_bytes().to_vec()).unwrap())
+    }
+
+    fn score_data(checksum: &str, client_version: &str) -> String {
+        format!(
+            "beatmap_md5:peace:{checksum}:100:10:1:20:5:2:123456:321:False:A:\
+             72:True:0:230326120000:{client_version}"
+        )
+    }
+
+    fn checksum() -> String {
+        format!(
+            "{:x}",
+            md5::compute(
+                "chickenmcnuggets110o15120smustard52uubeatmap_md5321Falsepeace\
+                 123456A72QTrue020230326230326120000client_hashstoryboard"
+            )
+        )
+    }
+
+    fn form(score_data: &str) -> OsuScoreSubmissionForm {
+        OsuScoreSubmissionForm {
+            score_data: encrypt(score_data),
+            iv: STANDARD.encode(IV),
+            client_hash: encrypt("client_hash"),
+            osu_version: OSU_VERSION.to_owned(),
+            password_md5: "password_md5".to_owned(),
+            storyboard_checksum: "storyboard".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_score_submission() {
+        // the two trailing spaces of the client version are the client flags
+        let data = score_data(&checksum(), &format!("{OSU_VERSION}  "));
+        let request = parse_osu_score_submission(form(&data)).unwrap();
+
+        assert_eq!(request.beatmap_md5, "beatmap_md5");
+        assert_eq!(request.username, "peace");
+        assert_eq!(request.checksum, checksum());
+        assert_eq!(
+            (request.n300, request.n100, request.n50, request.miss),
+            (100, 10, 1, 2)
+        );
+        assert_eq!((request.geki, request.katu), (20, 5));
+        assert_eq!(request.score, 123456);
+        assert_eq!(request.max_combo, 321);
+        assert!(!request.perfect);
+        assert_eq!(request.grade, "A");
+        assert_eq!(request.mods, 72);
+        assert!(request.passed);
+        assert_eq!(request.mode, 0);
+        assert_eq!(request.client_version, OSU_VERSION);
+        assert_eq!(request.client_flags, 2);
+    }
+
+    #[test]
+    fn test_parse_score_submission_mismatched_checksum() {
+        let data = score_data(&"0".repeat(32), OSU_VERSION);
+
+        assert!(matches!(
+            parse_osu_score_submission(form(&data)),
+            Err(SubmitScoreError::MismatchedChecksum)
+        ));
+    }
+
+    #[test]
+    fn test_parse_score_submission_mismatched_client_version() {
+        let data = score_data(&checksum(), "20230101");
+
+        assert!(matches!(
+            parse_osu_score_submission(form(&data)),
+            Err(SubmitScoreError::MismatchedClientVersion)
+        ));
+    }
+
+    #[test]
+    fn test_parse_score_submission_invalid_data() {
+        let mut invalid_iv = form(&score_data(&checksum(), OSU_VERSION));
+        invalid_iv.iv = "not base64!".to_owned();
+        assert!(matches!(
+            parse_osu_score_submission(invalid_iv),
+            Err(SubmitScoreError::DecryptFailed)
+        ));
+
+        assert!(matches!(
+            parse_osu_score_submission(form("beatmap_md5:peace")),
+            Err(SubmitScoreError::InvalidScoreData)
+        ));
+
+        let mut missing = form(&score_data(&checksum(), OSU_VERSION));
+        missing.password_md5.clear();
+        assert!(matches!(
+            parse_osu_score_submission(missing),
+            Err(SubmitScoreError::MissingField("pass"))
+        ));
+    }
+}
@@ -1,10 +1,26 @@
 use crate::bancho_endpoints::{
-    extractors::{BanchoClientVersion, BanchoRequestBody, OsuTokenHeader},
+    extractors::{
+        BanchoClientVersion, BanchoRequestBody, ChatHistoryQuery,
+        OsuAddFavouriteQuery, OsuBanchoConnectQuery, OsuCheckUpdatesQuery,
+        OsuClientCredentials, OsuGetBeatmapInfoBody, OsuGetReplayQuery,
+        OsuGetScoresQuery, OsuRateQuery, OsuRegistrationForm,
+        OsuScoreSubmissionForm, OsuScreenshotForm, OsuSearchQuery,
+        OsuSearchSetQuery, OsuTokenHeader,
+    },
     BanchoHttpError, DynBanchoRoutingService,
 };
-use axum::{extract::Path, response::Response, routing::*, Extension, Router};
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query},
+    response::Response,
+    routing::*,
+    Extension, Json, Router,
+};
 use peace_api::extractors::*;
 
+/// Max body size of the screenshot uploads, the size of the image itself is
+/// limited by the bancho service.
+const SCREENSHOT_BODY_LIMIT: usize = 4 * 1024 * 1024;
+
 pub struct BanchoRouter;
 
 impl BanchoRouter {
@@ -20,9 +36,13 @@ impl BanchoRouter {
             .route("/p/doyoureallywanttoaskpeppy", get(ask_peppy))
             .route("/difficulty-rating", get(difficulty_rating))
             .route("/web/osu-error.php", post(osu_error))
-            .route("/web/osu-screenshot.php", post(osu_screenshot))
+            .route(
+                "/web/osu-screenshot.php",
+                post(osu_screenshot)
+                    .layer(DefaultBodyLimit::max(SCREENSHOT_BODY_LIMIT)),
+            )
             .route("/web/osu-getfriends.php", get(osu_getfriends))
-            .route("/web/osu-getbeatmapinfo.php", get(osu_getbeatmapinfo))
+            .route("/web/osu-getbeatmapinfo.php", post(osu_getbeatmapinfo))
             .route("/web/osu-getfavourites.php", get(osu_getfavourites))
             .route("/web/osu-addfavourite.php", get(osu_addfavourite))
             .route("/web/lastfm.php", get(lastfm))
@@ -39,8 +59,14 @@ impl BanchoRouter {
             .route("/web/osu-markasread.php", get(osu_markasread))
             .route("/web/osu-getseasonal.php", get(osu_getseasonal))
             .route("/web/bancho_connect.php", get(bancho_connect))
-            .route("/web/check-updates", get(check_updates))
+            .route("/web/check-updates.php", get(check_updates))
             .route("/web/maps/:beatmap_file_name", get(update_beatmap))
+            .route("/replays/:score_id", get(download_replay))
+            .route(
+                "/chat/channels/:channel_name/messages",
+                get(get_channel_messages),
+            )
+            .route("/chat/users/:user_id/messages", get(get_private_messages))
             .layer(Extension(bancho_routing_service))
     }
 }
@@ -86,12 +112,14 @@ pub async fn bancho_post(
     tag = "bancho",
     responses(
         (status = 200, description = "Bancho get_screenshot"),
+        (status = 404, description = "Screenshot not exists"),
     )
 )]
 pub async fn get_screenshot(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.get_screenshot().await
+    Path(screenshot): Path<String>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.get_screenshot(screenshot).await
 }
 
 /// Bancho download_beatmapset
@@ -101,13 +129,15 @@ pub async fn get_screenshot(
     tag = "bancho",
     responses(
         (status = 200, description = "Bancho download_beatmapset"),
+        (status = 401, description = "Invalid credentials"),
     )
 )]
 pub async fn download_beatmapset(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-    Path(beatmapset_id): Path<i32>,
-) -> Response {
-    routing_service.download_beatmapset(beatmapset_id).await
+    Path(beatmapset): Path<String>,
+    Query(credentials): Query<OsuClientCredentials>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.download_beatmapset(credentials, beatmapset).await
 }
 
 /// Bancho client_register
@@ -117,12 +147,15 @@ pub async fn download_beatmapset(
     tag = "bancho",
     responses(
         (status = 200, description = "Bancho client_register", body = [String]),
+        (status = 400, description = "Errors of the registration form fields"),
     )
 )]
 pub async fn client_register(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.client_register().await
+    ClientIp(ip): ClientIp,
+    form: OsuRegistrationForm,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.client_register(ip, form).await
 }
 
 /// Bancho ask_peppy
@@ -177,12 +210,16 @@ pub async fn osu_error(
     tag = "bancho",
     responses(
         (status = 200, description = "Bancho osu_screenshot", body = [String]),
+        (status = 400, description = "Invalid screenshot"),
+        (status = 413, description = "Screenshot is too large"),
+        (status = 429, description = "Screenshot quota exceeded"),
     )
 )]
 pub async fn osu_screenshot(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_screenshot().await
+    form: OsuScreenshotForm,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_screenshot(form).await
 }
 
 /// Bancho osu_getfriends
@@ -196,13 +233,14 @@ pub async fn osu_screenshot(
 )]
 pub async fn osu_getfriends(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_getfriends().await
+    Query(credentials): Query<OsuClientCredentials>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_getfriends(credentials).await
 }
 
 /// Bancho osu_getbeatmapinfo
 #[utoipa::path(
-    get,
+    post,
     path = "/web/osu-getbeatmapinfo.php",
     tag = "bancho",
     responses(
@@ -211,8 +249,10 @@ pub async fn osu_getfriends(
 )]
 pub async fn osu_getbeatmapinfo(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_getbeatmapinfo().await
+    Query(credentials): Query<OsuClientCredentials>,
+    Json(body): Json<OsuGetBeatmapInfoBody>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_getbeatmapinfo(credentials, body).await
 }
 
 /// Bancho osu_getfavourites
@@ -226,8 +266,9 @@ pub async fn osu_getbeatmapinfo(
 )]
 pub async fn osu_getfavourites(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_getfavourites().await
+    Query(credentials): Query<OsuClientCredentials>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_getfavourites(credentials).await
 }
 
 /// Bancho osu_addfavourite
@@ -241,8 +282,9 @@ pub async fn osu_getfavourites(
 )]
 pub async fn osu_addfavourite(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_addfavourite().await
+    Query(query): Query<OsuAddFavouriteQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_addfavourite(query).await
 }
 
 /// Bancho lastfm
@@ -271,8 +313,9 @@ pub async fn lastfm(
 )]
 pub async fn osu_search(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Query(query): Query<OsuSearchQuery>,
 ) -> Response {
-    routing_service.osu_search().await
+    routing_service.osu_search(query).await
 }
 
 /// Bancho osu_search_set
@@ -286,8 +329,9 @@ pub async fn osu_search(
 )]
 pub async fn osu_search_set(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_search_set().await
+    Query(query): Query<OsuSearchSetQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_search_set(query).await
 }
 
 /// Bancho osu_submit_modular_selector
@@ -301,8 +345,9 @@ pub async fn osu_search_set(
 )]
 pub async fn osu_submit_modular_selector(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_submit_modular_selector().await
+    form: OsuScoreSubmissionForm,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_submit_modular_selector(form).await
 }
 
 /// Bancho osu_getreplay
@@ -316,8 +361,9 @@ pub async fn osu_submit_modular_selector(
 )]
 pub async fn osu_getreplay(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_getreplay().await
+    Query(query): Query<OsuGetReplayQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_getreplay(query).await
 }
 
 /// Bancho osu_rate
@@ -331,8 +377,9 @@ pub async fn osu_getreplay(
 )]
 pub async fn osu_rate(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Query(query): Query<OsuRateQuery>,
 ) -> Response {
-    routing_service.osu_rate().await
+    routing_service.osu_rate(query).await
 }
 
 /// Bancho osu_osz2_getscores
@@ -346,8 +393,9 @@ pub async fn osu_rate(
 )]
 pub async fn osu_osz2_getscores(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
-) -> Response {
-    routing_service.osu_osz2_getscores().await
+    Query(query): Query<OsuGetScoresQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.osu_osz2_getscores(query).await
 }
 
 /// Bancho osu_comment
@@ -406,8 +454,10 @@ pub async fn osu_getseasonal(
 )]
 pub async fn bancho_connect(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
+    ClientIp(ip): ClientIp,
+    Query(query): Query<OsuBanchoConnectQuery>,
 ) -> Response {
-    routing_service.bancho_connect().await
+    routing_service.bancho_connect(ip, query).await
 }
 
 /// Bancho check_updates
@@ -421,8 +471,9 @@ pub async fn bancho_connect(
 )]
 pub async fn check_updates(
     Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Query(query): Query<OsuCheckUpdatesQuery>,
 ) -> Response {
-    routing_service.check_updates().await
+    routing_service.check_updates(query).await
 }
 
 /// Bancho update_beatmap
@@ -439,3 +490,60 @@ pub async fn update_beatmap(
 ) -> Response {
     routing_service.update_beatmap().await
 }
+
+/// Bancho download_replay
+#[utoipa::path(
+    get,
+    path = "/replays/{score_id}",
+    tag = "bancho",
+    responses(
+        (status = 200, description = "Bancho download_replay"),
+    )
+)]
+pub async fn download_replay(
+    Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Path(score_id): Path<i64>,
+    credentials: Option<Query<OsuClientCredentials>>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service
+        .download_replay(score_id, credentials.map(|Query(c)| c))
+        .await
+}
+
+/// Get the message history of a channel, older pages are loaded with the
+/// `next_before_id` of the last response
+#[utoipa::path(
+    get,
+    path = "/chat/channels/{channel_name}/messages",
+    tag = "bancho",
+    responses(
+        (status = 200, description = "Bancho get_channel_messages"),
+    )
+)]
+pub async fn get_channel_messages(
+    Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Path(channel_name): Path<String>,
+    Query(credentials): Query<OsuClientCredentials>,
+    Query(query): Query<ChatHistoryQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.get_channel_messages(channel_name, credentials, query).await
+}
+
+/// Get the private conversation with a user, older pages are loaded with the
+/// `next_before_id` of the last response
+#[utoipa::path(
+    get,
+    path = "/chat/users/{user_id}/messages",
+    tag = "bancho",
+    responses(
+        (status = 200, description = "Bancho get_private_messages"),
+    )
+)]
+pub async fn get_private_messages(
+    Extension(routing_service): Extension<DynBanchoRoutingService>,
+    Path(user_id): Path<i32>,
+    Query(credentials): Query<OsuClientCredentials>,
+    Query(query): Query<ChatHistoryQuery>,
+) -> Result<Response, BanchoHttpError> {
+    routing_service.get_private_messages(user_id, credentials, query).await
+}
@@ -0,0 +1,426 @@
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::{Mutex, RwLock};
+
+/// The update actions of the osu! clients.
+const UPDATE_ACTIONS: [&str; 3] = ["check", "path", "error"];
+
+/// The release streams of the osu! clients, the update checks of other
+/// streams are never pinned or sent upstream.
+const RELEASE_STREAMS: [&str; 3] = ["stable40", "beta40", "cuttingedge"];
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliGatewayClientConfigs {
+    /// Urls of the seasonal backgrounds shown in the main menu of the osu!
+    /// clients.
+    #[arg(long, value_delimiter = ',')]
+    pub seasonal_backgrounds: Vec<String>,
+
+    /// A file of the seasonal background urls, one per line, replaces
+    /// `seasonal_backgrounds` and is reloaded when it's modified.
+    #[arg(long)]
+    pub seasonal_backgrounds_file: Option<String>,
+
+    /// Min seconds between the modification checks of the seasonal
+    /// backgrounds file.
+    #[default(30)]
+    #[arg(long, default_value = "30")]
+    pub seasonal_backgrounds_reload_secs: u64,
+
+    /// A directory of the pinned release streams, the update check of a
+    /// stream is answered with the `{stream}.json` file in it.
+    #[arg(long)]
+    pub client_update_pinned_dir: Option<String>,
+
+    /// Base url of the upstream the update checks of the streams not pinned
+    /// are proxied to, such as `https://osu.ppy.sh`.
+    #[arg(long)]
+    pub client_update_upstream: Option<String>,
+
+    /// Seconds the responses of the update upstream are cached.
+    #[default(3600)]
+    #[arg(long, default_value = "3600")]
+    pub client_update_cache_secs: u64,
+
+    #[default(10)]
+    #[arg(long, default_value = "10")]
+    pub client_update_timeout_secs: u64,
+}
+
+#[derive(Debug, Default)]
+struct SeasonalBackgroundsState {
+    checked_at: Option<Instant>,
+    modified: Option<SystemTime>,
+    urls: Arc<Vec<String>>,
+}
+
+/// The seasonal background urls, taken from the file if it's set, or the
+/// configured list otherwise.
+#[derive(Debug)]
+pub struct SeasonalBackgrounds {
+    pub file: Option<PathBuf>,
+    pub reload_interval: Duration,
+    state: RwLock<SeasonalBackgroundsState>,
+}
+
+impl SeasonalBackgrounds {
+    #[inline]
+    pub fn new(cfg: &CliGatewayClientConfigs) -> Self {
+        Self {
+            file: cfg.seasonal_backgrounds_file.as_ref().map(PathBuf::from),
+            reload_interval: Duration::from_secs(
+                cfg.seasonal_backgrounds_reload_secs,
+            ),
+            state: RwLock::new(SeasonalBackgroundsState {
+                urls: cfg.seasonal_backgrounds.clone().into(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[inline]
+    fn is_fresh(&self, state: &SeasonalBackgroundsState) -> bool {
+        state.checked_at.is_some_and(|checked_at| {
+            checked_at.elapsed() < self.reload_interval
+        })
+    }
+
+    /// The file is reloaded if it's modified since the last check, the
+    /// previous urls are kept if it fails to be read.
+    pub async fn urls(&self) -> Arc<Vec<String>> {
+        const LOG_TARGET: &str = "gateway::seasonal_backgrounds";
+
+        let Some(file) = &self.file else {
+            return self.state.read().await.urls.clone();
+        };
+
+        {
+            let state = self.state.read().await;
+            if self.is_fresh(&state) {
+                return state.urls.clone();
+            }
+        }
+
+        let mut state = self.state.write().await;
+        // checked by another request while waiting for the lock
+        if self.is_fresh(&state) {
+            return state.urls.clone();
+        }
+
+        state.checked_at = Some(Instant::now());
+
+        let modified =
+            match tokio::fs::metadata(file).await.and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(err) => {
+                    warn!(
+                        target: LOG_TARGET,
+                        "Failed to check the seasonal backgrounds file \
+                         {file:?}: {err}"
+                    );
+                    return state.urls.clone();
+                },
+            };
+
+        if state.modified == Some(modified) {
+            return state.urls.clone();
+        }
+
+        match tokio::fs::read_to_string(file).await {
+            Ok(content) => {
+                state.urls = parse_seasonal_backgrounds(&content).into();
+                state.modified = Some(modified);
+
+                info!(
+                    target: LOG_TARGET,
+                    "Loaded {} seasonal backgrounds from {file:?}",
+                    state.urls.len()
+                );
+            },
+            Err(err) => warn!(
+                target: LOG_TARGET,
+                "Failed to read the seasonal backgrounds file {file:?}: {err}"
+            ),
+        }
+
+        state.urls.clone()
+    }
+}
+
+/// One url per line, the empty lines and the lines starting with `#` are
+/// skipped.
+pub fn parse_seasonal_backgrounds(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The action and the release stream of an update check.
+type UpdateKey = (String, String);
+
+/// Answers the update checks of the osu! clients, with the pinned release
+/// streams first and the cached responses of the upstream then.
+#[derive(Debug)]
+pub struct ClientUpdates {
+    pub pinned_dir: Option<PathBuf>,
+    pub upstream: Option<String>,
+    pub client: reqwest::Client,
+    pub cache_expires: Duration,
+    cache: RwLock<HashMap<UpdateKey, (Instant, String)>>,
+    /// Held while requesting the upstream, so the concurrent checks of the
+    /// same stream wait for a single request.
+    fetching: std::sync::Mutex<HashMap<UpdateKey, Arc<Mutex<()>>>>,
+}
+
+impl ClientUpdates {
+    #[inline]
+    pub fn new(cfg: &CliGatewayClientConfigs) -> Self {
+        Self {
+            pinned_dir: cfg
+                .client_update_pinned_dir
+                .as_ref()
+                .map(PathBuf::from),
+            upstream: cfg
+                .client_update_upstream
+                .as_ref()
+                .map(|url| url.trim_end_matches('/').to_owned()),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(cfg.client_update_timeout_secs))
+                .build()
+                .unwrap_or_default(),
+            cache_expires: Duration::from_secs(cfg.client_update_cache_secs),
+            cache: RwLock::default(),
+            fetching: std::sync::Mutex::default(),
+        }
+    }
+
+    /// The cached response of the upstream, `None` if it's expired.
+    async fn cached(&self, key: &UpdateKey) -> Option<String> {
+        self.cache
+            .read()
+            .await
+            .get(key)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.cache_expires)
+            .map(|(_, response)| response.clone())
+    }
+
+    /// The json response of the update check, `None` if the stream is
+    /// neither pinned nor available from the upstream.
+    pub async fn check(&self, action: &str, stream: &str) -> Option<String> {
+        const LOG_TARGET: &str = "gateway::client_updates";
+
+        if !is_valid_update_param(action, stream) {
+            return None;
+        }
+
+        if let Some(dir) = &self.pinned_dir {
+            let path = dir.join(format!("{stream}.json"));
+            match tokio::fs::read_to_string(&path).await {
+                Ok(pinned) => return Some(pinned),
+                Err(err) if err.kind() == ErrorKind::NotFound => {},
+                Err(err) => warn!(
+                    target: LOG_TARGET,
+                    "Failed to read the pinned release stream {path:?}: {err}"
+                ),
+            }
+        }
+
+        let upstream = self.upstream.as_ref()?;
+        let key = (action.to_owned(), stream.to_owned());
+
+        if let Some(response) = self.cached(&key).await {
+            return Some(response);
+        }
+
+        let lock = self
+            .fetching
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let _fetching = lock.lock().await;
+
+        // fetched by another request while waiting for the lock
+        let cached = self.cache.read().await.get(&key).cloned();
+        if let Some((cached_at, response)) = &cached {
+            if cached_at.elapsed() < self.cache_expires {
+                return Some(response.clone());
+            }
+        }
+
+        let response = self
+            .client
+            .get(format!("{upstream}/web/check-updates.php"))
+            .query(&[("action", action), ("stream", stream)])
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+
+        let response = match response {
+            Ok(resp) => resp.text().await,
+            Err(err) => Err(err),
+        };
+
+        match response {
+            Ok(response) => {
+                let mut cache = self.cache.write().await;
+                cache.retain(|_, (cached_at, _)| {
+                    cached_at.elapsed() < self.cache_expires
+                });
+                cache.insert(key, (Instant::now(), response.clone()));
+
+                Some(response)
+            },
+            // serve the expired response if the upstream is unavailable
+            Err(err) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to check updates of \"{stream}\" from the \
+                     upstream: {err}"
+                );
+                cached.map(|(_, response)| response)
+            },
+        }
+    }
+}
+
+/// Only the known actions and release streams are answered, so the cache
+/// and the upstream requests are bounded.
+#[inline]
+fn is_valid_update_param(action: &str, stream: &str) -> bool {
+    UPDATE_ACTIONS.contains(&action) && RELEASE_STREAMS.contains(&stream)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const UPDATES: &str = r#"[{"file_version":"1"}]"#;
+
+    fn client_updates(
+        pinned_dir: Option<PathBuf>,
+        upstream: Option<String>,
+    ) -> ClientUpdates {
+        ClientUpdates {
+            pinned_dir,
+            upstream,
+            client: reqwest::Client::new(),
+            cache_expires: Duration::from_secs(60),
+            cache: RwLock::default(),
+            fetching: std::sync::Mutex::default(),
+        }
+    }
+
+    /// Answers every request with the updates after a delay.
+    async fn serve_upstream() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 4096];
+                    let _ = stream.read(&mut buf).await.unwrap();
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+
+                    let resp = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                        Connection: close\r\n\r\n{UPDATES}",
+                        UPDATES.len()
+                    );
+                    stream.write_all(resp.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    #[test]
+    fn test_parse_seasonal_backgrounds() {
+        let content = "# backgrounds\n\
+            https://a.ppy.sh/1.jpg\n\
+            \n  https://a.ppy.sh/2.jpg  \r\n\
+            #https://a.ppy.sh/3.jpg\n";
+
+        assert_eq!(
+            parse_seasonal_backgrounds(content),
+            ["https://a.ppy.sh/1.jpg", "https://a.ppy.sh/2.jpg"]
+        );
+        assert!(parse_seasonal_backgrounds("").is_empty());
+    }
+
+    #[test]
+    fn test_is_valid_update_param() {
+        assert!(is_valid_update_param("check", "stable40"));
+        assert!(is_valid_update_param("path", "beta40"));
+        assert!(is_valid_update_param("error", "cuttingedge"));
+
+        assert!(!is_valid_update_param("check", "stable"));
+        assert!(!is_valid_update_param("check", "random123"));
+        assert!(!is_valid_update_param("check", "../stable40"));
+        assert!(!is_valid_update_param("delete", "stable40"));
+        assert!(!is_valid_update_param("", ""));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_streams() {
+        let dir = std::env::temp_dir()
+            .join(format!("peace-release-streams-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stable40.json"), UPDATES).unwrap();
+        std::fs::write(dir.join("custom.json"), UPDATES).unwrap();
+
+        let updates = client_updates(Some(dir.clone()), None);
+        let pinned = updates.check("check", "stable40").await;
+        let not_pinned = updates.check("check", "beta40").await;
+        let unknown = updates.check("check", "custom").await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(pinned.as_deref(), Some(UPDATES));
+        assert_eq!(not_pinned, None);
+        // only the known release streams are answered
+        assert_eq!(unknown, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_checks_coalesced() {
+        let (upstream, requests) = serve_upstream().await;
+        let updates = client_updates(None, Some(upstream));
+
+        let responses = futures::future::join_all(
+            (0..4).map(|_| updates.check("check", "stable40")),
+        )
+        .await;
+
+        for response in responses {
+            assert_eq!(response.as_deref(), Some(UPDATES));
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // the unknown streams are never sent upstream
+        assert_eq!(updates.check("check", "random123").await, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,17 +1,33 @@
 use super::traits::{BanchoHandlerService, DynBanchoHandlerService};
-use crate::bancho_endpoints::{extractors::BanchoClientVersion, *};
+use crate::bancho_endpoints::{
+    extractors::{
+        BanchoClientVersion, ChatHistoryQuery, OsuAddFavouriteQuery,
+        OsuBanchoConnectQuery, OsuClientCredentials, OsuGetBeatmapInfoBody,
+        OsuGetReplayQuery, OsuGetScoresQuery, OsuRateQuery,
+        OsuRegistrationForm, OsuScoreSubmissionForm, OsuScreenshotForm,
+        OsuSearchQuery, OsuSearchSetQuery,
+    },
+    *,
+};
 use async_trait::async_trait;
 use axum::response::{IntoResponse, Response};
 use bancho_packets::PacketBuilder;
 use bancho_packets::PacketReader;
-use core_bancho::DynBanchoService;
+use core_bancho::{
+    BanchoServiceError, BeatmapsetChunkStream, DynBanchoService,
+};
 use core_bancho_state::{BanchoStateError, DynBanchoStateService};
 use core_chat::{ChatError, DynChatService};
 use domain_bancho::BanchoClientToken;
+use domain_chat::Platform;
 use pb_bancho::*;
 use pb_bancho_state::{
     CheckUserTokenResponse, DequeueBanchoPacketsRequest, UserQuery,
 };
+use pb_chat::{
+    ChannelQuery, ChatHistory, GetChannelHistoryRequest,
+    GetPrivateConversationRequest,
+};
 use std::{net::IpAddr, str::FromStr, sync::Arc};
 use tools::lazy_init;
 
@@ -167,7 +183,7 @@ impl BanchoHandlerService for BanchoHandlerServiceImpl {
         query: UserQuery,
     ) -> Result<Vec<u8>, ChatError> {
         self.chat_service
-            .dequeue_chat_packets(query)
+            .dequeue_chat_packets(query, Platform::Bancho)
             .await
             .map(|resp| resp.data)
     }
@@ -182,4 +198,380 @@ impl BanchoHandlerService for BanchoHandlerServiceImpl {
 
         Ok(is_valid)
     }
+
+    #[inline]
+    async fn get_friends(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Vec<i32>, BanchoServiceError> {
+        let OsuClientCredentials { username, password_md5 } = credentials;
+
+        let GetFriendsResponse { friends } = self
+            .bancho_service
+            .get_friends(GetFriendsRequest { username, password: password_md5 })
+            .await?;
+
+        Ok(friends)
+    }
+
+    #[inline]
+    async fn submit_score(
+        &self,
+        form: OsuScoreSubmissionForm,
+    ) -> Result<String, SubmitScoreError> {
+        let request = parser::parse_osu_score_submission(form)?;
+
+        let SubmitScoreResponse { chart } =
+            self.bancho_service.submit_score(request).await?;
+
+        Ok(chart)
+    }
+
+    #[inline]
+    async fn get_beatmap_scores(
+        &self,
+        query: OsuGetScoresQuery,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuGetScoresQuery {
+            username,
+            password_md5,
+            beatmap_md5,
+            mode,
+            mods,
+            leaderboard_type,
+        } = query;
+
+        let GetBeatmapScoresResponse { scores } = self
+            .bancho_service
+            .get_beatmap_scores(GetBeatmapScoresRequest {
+                username,
+                password: password_md5,
+                beatmap_md5,
+                mode,
+                mods,
+                leaderboard_type,
+            })
+            .await?;
+
+        Ok(scores)
+    }
+
+    #[inline]
+    async fn get_beatmap_info(
+        &self,
+        credentials: OsuClientCredentials,
+        body: OsuGetBeatmapInfoBody,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuClientCredentials { username, password_md5 } = credentials;
+        let OsuGetBeatmapInfoBody { file_names, beatmap_ids } = body;
+
+        let GetBeatmapInfoResponse { beatmaps } = self
+            .bancho_service
+            .get_beatmap_info(GetBeatmapInfoRequest {
+                username,
+                password: password_md5,
+                file_names,
+                beatmap_ids,
+            })
+            .await?;
+
+        Ok(beatmaps)
+    }
+
+    async fn get_replay(
+        &self,
+        query: OsuGetReplayQuery,
+    ) -> Result<Vec<u8>, BanchoServiceError> {
+        let OsuGetReplayQuery { score_id, username, password_md5 } = query;
+
+        let GetReplayResponse { replay } = self
+            .bancho_service
+            .get_replay(GetReplayRequest {
+                username,
+                password: password_md5,
+                score_id,
+            })
+            .await?;
+
+        Ok(replay)
+    }
+
+    async fn export_replay(
+        &self,
+        score_id: i64,
+        credentials: Option<OsuClientCredentials>,
+    ) -> Result<Vec<u8>, BanchoServiceError> {
+        let (username, password) = credentials
+            .map(|c| (Some(c.username), Some(c.password_md5)))
+            .unwrap_or_default();
+
+        let GetReplayResponse { replay } = self
+            .bancho_service
+            .export_replay(GetReplayRequest { username, password, score_id })
+            .await?;
+
+        Ok(replay)
+    }
+
+    async fn verify_credentials(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<i32, BanchoServiceError> {
+        let OsuClientCredentials { username, password_md5 } = credentials;
+
+        let VerifyCredentialsResponse { user_id } = self
+            .bancho_service
+            .verify_credentials(VerifyCredentialsRequest {
+                username,
+                password: password_md5,
+            })
+            .await?;
+
+        Ok(user_id)
+    }
+
+    async fn upload_screenshot(
+        &self,
+        form: OsuScreenshotForm,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuScreenshotForm { username, password_md5, screenshot } = form;
+
+        let UploadScreenshotResponse { name } = self
+            .bancho_service
+            .upload_screenshot(UploadScreenshotRequest {
+                username,
+                password: password_md5,
+                data: screenshot
+                    .ok_or(BanchoServiceError::InvalidScreenshot)?,
+            })
+            .await?;
+
+        Ok(name)
+    }
+
+    async fn get_screenshot(
+        &self,
+        name: String,
+    ) -> Result<GetScreenshotResponse, BanchoServiceError> {
+        self.bancho_service.get_screenshot(GetScreenshotRequest { name }).await
+    }
+
+    async fn get_favourite_beatmaps(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Vec<i32>, BanchoServiceError> {
+        let OsuClientCredentials { username, password_md5 } = credentials;
+
+        let GetFavouriteBeatmapsResponse { beatmapset_ids } = self
+            .bancho_service
+            .get_favourite_beatmaps(GetFavouriteBeatmapsRequest {
+                username,
+                password: password_md5,
+            })
+            .await?;
+
+        Ok(beatmapset_ids)
+    }
+
+    async fn add_favourite_beatmap(
+        &self,
+        query: OsuAddFavouriteQuery,
+    ) -> Result<bool, BanchoServiceError> {
+        let OsuAddFavouriteQuery { username, password_md5, beatmapset_id } =
+            query;
+
+        let AddFavouriteBeatmapResponse { added } = self
+            .bancho_service
+            .add_favourite_beatmap(AddFavouriteBeatmapRequest {
+                username,
+                password: password_md5,
+                beatmapset_id,
+            })
+            .await?;
+
+        Ok(added)
+    }
+
+    async fn rate_beatmap(
+        &self,
+        query: OsuRateQuery,
+    ) -> Result<Option<f64>, BanchoServiceError> {
+        let OsuRateQuery { username, password_md5, beatmap_md5, rating } =
+            query;
+
+        let RateBeatmapResponse { average_rating } = self
+            .bancho_service
+            .rate_beatmap(RateBeatmapRequest {
+                username,
+                password: password_md5,
+                beatmap_md5,
+                rating,
+            })
+            .await?;
+
+        Ok(average_rating)
+    }
+
+    async fn search_beatmapsets(
+        &self,
+        query: OsuSearchQuery,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuSearchQuery {
+            username,
+            password_md5,
+            query,
+            mode,
+            ranked_status,
+            page,
+        } = query;
+
+        let SearchBeatmapsetsResponse { beatmapsets } = self
+            .bancho_service
+            .search_beatmapsets(SearchBeatmapsetsRequest {
+                username,
+                password: password_md5,
+                query,
+                mode,
+                ranked_status,
+                page,
+            })
+            .await?;
+
+        Ok(beatmapsets)
+    }
+
+    async fn get_beatmapset_info(
+        &self,
+        query: OsuSearchSetQuery,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuSearchSetQuery {
+            username,
+            password_md5,
+            beatmapset_id,
+            beatmap_id,
+            beatmap_md5,
+        } = query;
+
+        let GetBeatmapsetInfoResponse { beatmapset } = self
+            .bancho_service
+            .get_beatmapset_info(GetBeatmapsetInfoRequest {
+                username,
+                password: password_md5,
+                beatmapset_id,
+                beatmap_id,
+                beatmap_md5,
+            })
+            .await?;
+
+        Ok(beatmapset)
+    }
+
+    async fn download_beatmapset(
+        &self,
+        credentials: OsuClientCredentials,
+        beatmapset_id: i32,
+        no_video: bool,
+    ) -> Result<BeatmapsetChunkStream, BanchoServiceError> {
+        let OsuClientCredentials { username, password_md5 } = credentials;
+
+        self.bancho_service
+            .download_beatmapset(DownloadBeatmapsetRequest {
+                beatmapset_id,
+                no_video,
+                username,
+                password: password_md5,
+            })
+            .await
+    }
+
+    async fn register(
+        &self,
+        client_ip: IpAddr,
+        form: OsuRegistrationForm,
+    ) -> Result<(), RegisterError> {
+        let OsuRegistrationForm { username, email, password, check } = form;
+
+        let request = RegisterRequest {
+            username: username
+                .ok_or(RegisterError::MissingField("username"))?,
+            email: email.ok_or(RegisterError::MissingField("user_email"))?,
+            password: password
+                .ok_or(RegisterError::MissingField("password"))?,
+            check,
+        };
+
+        self.bancho_service.register(client_ip, request).await?;
+
+        Ok(())
+    }
+
+    async fn bancho_connect(
+        &self,
+        client_ip: IpAddr,
+        query: OsuBanchoConnectQuery,
+    ) -> Result<String, BanchoServiceError> {
+        let OsuBanchoConnectQuery { username, password_md5, client_version } =
+            query;
+
+        let BanchoConnectResponse { country_code } = self
+            .bancho_service
+            .bancho_connect(
+                client_ip,
+                BanchoConnectRequest {
+                    username,
+                    password: password_md5,
+                    client_version,
+                },
+            )
+            .await?;
+
+        Ok(country_code)
+    }
+
+    async fn get_channel_history(
+        &self,
+        channel_name: String,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<ChatHistory, BanchoHttpError> {
+        let user_id = self.verify_credentials(credentials).await?;
+
+        // the channel name in the path can be given without the leading `#`
+        let channel_name = if channel_name.starts_with('#') {
+            channel_name
+        } else {
+            format!("#{channel_name}")
+        };
+
+        Ok(self
+            .chat_service
+            .get_channel_history(GetChannelHistoryRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelName(channel_name).into(),
+                ),
+                before_id: query.before,
+                limit: query.limit.unwrap_or_default(),
+                user_id,
+            })
+            .await?)
+    }
+
+    async fn get_private_conversation(
+        &self,
+        other_user_id: i32,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<ChatHistory, BanchoHttpError> {
+        let user_id = self.verify_credentials(credentials).await?;
+
+        Ok(self
+            .chat_service
+            .get_private_conversation(GetPrivateConversationRequest {
+                user_id,
+                other_user_id,
+                before_id: query.before,
+                limit: query.limit.unwrap_or_default(),
+            })
+            .await?)
+    }
 }
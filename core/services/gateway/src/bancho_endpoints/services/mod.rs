@@ -1,7 +1,9 @@
+pub mod client;
 pub mod handler;
 pub mod routing;
 pub mod traits;
 
+pub use client::*;
 pub use handler::*;
 pub use routing::*;
 pub use traits::*;
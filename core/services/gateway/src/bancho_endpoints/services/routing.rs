@@ -1,21 +1,50 @@
-use super::traits::{
-    BanchoRoutingService, DynBanchoHandlerService, DynBanchoRoutingService,
+use super::{
+    client::{CliGatewayClientConfigs, ClientUpdates, SeasonalBackgrounds},
+    traits::{
+        BanchoRoutingService, DynBanchoHandlerService, DynBanchoRoutingService,
+    },
 };
 use crate::bancho_endpoints::{
-    extractors::{BanchoClientVersion, OsuTokenHeader},
+    extractors::{
+        BanchoClientVersion, ChatHistoryQuery, OsuAddFavouriteQuery,
+        OsuBanchoConnectQuery, OsuCheckUpdatesQuery, OsuClientCredentials,
+        OsuGetBeatmapInfoBody, OsuGetReplayQuery, OsuGetScoresQuery,
+        OsuRateQuery, OsuRegistrationForm, OsuScoreSubmissionForm,
+        OsuScreenshotForm, OsuSearchQuery, OsuSearchSetQuery, OsuTokenHeader,
+    },
     BanchoHttpError,
 };
 use async_trait::async_trait;
-use axum::response::{IntoResponse, Response};
+use axum::{
+    body::StreamBody,
+    http::header::{
+        CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG,
+        X_CONTENT_TYPE_OPTIONS,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use core_bancho::BanchoServiceError;
+use futures::TryStreamExt;
+use pb_bancho::GetScreenshotResponse;
 use std::{net::IpAddr, sync::Arc};
 
 pub struct BanchoRoutingServiceImpl {
     pub bancho_handler_service: DynBanchoHandlerService,
+    pub seasonal_backgrounds: Arc<SeasonalBackgrounds>,
+    pub client_updates: Arc<ClientUpdates>,
 }
 
 impl BanchoRoutingServiceImpl {
-    pub fn new(bancho_handler_service: DynBanchoHandlerService) -> Self {
-        Self { bancho_handler_service }
+    pub fn new(
+        bancho_handler_service: DynBanchoHandlerService,
+        client_cfg: &CliGatewayClientConfigs,
+    ) -> Self {
+        Self {
+            bancho_handler_service,
+            seasonal_backgrounds: SeasonalBackgrounds::new(client_cfg).into(),
+            client_updates: ClientUpdates::new(client_cfg).into(),
+        }
     }
 
     pub fn into_service(self) -> DynBanchoRoutingService {
@@ -48,16 +77,70 @@ impl BanchoRoutingService for BanchoRoutingServiceImpl {
         }
     }
 
-    async fn get_screenshot(&self) -> Response {
-        unimplemented!()
+    async fn get_screenshot(
+        &self,
+        screenshot: String,
+    ) -> Result<Response, BanchoHttpError> {
+        let GetScreenshotResponse { data, content_type, hash } =
+            self.bancho_handler_service.get_screenshot(screenshot).await?;
+
+        // screenshots are content-addressed, so they never change
+        Ok((
+            [
+                (CONTENT_TYPE, content_type),
+                (
+                    CACHE_CONTROL,
+                    "public, max-age=31536000, immutable".to_owned(),
+                ),
+                (ETAG, format!("\"{hash}\"")),
+                // served as the detected image type only
+                (X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+            ],
+            data,
+        )
+            .into_response())
     }
 
-    async fn download_beatmapset(&self, _beatmapset_id: i32) -> Response {
-        unimplemented!()
+    async fn download_beatmapset(
+        &self,
+        credentials: OsuClientCredentials,
+        beatmapset: String,
+    ) -> Result<Response, BanchoHttpError> {
+        let (beatmapset_id, no_video) = match beatmapset.strip_suffix('n') {
+            Some(beatmapset_id) => (beatmapset_id, true),
+            None => (beatmapset.as_str(), false),
+        };
+
+        let beatmapset_id = beatmapset_id
+            .parse::<i32>()
+            .map_err(|_| BanchoHttpError::ParseRequestError)?;
+
+        let stream = self
+            .bancho_handler_service
+            .download_beatmapset(credentials, beatmapset_id, no_video)
+            .await?;
+
+        Ok((
+            [
+                (CONTENT_TYPE, "application/x-osu-beatmap-archive".to_owned()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{beatmapset_id}.osz\""),
+                ),
+            ],
+            StreamBody::new(stream.map_ok(|chunk| chunk.data)),
+        )
+            .into_response())
     }
 
-    async fn client_register(&self) -> Response {
-        unimplemented!()
+    async fn client_register(
+        &self,
+        ip: IpAddr,
+        form: OsuRegistrationForm,
+    ) -> Result<Response, BanchoHttpError> {
+        self.bancho_handler_service.register(ip, form).await?;
+
+        Ok("ok".into_response())
     }
 
     async fn ask_peppy(&self) -> Response {
@@ -72,52 +155,143 @@ impl BanchoRoutingService for BanchoRoutingServiceImpl {
         "ok".into_response()
     }
 
-    async fn osu_screenshot(&self) -> Response {
-        unimplemented!()
+    async fn osu_screenshot(
+        &self,
+        form: OsuScreenshotForm,
+    ) -> Result<Response, BanchoHttpError> {
+        let name = self.bancho_handler_service.upload_screenshot(form).await?;
+
+        Ok(name.into_response())
     }
 
-    async fn osu_getfriends(&self) -> Response {
-        "".into_response()
+    async fn osu_getfriends(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Response, BanchoHttpError> {
+        let friends =
+            self.bancho_handler_service.get_friends(credentials).await?;
+
+        Ok(friends
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .into_response())
     }
 
-    async fn osu_getbeatmapinfo(&self) -> Response {
-        unimplemented!()
+    async fn osu_getbeatmapinfo(
+        &self,
+        credentials: OsuClientCredentials,
+        body: OsuGetBeatmapInfoBody,
+    ) -> Result<Response, BanchoHttpError> {
+        let beatmaps = self
+            .bancho_handler_service
+            .get_beatmap_info(credentials, body)
+            .await?;
+
+        Ok(beatmaps.into_response())
     }
 
-    async fn osu_getfavourites(&self) -> Response {
-        unimplemented!()
+    async fn osu_getfavourites(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Response, BanchoHttpError> {
+        let beatmapset_ids = self
+            .bancho_handler_service
+            .get_favourite_beatmaps(credentials)
+            .await?;
+
+        Ok(beatmapset_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .into_response())
     }
 
-    async fn osu_addfavourite(&self) -> Response {
-        unimplemented!()
+    async fn osu_addfavourite(
+        &self,
+        query: OsuAddFavouriteQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let added =
+            self.bancho_handler_service.add_favourite_beatmap(query).await?;
+
+        Ok(if added {
+            "Added favourite!"
+        } else {
+            "You've already favourited this beatmap!"
+        }
+        .into_response())
     }
 
     async fn lastfm(&self) -> Response {
         "ok".into_response()
     }
 
-    async fn osu_search(&self) -> Response {
-        unimplemented!()
+    async fn osu_search(&self, query: OsuSearchQuery) -> Response {
+        match self.bancho_handler_service.search_beatmapsets(query).await {
+            Ok(beatmapsets) => beatmapsets.into_response(),
+            Err(
+                err @ (BanchoServiceError::PasswordError(..)
+                | BanchoServiceError::UserNotExists(..)),
+            ) => BanchoHttpError::from(err).into_response(),
+            // the client displays the message of the negative count
+            Err(BanchoServiceError::DirectDisabled) => {
+                "-1\nosu!direct is disabled on this server.".into_response()
+            },
+            Err(err) => {
+                warn!("[osu!direct] search failed: {err}");
+                "-1\nFailed to retrieve data from the beatmap mirror."
+                    .into_response()
+            },
+        }
     }
 
-    async fn osu_search_set(&self) -> Response {
-        unimplemented!()
+    async fn osu_search_set(
+        &self,
+        query: OsuSearchSetQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let beatmapset =
+            self.bancho_handler_service.get_beatmapset_info(query).await?;
+
+        Ok(beatmapset.into_response())
     }
 
-    async fn osu_submit_modular_selector(&self) -> Response {
-        unimplemented!()
+    async fn osu_submit_modular_selector(
+        &self,
+        form: OsuScoreSubmissionForm,
+    ) -> Result<Response, BanchoHttpError> {
+        let chart = self.bancho_handler_service.submit_score(form).await?;
+
+        Ok(chart.into_response())
     }
 
-    async fn osu_getreplay(&self) -> Response {
-        unimplemented!()
+    async fn osu_getreplay(
+        &self,
+        query: OsuGetReplayQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let replay = self.bancho_handler_service.get_replay(query).await?;
+
+        Ok(replay.into_response())
     }
 
-    async fn osu_rate(&self) -> Response {
-        unimplemented!()
+    async fn osu_rate(&self, query: OsuRateQuery) -> Response {
+        match osu_rate_reply(
+            self.bancho_handler_service.rate_beatmap(query).await,
+        ) {
+            Ok(reply) => reply.into_response(),
+            Err(err) => BanchoHttpError::from(err).into_response(),
+        }
     }
 
-    async fn osu_osz2_getscores(&self) -> Response {
-        unimplemented!()
+    async fn osu_osz2_getscores(
+        &self,
+        query: OsuGetScoresQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let scores =
+            self.bancho_handler_service.get_beatmap_scores(query).await?;
+
+        Ok(scores.into_response())
     }
 
     async fn osu_comment(&self) -> Response {
@@ -129,18 +303,161 @@ impl BanchoRoutingService for BanchoRoutingServiceImpl {
     }
 
     async fn osu_getseasonal(&self) -> Response {
-        "ok".into_response()
+        Json(self.seasonal_backgrounds.urls().await.as_slice()).into_response()
     }
 
-    async fn bancho_connect(&self) -> Response {
-        "ok".into_response()
+    async fn bancho_connect(
+        &self,
+        ip: IpAddr,
+        query: OsuBanchoConnectQuery,
+    ) -> Response {
+        let reply =
+            match self.bancho_handler_service.bancho_connect(ip, query).await {
+                Ok(country_code) => return country_code.into_response(),
+                Err(
+                    BanchoServiceError::PasswordError(..)
+                    | BanchoServiceError::UserNotExists(..),
+                ) => "error: pass",
+                Err(BanchoServiceError::OutdatedClient(..)) => "error: oldver",
+                Err(
+                    BanchoServiceError::UserBanned
+                    | BanchoServiceError::CountryNotAllowed(..),
+                ) => "error: disabled",
+                Err(err) => {
+                    warn!("[bancho_connect] {err}");
+                    "error: no"
+                },
+            };
+
+        reply.into_response()
     }
 
-    async fn check_updates(&self) -> Response {
-        "ok".into_response()
+    async fn check_updates(&self, query: OsuCheckUpdatesQuery) -> Response {
+        let OsuCheckUpdatesQuery { action, stream } = query;
+
+        let updates = self
+            .client_updates
+            .check(&action, &stream)
+            .await
+            .unwrap_or_else(|| "[]".to_owned());
+
+        ([(CONTENT_TYPE, "application/json")], updates).into_response()
     }
 
     async fn update_beatmap(&self) -> Response {
         "ok".into_response()
     }
+
+    async fn download_replay(
+        &self,
+        score_id: i64,
+        credentials: Option<OsuClientCredentials>,
+    ) -> Result<Response, BanchoHttpError> {
+        let replay = self
+            .bancho_handler_service
+            .export_replay(score_id, credentials)
+            .await?;
+
+        Ok((
+            [
+                (CONTENT_TYPE, "application/octet-stream".to_owned()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{score_id}.osr\""),
+                ),
+            ],
+            replay,
+        )
+            .into_response())
+    }
+
+    async fn get_channel_messages(
+        &self,
+        channel_name: String,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let history = self
+            .bancho_handler_service
+            .get_channel_history(channel_name, credentials, query)
+            .await?;
+
+        Ok(Json(history).into_response())
+    }
+
+    async fn get_private_messages(
+        &self,
+        user_id: i32,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<Response, BanchoHttpError> {
+        let history = self
+            .bancho_handler_service
+            .get_private_conversation(user_id, credentials, query)
+            .await?;
+
+        Ok(Json(history).into_response())
+    }
+}
+
+/// The replies of `osu-rate.php` read by the osu! clients, the other errors
+/// are http errors.
+pub fn osu_rate_reply(
+    result: Result<Option<f64>, BanchoServiceError>,
+) -> Result<String, BanchoServiceError> {
+    match result {
+        Ok(None) => Ok("ok".to_owned()),
+        Ok(Some(rating)) => Ok(format!("alreadyvoted\n{rating:.2}")),
+        Err(
+            BanchoServiceError::BeatmapError(..)
+            | BanchoServiceError::BeatmapNotRateable,
+        ) => Ok("no exist".to_owned()),
+        Err(
+            BanchoServiceError::PasswordError(..)
+            | BanchoServiceError::UserNotExists(..),
+        ) => Ok("auth fail".to_owned()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::http::StatusCode;
+    use domain_users::PasswordError;
+    use peace_repositories::{GetBeatmapError, GetUserError};
+
+    #[test]
+    fn test_osu_rate_reply() {
+        assert_eq!(osu_rate_reply(Ok(None)).unwrap(), "ok");
+        assert_eq!(
+            osu_rate_reply(Ok(Some(7.5))).unwrap(),
+            "alreadyvoted\n7.50"
+        );
+
+        for err in [
+            BanchoServiceError::BeatmapError(GetBeatmapError::BeatmapNotExists),
+            BanchoServiceError::BeatmapNotRateable,
+        ] {
+            assert_eq!(osu_rate_reply(Err(err)).unwrap(), "no exist");
+        }
+
+        for err in [
+            BanchoServiceError::PasswordError(PasswordError::InvalidPassword),
+            BanchoServiceError::UserNotExists(GetUserError::UserNotExists),
+        ] {
+            assert_eq!(osu_rate_reply(Err(err)).unwrap(), "auth fail");
+        }
+    }
+
+    #[test]
+    fn test_osu_rate_invalid_rating() {
+        let err = osu_rate_reply(Err(BanchoServiceError::InvalidRating(11)))
+            .unwrap_err();
+
+        assert_eq!(
+            BanchoHttpError::from(err).into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
 }
@@ -1,14 +1,22 @@
 use crate::bancho_endpoints::{
-    extractors::{BanchoClientVersion, OsuTokenHeader},
+    extractors::{
+        BanchoClientVersion, ChatHistoryQuery, OsuAddFavouriteQuery,
+        OsuBanchoConnectQuery, OsuCheckUpdatesQuery, OsuClientCredentials,
+        OsuGetBeatmapInfoBody, OsuGetReplayQuery, OsuGetScoresQuery,
+        OsuRateQuery, OsuRegistrationForm, OsuScoreSubmissionForm,
+        OsuScreenshotForm, OsuSearchQuery, OsuSearchSetQuery, OsuTokenHeader,
+    },
     *,
 };
 use async_trait::async_trait;
 use axum::response::Response;
+use core_bancho::{BanchoServiceError, BeatmapsetChunkStream};
 use core_bancho_state::BanchoStateError;
 use core_chat::ChatError;
 use domain_bancho::BanchoClientToken;
-use pb_bancho::LoginSuccess;
+use pb_bancho::{GetScreenshotResponse, LoginSuccess};
 use pb_bancho_state::UserQuery;
+use pb_chat::ChatHistory;
 use std::{net::IpAddr, sync::Arc};
 
 pub type DynBanchoRoutingService = Arc<dyn BanchoRoutingService + Send + Sync>;
@@ -29,13 +37,24 @@ pub trait BanchoRoutingService {
     ) -> Result<Response, BanchoHttpError>;
 
     /// get `/ss/{screenshot}`
-    async fn get_screenshot(&self) -> Response;
+    async fn get_screenshot(
+        &self,
+        screenshot: String,
+    ) -> Result<Response, BanchoHttpError>;
 
-    /// get `/d/{beatmapset_id}`
-    async fn download_beatmapset(&self, beatmapset_id: i32) -> Response;
+    /// get `/d/{beatmapset_id}`, the `n` suffix of the id means no video
+    async fn download_beatmapset(
+        &self,
+        credentials: OsuClientCredentials,
+        beatmapset: String,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// post `/users`
-    async fn client_register(&self) -> Response;
+    async fn client_register(
+        &self,
+        ip: IpAddr,
+        form: OsuRegistrationForm,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/p/doyoureallywanttoaskpeppy`
     async fn ask_peppy(&self) -> Response;
@@ -47,40 +66,68 @@ pub trait BanchoRoutingService {
     async fn osu_error(&self) -> Response;
 
     /// post `/web/osu-screenshot.php`
-    async fn osu_screenshot(&self) -> Response;
+    async fn osu_screenshot(
+        &self,
+        form: OsuScreenshotForm,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-getfriends.php`
-    async fn osu_getfriends(&self) -> Response;
+    async fn osu_getfriends(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Response, BanchoHttpError>;
 
-    /// get `/web/osu-getbeatmapinfo.php`
-    async fn osu_getbeatmapinfo(&self) -> Response;
+    /// post `/web/osu-getbeatmapinfo.php`
+    async fn osu_getbeatmapinfo(
+        &self,
+        credentials: OsuClientCredentials,
+        body: OsuGetBeatmapInfoBody,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-getfavourites.php`
-    async fn osu_getfavourites(&self) -> Response;
+    async fn osu_getfavourites(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-addfavourite.php`
-    async fn osu_addfavourite(&self) -> Response;
+    async fn osu_addfavourite(
+        &self,
+        query: OsuAddFavouriteQuery,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-lastfm.php`
     async fn lastfm(&self) -> Response;
 
     /// get `/web/osu-search.php`
-    async fn osu_search(&self) -> Response;
+    async fn osu_search(&self, query: OsuSearchQuery) -> Response;
 
     /// get `/web/osu-search-set.php`
-    async fn osu_search_set(&self) -> Response;
+    async fn osu_search_set(
+        &self,
+        query: OsuSearchSetQuery,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// post `/web/osu-submit-modular-selector.php`
-    async fn osu_submit_modular_selector(&self) -> Response;
+    async fn osu_submit_modular_selector(
+        &self,
+        form: OsuScoreSubmissionForm,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-getreplay.php`
-    async fn osu_getreplay(&self) -> Response;
+    async fn osu_getreplay(
+        &self,
+        query: OsuGetReplayQuery,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// get `/web/osu-rate.php`
-    async fn osu_rate(&self) -> Response;
+    async fn osu_rate(&self, query: OsuRateQuery) -> Response;
 
     /// get `/web/osu-osz2-getscores.php`
-    async fn osu_osz2_getscores(&self) -> Response;
+    async fn osu_osz2_getscores(
+        &self,
+        query: OsuGetScoresQuery,
+    ) -> Result<Response, BanchoHttpError>;
 
     /// post `/web/osu-comment.php`
     async fn osu_comment(&self) -> Response;
@@ -92,13 +139,40 @@ pub trait BanchoRoutingService {
     async fn osu_getseasonal(&self) -> Response;
 
     /// get `/web/bancho_connect.php`
-    async fn bancho_connect(&self) -> Response;
+    async fn bancho_connect(
+        &self,
+        ip: IpAddr,
+        query: OsuBanchoConnectQuery,
+    ) -> Response;
 
     /// get `/web/check-updates.php`
-    async fn check_updates(&self) -> Response;
+    async fn check_updates(&self, query: OsuCheckUpdatesQuery) -> Response;
 
     /// get `/web/maps/{beatmap_file_name}`
     async fn update_beatmap(&self) -> Response;
+
+    /// get `/replays/{score_id}`
+    async fn download_replay(
+        &self,
+        score_id: i64,
+        credentials: Option<OsuClientCredentials>,
+    ) -> Result<Response, BanchoHttpError>;
+
+    /// get `/chat/channels/{channel_name}/messages`
+    async fn get_channel_messages(
+        &self,
+        channel_name: String,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<Response, BanchoHttpError>;
+
+    /// get `/chat/users/{user_id}/messages`
+    async fn get_private_messages(
+        &self,
+        user_id: i32,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<Response, BanchoHttpError>;
 }
 
 #[async_trait]
@@ -143,4 +217,110 @@ pub trait BanchoHandlerService {
         &self,
         token: BanchoClientToken,
     ) -> Result<bool, BanchoStateError>;
+
+    async fn get_friends(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Vec<i32>, BanchoServiceError>;
+
+    async fn submit_score(
+        &self,
+        form: OsuScoreSubmissionForm,
+    ) -> Result<String, SubmitScoreError>;
+
+    async fn get_beatmap_scores(
+        &self,
+        query: OsuGetScoresQuery,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn get_beatmap_info(
+        &self,
+        credentials: OsuClientCredentials,
+        body: OsuGetBeatmapInfoBody,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn get_replay(
+        &self,
+        query: OsuGetReplayQuery,
+    ) -> Result<Vec<u8>, BanchoServiceError>;
+
+    async fn export_replay(
+        &self,
+        score_id: i64,
+        credentials: Option<OsuClientCredentials>,
+    ) -> Result<Vec<u8>, BanchoServiceError>;
+
+    async fn verify_credentials(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<i32, BanchoServiceError>;
+
+    async fn upload_screenshot(
+        &self,
+        form: OsuScreenshotForm,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn get_screenshot(
+        &self,
+        name: String,
+    ) -> Result<GetScreenshotResponse, BanchoServiceError>;
+
+    async fn get_favourite_beatmaps(
+        &self,
+        credentials: OsuClientCredentials,
+    ) -> Result<Vec<i32>, BanchoServiceError>;
+
+    async fn add_favourite_beatmap(
+        &self,
+        query: OsuAddFavouriteQuery,
+    ) -> Result<bool, BanchoServiceError>;
+
+    /// Returns the average rating if the user has rated the beatmap.
+    async fn rate_beatmap(
+        &self,
+        query: OsuRateQuery,
+    ) -> Result<Option<f64>, BanchoServiceError>;
+
+    async fn search_beatmapsets(
+        &self,
+        query: OsuSearchQuery,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn get_beatmapset_info(
+        &self,
+        query: OsuSearchSetQuery,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn download_beatmapset(
+        &self,
+        credentials: OsuClientCredentials,
+        beatmapset_id: i32,
+        no_video: bool,
+    ) -> Result<BeatmapsetChunkStream, BanchoServiceError>;
+
+    async fn register(
+        &self,
+        client_ip: IpAddr,
+        form: OsuRegistrationForm,
+    ) -> Result<(), RegisterError>;
+
+    async fn bancho_connect(
+        &self,
+        client_ip: IpAddr,
+        query: OsuBanchoConnectQuery,
+    ) -> Result<String, BanchoServiceError>;
+
+    async fn get_channel_history(
+        &self,
+        channel_name: String,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<ChatHistory, BanchoHttpError>;
+
+    async fn get_private_conversation(
+        &self,
+        other_user_id: i32,
+        credentials: OsuClientCredentials,
+        query: ChatHistoryQuery,
+    ) -> Result<ChatHistory, BanchoHttpError>;
 }
@@ -0,0 +1,774 @@
+use crate::{
+    irc_nickname, reply::*, IrcError, IrcLineReader, IrcMessage, IrcServer,
+};
+use bancho_packets::{BanchoMessage, PacketId, PacketReader, PayloadReader};
+use core_chat::ChatError;
+use domain_bancho::UserPrivileges;
+use domain_chat::{MultiplayerChannel, Platform, SpectatorChannel};
+use pb_bancho_state::UserQuery;
+use pb_chat::{
+    ChannelMembers, ChannelQuery, ChatMessageTarget, GetChannelMembersRequest,
+    JoinChannelRequest, LeaveChannelRequest, LoginRequest, SendMessageRequest,
+};
+use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Instant};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+/// The user of a registered connection.
+#[derive(Debug, Clone)]
+pub struct IrcUser {
+    pub user_id: i32,
+    pub username: String,
+    pub nickname: String,
+}
+
+pub enum IrcControl {
+    Continue,
+    Quit,
+}
+
+/// State of a client connection, the commands and the chat packets of the
+/// user are handled in one task.
+pub struct IrcConnection {
+    pub server: Arc<IrcServer>,
+    pub peer_addr: SocketAddr,
+    pub password: Option<String>,
+    pub nickname: Option<String>,
+    pub user_received: bool,
+    pub user: Option<IrcUser>,
+    /// Lowercase names of the joined channels.
+    pub channels: HashSet<String>,
+    pub outgoing: Vec<String>,
+    pub last_active: Instant,
+    pub pinged: bool,
+}
+
+impl IrcConnection {
+    #[inline]
+    pub fn new(server: Arc<IrcServer>, peer_addr: SocketAddr) -> Self {
+        Self {
+            server,
+            peer_addr,
+            password: None,
+            nickname: None,
+            user_received: false,
+            user: None,
+            channels: HashSet::new(),
+            outgoing: Vec::new(),
+            last_active: Instant::now(),
+            pinged: false,
+        }
+    }
+
+    pub async fn run(mut self, stream: TcpStream) -> Result<(), IrcError> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = IrcLineReader::new(reader);
+        let mut pull_interval =
+            tokio::time::interval(self.server.pull_interval);
+
+        let result = loop {
+            let control = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => {
+                        self.last_active = Instant::now();
+                        self.pinged = false;
+                        self.handle_line(&line).await
+                    },
+                    Ok(None) => IrcControl::Quit,
+                    Err(err) => break Err(err.into()),
+                },
+                _ = pull_interval.tick() => self.tick().await,
+            };
+
+            if !self.outgoing.is_empty() {
+                let data = self.outgoing.drain(..).collect::<String>();
+                if let Err(err) = writer.write_all(data.as_bytes()).await {
+                    break Err(err.into());
+                }
+            }
+
+            if let IrcControl::Quit = control {
+                break Ok(());
+            }
+        };
+
+        if let Some(user) = &self.user {
+            let _ = self
+                .server
+                .chat_service
+                .logout(UserQuery::UserId(user.user_id), Platform::Irc)
+                .await;
+        }
+
+        result
+    }
+
+    #[inline]
+    pub fn send(&mut self, message: IrcMessage) {
+        // the line breaks in the params would start new commands
+        let mut line = message.to_string().replace(['\r', '\n', '\0'], "");
+        line.push_str("\r\n");
+        self.outgoing.push(line);
+    }
+
+    /// Send a message from the server.
+    #[inline]
+    pub fn send_server<C: Into<String>>(
+        &mut self,
+        command: C,
+        params: Vec<String>,
+    ) {
+        let prefix = self.server.server_name.clone();
+        self.send(IrcMessage::new(Some(&prefix), command, params));
+    }
+
+    /// Send a numeric reply, the nickname is the first param.
+    #[inline]
+    pub fn send_numeric(&mut self, numeric: &str, params: &[&str]) {
+        let mut all_params = Vec::with_capacity(params.len() + 1);
+        all_params.push(self.nickname.clone().unwrap_or_else(|| "*".into()));
+        all_params.extend(params.iter().map(|s| s.to_string()));
+
+        self.send_server(numeric, all_params);
+    }
+
+    #[inline]
+    fn user_prefix(&self, nickname: &str) -> String {
+        format!("{nickname}!{nickname}@{}", self.server.server_name)
+    }
+
+    /// Ping the idle client, the connection is closed if it is timeout.
+    pub async fn tick(&mut self) -> IrcControl {
+        let idle = self.last_active.elapsed();
+
+        if idle > self.server.ping_timeout {
+            self.send(IrcMessage::new(
+                None,
+                "ERROR",
+                vec!["Closing link: ping timeout".into()],
+            ));
+            return IrcControl::Quit;
+        }
+
+        if idle > self.server.ping_timeout / 2 && !self.pinged {
+            self.pinged = true;
+            let server_name = self.server.server_name.clone();
+            self.send(IrcMessage::new(None, "PING", vec![server_name]));
+        }
+
+        if self.user.is_some() {
+            self.pull_chat_packets().await;
+        }
+
+        IrcControl::Continue
+    }
+
+    pub async fn handle_line(&mut self, line: &str) -> IrcControl {
+        const LOG_TARGET: &str = "irc::handle_line";
+
+        let Some(message) = IrcMessage::parse(line) else {
+            return IrcControl::Continue;
+        };
+
+        trace!(target: LOG_TARGET, "{}: {message}", self.peer_addr);
+
+        match (message.command.as_str(), self.user.is_some()) {
+            ("PING", _) => {
+                let token = message.param(0).unwrap_or_default().to_owned();
+                let server_name = self.server.server_name.clone();
+                self.send_server("PONG", vec![server_name, token]);
+            },
+            ("PONG", _) | ("CAP", _) => {},
+            ("QUIT", _) => {
+                self.send(IrcMessage::new(
+                    None,
+                    "ERROR",
+                    vec!["Closing link: quit".into()],
+                ));
+                return IrcControl::Quit;
+            },
+            ("PASS", false) => self.password = message.param(0).map(Into::into),
+            ("NICK", false) => match message.param(0) {
+                Some(nickname) => {
+                    self.nickname = Some(nickname.to_owned());
+                    return self.try_register().await;
+                },
+                None => self
+                    .send_numeric(ERR_NONICKNAMEGIVEN, &["No nickname given"]),
+            },
+            ("USER", false) => {
+                if message.params.len() < 4 {
+                    self.send_numeric(
+                        ERR_NEEDMOREPARAMS,
+                        &["USER", "Not enough parameters"],
+                    );
+                } else {
+                    self.user_received = true;
+                    return self.try_register().await;
+                }
+            },
+            ("PASS", true) | ("USER", true) => self.send_numeric(
+                ERR_ALREADYREGISTRED,
+                &["You may not reregister"],
+            ),
+            // nicknames are the usernames
+            ("NICK", true) => {},
+            (_, false) => self
+                .send_numeric(ERR_NOTREGISTERED, &["You have not registered"]),
+            ("JOIN", true) => self.handle_join(&message).await,
+            ("PART", true) => self.handle_part(&message).await,
+            ("PRIVMSG", true) => self.handle_privmsg(&message).await,
+            ("NOTICE", true) => {},
+            ("NAMES", true) => self.handle_names(&message).await,
+            ("WHO", true) => self.handle_who(&message).await,
+            (command, true) => {
+                let command = command.to_owned();
+                self.send_numeric(
+                    ERR_UNKNOWNCOMMAND,
+                    &[&command, "Unknown command"],
+                )
+            },
+        }
+
+        IrcControl::Continue
+    }
+
+    /// Log into the chat service after both `NICK` and `USER` are received.
+    pub async fn try_register(&mut self) -> IrcControl {
+        const LOG_TARGET: &str = "irc::register";
+
+        let (Some(nickname), true) =
+            (self.nickname.clone(), self.user_received)
+        else {
+            return IrcControl::Continue;
+        };
+
+        let ip = self.peer_addr.ip();
+        if self.server.login_guard.is_limited(ip) {
+            self.send(IrcMessage::new(
+                None,
+                "ERROR",
+                vec!["Closing link: too many failed logins".into()],
+            ));
+            return IrcControl::Quit;
+        }
+
+        let password = self.password.clone().unwrap_or_default();
+
+        let (user, privileges) = match self
+            .server
+            .authenticate(&nickname, &password)
+            .await
+        {
+            Ok(user) => user,
+            Err(err) => {
+                debug!(
+                    target: LOG_TARGET,
+                    "IRC login failed {nickname} ({}): {err}",
+                    self.peer_addr
+                );
+                self.server.login_guard.record_failure(ip);
+                self.send_numeric(ERR_PASSWDMISMATCH, &["Password incorrect"]);
+                self.send(IrcMessage::new(
+                    None,
+                    "ERROR",
+                    vec!["Closing link: authentication failed".into()],
+                ));
+                return IrcControl::Quit;
+            },
+        };
+
+        if let Err(err) = self
+            .server
+            .chat_service
+            .login(LoginRequest {
+                user_id: user.id,
+                username: user.name.to_owned(),
+                username_unicode: user.name_unicode,
+                privileges: privileges.bits(),
+                platforms: Platform::Irc.bits(),
+                silence_end: user.silence_end.map(|end| end.timestamp()),
+                only_friend_pm_allowed: false,
+            })
+            .await
+        {
+            warn!(
+                target: LOG_TARGET,
+                "Failed login into chat server user {}({}): {err}",
+                user.name,
+                user.id,
+            );
+            self.send(IrcMessage::new(
+                None,
+                "ERROR",
+                vec!["Closing link: chat server unavailable".into()],
+            ));
+            return IrcControl::Quit;
+        }
+
+        let nickname = irc_nickname(&user.name);
+        self.nickname = Some(nickname.clone());
+        self.user = Some(IrcUser {
+            user_id: user.id,
+            username: user.name.to_owned(),
+            nickname: nickname.clone(),
+        });
+
+        info!(
+            target: LOG_TARGET,
+            "User {}({}) logged in from irc ({})",
+            user.name,
+            user.id,
+            self.peer_addr
+        );
+
+        let server_name = self.server.server_name.clone();
+        self.send_numeric(
+            RPL_WELCOME,
+            &[&format!("Welcome to the osu! chat, {nickname}")],
+        );
+        self.send_numeric(
+            RPL_YOURHOST,
+            &[&format!("Your host is {server_name}")],
+        );
+        self.send_numeric(RPL_CREATED, &["This server is powered by peace"]);
+        self.send_numeric(RPL_MYINFO, &[&server_name, "peace", "o", "o"]);
+
+        self.send_numeric(
+            RPL_MOTDSTART,
+            &[&format!("- {server_name} Message of the day -")],
+        );
+        let motd = self.server.motd.clone().unwrap_or_default();
+        for line in motd.lines() {
+            self.send_numeric(RPL_MOTD, &[&format!("- {line}")]);
+        }
+        self.send_numeric(RPL_ENDOFMOTD, &["End of /MOTD command"]);
+
+        IrcControl::Continue
+    }
+
+    #[inline]
+    fn user_query(&self) -> Option<UserQuery> {
+        self.user.as_ref().map(|u| UserQuery::UserId(u.user_id))
+    }
+
+    pub async fn handle_join(&mut self, message: &IrcMessage) {
+        let Some(channels) = message.param(0) else {
+            return self.send_numeric(
+                ERR_NEEDMOREPARAMS,
+                &["JOIN", "Not enough parameters"],
+            );
+        };
+
+        for channel in channels.split(',').filter(|c| !c.is_empty()) {
+            let channel = channel.to_ascii_lowercase();
+
+            if !channel.starts_with('#') {
+                self.send_numeric(
+                    ERR_NOSUCHCHANNEL,
+                    &[&channel, "No such channel"],
+                );
+                continue;
+            }
+
+            let res = self
+                .server
+                .chat_service
+                .join_channel(JoinChannelRequest {
+                    channel_query: Some(
+                        ChannelQuery::ChannelName(channel.clone()).into(),
+                    ),
+                    user_query: self.user_query().map(Into::into),
+                })
+                .await;
+
+            match res {
+                Ok(_) => {
+                    self.channels.insert(channel.clone());
+
+                    let nickname = self.nickname.clone().unwrap_or_default();
+                    let prefix = self.user_prefix(&nickname);
+                    self.send(IrcMessage::new(
+                        Some(&prefix),
+                        "JOIN",
+                        vec![channel.clone()],
+                    ));
+
+                    if let Some(members) = self.channel_members(&channel).await
+                    {
+                        match members
+                            .channel
+                            .as_ref()
+                            .and_then(|c| c.description.clone())
+                        {
+                            Some(topic) => self
+                                .send_numeric(RPL_TOPIC, &[&channel, &topic]),
+                            None => self.send_numeric(
+                                RPL_NOTOPIC,
+                                &[&channel, "No topic is set"],
+                            ),
+                        }
+                        self.send_names(&channel, &members);
+                    }
+                },
+                Err(ChatError::PermissionDenied) => self.send_numeric(
+                    ERR_BANNEDFROMCHAN,
+                    &[&channel, "Cannot join channel"],
+                ),
+                Err(_) => self.send_numeric(
+                    ERR_NOSUCHCHANNEL,
+                    &[&channel, "No such channel"],
+                ),
+            }
+        }
+    }
+
+    pub async fn handle_part(&mut self, message: &IrcMessage) {
+        let Some(channels) = message.param(0) else {
+            return self.send_numeric(
+                ERR_NEEDMOREPARAMS,
+                &["PART", "Not enough parameters"],
+            );
+        };
+
+        for channel in channels.split(',').filter(|c| !c.is_empty()) {
+            let channel = channel.to_ascii_lowercase();
+
+            if !self.channels.remove(&channel) {
+                self.send_numeric(
+                    ERR_NOTONCHANNEL,
+                    &[&channel, "You're not on that channel"],
+                );
+                continue;
+            }
+
+            let _ = self
+                .server
+                .chat_service
+                .leave_channel(LeaveChannelRequest {
+                    channel_query: Some(
+                        ChannelQuery::ChannelName(channel.clone()).into(),
+                    ),
+                    user_query: self.user_query().map(Into::into),
+                })
+                .await;
+
+            let nickname = self.nickname.clone().unwrap_or_default();
+            let prefix = self.user_prefix(&nickname);
+            self.send(IrcMessage::new(Some(&prefix), "PART", vec![channel]));
+        }
+    }
+
+    pub async fn handle_privmsg(&mut self, message: &IrcMessage) {
+        let Some(target) = message.param(0) else {
+            return self.send_numeric(
+                ERR_NORECIPIENT,
+                &["No recipient given (PRIVMSG)"],
+            );
+        };
+
+        let Some(text) = message.param(1).filter(|s| !s.is_empty()) else {
+            return self.send_numeric(ERR_NOTEXTTOSEND, &["No text to send"]);
+        };
+
+        let target = if target.starts_with('#') {
+            let channel = target.to_ascii_lowercase();
+
+            if !self.channels.contains(&channel) {
+                return self.send_numeric(
+                    ERR_CANNOTSENDTOCHAN,
+                    &[target, "Cannot send to channel"],
+                );
+            }
+
+            ChatMessageTarget::Channel(ChannelQuery::ChannelName(channel))
+        } else {
+            // the nicknames are the safe names of the users
+            match self
+                .server
+                .users_repository
+                .get_user(None, Some(target), None)
+                .await
+            {
+                Ok(user) => ChatMessageTarget::User(UserQuery::UserId(user.id)),
+                Err(_) => {
+                    return self.send_numeric(
+                        ERR_NOSUCHNICK,
+                        &[target, "No such nick/channel"],
+                    )
+                },
+            }
+        };
+
+        let res = self
+            .server
+            .chat_service
+            .send_message(SendMessageRequest {
+                sender: self.user_query().map(Into::into),
+                message: text.to_owned(),
+                target: Some(target.into()),
+            })
+            .await;
+
+        let reason = match res {
+            Ok(_) => return,
+            Err(ChatError::ChannelNotExists) => {
+                return self.send_numeric(
+                    ERR_NOSUCHCHANNEL,
+                    &[target_name(message), "No such channel"],
+                )
+            },
+            Err(ChatError::UserMuted) => "You are muted in the channel",
+            Err(ChatError::UserSilenced) => "You are silenced",
+            Err(ChatError::PrivateMessageBlocked) => {
+                "The user only accepts private messages from friends"
+            },
+            Err(ChatError::PermissionDenied) => "Permission denied",
+            Err(_) => "Cannot send the message",
+        };
+
+        self.send_numeric(
+            ERR_CANNOTSENDTOCHAN,
+            &[target_name(message), reason],
+        );
+    }
+
+    pub async fn handle_names(&mut self, message: &IrcMessage) {
+        let Some(channels) = message.param(0) else {
+            return self
+                .send_numeric(RPL_ENDOFNAMES, &["*", "End of /NAMES list"]);
+        };
+
+        for channel in channels.split(',').filter(|c| !c.is_empty()) {
+            let channel = channel.to_ascii_lowercase();
+
+            match self.channel_members(&channel).await {
+                Some(members) => self.send_names(&channel, &members),
+                None => self.send_numeric(
+                    RPL_ENDOFNAMES,
+                    &[&channel, "End of /NAMES list"],
+                ),
+            }
+        }
+    }
+
+    pub async fn handle_who(&mut self, message: &IrcMessage) {
+        let mask = message.param(0).unwrap_or("*").to_owned();
+
+        let members = if mask.starts_with('#') {
+            let channel = mask.to_ascii_lowercase();
+            self.channel_members(&channel)
+                .await
+                .map(|m| {
+                    m.members
+                        .into_iter()
+                        .map(|m| (channel.clone(), m.username, m.privileges))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        } else {
+            match self
+                .server
+                .users_repository
+                .get_user(None, Some(&mask), None)
+                .await
+            {
+                Ok(user) => vec![("*".to_owned(), user.name, 0)],
+                Err(_) => Vec::new(),
+            }
+        };
+
+        let server_name = self.server.server_name.clone();
+        for (channel, username, privileges) in members {
+            let nickname = irc_nickname(&username);
+            let flags = if UserPrivileges::from(privileges).is_staff() {
+                "H@"
+            } else {
+                "H"
+            };
+
+            self.send_numeric(
+                RPL_WHOREPLY,
+                &[
+                    &channel,
+                    &nickname,
+                    &server_name,
+                    &server_name,
+                    &nickname,
+                    flags,
+                    &format!("0 {username}"),
+                ],
+            );
+        }
+
+        self.send_numeric(RPL_ENDOFWHO, &[&mask, "End of /WHO list"]);
+    }
+
+    pub async fn channel_members(
+        &self,
+        channel: &str,
+    ) -> Option<ChannelMembers> {
+        self.server
+            .chat_service
+            .get_channel_members(GetChannelMembersRequest {
+                channel_query: Some(
+                    ChannelQuery::ChannelName(channel.to_owned()).into(),
+                ),
+            })
+            .await
+            .ok()
+    }
+
+    /// `RPL_NAMREPLY` in chunks to keep the lines short, staff are shown as
+    /// the operators.
+    pub fn send_names(&mut self, channel: &str, members: &ChannelMembers) {
+        const NAMES_PER_LINE: usize = 30;
+
+        let names = members
+            .members
+            .iter()
+            .map(|m| {
+                let nickname = irc_nickname(&m.username);
+                if UserPrivileges::from(m.privileges).is_staff() {
+                    format!("@{nickname}")
+                } else {
+                    nickname
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for chunk in names.chunks(NAMES_PER_LINE) {
+            self.send_numeric(RPL_NAMREPLY, &["=", channel, &chunk.join(" ")]);
+        }
+
+        self.send_numeric(RPL_ENDOFNAMES, &[channel, "End of /NAMES list"]);
+    }
+
+    /// Pull the bancho packets of the user from the chat service and send
+    /// them as irc messages.
+    pub async fn pull_chat_packets(&mut self) {
+        const LOG_TARGET: &str = "irc::pull_chat_packets";
+
+        let Some(query) = self.user_query() else { return };
+
+        let packets = match self
+            .server
+            .chat_service
+            .dequeue_chat_packets(query, Platform::Irc)
+            .await
+        {
+            Ok(packets) => packets.data,
+            Err(err) => {
+                warn!(target: LOG_TARGET, "Failed to pull packets: {err}");
+                return;
+            },
+        };
+
+        for packet in PacketReader::new(&packets) {
+            let Some(payload) = packet.payload else { continue };
+            let mut reader = PayloadReader::new(payload);
+
+            match packet.id {
+                PacketId::BANCHO_SEND_MESSAGE => {
+                    if let Some(message) = reader.read::<BanchoMessage>() {
+                        self.relay_message(message);
+                    }
+                },
+                PacketId::BANCHO_CHANNEL_KICK => {
+                    if let Some(channel) = reader.read::<String>() {
+                        let channel = self.irc_channel_name(&channel);
+                        if self.channels.remove(&channel) {
+                            let nickname =
+                                self.nickname.clone().unwrap_or_default();
+                            let prefix = self.user_prefix(&nickname);
+                            self.send(IrcMessage::new(
+                                Some(&prefix),
+                                "PART",
+                                vec![channel],
+                            ));
+                        }
+                    }
+                },
+                PacketId::BANCHO_NOTIFICATION => {
+                    if let Some(text) = reader.read::<String>() {
+                        let nickname =
+                            self.nickname.clone().unwrap_or_default();
+                        for line in text.lines().filter(|l| !l.is_empty()) {
+                            self.send_server(
+                                "NOTICE",
+                                vec![nickname.clone(), line.to_owned()],
+                            );
+                        }
+                    }
+                },
+                PacketId::BANCHO_TARGET_IS_SILENCED => {
+                    if let Some(message) = reader.read::<BanchoMessage>() {
+                        self.send_numeric(
+                            ERR_CANNOTSENDTOCHAN,
+                            &[
+                                &irc_nickname(&message.target),
+                                "User is silenced",
+                            ],
+                        );
+                    }
+                },
+                PacketId::BANCHO_USER_DM_BLOCKED => {
+                    if let Some(message) = reader.read::<BanchoMessage>() {
+                        self.send_numeric(
+                            ERR_CANNOTSENDTOCHAN,
+                            &[
+                                &irc_nickname(&message.target),
+                                "The user only accepts private messages from \
+                                friends",
+                            ],
+                        );
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// The channel messages use the bancho names, e.g. `#multiplayer`, they
+    /// are mapped to the joined channels.
+    fn irc_channel_name(&self, bancho_name: &str) -> String {
+        let prefix = match bancho_name {
+            MultiplayerChannel::BANCHO_NAME => "#mp_",
+            SpectatorChannel::BANCHO_NAME => "#spec_",
+            _ => return bancho_name.to_ascii_lowercase(),
+        };
+
+        self.channels
+            .iter()
+            .find(|c| c.starts_with(prefix))
+            .cloned()
+            .unwrap_or_else(|| bancho_name.to_owned())
+    }
+
+    fn relay_message(&mut self, message: BanchoMessage) {
+        let Some(user) = self.user.clone() else { return };
+
+        let target = if message.target.starts_with('#') {
+            if message.sender_id == user.user_id {
+                return;
+            }
+            self.irc_channel_name(&message.target)
+        } else {
+            user.nickname
+        };
+
+        let prefix = self.user_prefix(&irc_nickname(&message.sender));
+
+        for line in message.content.lines().filter(|l| !l.is_empty()) {
+            self.send(IrcMessage::new(
+                Some(&prefix),
+                "PRIVMSG",
+                vec![target.clone(), line.to_owned()],
+            ));
+        }
+    }
+}
+
+#[inline]
+fn target_name(message: &IrcMessage) -> &str {
+    message.param(0).unwrap_or_default()
+}
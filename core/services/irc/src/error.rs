@@ -0,0 +1,17 @@
+use core_chat::ChatError;
+use domain_users::PasswordError;
+use peace_repositories::GetUserError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum IrcError {
+    #[error(transparent)]
+    GetUserError(#[from] GetUserError),
+    #[error(transparent)]
+    PasswordError(#[from] PasswordError),
+    #[error(transparent)]
+    ChatError(#[from] ChatError),
+    #[error("user is banned")]
+    UserBanned,
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,17 @@
+#[macro_use]
+extern crate peace_logs;
+
+#[macro_use]
+extern crate serde;
+
+pub mod connection;
+pub mod error;
+pub mod message;
+pub mod reader;
+pub mod server;
+
+pub use connection::*;
+pub use error::*;
+pub use message::*;
+pub use reader::*;
+pub use server::*;
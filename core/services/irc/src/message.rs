@@ -0,0 +1,200 @@
+use std::fmt;
+
+/// Max length of a line including the trailing `\r\n` (RFC 1459).
+pub const MAX_LINE_LENGTH: usize = 512;
+
+/// Numeric replies used by the server.
+pub mod reply {
+    pub const RPL_WELCOME: &str = "001";
+    pub const RPL_YOURHOST: &str = "002";
+    pub const RPL_CREATED: &str = "003";
+    pub const RPL_MYINFO: &str = "004";
+    pub const RPL_ENDOFWHO: &str = "315";
+    pub const RPL_NOTOPIC: &str = "331";
+    pub const RPL_TOPIC: &str = "332";
+    pub const RPL_WHOREPLY: &str = "352";
+    pub const RPL_NAMREPLY: &str = "353";
+    pub const RPL_ENDOFNAMES: &str = "366";
+    pub const RPL_MOTD: &str = "372";
+    pub const RPL_MOTDSTART: &str = "375";
+    pub const RPL_ENDOFMOTD: &str = "376";
+
+    pub const ERR_NOSUCHNICK: &str = "401";
+    pub const ERR_NOSUCHCHANNEL: &str = "403";
+    pub const ERR_CANNOTSENDTOCHAN: &str = "404";
+    pub const ERR_NORECIPIENT: &str = "411";
+    pub const ERR_NOTEXTTOSEND: &str = "412";
+    pub const ERR_UNKNOWNCOMMAND: &str = "421";
+    pub const ERR_NONICKNAMEGIVEN: &str = "431";
+    pub const ERR_NOTONCHANNEL: &str = "442";
+    pub const ERR_NOTREGISTERED: &str = "451";
+    pub const ERR_NEEDMOREPARAMS: &str = "461";
+    pub const ERR_ALREADYREGISTRED: &str = "462";
+    pub const ERR_PASSWDMISMATCH: &str = "464";
+    pub const ERR_BANNEDFROMCHAN: &str = "474";
+}
+
+/// A line of the irc protocol, `[:prefix] COMMAND [params] [:trailing]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IrcMessage {
+    pub prefix: Option<String>,
+    pub command: String,
+    pub params: Vec<String>,
+}
+
+impl IrcMessage {
+    #[inline]
+    pub fn new<C: Into<String>>(
+        prefix: Option<&str>,
+        command: C,
+        params: Vec<String>,
+    ) -> Self {
+        Self {
+            prefix: prefix.map(|s| s.to_owned()),
+            command: command.into(),
+            params,
+        }
+    }
+
+    /// Parse a line without the line ending, the command is uppercased.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        let (prefix, mut rest) = match line.strip_prefix(':') {
+            Some(line) => {
+                let (prefix, rest) = line.split_once(' ')?;
+                (Some(prefix.to_owned()), rest)
+            },
+            None => (None, line),
+        };
+
+        let mut params = Vec::new();
+        let mut command = None;
+
+        loop {
+            rest = rest.trim_start_matches(' ');
+            if rest.is_empty() {
+                break;
+            }
+
+            if command.is_some() {
+                if let Some(trailing) = rest.strip_prefix(':') {
+                    params.push(trailing.to_owned());
+                    break;
+                }
+            }
+
+            let (word, next) = rest.split_once(' ').unwrap_or((rest, ""));
+
+            match command {
+                None => command = Some(word.to_ascii_uppercase()),
+                Some(_) => params.push(word.to_owned()),
+            }
+
+            rest = next;
+        }
+
+        Some(Self { prefix, command: command?, params })
+    }
+
+    #[inline]
+    pub fn param(&self, index: usize) -> Option<&str> {
+        self.params.get(index).map(|s| s.as_str())
+    }
+}
+
+impl fmt::Display for IrcMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(prefix) = &self.prefix {
+            write!(f, ":{prefix} ")?;
+        }
+
+        write!(f, "{}", self.command)?;
+
+        if let Some((last, params)) = self.params.split_last() {
+            for param in params {
+                write!(f, " {param}")?;
+            }
+
+            if last.is_empty() || last.starts_with(':') || last.contains(' ') {
+                write!(f, " :{last}")?;
+            } else {
+                write!(f, " {last}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Irc nicknames can not contain spaces, they are replaced with `_` the same
+/// as the safe names of the users.
+#[inline]
+pub fn irc_nickname(username: &str) -> String {
+    username.replace(' ', "_")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(message: &IrcMessage) -> Vec<&str> {
+        message.params.iter().map(|s| s.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let message = IrcMessage::parse("nick alice\r\n").unwrap();
+        assert_eq!(message.prefix, None);
+        assert_eq!(message.command, "NICK");
+        assert_eq!(params(&message), ["alice"]);
+
+        let message = IrcMessage::parse("QUIT").unwrap();
+        assert_eq!(message.command, "QUIT");
+        assert!(message.params.is_empty());
+    }
+
+    #[test]
+    fn test_parse_trailing() {
+        let message =
+            IrcMessage::parse("PRIVMSG #osu :hello :) world").unwrap();
+        assert_eq!(params(&message), ["#osu", "hello :) world"]);
+
+        let message = IrcMessage::parse("USER alice 0 * :").unwrap();
+        assert_eq!(params(&message), ["alice", "0", "*", ""]);
+
+        // the command is never a trailing param
+        let message = IrcMessage::parse(":cmd x").unwrap();
+        assert_eq!(message.prefix.as_deref(), Some("cmd"));
+        assert_eq!(message.command, "X");
+    }
+
+    #[test]
+    fn test_parse_prefix_and_spaces() {
+        let message =
+            IrcMessage::parse(":alice!alice@peace  JOIN   #osu  ").unwrap();
+        assert_eq!(message.prefix.as_deref(), Some("alice!alice@peace"));
+        assert_eq!(message.command, "JOIN");
+        assert_eq!(params(&message), ["#osu"]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(IrcMessage::parse(""), None);
+        assert_eq!(IrcMessage::parse("   \r\n"), None);
+        assert_eq!(IrcMessage::parse(":prefix"), None);
+        assert_eq!(IrcMessage::parse(":prefix "), None);
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for line in [
+            ":peace 001 alice :Welcome to the osu! chat",
+            "PRIVMSG #osu ::)",
+            "PING peace",
+            "JOIN #osu",
+        ] {
+            assert_eq!(IrcMessage::parse(line).unwrap().to_string(), line);
+        }
+    }
+}
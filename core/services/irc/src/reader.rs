@@ -0,0 +1,108 @@
+use crate::MAX_LINE_LENGTH;
+use std::io;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// Reads the lines of a connection, the lines longer than
+/// [`MAX_LINE_LENGTH`] are dropped without being buffered.
+///
+/// [`IrcLineReader::next_line`] is cancel safe, the partial line is kept
+/// in the reader.
+pub struct IrcLineReader<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    oversized: bool,
+}
+
+impl<R: AsyncRead + Unpin> IrcLineReader<R> {
+    #[inline]
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            line: Vec::with_capacity(MAX_LINE_LENGTH),
+            oversized: false,
+        }
+    }
+
+    /// Read the next line without the line ending, `None` at the end of the
+    /// stream.
+    pub async fn next_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let buf = self.reader.fill_buf().await?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+
+            let (chunk, line_end) = match buf.iter().position(|b| *b == b'\n') {
+                Some(pos) => (&buf[..pos], true),
+                None => (buf, false),
+            };
+
+            // the length includes the trailing `\n`
+            let line_len = self.line.len() + chunk.len() + line_end as usize;
+            if self.oversized || line_len > MAX_LINE_LENGTH {
+                self.oversized = true;
+                self.line.clear();
+            } else {
+                self.line.extend_from_slice(chunk);
+            }
+
+            let consumed = chunk.len() + line_end as usize;
+            self.reader.consume(consumed);
+
+            if line_end {
+                let oversized = std::mem::take(&mut self.oversized);
+                let line = std::mem::take(&mut self.line);
+
+                if !oversized {
+                    let line = line.strip_suffix(b"\r").unwrap_or(&line);
+                    return Ok(Some(String::from_utf8_lossy(line).into()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_lines() {
+        let data = b"NICK alice\r\nUSER alice 0 * :alice\nPING";
+        let mut reader = IrcLineReader::new(&data[..]);
+
+        assert_eq!(
+            reader.next_line().await.unwrap().as_deref(),
+            Some("NICK alice")
+        );
+        assert_eq!(
+            reader.next_line().await.unwrap().as_deref(),
+            Some("USER alice 0 * :alice")
+        );
+        // the incomplete line at the end of the stream is dropped
+        assert_eq!(reader.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_lines_dropped() {
+        let max = "a".repeat(MAX_LINE_LENGTH - 1);
+        let data =
+            format!("{}\n{max}\nPING\n", "b".repeat(MAX_LINE_LENGTH * 3));
+        let mut reader = IrcLineReader::new(data.as_bytes());
+
+        assert_eq!(reader.next_line().await.unwrap(), Some(max));
+        assert_eq!(reader.next_line().await.unwrap().as_deref(), Some("PING"));
+        assert_eq!(reader.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_replaced() {
+        let data = b"PRIVMSG #osu :\xff\n";
+        let mut reader = IrcLineReader::new(&data[..]);
+
+        assert_eq!(
+            reader.next_line().await.unwrap().as_deref(),
+            Some("PRIVMSG #osu :\u{fffd}")
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{IrcConnection, IrcError};
+use clap::Parser;
+use clap_serde_derive::ClapSerde;
+use core_chat::DynChatService;
+use domain_bancho::UserPrivileges;
+use domain_users::Password;
+use peace_db::peace::entity::users;
+use peace_repositories::users::DynUsersRepository;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::net::TcpListener;
+
+pub const DEFAULT_IRC_ADDR: &str = "0.0.0.0:6667";
+
+#[derive(Debug, Clone, Parser, ClapSerde, Serialize, Deserialize)]
+pub struct CliIrcServerConfigs {
+    /// The address and port the `irc` server listens on.
+    #[arg(long)]
+    pub irc_addr: Option<SocketAddr>,
+
+    /// The server name sent to the irc clients.
+    #[default("peace".to_owned())]
+    #[arg(long, default_value = "peace")]
+    pub irc_server_name: String,
+
+    /// Message of the day sent after the registration.
+    #[arg(long)]
+    pub irc_motd: Option<String>,
+
+    /// Interval (ms) of pulling the chat packets of the connected users.
+    #[default(500)]
+    #[arg(long, default_value = "500")]
+    pub irc_pull_interval: u64,
+
+    /// Connections without any message in the seconds are closed, they are
+    /// pinged at the half of the timeout.
+    #[default(180)]
+    #[arg(long, default_value = "180")]
+    pub irc_ping_timeout: u64,
+
+    /// Max failed logins from an ip in the window, `0` means unlimited.
+    #[default(5)]
+    #[arg(long, default_value = "5")]
+    pub irc_login_attempts: u32,
+
+    /// The window (secs) of the failed logins.
+    #[default(300)]
+    #[arg(long, default_value = "300")]
+    pub irc_login_attempts_secs: u64,
+}
+
+/// Limits the failed logins from an ip in a sliding window, a connection
+/// is closed after its first failed login.
+#[derive(Debug)]
+pub struct IrcLoginGuard {
+    pub limit: u32,
+    pub window: Duration,
+    pub failures: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl IrcLoginGuard {
+    #[inline]
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window, failures: Mutex::default() }
+    }
+
+    /// Whether the ip has failed to login too many times in the window.
+    pub fn is_limited(&self, ip: IpAddr) -> bool {
+        if self.limit == 0 {
+            return false;
+        }
+
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+
+        failures.get(&ip).is_some_and(|failed| {
+            failed
+                .iter()
+                .filter(|time| now.duration_since(**time) < self.window)
+                .count()
+                >= self.limit as usize
+        })
+    }
+
+    /// Record a failed login, the expired records of all ips are removed.
+    pub fn record_failure(&self, ip: IpAddr) {
+        if self.limit == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+
+        failures.retain(|_, failed| {
+            while failed
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= self.window)
+            {
+                failed.pop_front();
+            }
+            !failed.is_empty()
+        });
+
+        failures.entry(ip).or_default().push_back(now);
+    }
+}
+
+pub struct IrcServer {
+    pub server_name: String,
+    pub motd: Option<String>,
+    pub pull_interval: Duration,
+    pub ping_timeout: Duration,
+    pub login_guard: IrcLoginGuard,
+    pub users_repository: DynUsersRepository,
+    pub chat_service: DynChatService,
+}
+
+impl IrcServer {
+    #[inline]
+    pub fn new(
+        cfg: &CliIrcServerConfigs,
+        users_repository: DynUsersRepository,
+        chat_service: DynChatService,
+    ) -> Self {
+        Self {
+            server_name: cfg.irc_server_name.clone(),
+            motd: cfg.irc_motd.clone(),
+            pull_interval: Duration::from_millis(cfg.irc_pull_interval),
+            ping_timeout: Duration::from_secs(cfg.irc_ping_timeout),
+            login_guard: IrcLoginGuard::new(
+                cfg.irc_login_attempts,
+                Duration::from_secs(cfg.irc_login_attempts_secs),
+            ),
+            users_repository,
+            chat_service,
+        }
+    }
+
+    #[inline]
+    pub fn into_service(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    /// Accept the irc clients until the listener fails.
+    pub async fn serve(
+        self: Arc<Self>,
+        addr: SocketAddr,
+    ) -> Result<(), IrcError> {
+        const LOG_TARGET: &str = "irc::serve";
+
+        let listener = TcpListener::bind(addr).await?;
+        info!(target: LOG_TARGET, "IRC server listening on: {addr}");
+
+        loop {
+            let (stream, peer_addr) = listener.accept().await?;
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                debug!(target: LOG_TARGET, "IRC client connected: {peer_addr}");
+
+                if let Err(err) =
+                    IrcConnection::new(server, peer_addr).run(stream).await
+                {
+                    debug!(
+                        target: LOG_TARGET,
+                        "IRC connection {peer_addr} closed with error: {err}"
+                    );
+                }
+            });
+        }
+    }
+
+    /// Authenticate the user with the password of the account, the
+    /// nickname is matched with the safe name of the user.
+    pub async fn authenticate(
+        &self,
+        nickname: &str,
+        password: &str,
+    ) -> Result<(users::Model, UserPrivileges), IrcError> {
+        let user =
+            self.users_repository.get_user(None, Some(nickname), None).await?;
+
+        // the passwords are hashed from the md5 sent by the osu! clients
+        let () = Password::verify_password(
+            user.password.as_str(),
+            format!("{:x}", md5::compute(password)),
+        )?;
+
+        let privileges = UserPrivileges::from_names(
+            self.users_repository
+                .get_user_privileges(user.id)
+                .await?
+                .iter()
+                .map(|p| p.name.as_str()),
+        );
+
+        if privileges.is_banned() {
+            return Err(IrcError::UserBanned);
+        }
+
+        Ok((user, privileges))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_login_guard() {
+        let guard = IrcLoginGuard::new(2, Duration::from_secs(60));
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let other = IpAddr::from([127, 0, 0, 2]);
+
+        guard.record_failure(ip);
+        assert!(!guard.is_limited(ip));
+
+        guard.record_failure(ip);
+        assert!(guard.is_limited(ip));
+        assert!(!guard.is_limited(other));
+    }
+
+    #[test]
+    fn test_login_guard_evicts_expired() {
+        let guard = IrcLoginGuard::new(1, Duration::ZERO);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        guard.record_failure(ip);
+        assert!(!guard.is_limited(ip));
+
+        guard.record_failure(IpAddr::from([127, 0, 0, 2]));
+        assert!(!guard.failures.lock().unwrap().contains_key(&ip));
+    }
+
+    #[test]
+    fn test_login_guard_unlimited() {
+        let guard = IrcLoginGuard::new(0, Duration::from_secs(60));
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        guard.record_failure(ip);
+        assert!(!guard.is_limited(ip));
+        assert!(guard.failures.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+use peace_rpc_error::{RpcError, TonicError};
+use tonic::Status;
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, RpcError)]
+pub enum PpError {
+    #[error("invalid game mode: {0}")]
+    InvalidGameMode(i32),
+    #[error("the .osu file of beatmap {0} was not found")]
+    BeatmapFileNotExists(i32),
+    #[error("failed to read the .osu file: {0}")]
+    IoError(String),
+    #[error("failed to parse the .osu file: {0}")]
+    ParseBeatmapError(String),
+    #[error("failed to calculate performance: {0}")]
+    CalculateError(String),
+    #[error("TonicError: {0}")]
+    TonicError(String),
+}
+
+impl TonicError for PpError {
+    fn tonic_error(s: Status) -> Self {
+        Self::TonicError(s.message().to_owned())
+    }
+}
@@ -0,0 +1,30 @@
+#[macro_use]
+extern crate peace_logs;
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate anyhow;
+
+#[macro_use]
+extern crate serde;
+
+pub mod error;
+pub mod pp;
+pub mod traits;
+
+pub use error::*;
+pub use pp::*;
+pub use traits::*;
+
+pub mod rpc_config {
+    use clap_serde_derive::ClapSerde;
+    use pb_pp as pp;
+    use peace_cfg::macro_define_rpc_client_config;
+
+    macro_define_rpc_client_config!(
+        service_name: pp,
+        config_name: PpRpcConfig,
+        default_uri: "http://127.0.0.1:5016"
+    );
+}
+pub use rpc_config::*;
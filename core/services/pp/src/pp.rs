@@ -0,0 +1,216 @@
+use crate::*;
+use async_trait::async_trait;
+use domain_pp::PerformanceAttributes;
+use infra_services::{FromRpcClient, IntoService, RpcClient};
+use pb_pp::{pp_rpc_client::PpRpcClient, CalculatePerformanceRequest};
+use peace_cfg::RpcClientConfig;
+use rosu_pp::{AnyPP, Beatmap, GameMode};
+use std::{io::ErrorKind, path::PathBuf, sync::Arc};
+use tonic::transport::Channel;
+
+const DEFAULT_OSU_FILES_DIR: &str = "./.data/beatmaps";
+
+pub struct PpServiceBuilder;
+
+impl PpServiceBuilder {
+    pub async fn build<I, R>(
+        osu_files_dir: Option<&str>,
+        cfg: Option<&PpRpcConfig>,
+    ) -> DynPpService
+    where
+        I: IntoService<DynPpService> + FromOsuFilesDir,
+        R: IntoService<DynPpService>
+            + FromRpcClient<Client = PpRpcClient<Channel>>,
+    {
+        info!("initializing Pp service...");
+        if let Some(dir) = osu_files_dir {
+            info!("Pp service init successful, type: \"Local\"");
+            return I::from_osu_files_dir(dir).into_service();
+        }
+
+        if let Some(cfg) = cfg {
+            if let Ok(client) = cfg.try_connect().await {
+                info!("Pp service init successful, type: \"Remote\"");
+                return R::from_client(client).into_service();
+            }
+        }
+
+        warn!(
+            "Pp service: failed to connect to the remote service, \
+            fallback to local with the default .osu files directory \
+            (\"{DEFAULT_OSU_FILES_DIR}\")"
+        );
+        I::from_osu_files_dir(DEFAULT_OSU_FILES_DIR).into_service()
+    }
+}
+
+/// Calculates the performance with the `.osu` files in a local directory,
+/// named by the beatmap id.
+#[derive(Debug, Clone)]
+pub struct PpServiceImpl {
+    pub osu_files_dir: PathBuf,
+}
+
+impl PpServiceImpl {
+    #[inline]
+    pub fn new(osu_files_dir: impl Into<PathBuf>) -> Self {
+        Self { osu_files_dir: osu_files_dir.into() }
+    }
+
+    #[inline]
+    pub fn osu_file_path(&self, beatmap_id: i32) -> PathBuf {
+        self.osu_files_dir.join(format!("{beatmap_id}.osu"))
+    }
+}
+
+impl Default for PpServiceImpl {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_OSU_FILES_DIR)
+    }
+}
+
+impl FromOsuFilesDir for PpServiceImpl {
+    #[inline]
+    fn from_osu_files_dir(dir: impl Into<PathBuf>) -> Self {
+        Self::new(dir)
+    }
+}
+
+impl PpService for PpServiceImpl {}
+
+impl IntoService<DynPpService> for PpServiceImpl {
+    #[inline]
+    fn into_service(self) -> DynPpService {
+        Arc::new(self) as DynPpService
+    }
+}
+
+#[async_trait]
+impl CalculatePerformance for PpServiceImpl {
+    async fn calculate_performance(
+        &self,
+        request: CalculatePerformanceRequest,
+    ) -> Result<PerformanceAttributes, PpError> {
+        let mode = match request.mode {
+            0 => GameMode::Osu,
+            1 => GameMode::Taiko,
+            2 => GameMode::Catch,
+            3 => GameMode::Mania,
+            mode => return Err(PpError::InvalidGameMode(mode)),
+        };
+
+        let osu_file =
+            match tokio::fs::read(self.osu_file_path(request.beatmap_id)).await
+            {
+                Ok(osu_file) => osu_file,
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    return Err(PpError::BeatmapFileNotExists(
+                        request.beatmap_id,
+                    ))
+                },
+                Err(err) => return Err(PpError::IoError(err.to_string())),
+            };
+
+        tokio::task::spawn_blocking(move || {
+            let beatmap = Beatmap::from_bytes(&osu_file)
+                .map_err(|err| PpError::ParseBeatmapError(err.to_string()))?;
+
+            Ok(calculate(&beatmap, mode, &request))
+        })
+        .await
+        .map_err(|err| PpError::CalculateError(err.to_string()))?
+    }
+}
+
+/// Calculate the performance of the score on the beatmap.
+pub fn calculate(
+    beatmap: &Beatmap,
+    mode: GameMode,
+    request: &CalculatePerformanceRequest,
+) -> PerformanceAttributes {
+    let mut calculator = AnyPP::new(beatmap)
+        .mode(mode)
+        .mods(request.mods)
+        .n300(request.n300.max(0) as usize)
+        .n100(request.n100.max(0) as usize)
+        .n50(request.n50.max(0) as usize)
+        .n_geki(request.geki.max(0) as usize)
+        .n_katu(request.katu.max(0) as usize)
+        .n_misses(request.miss.max(0) as usize)
+        .combo(request.max_combo.max(0) as usize);
+
+    if let Some(passed_objects) = request.passed_objects {
+        calculator = calculator.passed_objects(passed_objects.max(0) as usize);
+    }
+
+    let attributes = calculator.calculate();
+
+    let mut result = PerformanceAttributes {
+        pp: attributes.pp(),
+        stars: attributes.stars(),
+        max_combo: attributes.max_combo() as i32,
+        ..Default::default()
+    };
+
+    match attributes {
+        rosu_pp::PerformanceAttributes::Osu(attrs) => {
+            result.pp_acc = Some(attrs.pp_acc);
+            result.pp_aim = Some(attrs.pp_aim);
+            result.pp_speed = Some(attrs.pp_speed);
+            result.pp_flashlight = Some(attrs.pp_flashlight);
+        },
+        rosu_pp::PerformanceAttributes::Taiko(attrs) => {
+            result.pp_acc = Some(attrs.pp_acc);
+            result.pp_difficulty = Some(attrs.pp_difficulty);
+        },
+        rosu_pp::PerformanceAttributes::Mania(attrs) => {
+            result.pp_difficulty = Some(attrs.pp_difficulty);
+        },
+        rosu_pp::PerformanceAttributes::Catch(_) => {},
+    }
+
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct PpServiceRemote(PpRpcClient<Channel>);
+
+impl RpcClient for PpServiceRemote {
+    type Client = PpRpcClient<Channel>;
+
+    #[inline]
+    fn client(&self) -> Self::Client {
+        self.0.clone()
+    }
+}
+
+impl FromRpcClient for PpServiceRemote {
+    #[inline]
+    fn from_client(client: Self::Client) -> Self {
+        Self(client)
+    }
+}
+
+impl PpService for PpServiceRemote {}
+
+impl IntoService<DynPpService> for PpServiceRemote {
+    #[inline]
+    fn into_service(self) -> DynPpService {
+        Arc::new(self) as DynPpService
+    }
+}
+
+#[async_trait]
+impl CalculatePerformance for PpServiceRemote {
+    async fn calculate_performance(
+        &self,
+        request: CalculatePerformanceRequest,
+    ) -> Result<PerformanceAttributes, PpError> {
+        self.client()
+            .calculate_performance(request)
+            .await
+            .map_err(PpError::from)
+            .map(|resp| resp.into_inner().into())
+    }
+}
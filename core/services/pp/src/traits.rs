@@ -0,0 +1,21 @@
+use super::PpError;
+use async_trait::async_trait;
+use domain_pp::PerformanceAttributes;
+use pb_pp::CalculatePerformanceRequest;
+use std::{path::PathBuf, sync::Arc};
+
+pub type DynPpService = Arc<dyn PpService + Send + Sync>;
+
+pub trait PpService: CalculatePerformance {}
+
+#[async_trait]
+pub trait CalculatePerformance {
+    async fn calculate_performance(
+        &self,
+        request: CalculatePerformanceRequest,
+    ) -> Result<PerformanceAttributes, PpError>;
+}
+
+pub trait FromOsuFilesDir {
+    fn from_osu_files_dir(dir: impl Into<PathBuf>) -> Self;
+}
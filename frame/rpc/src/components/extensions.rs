@@ -5,6 +5,7 @@ use tonic::{Request, Status};
 pub struct ClientIp(pub IpAddr);
 
 impl ClientIp {
+    #[allow(clippy::result_large_err)]
     pub fn from_request<T>(request: &Request<T>) -> Result<Self, Status> {
         Ok(request
             .extensions()
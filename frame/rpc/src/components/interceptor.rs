@@ -10,6 +10,7 @@ pub const X_REAL_IP: &str = "x-real-ip";
 pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
 
 // Authorization middleware for admin endpoints
+#[allow(clippy::result_large_err)]
 pub fn admin_endpoints_authorization(
     req: Request<()>,
     token: MetadataValue<Ascii>,
@@ -26,6 +27,7 @@ pub fn admin_endpoints_authorization(
 }
 
 // Middleware that extracts the client IP address from the request metadata
+#[allow(clippy::result_large_err)]
 pub fn client_ip(mut request: Request<()>) -> Result<Request<()>, Status> {
     // Try to extract the IP address from the `X-Real-IP` or `X-Forwarded-For`
     // headers
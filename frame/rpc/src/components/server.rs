@@ -31,6 +31,7 @@ const BANNER: &str = r#"
 "#;
 
 /// Start service.
+#[allow(clippy::result_large_err)]
 pub async fn serve(app: impl RpcApplication) {
     tools::framework_info!(BANNER);
     println!(">> Starting service...");
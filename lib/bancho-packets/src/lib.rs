@@ -39,6 +39,11 @@ pub const BANCHO_PACKET_HEADER_LENGTH: usize = 7;
 
 pub const EMPTY_STRING_PACKET: &[u8; 2] = b"\x0b\x00";
 
+/// The number of slots in a multiplayer match.
+pub const MATCH_SLOTS: usize = 16;
+/// Slot status bits which mean there is a player in the slot.
+pub const SLOT_HAS_PLAYER: u8 = 0b01111100;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -371,6 +376,64 @@ pub struct ClientChangeAction {
     pub beatmap_id: i32,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
+/// The beatmaps queried by the song select of the bancho client, by the
+/// `.osu` file names and the beatmap ids.
+pub struct BeatmapInfoQuery {
+    pub file_names: Vec<String>,
+    pub beatmap_ids: Vec<i32>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, WritePacket, PacketLength, Default)]
+/// [`BeatmapInfo`] is the ranked status of a beatmap and the user's grades on
+/// it, `index` refers to the queried file name.
+///
+/// The grades are the values of [`Grade`].
+pub struct BeatmapInfo {
+    pub index: i16,
+    pub beatmap_id: i32,
+    pub beatmapset_id: i32,
+    pub thread_id: i32,
+    pub rank_status: u8,
+    pub osu_grade: u8,
+    pub fruits_grade: u8,
+    pub taiko_grade: u8,
+    pub mania_grade: u8,
+    pub beatmap_md5: String,
+}
+
+#[rustfmt::skip]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Primitive
+)]
+#[repr(u8)]
+/// The score grades known by the bancho client, `N` means no score.
+pub enum Grade {
+    XH  = 0,
+    SH  = 1,
+    X   = 2,
+    S   = 3,
+    A   = 4,
+    B   = 5,
+    C   = 6,
+    D   = 7,
+    F   = 8,
+    #[default]
+    N   = 9,
+}
+
 #[derive(Debug, Clone)]
 /// [`PayloadReader`] helps to read Bacho packet data.
 ///
@@ -748,6 +811,98 @@ impl BanchoPacketRead<String> for String {
     }
 }
 
+impl BanchoPacketRead<MatchData> for MatchData {
+    #[inline]
+    fn read(reader: &mut PayloadReader) -> Option<MatchData> {
+        /// Empty strings are sent as a single `0x00` byte.
+        #[inline]
+        fn read_string(reader: &mut PayloadReader) -> Option<String> {
+            if reader.payload.get(reader.index())? == &0 {
+                reader.increase_index(1);
+                return Some(String::new());
+            }
+            reader.read::<String>()
+        }
+
+        let match_id = reader.read::<u16>()? as i32;
+        let in_progress = reader.read::<bool>()?;
+        let match_type = reader.read::<i8>()?;
+        let play_mods = reader.read::<u32>()?;
+        let match_name = read_string(reader)?;
+        let password = Some(read_string(reader)?).filter(|p| !p.is_empty());
+        let beatmap_name = read_string(reader)?;
+        let beatmap_id = reader.read::<i32>()?;
+        let beatmap_md5 = read_string(reader)?;
+
+        let slot_status = reader.next_with_length(MATCH_SLOTS)?.to_vec();
+        let slot_teams = reader.next_with_length(MATCH_SLOTS)?.to_vec();
+
+        let mut slot_players = Vec::with_capacity(MATCH_SLOTS);
+        for status in slot_status.iter() {
+            if status & SLOT_HAS_PLAYER != 0 {
+                slot_players.push(reader.read::<i32>()?);
+            }
+        }
+
+        let host_player_id = reader.read::<i32>()?;
+        let match_game_mode = reader.read::<u8>()?;
+        let win_condition = reader.read::<u8>()?;
+        let team_type = reader.read::<u8>()?;
+        let freemods = reader.read::<bool>()?;
+
+        let mut player_mods = Vec::new();
+        if freemods {
+            player_mods.reserve(MATCH_SLOTS);
+            for _ in 0..MATCH_SLOTS {
+                player_mods.push(reader.read::<i32>()?);
+            }
+        }
+
+        let match_seed = reader.read::<i32>().unwrap_or_default();
+
+        Some(MatchData {
+            match_id,
+            in_progress,
+            match_type,
+            play_mods,
+            match_name,
+            password,
+            beatmap_name,
+            beatmap_id,
+            beatmap_md5,
+            slot_status,
+            slot_teams,
+            slot_players,
+            host_player_id,
+            match_game_mode,
+            win_condition,
+            team_type,
+            freemods,
+            player_mods,
+            match_seed,
+        })
+    }
+}
+
+impl BanchoPacketRead<BeatmapInfoQuery> for BeatmapInfoQuery {
+    #[inline]
+    fn read(reader: &mut PayloadReader) -> Option<BeatmapInfoQuery> {
+        let file_names_count = reader.read::<i32>()?.max(0) as usize;
+        let mut file_names = Vec::with_capacity(file_names_count.min(1024));
+        for _ in 0..file_names_count {
+            file_names.push(reader.read::<String>()?);
+        }
+
+        let beatmap_ids_count = reader.read::<i32>()?.max(0) as usize;
+        let mut beatmap_ids = Vec::with_capacity(beatmap_ids_count.min(1024));
+        for _ in 0..beatmap_ids_count {
+            beatmap_ids.push(reader.read::<i32>()?);
+        }
+
+        Some(BeatmapInfoQuery { file_names, beatmap_ids })
+    }
+}
+
 impl BanchoPacketRead<bool> for bool {
     #[inline]
     fn read(reader: &mut PayloadReader) -> Option<bool> {
@@ -1011,16 +1166,30 @@ impl BanchoPacketWrite for MatchUpdate {
             beatmap_id,
             beatmap_md5,
             slot_status,
-            slot_teams,
-            slot_players,
+            slot_teams
+        ));
+
+        // players of occupied slots only, without length prefix
+        for player_id in slot_players {
+            buf.extend(player_id.to_le_bytes());
+        }
+
+        buf.extend(data!(
             host_player_id,
             match_game_mode,
             win_condition,
             team_type,
-            freemods,
-            player_mods,
-            match_seed
+            freemods
         ));
+
+        // mods of each slot are only sent when freemods enabled
+        if freemods {
+            for mods in player_mods {
+                buf.extend(mods.to_le_bytes());
+            }
+        }
+
+        buf.extend(match_seed.to_le_bytes());
     }
 }
 
@@ -1031,6 +1200,30 @@ impl BanchoPacketWrite for MatchData {
     }
 }
 
+/// Unlike other arrays, the beatmap infos are prefixed with an `i32` count.
+impl BanchoPacketWrite for Vec<BeatmapInfo> {
+    #[inline]
+    fn write_into_buf(self, buf: &mut Vec<u8>) {
+        let estimate_len = self.packet_len();
+        if buf.capacity() < estimate_len {
+            buf.reserve(estimate_len);
+        }
+
+        (self.len() as i32).write_into_buf(buf);
+        for info in self {
+            info.write_into_buf(buf);
+        }
+    }
+}
+
+impl BanchoPacketLength for Vec<BeatmapInfo> {
+    #[inline]
+    fn packet_len(&self) -> usize {
+        std::mem::size_of::<i32>()
+            + self.iter().map(|info| info.packet_len()).sum::<usize>()
+    }
+}
+
 /// [`BanchoPacketLength`] is a trait used to calculate the byte length of the
 /// data converted to bancho packet.
 pub trait BanchoPacketLength {
@@ -1059,7 +1252,7 @@ impl BanchoPacketLength for &str {
     #[inline]
     fn packet_len(&self) -> usize {
         if !self.is_empty() {
-            self.as_bytes().len() + 6
+            self.len() + 6
         } else {
             1
         }
@@ -1070,7 +1263,7 @@ impl BanchoPacketLength for String {
     #[inline]
     fn packet_len(&self) -> usize {
         if !self.is_empty() {
-            self.as_bytes().len() + 6
+            self.len() + 6
         } else {
             1
         }
@@ -193,7 +193,11 @@ packet_struct!(
 packet_struct!(
     PacketId::BANCHO_NEW_MATCH,
     /// #27: BANCHO_NEW_MATCH
-    NewMatch { match_data: MatchData }
+    NewMatch { match_data: MatchData },
+    fn into_packet_data(self) -> Vec<u8> {
+        let data = MatchUpdate { data: self.match_data, send_password: false };
+        packet!(Self::ID, data)
+    }
 );
 
 packet_struct!(
@@ -311,11 +315,7 @@ packet_struct!(
 packet_struct!(
     PacketId::BANCHO_BEATMAP_INFO_REPLY,
     /// #69: BANCHO_BEATMAP_INFO_REPLY
-    /// UNUSED
-    BeatmapInfoReply {},
-    fn into_packet_data(self) -> Vec<u8> {
-        unimplemented!()
-    }
+    BeatmapInfoReply { infos: Vec<BeatmapInfo> }
 );
 
 packet_struct!(
@@ -480,7 +480,7 @@ packet_struct!(
     },
     fn into_packet_data(self) -> Vec<u8> {
         packet!(
-            PacketId::BANCHO_USER_DM_BLOCKED,
+            PacketId::BANCHO_TARGET_IS_SILENCED,
             "", "", self.target, 0_i32
         )
     }
@@ -1,5 +1,8 @@
 mod packets_reading {
-    use crate::{uleb128_to_u32, BanchoMessage, PacketReader, PayloadReader};
+    use crate::{
+        uleb128_to_u32, BanchoMessage, BeatmapInfoQuery, PacketReader,
+        PayloadReader,
+    };
 
     #[test]
     fn test_read_header() {
@@ -179,6 +182,19 @@ mod packets_reading {
         println!("{:?}", int_list);
         assert_eq!(int_list, Some(vec![1001, 1002, 1003, 1004]))
     }
+
+    #[test]
+    fn test_read_beatmap_info_query() {
+        let payload = vec![
+            1, 0, 0, 0, 11, 5, 97, 46, 111, 115, 117, 2, 0, 0, 0, 233, 3, 0, 0,
+            234, 3, 0, 0,
+        ];
+        let query =
+            PayloadReader::new(&payload).read::<BeatmapInfoQuery>().unwrap();
+
+        assert_eq!(query.file_names, vec!["a.osu".to_owned()]);
+        assert_eq!(query.beatmap_ids, vec![1001, 1002]);
+    }
 }
 
 mod packets_writing {
@@ -238,6 +254,22 @@ mod packets_writing {
         )
     }
 
+    #[test]
+    fn test_target_silenced() {
+        let data = server::TargetSilenced::pack("peppy".into());
+        let mut reader = PacketReader::new(&data);
+        let packet = reader.next().unwrap();
+
+        assert_eq!(packet.id, PacketId::BANCHO_TARGET_IS_SILENCED);
+        assert_eq!(
+            data,
+            vec![
+                101, 0, 0, 13, 0, 0, 0, 0, 0, 11, 5, 112, 101, 112, 112, 121,
+                0, 0, 0, 0
+            ]
+        );
+    }
+
     #[test]
     fn test_login() {
         let resp = PacketBuilder::new();
@@ -276,6 +308,28 @@ mod packets_writing {
         )
     }
 
+    #[test]
+    fn test_beatmap_info_reply() {
+        assert_eq!(
+            server::BeatmapInfoReply::pack(vec![BeatmapInfo {
+                index: 0,
+                beatmap_id: 1001,
+                beatmapset_id: 1,
+                thread_id: 0,
+                rank_status: 2,
+                osu_grade: Grade::S as u8,
+                fruits_grade: Grade::N as u8,
+                taiko_grade: Grade::N as u8,
+                mania_grade: Grade::N as u8,
+                beatmap_md5: "md5".into(),
+            }]),
+            vec![
+                69, 0, 0, 28, 0, 0, 0, 1, 0, 0, 0, 0, 0, 233, 3, 0, 0, 1, 0, 0,
+                0, 0, 0, 0, 0, 2, 3, 9, 9, 9, 11, 3, 109, 100, 53
+            ]
+        )
+    }
+
     #[test]
     fn test_write_u32_i32() {
         let int_u32 = 536870912_u32.into_packet();
@@ -376,4 +430,44 @@ mod packets_writing {
             server::UserPresenceBundle::pack(&[4, 5, 6]).len()
         );
     }
+
+    #[test]
+    fn test_match_data() {
+        let mut slot_status = vec![1; MATCH_SLOTS];
+        slot_status[0] = 4;
+        slot_status[1] = 8;
+
+        let mut player_mods = vec![0; MATCH_SLOTS];
+        player_mods[1] = 64;
+
+        let match_data = MatchData {
+            match_id: 1,
+            match_name: "test".into(),
+            password: Some("pw".into()),
+            beatmap_id: 114514,
+            slot_status,
+            slot_teams: vec![0; MATCH_SLOTS],
+            slot_players: vec![1000, 1001],
+            host_player_id: 1000,
+            freemods: true,
+            player_mods,
+            match_seed: 42,
+            ..Default::default()
+        };
+
+        let data = server::MatchJoinSuccess::pack(match_data.clone());
+        let mut reader = PacketReader::new(&data);
+        let packet = reader.next().unwrap();
+
+        let read = PayloadReader::new(packet.payload.unwrap())
+            .read::<MatchData>()
+            .unwrap();
+
+        assert_eq!(read.match_name, match_data.match_name);
+        assert_eq!(read.password, match_data.password);
+        assert_eq!(read.beatmap_id, match_data.beatmap_id);
+        assert_eq!(read.slot_players, match_data.slot_players);
+        assert_eq!(read.player_mods, match_data.player_mods);
+        assert_eq!(read.match_seed, match_data.match_seed);
+    }
 }
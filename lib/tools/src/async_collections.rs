@@ -70,12 +70,15 @@ impl SignalHandle {
 ///
 /// # Example
 ///
-/// ```
-/// async fn my_shutdown_function(signal: &str) {
+/// ```no_run
+/// # use tools::async_collections::shutdown_signal;
+/// # async fn run() {
+/// fn my_shutdown_function(signal: &str) {
 ///     println!("Received signal: {}", signal);
 /// }
 ///
 /// shutdown_signal(my_shutdown_function).await;
+/// # }
 /// ```
 ///
 /// # Panics